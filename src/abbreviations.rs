@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::text_buffer::{SubString, TextBuffer};
+
+/// Abbreviations from the file named by `$FLYLINE_ABBR`, else
+/// `~/.config/flyline/abbr.toml` if it exists.
+pub fn load_at_startup() -> HashMap<String, String> {
+    abbreviations(crate::config_file::load(
+        "FLYLINE_ABBR",
+        "abbr.toml",
+        "abbreviations",
+    ))
+}
+
+/// Map each abbreviation in the entries of an abbreviations file to its
+/// expansion, e.g.
+///
+/// ```toml
+/// gco = "git checkout"
+/// "k" = "kubectl"
+/// ```
+///
+/// Abbreviations must be a single word; other entries are logged and skipped.
+fn abbreviations(entries: Vec<(String, String)>) -> HashMap<String, String> {
+    entries
+        .into_iter()
+        .filter(|(abbr, _)| {
+            let valid = !abbr.is_empty() && !abbr.contains(char::is_whitespace);
+            if !valid {
+                log::warn!("Abbreviation {:?} must be a single word", abbr);
+            }
+            valid
+        })
+        .collect()
+}
+
+/// The first word of `buffer` and its expansion, if that word is an
+/// abbreviation and the cursor sits right at its end. A cursor in the middle
+/// of the word, or anywhere after it, expands nothing.
+pub fn expansion_at_cursor<'a>(
+    abbrs: &'a HashMap<String, String>,
+    buffer: &str,
+    cursor_byte: usize,
+) -> Option<(SubString, &'a str)> {
+    let start = buffer.len() - buffer.trim_start().len();
+    let end = buffer[start..]
+        .find(char::is_whitespace)
+        .map_or(buffer.len(), |len| start + len);
+    if start == end || cursor_byte != end {
+        return None;
+    }
+    let word = &buffer[start..end];
+    let expansion = abbrs.get(word)?;
+    Some((SubString::from_parts(word, start), expansion.as_str()))
+}
+
+/// Expand the abbreviation under the cursor in place. Returns whether the
+/// buffer changed.
+pub fn expand_at_cursor(abbrs: &HashMap<String, String>, buffer: &mut TextBuffer) -> bool {
+    let Some((word, expansion)) =
+        expansion_at_cursor(abbrs, buffer.buffer(), buffer.cursor_byte_pos())
+    else {
+        return false;
+    };
+    log::debug!("Expanding abbreviation {:?} to {:?}", word.s, expansion);
+    buffer.replace_word_under_cursor(expansion, &word).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abbrs() -> HashMap<String, String> {
+        abbreviations(crate::config_file::parse(
+            "\
+# git
+gco = \"git checkout\"
+'k' = 'kubectl'
+\"g co\" = \"git checkout\"
+",
+            "abbreviations",
+        ))
+    }
+
+    #[test]
+    fn parses_abbreviation_file() {
+        let abbrs = abbrs();
+        assert_eq!(abbrs.len(), 2);
+        assert_eq!(abbrs["gco"], "git checkout");
+        assert_eq!(abbrs["k"], "kubectl");
+    }
+
+    #[test]
+    fn expands_on_space() {
+        // The space key runs after the expansion, so the cursor must sit
+        // at the end of the expanded text.
+        let mut buffer = TextBuffer::new("gco");
+        assert!(expand_at_cursor(&abbrs(), &mut buffer));
+        buffer.insert_char(' ');
+        assert_eq!(buffer.buffer(), "git checkout ");
+        assert_eq!(buffer.cursor_byte_pos(), "git checkout ".len());
+    }
+
+    #[test]
+    fn expands_before_enter() {
+        let mut buffer = TextBuffer::new("  k");
+        assert!(expand_at_cursor(&abbrs(), &mut buffer));
+        assert_eq!(buffer.buffer(), "  kubectl");
+    }
+
+    #[test]
+    fn does_not_expand_mid_word() {
+        let mut buffer = TextBuffer::new("gcox");
+        assert!(!expand_at_cursor(&abbrs(), &mut buffer));
+
+        let mut buffer = TextBuffer::new_with_cursor("gc█o");
+        assert!(!expand_at_cursor(&abbrs(), &mut buffer));
+        assert_eq!(buffer.buffer(), "gco");
+    }
+
+    #[test]
+    fn only_expands_the_first_word() {
+        let mut buffer = TextBuffer::new("echo gco");
+        assert!(!expand_at_cursor(&abbrs(), &mut buffer));
+
+        let mut buffer = TextBuffer::new("gco main");
+        assert!(!expand_at_cursor(&abbrs(), &mut buffer));
+
+        let mut buffer = TextBuffer::new_with_cursor("gco█ main");
+        assert!(expand_at_cursor(&abbrs(), &mut buffer));
+        assert_eq!(buffer.buffer(), "git checkout main");
+    }
+}
@@ -3,6 +3,7 @@ use crate::content_utils::{
     take_prefix_of_spans, ts_to_timeago_string_5chars, vec_spans_width,
 };
 use crate::palette::Palette;
+use crate::settings::CompletionTrailing;
 use crate::stateful_sliding_window::StatefulSlidingWindow;
 use crate::text_buffer::{SubString, TextBuffer};
 use crate::{bash_funcs, tab_completion_context};
@@ -499,6 +500,52 @@ mod description_tests {
         assert_eq!(fw, "hello".len());
     }
 
+    #[test]
+    fn wide_character_suggestions_align_to_visual_width() {
+        let palette = crate::palette::Palette::default();
+        let suggestions = [
+            ProcessedSuggestion::new("readme.md", "", " "),
+            ProcessedSuggestion::new("日本語.txt", "", " "),
+            ProcessedSuggestion::new("🎉.log", "", " "),
+        ];
+        let formatted: Vec<SuggestionFormatted> = suggestions
+            .iter()
+            .map(|sug| SuggestionFormatted::new(sug, 0, 0, vec![], &palette, 0))
+            .collect();
+
+        // Display width counts terminal columns, not bytes.
+        assert_eq!(formatted[1].display_width, 10);
+        assert_eq!(formatted[2].display_width, 6);
+        assert_eq!(suggestions[1].display_width(), 10);
+
+        let col_width = formatted.iter().map(|f| f.display_width).max().unwrap();
+        for f in &formatted {
+            assert_eq!(vec_spans_width(&f.render(col_width, false)), col_width);
+            assert_eq!(vec_spans_width(&f.render(col_width, true)), col_width);
+        }
+    }
+
+    #[test]
+    fn wide_character_suggestions_with_descriptions_align() {
+        let palette = crate::palette::Palette::default();
+        let with_desc = |s: &str| {
+            ProcessedSuggestion::new(s, "", " ")
+                .with_description(SuggestionDescription::Static(vec![Span::raw("説明")]))
+        };
+        let formatted: Vec<SuggestionFormatted> = [with_desc("ab"), with_desc("漢字")]
+            .iter()
+            .map(|sug| SuggestionFormatted::new(sug, 0, 0, vec![], &palette, 0))
+            .collect();
+
+        assert_eq!(formatted[0].display_width, 2 + 2 + 4);
+        assert_eq!(formatted[1].display_width, 4 + 2 + 4);
+
+        let col_width = formatted[1].display_width;
+        for f in &formatted {
+            assert_eq!(vec_spans_width(&f.render(col_width, false)), col_width);
+        }
+    }
+
     #[test]
     fn last_mtime_description_max_width_is_5() {
         let sug = ProcessedSuggestion::new("file.txt", "", " ")
@@ -601,6 +648,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
         let mut active = ActiveSuggestions::new(
             builder,
@@ -672,6 +721,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
         let mut active = ActiveSuggestions::new(
             builder,
@@ -712,6 +763,8 @@ mod description_tests {
                 nosort: false,
                 compspec_was_useful: Some(true),
                 should_run_flycomp: false,
+                trailing: CompletionTrailing::default(),
+                truncated_to: None,
             },
             SubString::new("c", "c").unwrap(),
             std::time::Duration::from_millis(0),
@@ -746,6 +799,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         // mtime descending: c(200), then {a, b} (100), then d(0).
@@ -788,6 +843,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let active_alpha = ActiveSuggestions::new(
@@ -827,6 +884,8 @@ mod description_tests {
             nosort: true,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let mut active = ActiveSuggestions::new(
@@ -870,6 +929,52 @@ mod description_tests {
         assert_eq!(active.selected_coord, Some((0, 1)));
     }
 
+    #[test]
+    fn test_with_trailing_on_file_and_directory() {
+        let file = || ProcessedSuggestion::new("bar.txt", "", " ");
+        let dir = || ProcessedSuggestion::new("foo/", "src/", "").with_type(SuggestionType::Folder);
+        let formatted =
+            |sug: ProcessedSuggestion, trailing| sug.with_trailing(trailing).formatted();
+
+        assert_eq!(formatted(file(), CompletionTrailing::Space), "bar.txt ");
+        assert_eq!(formatted(dir(), CompletionTrailing::Space), "src/foo/");
+        assert_eq!(formatted(file(), CompletionTrailing::SlashOnly), "bar.txt");
+        assert_eq!(formatted(dir(), CompletionTrailing::SlashOnly), "src/foo/");
+        assert_eq!(formatted(file(), CompletionTrailing::None), "bar.txt");
+        assert_eq!(formatted(dir(), CompletionTrailing::None), "src/foo");
+
+        // The root directory keeps its only character.
+        let root = ProcessedSuggestion::new("/", "", "").with_type(SuggestionType::Folder);
+        assert_eq!(formatted(root, CompletionTrailing::None), "/");
+    }
+
+    #[test]
+    fn test_selected_formatted_follows_selection() {
+        let builder = ActiveSuggestionsBuilder::from_processed(vec![
+            ProcessedSuggestion::new("foo.txt", "", " "),
+            ProcessedSuggestion::new("food/", "./", ""),
+        ])
+        .with_nosort(true);
+        let mut active = ActiveSuggestions::new(
+            builder,
+            SubString::new("fo", "fo").unwrap(),
+            std::time::Duration::from_millis(0),
+            false,
+            crate::settings::SuggestionSortOrder::Alphabetical,
+            crate::settings::FuzzyMode::default(),
+        );
+        active.last_num_rows_per_col = 10;
+
+        assert_eq!(active.selected_formatted().as_deref(), Some("foo.txt "));
+        active.on_down_arrow();
+        assert_eq!(active.selected_formatted().as_deref(), Some("./food/"));
+        active.set_selected_by_idx(0);
+        assert_eq!(active.selected_formatted().as_deref(), Some("foo.txt "));
+
+        active.selected_coord = None;
+        assert_eq!(active.selected_formatted(), None);
+    }
+
     #[test]
     fn test_nosort_on_large_list() {
         // 1. Boundary check: exactly FILENAME_INFERENCE_LIMIT suggestions -> nosort should be false
@@ -887,6 +992,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
         let active_boundary = ActiveSuggestions::new(
             builder_boundary,
@@ -913,6 +1020,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
         let active_large = ActiveSuggestions::new(
             builder_large,
@@ -969,6 +1078,8 @@ mod description_tests {
             nosort: false,
             sort_order: crate::settings::SuggestionSortOrder::default(),
             fuzzy_mode: crate::settings::FuzzyMode::default(),
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
             formatted_cache: vec![None, None, None],
             max_width_cache: std::cell::Cell::new(None),
         };
@@ -993,6 +1104,8 @@ mod description_tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         // Case 1: pattern "oo" - should only match RegularFile and Misc, but NOT Folder (as "oo" is not a prefix of "foobar").
@@ -1089,22 +1202,26 @@ impl ProcessedSuggestion {
         }
     }
 
-    pub fn from_string_vec(
-        suggestions: Vec<String>,
-        prefix: &str,
-        suffix: &str,
-    ) -> Vec<ProcessedSuggestion> {
-        suggestions
-            .into_iter()
-            .map(|s| {
-                let new_suffix = if suffix == " " && s.ends_with(suffix) {
-                    "".to_string()
-                } else {
-                    suffix.to_string()
-                };
-                ProcessedSuggestion::new(s, prefix.to_string(), new_suffix)
-            })
-            .collect()
+    /// Drop the trailing space, and for [`CompletionTrailing::None`] also a
+    /// directory's trailing `/`, that accepting this suggestion would insert.
+    pub fn with_trailing(mut self, trailing: CompletionTrailing) -> Self {
+        match trailing {
+            CompletionTrailing::Space => {}
+            CompletionTrailing::SlashOnly => {
+                self.suffix = self.suffix.trim_end_matches(' ').to_string();
+            }
+            CompletionTrailing::None => {
+                self.suffix = self.suffix.trim_end_matches([' ', '/']).to_string();
+                if self.suffix.is_empty()
+                    && self.sug_type == SuggestionType::Folder
+                    && self.s.len() > 1
+                    && self.s.ends_with('/')
+                {
+                    self.s.pop();
+                }
+            }
+        }
+        self
     }
 }
 
@@ -1312,6 +1429,12 @@ pub struct ActiveSuggestionsBuilder {
     pub nosort: bool,
     pub compspec_was_useful: Option<bool>,
     pub should_run_flycomp: bool,
+    /// Applied to every suggestion as it is processed.
+    #[serde(default)]
+    pub trailing: CompletionTrailing,
+    /// Set when the source had more matches and only this many were kept.
+    #[serde(default)]
+    pub truncated_to: Option<usize>,
 }
 
 impl ActiveSuggestionsBuilder {
@@ -1328,6 +1451,8 @@ impl ActiveSuggestionsBuilder {
             nosort: false,
             compspec_was_useful: None,
             should_run_flycomp: false,
+            trailing: CompletionTrailing::default(),
+            truncated_to: None,
         }
     }
 
@@ -1339,6 +1464,17 @@ impl ActiveSuggestionsBuilder {
         self
     }
 
+    /// Require the user to confirm a lone candidate from the menu instead of
+    /// accepting it straight away. The common prefix of a single candidate is
+    /// the candidate itself, so it is dropped too to leave the buffer untouched.
+    pub fn with_confirm_single_completion(mut self) -> Self {
+        self.auto_accept_if_solo = false;
+        if self.len() == 1 {
+            self.common_prefix = None;
+        }
+        self
+    }
+
     pub fn with_insert_common_prefix(mut self, insert_common_prefix: bool) -> Self {
         self.insert_common_prefix = insert_common_prefix;
         self
@@ -1354,11 +1490,32 @@ impl ActiveSuggestionsBuilder {
         self
     }
 
+    pub fn with_truncated_to(mut self, truncated_to: Option<usize>) -> Self {
+        self.truncated_to = truncated_to;
+        self
+    }
+
     pub fn with_compspec_was_useful(mut self, compspec_was_useful: Option<bool>) -> Self {
         self.compspec_was_useful = compspec_was_useful;
         self
     }
 
+    /// Set what accepted suggestions leave after them, applying it to the
+    /// suggestions processed so far and to the rest as they are processed.
+    pub fn with_trailing(mut self, trailing: CompletionTrailing) -> Self {
+        self.trailing = trailing;
+        self.processed = std::mem::take(&mut self.processed)
+            .into_iter()
+            .map(|sug| sug.with_trailing(trailing))
+            .collect();
+        if trailing != CompletionTrailing::Space
+            && let Some(prefix) = self.common_prefix.as_mut()
+        {
+            prefix.truncate(prefix.trim_end_matches(' ').len());
+        }
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_should_run_flycomp(mut self, should_run_flycomp: bool) -> Self {
         self.should_run_flycomp = should_run_flycomp;
@@ -1410,7 +1567,8 @@ impl ActiveSuggestionsBuilder {
     pub fn try_process_all(&mut self) -> bool {
         let start_time = std::time::Instant::now();
         while let Some(raw) = self.unprocessed.pop_front() {
-            self.processed.push(raw.into_processed());
+            self.processed
+                .push(raw.into_processed().with_trailing(self.trailing));
             if start_time.elapsed() > CHUNK_PROCESSING_TIMEOUT {
                 return self.unprocessed.is_empty();
             }
@@ -1420,7 +1578,8 @@ impl ActiveSuggestionsBuilder {
 
     pub fn process_all_blocking(&mut self) {
         while let Some(raw) = self.unprocessed.pop_front() {
-            self.processed.push(raw.into_processed());
+            self.processed
+                .push(raw.into_processed().with_trailing(self.trailing));
         }
     }
 
@@ -1516,6 +1675,10 @@ pub struct ActiveSuggestions {
     pub sort_order: crate::settings::SuggestionSortOrder,
     /// Controls fuzzy matching behavior for suggestions.
     pub fuzzy_mode: crate::settings::FuzzyMode,
+    /// Applied to suggestions as they are processed.
+    trailing: CompletionTrailing,
+    /// Set when only the first this-many matches of the source were kept.
+    pub truncated_to: Option<usize>,
     formatted_cache: Vec<Option<SuggestionFormatted>>,
     max_width_cache: std::cell::Cell<Option<usize>>,
 }
@@ -1537,6 +1700,8 @@ impl ActiveSuggestions {
             insert_common_prefix: _,
             comp_type,
             nosort,
+            trailing,
+            truncated_to,
             ..
         } = builder;
         let sug_len = processed_suggestions.len() + unprocessed_suggestions.len();
@@ -1565,6 +1730,8 @@ impl ActiveSuggestions {
             nosort: nosort || sug_len > crate::FILENAME_INFERENCE_LIMIT,
             sort_order,
             fuzzy_mode,
+            trailing,
+            truncated_to,
             formatted_cache: vec![],
             max_width_cache: std::cell::Cell::new(Some(initial_max_width)),
         };
@@ -1573,6 +1740,12 @@ impl ActiveSuggestions {
         active_sug
     }
 
+    /// A note for the menu when the source kept only some of its matches.
+    pub fn truncation_note(&self) -> Option<String> {
+        self.truncated_to
+            .map(|shown| format!("(showing first {})", shown))
+    }
+
     /// Move as many entries as fit within [`CHUNK_PROCESSING_TIMEOUT`] from
     /// `unprocessed_suggestions` into `processed_suggestions`, returning the
     /// range of newly-processed indices in `processed_suggestions`.
@@ -1589,7 +1762,7 @@ impl ActiveSuggestions {
         let start_time = std::time::Instant::now();
         for _ in 0..max_to_process {
             if let Some(raw) = self.unprocessed_suggestions.pop_front() {
-                let processed = raw.into_processed();
+                let processed = raw.into_processed().with_trailing(self.trailing);
                 current_max = current_max.max(processed.display_width());
                 self.processed_suggestions.push(processed);
             }
@@ -1957,6 +2130,13 @@ impl ActiveSuggestions {
         final_grid
     }
 
+    /// How many filtered suggestions are not shown in `grid`, e.g. because
+    /// their columns are scrolled out of view.
+    pub fn num_hidden_in_grid(&self, grid: &[ColumnInfo]) -> usize {
+        let shown: usize = grid.iter().map(|col| col.items.len()).sum();
+        self.filtered_suggestions.len().saturating_sub(shown)
+    }
+
     pub fn into_list(&mut self, max_rows: usize, palette: &Palette) -> Vec<SuggestionFormatted> {
         let newly_processed = self.process_chunk();
         if !newly_processed.is_empty() {
@@ -2163,6 +2343,15 @@ impl ActiveSuggestions {
         self.max_width_cache.get().unwrap_or(0)
     }
 
+    /// The text the selected suggestion would put in place of the word under
+    /// the cursor, for previewing it before it is accepted.
+    pub fn selected_formatted(&self) -> Option<String> {
+        let filtered_item = self.filtered_suggestions.get(self.current_1d_index()?)?;
+        self.processed_suggestions
+            .get(filtered_item.suggestion_idx)
+            .map(ProcessedSuggestion::formatted)
+    }
+
     pub fn accept_selected_filtered_item(&mut self, buffer: &mut TextBuffer) {
         let selected_idx = if let Some(selected_idx) = self.current_1d_index() {
             selected_idx
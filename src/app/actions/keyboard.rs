@@ -1,8 +1,10 @@
 use crate::app::auto_close::surround_closing_char;
+use crate::app::command_palette::CommandPalette;
 use crate::app::{App, ContentMode, FlycompPromptSelection, FuzzyHistorySource};
-use crate::history::HistorySearchDirection;
+use crate::dparser::DParser;
+use crate::history::{HistoryManager, HistorySearchDirection};
 use crate::settings::MouseMode;
-use crate::text_buffer::WordDelim;
+use crate::text_buffer::{TextBuffer, WordDelim};
 use anyhow::Result;
 use clap_complete::CompletionCandidate;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -42,6 +44,12 @@ pub enum KeyEventAction {
     FlycompAskAcceptChoice,
     #[strum(message = "Accept inline history suggestion")]
     InlineSuggestionAccept,
+    #[strum(
+        message = "Accept the inline history suggestion without running it, with the cursor where it differs from similar history"
+    )]
+    InlineSuggestionAcceptAndEdit,
+    #[strum(message = "Accept the next word of the inline history suggestion")]
+    InlineSuggestionAcceptWord,
     #[strum(message = "Temporarily dismiss the inline history suggestion")]
     InlineSuggestionDismiss,
     #[strum(message = "Move down in agent output selection")]
@@ -94,6 +102,10 @@ pub enum KeyEventAction {
     FuzzyHistoryAcceptAndEdit,
     #[strum(message = "Accept the current fuzzy history search suggestion and immediately run it")]
     FuzzyHistoryAcceptAndRun,
+    #[strum(
+        message = "Close fuzzy history search, restoring the buffer from before the search started"
+    )]
+    FuzzyHistoryCancel,
     #[strum(message = "Run the agent mode command")]
     RunAgentMode,
     #[strum(message = "Run the agent mode help command")]
@@ -120,6 +132,10 @@ pub enum KeyEventAction {
     RunFuzzyCancelledHistorySearch,
     #[strum(message = "Clear the screen")]
     ClearScreen,
+    #[strum(
+        message = "Reload settings and completions: re-read the theme, keymap and abbreviation files, re-scan PATH and re-read aliases, functions and builtins"
+    )]
+    ReloadCompletions,
     #[strum(message = "Delete until start of line")]
     DeleteLeftUntilStartOfLine,
     #[strum(
@@ -128,6 +144,12 @@ pub enum KeyEventAction {
     DeleteLeftOneWordPart,
     #[strum(message = "Delete one word to the left using whitespace as delimiter")]
     DeleteLeftOneWord,
+    #[strum(message = "Delete back to the previous whitespace, like bash's unix-word-rubout")]
+    DeleteLeftWhitespaceWord,
+    #[strum(
+        message = "Delete back to the start of the previous alphanumeric word, like bash's backward-kill-word"
+    )]
+    DeleteLeftShellWord,
     #[strum(message = "Delete character before cursor")]
     DeleteLeft,
     #[strum(message = "Delete until end of line")]
@@ -142,7 +164,7 @@ pub enum KeyEventAction {
     DeleteRight,
     #[strum(message = "Move cursor to start of line")]
     MoveLeftStartOfLine,
-    #[strum(message = "Move one word left using whitespace as delimiter")]
+    #[strum(message = "Move one word left, stopping at punctuation like readline")]
     MoveLeftOneWord,
     #[strum(
         message = "Move one word part to the left, stopping at punctuation or path segment boundaries"
@@ -152,7 +174,7 @@ pub enum KeyEventAction {
     MoveLeft,
     #[strum(message = "Move cursor to end of line")]
     MoveRightEndOfLine,
-    #[strum(message = "Move one word right using whitespace as delimiter")]
+    #[strum(message = "Move one word right, stopping at punctuation like readline")]
     MoveRightOneWord,
     #[strum(
         message = "Move one word part to the right, stopping at punctuation or path segment boundaries"
@@ -160,18 +182,30 @@ pub enum KeyEventAction {
     MoveRightOneWordPart,
     #[strum(message = "Move cursor right")]
     MoveRight,
+    #[strum(message = "Move cursor to the start of the previous top-level command")]
+    MoveToPrevCommand,
+    #[strum(message = "Move cursor to the start of the next top-level command")]
+    MoveToNextCommand,
     #[strum(message = "Move cursor up one line")]
     MoveLineUp,
     #[strum(message = "Move cursor down one line")]
     MoveLineDown,
     #[strum(message = "Navigate to previous history entry")]
     PrevHistoryEntry,
+    #[strum(
+        message = "Recall the previous history entry and place the cursor on its first argument"
+    )]
+    PrevHistoryEntryEditArgs,
     #[strum(message = "Navigate to next history entry")]
     NextHistoryEntry,
     #[strum(message = "Undo last action")]
     Undo,
     #[strum(message = "Redo last action")]
     Redo,
+    #[strum(message = "Insert the most recently deleted text at the cursor")]
+    Yank,
+    #[strum(message = "After a yank, replace the yanked text with the previous deleted text")]
+    YankPop,
     #[strum(message = "Insert character")]
     InsertChar,
     #[strum(message = "Move cursor left, extending the text selection")]
@@ -204,6 +238,10 @@ pub enum KeyEventAction {
     PasteSystemClipboard,
     #[strum(message = "Insert the last word from the previous command in history")]
     InsertLastWordFromPrevCommand,
+    #[strum(
+        message = "Insert the next key literally, e.g. a Tab or newline (readline's quoted-insert)"
+    )]
+    QuotedInsert,
     #[strum(message = "Select the entire command buffer")]
     SelectAll,
     #[strum(message = "Do nothing (useful for unbinding a key)")]
@@ -222,6 +260,18 @@ pub enum KeyEventAction {
     PromptDirMoveToStart,
     #[strum(message = "Move selection to the rightmost (current) directory segment in the prompt")]
     PromptDirMoveToEnd,
+    #[strum(message = "Open the command palette to search and run flyline commands")]
+    RunCommandPalette,
+    #[strum(message = "Move down in the command palette")]
+    CommandPaletteSelectNext,
+    #[strum(message = "Move up in the command palette")]
+    CommandPaletteSelectPrev,
+    #[strum(message = "Run the currently selected command palette command")]
+    CommandPaletteAcceptEntry,
+    #[strum(message = "Type a character into the command palette query")]
+    CommandPaletteInsertChar,
+    #[strum(message = "Delete the last character of the command palette query")]
+    CommandPaletteDeleteChar,
     #[strum(message = "Return to the normal command editing mode")]
     EscapeToNormalMode,
 }
@@ -242,14 +292,26 @@ impl KeyEventAction {
     pub(crate) fn run(&self, app: &mut App, key: KeyEvent) {
         match self {
             KeyEventAction::InlineSuggestionAccept => {
-                if let Some((_, suf)) = &app.inline_history_suggestion {
+                if let Some((_, suf)) = &app.inline_suggestion {
                     let new_buffer = format!("{}{}", app.buffer.buffer(), suf);
                     app.buffer.replace_buffer(&new_buffer);
                 }
             }
+            KeyEventAction::InlineSuggestionAcceptAndEdit => {
+                if let Some((_, suf)) = &app.inline_suggestion {
+                    accept_suggestion_for_edit(&mut app.buffer, &app.history_manager, suf);
+                }
+            }
+            KeyEventAction::InlineSuggestionAcceptWord => {
+                if let Some((_, suf)) = &app.inline_suggestion {
+                    let word = next_suggestion_word(suf);
+                    app.buffer.move_to_end();
+                    app.buffer.insert_str(word);
+                }
+            }
             KeyEventAction::InlineSuggestionDismiss => {
                 app.dismissed_inline_suggestion_buffer = Some(app.buffer.buffer().to_string());
-                app.inline_history_suggestion = None;
+                app.inline_suggestion = None;
             }
             KeyEventAction::AgentOutputSelectNext => {
                 if let ContentMode::AgentOutputSelection(selection) = &mut app.content_mode {
@@ -437,6 +499,17 @@ impl KeyEventAction {
                 app.accept_fuzzy_history_search();
                 app.try_submit_current_buffer();
             }
+            KeyEventAction::FuzzyHistoryCancel => {
+                if let Some(original) = app.buffer_before_fuzzy_search.take()
+                    && !matches!(
+                        app.content_mode,
+                        ContentMode::FuzzyHistorySearch(FuzzyHistorySource::AgentPrompts)
+                    )
+                {
+                    app.buffer.replace_buffer(&original);
+                }
+                KeyEventAction::EscapeToNormalMode.run(app, key);
+            }
             KeyEventAction::RunAgentMode => {
                 if let Some((agent_cmd, buffer)) = app.resolve_agent_command(false) {
                     app.start_agent_mode(agent_cmd, &buffer);
@@ -505,12 +578,14 @@ impl KeyEventAction {
                 app.try_submit_current_buffer();
             }
             KeyEventAction::RunFuzzyHistorySearch => {
+                app.buffer_before_fuzzy_search = Some(app.buffer.buffer().to_string());
                 app.history_manager
                     .warm_fuzzy_search_cache(app.buffer.buffer(), Some(0));
                 app.content_mode =
                     ContentMode::FuzzyHistorySearch(FuzzyHistorySource::PastCommands);
             }
             KeyEventAction::RunFuzzyCancelledHistorySearch => {
+                app.buffer_before_fuzzy_search = Some(app.buffer.buffer().to_string());
                 app.settings
                     .cancelled_command_history_manager
                     .warm_fuzzy_search_cache(app.buffer.buffer(), Some(0));
@@ -520,6 +595,9 @@ impl KeyEventAction {
             KeyEventAction::ClearScreen => {
                 app.needs_screen_cleared = true;
             }
+            KeyEventAction::ReloadCompletions => {
+                app.reload();
+            }
             KeyEventAction::DeleteLeftUntilStartOfLine => {
                 if app.buffer.delete_selection() {
                     return;
@@ -538,6 +616,18 @@ impl KeyEventAction {
                 }
                 app.buffer.delete_one_word_left(WordDelim::WhiteSpace);
             }
+            KeyEventAction::DeleteLeftWhitespaceWord => {
+                if app.buffer.delete_selection() {
+                    return;
+                }
+                app.buffer.delete_whitespace_word_left();
+            }
+            KeyEventAction::DeleteLeftShellWord => {
+                if app.buffer.delete_selection() {
+                    return;
+                }
+                app.buffer.delete_shell_word_left();
+            }
             KeyEventAction::DeleteLeft => {
                 if app.buffer.delete_selection() {
                     return;
@@ -579,7 +669,7 @@ impl KeyEventAction {
             }
             KeyEventAction::MoveLeftOneWord => {
                 app.buffer.clear_selection();
-                app.buffer.move_one_word_left(WordDelim::WhiteSpace);
+                app.buffer.move_one_word_left(WordDelim::Alphanumeric);
             }
             KeyEventAction::MoveLeftOneWordPart => {
                 app.buffer.clear_selection();
@@ -594,7 +684,7 @@ impl KeyEventAction {
             }
             KeyEventAction::MoveRightOneWord => {
                 app.buffer.clear_selection();
-                app.buffer.move_one_word_right(WordDelim::WhiteSpace);
+                app.buffer.move_one_word_right(WordDelim::Alphanumeric);
             }
             KeyEventAction::MoveRightOneWordPart => {
                 app.buffer.clear_selection();
@@ -603,6 +693,14 @@ impl KeyEventAction {
             KeyEventAction::MoveRight => {
                 app.buffer.move_right();
             }
+            KeyEventAction::MoveToPrevCommand => {
+                app.buffer.clear_selection();
+                app.buffer.move_to_prev_command();
+            }
+            KeyEventAction::MoveToNextCommand => {
+                app.buffer.clear_selection();
+                app.buffer.move_to_next_command();
+            }
             KeyEventAction::MoveLineUp => {
                 app.buffer.clear_selection();
                 app.buffer.move_line_up();
@@ -613,8 +711,7 @@ impl KeyEventAction {
             }
             KeyEventAction::PrevHistoryEntry => {
                 app.buffer.clear_selection();
-                app.buffer_before_history_navigation
-                    .get_or_insert_with(|| app.buffer.buffer().to_string());
+                save_history_draft(&mut app.buffer_before_history_navigation, &app.buffer);
                 if let Some(entry) = app
                     .history_manager
                     .search_in_history(app.buffer.buffer(), HistorySearchDirection::Backward)
@@ -622,6 +719,16 @@ impl KeyEventAction {
                     app.buffer.replace_buffer(&entry.command);
                 }
             }
+            KeyEventAction::PrevHistoryEntryEditArgs => {
+                app.buffer.clear_selection();
+                save_history_draft(&mut app.buffer_before_history_navigation, &app.buffer);
+                if let Some(entry) = app
+                    .history_manager
+                    .search_in_history(app.buffer.buffer(), HistorySearchDirection::Backward)
+                {
+                    recall_with_cursor_on_first_arg(&mut app.buffer, &entry.command);
+                }
+            }
             KeyEventAction::NextHistoryEntry => {
                 app.buffer.clear_selection();
                 match app
@@ -632,8 +739,8 @@ impl KeyEventAction {
                         app.buffer.replace_buffer(&entry.command);
                     }
                     None => {
-                        if let Some(original_buffer) = app.buffer_before_history_navigation.take() {
-                            app.buffer.replace_buffer(&original_buffer);
+                        if let Some(draft) = app.buffer_before_history_navigation.take() {
+                            restore_history_draft(&mut app.buffer, draft);
                         }
                     }
                 }
@@ -646,6 +753,13 @@ impl KeyEventAction {
                 app.buffer.clear_selection();
                 app.buffer.redo();
             }
+            KeyEventAction::Yank => {
+                app.buffer.delete_selection();
+                app.buffer.yank();
+            }
+            KeyEventAction::YankPop => {
+                app.buffer.yank_pop();
+            }
             KeyEventAction::InsertChar => {
                 if let KeyCode::Char(c) = key.code {
                     // If a non-empty selection is active and the character is a
@@ -791,36 +905,11 @@ impl KeyEventAction {
                     crossterm::clipboard::RequestClipboardContents::clipboard()
                 );
             }
+            KeyEventAction::QuotedInsert => {
+                app.quoted_insert_pending = true;
+            }
             KeyEventAction::InsertLastWordFromPrevCommand => {
-                app.buffer.clear_selection();
-
-                // Get the last word of the history command we are currently looking at
-                let last_word_of_current_history_cmd = app
-                    .history_manager
-                    .get_last_word_insert_command()
-                    .and_then(|cmd| crate::history::get_last_word(cmd));
-
-                // Find if the last word of the current history command is touching the cursor
-                let target_sub = last_word_of_current_history_cmd
-                    .as_ref()
-                    .and_then(|last_word| app.buffer.is_cursor_on_s(last_word));
-
-                let is_continuation = target_sub.is_some();
-
-                if !is_continuation {
-                    app.history_manager.last_word_insert_reset();
-                }
-
-                // Move to the previous command with non-empty words
-                if let Some(cmd) = app.history_manager.last_word_insert_move_prev() {
-                    if let Some(w) = crate::history::get_last_word(cmd) {
-                        if let Some(sub) = &target_sub {
-                            let _ = app.buffer.replace_word_under_cursor(&w, sub);
-                        } else {
-                            app.buffer.insert_str(&w);
-                        }
-                    }
-                }
+                insert_last_word_from_prev_command(&mut app.buffer, &mut app.history_manager);
             }
             KeyEventAction::Nothing => {}
             KeyEventAction::StartPromptDirSelect => {
@@ -875,6 +964,41 @@ impl KeyEventAction {
                     *index = 0;
                 }
             }
+            KeyEventAction::RunCommandPalette => {
+                app.content_mode = ContentMode::CommandPalette(CommandPalette::new());
+            }
+            KeyEventAction::CommandPaletteSelectNext => {
+                if let ContentMode::CommandPalette(palette) = &mut app.content_mode {
+                    palette.move_down();
+                }
+            }
+            KeyEventAction::CommandPaletteSelectPrev => {
+                if let ContentMode::CommandPalette(palette) = &mut app.content_mode {
+                    palette.move_up();
+                }
+            }
+            KeyEventAction::CommandPaletteAcceptEntry => {
+                let command = match &app.content_mode {
+                    ContentMode::CommandPalette(palette) => palette.selected_command(),
+                    _ => return,
+                };
+                app.content_mode = ContentMode::Normal;
+                if let Some(command) = command {
+                    app.run_palette_command(command, key);
+                }
+            }
+            KeyEventAction::CommandPaletteInsertChar => {
+                if let ContentMode::CommandPalette(palette) = &mut app.content_mode
+                    && let KeyCode::Char(c) = key.code
+                {
+                    palette.insert_char(c);
+                }
+            }
+            KeyEventAction::CommandPaletteDeleteChar => {
+                if let ContentMode::CommandPalette(palette) = &mut app.content_mode {
+                    palette.delete_char();
+                }
+            }
             KeyEventAction::EscapeToNormalMode => {
                 // Capture the word-under-cursor when dismissing tab completion, so we don't
                 // auto-suggest on the same word the user just dismissed.
@@ -1463,6 +1587,80 @@ pub fn expand_variations_one(kem: KeyEventMatch) -> Vec<KeyEventMatch> {
     vec![kem]
 }
 
+/// Replace the buffer with a recalled `command` and put the cursor at the start
+/// of its first argument, or at the end if it has none.
+fn recall_with_cursor_on_first_arg(buffer: &mut TextBuffer, command: &str) {
+    buffer.replace_buffer(command);
+    if let Some(pos) = DParser::first_argument_byte_pos(command) {
+        buffer.try_move_cursor_to_byte_pos(pos, false);
+    }
+}
+
+/// Fill the buffer with the inline suggestion continuing it with `suffix`,
+/// leaving the cursor where the suggestion differs from similar history
+/// entries so it can be tweaked before running.
+fn accept_suggestion_for_edit(buffer: &mut TextBuffer, history: &HistoryManager, suffix: &str) {
+    let typed = buffer.buffer().to_string();
+    let pos = history.suggestion_divergence(&typed, suffix);
+    buffer.replace_buffer(&format!("{}{}", typed, suffix));
+    buffer.try_move_cursor_to_byte_pos(pos, false);
+}
+
+/// Remember the buffer and cursor being edited when history navigation starts.
+/// Later steps through history keep the first draft.
+fn save_history_draft(draft: &mut Option<(String, usize)>, buffer: &TextBuffer) {
+    draft.get_or_insert_with(|| (buffer.buffer().to_string(), buffer.cursor_byte_pos()));
+}
+
+/// Put back the draft saved by [`save_history_draft`] once navigation moves
+/// past the newest entry.
+fn restore_history_draft(buffer: &mut TextBuffer, (draft, cursor_byte): (String, usize)) {
+    buffer.replace_buffer(&draft);
+    buffer.try_move_cursor_to_byte_pos(cursor_byte, false);
+}
+
+/// Insert the last word of the previous command, like readline's Alt+.
+/// Pressing it again while the inserted word still touches the cursor
+/// replaces it with the last word of the command before that.
+fn insert_last_word_from_prev_command(buffer: &mut TextBuffer, history: &mut HistoryManager) {
+    buffer.clear_selection();
+
+    // Get the last word of the history command we are currently looking at
+    let last_word_of_current_history_cmd = history
+        .get_last_word_insert_command()
+        .and_then(crate::history::get_last_word);
+
+    // Find if the last word of the current history command is touching the cursor
+    let target_sub = last_word_of_current_history_cmd
+        .as_ref()
+        .and_then(|last_word| buffer.is_cursor_on_s(last_word));
+
+    if target_sub.is_none() {
+        history.last_word_insert_reset();
+    }
+
+    // Move to the previous command with non-empty words
+    if let Some(cmd) = history.last_word_insert_move_prev()
+        && let Some(w) = crate::history::get_last_word(cmd)
+    {
+        if let Some(sub) = &target_sub {
+            let _ = buffer.replace_word_under_cursor(&w, sub);
+        } else {
+            buffer.insert_str(&w);
+        }
+    }
+}
+
+/// The next word of an inline suggestion suffix, with the whitespace before
+/// it, e.g. `" commit"` from `" commit -m"`.
+fn next_suggestion_word(suffix: &str) -> &str {
+    let word_start = suffix.len() - suffix.trim_start().len();
+    let word_end = suffix[word_start..]
+        .find(char::is_whitespace)
+        .map_or(suffix.len(), |len| word_start + len);
+    &suffix[..word_end]
+}
+
 /// Expand a list of [`KeyEventMatch`] values to include their common terminal
 /// equivalents.
 ///
@@ -1712,6 +1910,50 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
     use KeyCode as KC;
     use KeyModifiers as M;
     vec![
+        // --- CommandPalette bindings ---
+        // The palette captures all typed characters, so its bindings must
+        // appear before every other binding.
+        Binding::new(
+            &[KC::Esc.into(), M::CONTROL + KC::Char('c').into()],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::EscapeToNormalMode,
+        ),
+        Binding::new(
+            &expand_variations![KC::Enter.into()],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::CommandPaletteAcceptEntry,
+        ),
+        Binding::new(
+            &[
+                KC::Down.into(),
+                KC::Tab.into(),
+                M::CONTROL + KC::Char('n').into(),
+            ],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::CommandPaletteSelectNext,
+        ),
+        Binding::new(
+            &expand_variations![
+                KC::Up.into(),
+                KC::BackTab.into(),
+                M::CONTROL + KC::Char('p').into(),
+            ],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::CommandPaletteSelectPrev,
+        ),
+        Binding::new(
+            &[KC::Backspace.into()],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::CommandPaletteDeleteChar,
+        ),
+        Binding::new(
+            &[
+                KeyEventMatch::AnyCharAndMods(M::empty()),
+                KeyEventMatch::AnyCharAndMods(M::SHIFT),
+            ],
+            ContextVar::CommandPalette.into(),
+            KeyEventAction::CommandPaletteInsertChar,
+        ),
         // --- TabCompletionAskForFlycomp bindings ---
         Binding::new(
             &expand_variations![
@@ -1857,6 +2099,17 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::FuzzyHistorySearch.into(),
             KeyEventAction::FuzzyHistoryScrollPageDown,
         ),
+        // Pressing the key that opened the search again steps to the next match.
+        Binding::new(
+            &[M::CONTROL + KC::Char('r').into()],
+            ContextVar::FuzzyHistorySearchNormalCommands.into(),
+            KeyEventAction::FuzzyHistorySelectNext,
+        ),
+        Binding::new(
+            &expand_variations![M::ALT + KC::Char('r').into(),],
+            ContextVar::FuzzyHistorySearchCancelledCommands.into(),
+            KeyEventAction::FuzzyHistorySelectNext,
+        ),
         Binding::new(
             &expand_variations![
                 M::CONTROL + KC::Char('r').into(),
@@ -1921,6 +2174,11 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::PromptDirSelection.into(),
             KeyEventAction::PromptDirAcceptEntry,
         ),
+        Binding::new(
+            &expand_variations![KC::Enter.into()],
+            ContextVar::PastedCompleteCommands.into(),
+            KeyEventAction::SubmitOrNewline,
+        ),
         Binding::new(
             &expand_variations![KC::Enter.into()],
             ContextVar::MultilineBuffer + ContextVar::CursorAtEndTrimmed,
@@ -2005,7 +2263,7 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
         Binding::new(
             &[KC::Esc.into()],
             ContextVar::FuzzyHistorySearch.into(),
-            KeyEventAction::EscapeToNormalMode,
+            KeyEventAction::FuzzyHistoryCancel,
         ),
         Binding::new(
             &[KC::Esc.into()],
@@ -2096,6 +2354,12 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::PasteSystemClipboard,
         ),
+        // Insert the next key literally on Ctrl+q (Ctrl+v pastes instead)
+        Binding::new(
+            &[M::CONTROL + KC::Char('q').into()],
+            ContextVar::Always.into(),
+            KeyEventAction::QuotedInsert,
+        ),
         // Insert last word from previous history command on Alt+.
         Binding::new(
             &expand_variations![M::ALT + KC::Char('.').into(),],
@@ -2123,11 +2387,31 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::RunFuzzyCancelledHistorySearch,
         ),
+        // Ctrl+Alt+L must appear before the Ctrl+L binding.
+        Binding::new(
+            &[
+                (M::CONTROL | M::ALT) + KC::Char('l').into(),
+                (M::CONTROL | M::META) + KC::Char('l').into(),
+            ],
+            ContextVar::Always.into(),
+            KeyEventAction::ReloadCompletions,
+        ),
         Binding::new(
             &[M::CONTROL + KC::Char('l').into()],
             ContextVar::Always.into(),
             KeyEventAction::ClearScreen,
         ),
+        // Ctrl+Shift+P is only reported by terminals with keyboard
+        // enhancement; Alt+x works everywhere.
+        Binding::new(
+            &[
+                (M::CONTROL | M::SHIFT) + KC::Char('p').into(),
+                M::ALT + KC::Char('x').into(),
+                M::META + KC::Char('x').into(),
+            ],
+            ContextVar::Always.into(),
+            KeyEventAction::RunCommandPalette,
+        ),
         Binding::new(
             &[
                 M::SUPER + KC::Backspace.into(),
@@ -2140,17 +2424,23 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
         Binding::new(
             &expand_variations![M::ALT + KC::Backspace.into()],
             ContextVar::Always.into(),
-            KeyEventAction::DeleteLeftOneWordPart,
+            KeyEventAction::DeleteLeftShellWord,
         ),
         Binding::new(
             &expand_variations![
                 M::CONTROL + KC::Backspace.into(),
                 M::CONTROL + KC::Char('h').into(),
+            ],
+            ContextVar::Always.into(),
+            KeyEventAction::DeleteLeftOneWord,
+        ),
+        Binding::new(
+            &expand_variations![
                 M::ALT + KC::Char('w').into(),
                 M::CONTROL + KC::Char('w').into(),
             ],
             ContextVar::Always.into(),
-            KeyEventAction::DeleteLeftOneWord,
+            KeyEventAction::DeleteLeftWhitespaceWord,
         ),
         Binding::new(
             &[KC::Backspace.into()],
@@ -2233,7 +2523,7 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             KeyEventAction::MoveLeftOneWordExtendSelection,
         ),
         Binding::new(
-            &[M::CONTROL + KC::Left.into()], // Emacs-style whitespace word-left
+            &[M::CONTROL + KC::Left.into()], // readline-style word-left
             ContextVar::Always.into(),
             KeyEventAction::MoveLeftOneWord,
         ),
@@ -2272,6 +2562,19 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::MoveLeft,
         ),
+        Binding::new(
+            &[M::CONTROL + KC::Char('o').into()],
+            ContextVar::InlineSuggestionAvailable.into(),
+            KeyEventAction::InlineSuggestionAcceptAndEdit,
+        ),
+        Binding::new(
+            &expand_variations![M::ALT + KC::Right.into(), M::CONTROL + KC::Right.into()],
+            (ContextVar::InlineSuggestionAvailable
+                + ContextVar::CursorAtEnd
+                + !ContextVar::TabCompletionMultiColAvailable)
+                .into(),
+            KeyEventAction::InlineSuggestionAcceptWord,
+        ),
         Binding::new(
             &expand_variations![KC::Right.into(), KC::End.into()],
             (ContextVar::InlineSuggestionAvailable
@@ -2304,7 +2607,7 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             KeyEventAction::MoveRightOneWordExtendSelection,
         ),
         Binding::new(
-            &[M::CONTROL + KC::Right.into()], // Emacs-style whitespace word-right
+            &[M::CONTROL + KC::Right.into()], // readline-style word-right
             ContextVar::Always.into(),
             KeyEventAction::MoveRightOneWord,
         ),
@@ -2322,6 +2625,18 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::MoveRightOneWordPart,
         ),
+        // Not Alt+[ / Alt+]: terminals send `ESC [` and `ESC ]` as the CSI and
+        // OSC introducers.
+        Binding::new(
+            &expand_variations![M::ALT + KC::Char('p').into()],
+            ContextVar::Always.into(),
+            KeyEventAction::MoveToPrevCommand,
+        ),
+        Binding::new(
+            &expand_variations![M::ALT + KC::Char('n').into()],
+            ContextVar::Always.into(),
+            KeyEventAction::MoveToNextCommand,
+        ),
         // PromptCwdEdit Right must appear before the Normal Right binding.
         Binding::new(
             &[KC::Right.into()],
@@ -2338,6 +2653,12 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::MoveRight,
         ),
+        // Alt+Up must appear before the plain Up bindings.
+        Binding::new(
+            &expand_variations![M::ALT + KC::Up.into()],
+            ContextVar::Always.into(),
+            KeyEventAction::PrevHistoryEntryEditArgs,
+        ),
         Binding::new(
             &[M::SHIFT + KC::Up.into()],
             ContextVar::Always.into(),
@@ -2368,9 +2689,18 @@ pub static DEFAULT_BINDINGS: LazyLock<Vec<Binding>> = LazyLock::new(|| {
             ContextVar::Always.into(),
             KeyEventAction::NextHistoryEntry,
         ),
+        Binding::new(
+            &[M::CONTROL + KC::Char('y').into()],
+            ContextVar::Always.into(),
+            KeyEventAction::Yank,
+        ),
+        Binding::new(
+            &expand_variations![M::ALT + KC::Char('y').into()],
+            ContextVar::Always.into(),
+            KeyEventAction::YankPop,
+        ),
         Binding::new(
             &[
-                M::CONTROL + KC::Char('y').into(),
                 M::SUPER + KC::Char('y').into(),
                 (M::CONTROL | M::SHIFT) + KC::Char('z').into(),
                 (M::SUPER | M::SHIFT) + KC::Char('z').into(),
@@ -2681,6 +3011,65 @@ fn detect_binding_conflicts(user_bindings: &[Binding], remappings: &[KeyRemap])
     conflicts
 }
 
+/// Find the highest-priority binding for `key`: user bindings (most recently
+/// added first), then [`DEFAULT_BINDINGS`].
+fn resolve_binding<'a>(
+    user_bindings: &'a [Binding],
+    key: KeyEvent,
+    context_values: &ContextValues,
+) -> Option<&'a Binding> {
+    user_bindings
+        .iter()
+        .rev()
+        .chain(DEFAULT_BINDINGS.iter())
+        .find(|binding| binding.context.evaluate(context_values) && binding.matches(key))
+}
+
+/// Whether one of the user's own bindings (not a default) handles `key`.
+fn user_binding_matches(
+    user_bindings: &[Binding],
+    key: KeyEvent,
+    context_values: &ContextValues,
+) -> bool {
+    user_bindings
+        .iter()
+        .any(|binding| binding.context.evaluate(context_values) && binding.matches(key))
+}
+
+// ── Keymap file ──────────────────────────────────────────────────────
+
+/// User bindings from the keymap file named by `$FLYLINE_KEYMAP`, else
+/// `~/.config/flyline/keymap.toml` if it exists. Bindings added later with
+/// `flyline key bind` take priority over these.
+pub fn load_keymap_file() -> Vec<Binding> {
+    keymap_bindings(&crate::config_file::load(
+        "FLYLINE_KEYMAP",
+        "keymap.toml",
+        "keymap",
+    ))
+}
+
+/// Bindings from the entries of a keymap file, each of the same form as
+/// `flyline key bind`, e.g.
+///
+/// ```toml
+/// "Ctrl+Enter" = "always=submitOrNewline"
+/// "Alt+Left" = "always=moveLeftOneWordPart"
+/// ```
+///
+/// Later entries take priority over earlier ones. Invalid entries are logged
+/// and skipped.
+fn keymap_bindings(entries: &[(String, String)]) -> Vec<Binding> {
+    entries
+        .iter()
+        .filter_map(|(key, context_and_action)| {
+            Binding::try_new_from_strs(key, context_and_action)
+                .inspect_err(|e| log::warn!("Keymap file entry {:?}: {}", key, e))
+                .ok()
+        })
+        .collect()
+}
+
 /// Print all keybindings as a formatted table to stdout, ordered from lowest
 /// to highest priority.  User-defined bindings appear above the defaults and
 /// are marked with `*` in the rightmost column.
@@ -2819,6 +3208,41 @@ pub fn print_bindings_table(
     }
 }
 
+/// The raw text a key stands for when inserted literally after quoted-insert.
+/// Ctrl+letter gives the matching control character, as in a terminal.
+fn literal_key_text(key: KeyEvent) -> Option<String> {
+    let c = match key.code {
+        KeyCode::Tab => '\t',
+        KeyCode::Enter => '\n',
+        KeyCode::Esc => '\x1b',
+        KeyCode::Backspace => '\x7f',
+        KeyCode::Char(c)
+            if key.modifiers.contains(KeyModifiers::CONTROL)
+                && matches!(c.to_ascii_uppercase(), '@'..='_') =>
+        {
+            (c.to_ascii_uppercase() as u8 ^ 0x40) as char
+        }
+        KeyCode::Char(c) => c,
+        _ => return None,
+    };
+    Some(c.to_string())
+}
+
+/// If quoted-insert is `pending`, insert `key` literally into `buffer` and
+/// clear it. Returns whether the key was consumed, bypassing the bindings
+/// entirely so a literal Tab doesn't complete and a literal Enter doesn't
+/// submit.
+fn insert_quoted_key(pending: &mut bool, key: KeyEvent, buffer: &mut TextBuffer) -> bool {
+    if !std::mem::take(pending) {
+        return false;
+    }
+    match literal_key_text(key) {
+        Some(text) => buffer.insert_str(&text),
+        None => log::debug!("Quoted insert of {:?} inserts nothing", key),
+    }
+    true
+}
+
 impl<'a> App<'a> {
     pub fn handle_key_event(&mut self, key: KeyEvent) {
         let _timer = crate::perf::PerfTimer::start("handle_key_event");
@@ -2826,9 +3250,48 @@ impl<'a> App<'a> {
         self.right_click_popup_pos = None;
         self.right_click_copy_target = None;
 
-        let key = apply_remappings(key, &self.settings.key_remappings);
+        let mut key = apply_remappings(key, &self.settings.key_remappings);
         log::trace!("Key event after remapping: {:?}", key);
 
+        if insert_quoted_key(&mut self.quoted_insert_pending, key, &mut self.buffer) {
+            self.on_possible_buffer_change();
+            return;
+        }
+
+        // In vi mode, keys that edit the command buffer go through the modal
+        // translation first; menus and searches keep their own bindings, and
+        // a user's own Esc binding wins over leaving Insert mode.
+        if matches!(self.content_mode, ContentMode::Normal)
+            && self.vi_mode.is_some()
+            && !(key.code == KeyCode::Esc
+                && user_binding_matches(
+                    &self.settings.keybindings,
+                    key,
+                    &ContextValues::evaluate(self),
+                ))
+            && let Some(vi) = self.vi_mode.as_mut()
+        {
+            match vi.translate(key) {
+                crate::vi::ViKey::Command(command) => {
+                    vi.apply(command, &mut self.buffer);
+                    self.on_possible_buffer_change();
+                    return;
+                }
+                crate::vi::ViKey::Consumed => return,
+                crate::vi::ViKey::PassThrough => {}
+                crate::vi::ViKey::Key(code) => key = KeyEvent::new(code, KeyModifiers::NONE),
+            }
+        }
+
+        // Abbreviations expand before Space or Enter does anything else, so
+        // the inserted space follows the expansion and Enter submits it.
+        if matches!(self.content_mode, ContentMode::Normal)
+            && (key.modifiers - KeyModifiers::SHIFT).is_empty()
+            && matches!(key.code, KeyCode::Char(' ') | KeyCode::Enter)
+        {
+            crate::abbreviations::expand_at_cursor(&self.settings.abbreviations, &mut self.buffer);
+        }
+
         // Evaluate every context variable once up front, so each variable's
         // condition runs at most once per key press regardless of how many
         // bindings reference it.
@@ -2838,19 +3301,9 @@ impl<'a> App<'a> {
         // whose key matches.  We extract the action (Copy) before running it
         // so that running the action does not overlap with the immutable
         // borrow of `self.settings.keybindings`.
-        let mut matched: Option<(KeyEventAction, String)> = None;
-        for binding in self
-            .settings
-            .keybindings
-            .iter()
-            .rev()
-            .chain(DEFAULT_BINDINGS.iter())
-        {
-            if binding.context.evaluate(&context_values) && binding.matches(key) {
-                matched = Some((binding.action, binding.context.display()));
-                break;
-            }
-        }
+        let matched: Option<(KeyEventAction, String)> =
+            resolve_binding(&self.settings.keybindings, key, &context_values)
+                .map(|binding| (binding.action, binding.context.display()));
 
         let (context_debug, action_enum) = match matched.as_ref() {
             Some((action, context)) => (context.clone(), *action),
@@ -3455,6 +3908,357 @@ mod tests {
             assert!(!a.description().is_empty());
         }
     }
+
+    #[test]
+    fn test_recall_with_cursor_on_first_arg() {
+        let mut buffer = TextBuffer::new("git st");
+        recall_with_cursor_on_first_arg(&mut buffer, "grep -rn needle src");
+        assert_eq!(buffer.buffer(), "grep -rn needle src");
+        assert_eq!(buffer.cursor_byte_pos(), "grep ".len());
+
+        // No arguments: the cursor stays at the end of the recalled line.
+        recall_with_cursor_on_first_arg(&mut buffer, "make");
+        assert_eq!(buffer.cursor_byte_pos(), "make".len());
+    }
+
+    #[test]
+    fn test_accept_suggestion_for_edit() {
+        let mut history = crate::history::HistoryManager::new_empty();
+        history.push_entry("git push origin dev".to_string());
+        history.push_entry("git push origin main".to_string());
+
+        let mut buffer = TextBuffer::new("git p");
+        accept_suggestion_for_edit(&mut buffer, &history, "ush origin main");
+        assert_eq!(buffer.buffer(), "git push origin main");
+        assert_eq!(buffer.cursor_byte_pos(), "git push origin ".len());
+    }
+
+    #[test]
+    fn test_history_navigation_restores_draft_and_cursor() {
+        let mut history = crate::history::HistoryManager::new_empty();
+        history.push_entry("ls -la".to_string());
+        history.push_entry("cargo build".to_string());
+
+        let mut buffer = TextBuffer::new("git st");
+        buffer.try_move_cursor_to_byte_pos(2, false);
+        let mut draft = None;
+
+        // Up: the draft is saved and replaced by the newest entry.
+        save_history_draft(&mut draft, &buffer);
+        let entry = history
+            .search_in_history("", HistorySearchDirection::Backward)
+            .unwrap();
+        buffer.replace_buffer(&entry.command);
+        assert_eq!(buffer.buffer(), "cargo build");
+
+        // A second Up keeps the original draft.
+        save_history_draft(&mut draft, &buffer);
+        assert_eq!(draft, Some(("git st".to_string(), 2)));
+
+        // Down past the newest entry restores the draft and its cursor.
+        assert!(
+            history
+                .search_in_history(buffer.buffer(), HistorySearchDirection::Forward)
+                .is_none()
+        );
+        restore_history_draft(&mut buffer, draft.take().unwrap());
+        assert_eq!(buffer.buffer(), "git st");
+        assert_eq!(buffer.cursor_byte_pos(), 2);
+    }
+
+    #[test]
+    fn test_accept_suggestion_word_by_word() {
+        let mut buffer = TextBuffer::new("git");
+        let entry = "git commit -m";
+        let mut inserted = vec![];
+        while let Some(suffix) = entry.strip_prefix(buffer.buffer())
+            && !suffix.is_empty()
+        {
+            let word = next_suggestion_word(suffix);
+            inserted.push(word.to_string());
+            buffer.insert_str(word);
+        }
+        assert_eq!(inserted, vec![" commit", " -m"]);
+        assert_eq!(buffer.buffer(), entry);
+
+        assert_eq!(next_suggestion_word("status"), "status");
+        assert_eq!(next_suggestion_word("  a  b"), "  a");
+        assert_eq!(next_suggestion_word(""), "");
+
+        let alt_right = key_with_mods(KeyCode::Right, KeyModifiers::ALT);
+        assert_eq!(
+            default_action_for(
+                alt_right,
+                &[
+                    ContextVar::Always,
+                    ContextVar::InlineSuggestionAvailable,
+                    ContextVar::CursorAtEnd
+                ]
+            ),
+            Some(KeyEventAction::InlineSuggestionAcceptWord)
+        );
+        assert_ne!(
+            default_action_for(alt_right, &[ContextVar::Always]),
+            Some(KeyEventAction::InlineSuggestionAcceptWord)
+        );
+    }
+
+    #[test]
+    fn test_literal_key_text() {
+        assert_eq!(literal_key_text(key(KeyCode::Tab)).as_deref(), Some("\t"));
+        assert_eq!(literal_key_text(key(KeyCode::Enter)).as_deref(), Some("\n"));
+        assert_eq!(
+            literal_key_text(key(KeyCode::Char('x'))).as_deref(),
+            Some("x")
+        );
+        assert_eq!(
+            literal_key_text(key_with_mods(KeyCode::Char('a'), KeyModifiers::CONTROL)).as_deref(),
+            Some("\x01")
+        );
+        assert_eq!(
+            literal_key_text(key_with_mods(KeyCode::Char('['), KeyModifiers::CONTROL)).as_deref(),
+            Some("\x1b")
+        );
+        assert_eq!(literal_key_text(key(KeyCode::Left)), None);
+    }
+
+    fn default_action_for(key: KeyEvent, true_vars: &[ContextVar]) -> Option<KeyEventAction> {
+        let values = ContextValues::from_true_vars(true_vars);
+        DEFAULT_BINDINGS
+            .iter()
+            .find(|b| b.context.evaluate(&values) && b.matches(key))
+            .map(|b| b.action)
+    }
+
+    #[test]
+    fn test_alt_dot_cycles_through_previous_last_words() {
+        let mut history = HistoryManager::new_empty();
+        for command in ["ls /tmp", "echo \"a b\"", "git commit -m 'fix it'"] {
+            history.push_entry(command.to_string());
+        }
+        let mut buffer = TextBuffer::new("cat ");
+
+        insert_last_word_from_prev_command(&mut buffer, &mut history);
+        assert_eq!(buffer.buffer(), "cat 'fix it'");
+        insert_last_word_from_prev_command(&mut buffer, &mut history);
+        assert_eq!(buffer.buffer(), "cat \"a b\"");
+        insert_last_word_from_prev_command(&mut buffer, &mut history);
+        assert_eq!(buffer.buffer(), "cat /tmp");
+        // Past the oldest entry the last insertion stays.
+        insert_last_word_from_prev_command(&mut buffer, &mut history);
+        assert_eq!(buffer.buffer(), "cat /tmp");
+
+        // Typing in between starts again from the most recent command.
+        buffer.insert_str(" ");
+        insert_last_word_from_prev_command(&mut buffer, &mut history);
+        assert_eq!(buffer.buffer(), "cat /tmp 'fix it'");
+    }
+
+    #[test]
+    fn test_ctrl_w_and_alt_backspace_use_bash_word_semantics() {
+        let ctrl_w = key_with_mods(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(
+            default_action_for(ctrl_w, &[ContextVar::Always]),
+            Some(KeyEventAction::DeleteLeftWhitespaceWord)
+        );
+        let alt_backspace = key_with_mods(KeyCode::Backspace, KeyModifiers::ALT);
+        assert_eq!(
+            default_action_for(alt_backspace, &[ContextVar::Always]),
+            Some(KeyEventAction::DeleteLeftShellWord)
+        );
+    }
+
+    #[test]
+    fn test_user_binding_matches_ignores_defaults() {
+        let esc = key(KeyCode::Esc);
+        let values = ContextValues::from_true_vars(&[ContextVar::Always]);
+        assert!(resolve_binding(&[], esc, &values).is_some());
+        assert!(!user_binding_matches(&[], esc, &values));
+
+        let user_bindings = keymap_bindings(&crate::config_file::parse(
+            r#"
+[bindings]
+"Esc" = "always=toggleMouse"
+"#,
+            "keymap",
+        ));
+        assert!(user_binding_matches(&user_bindings, esc, &values));
+        assert!(!user_binding_matches(
+            &user_bindings,
+            key(KeyCode::Tab),
+            &values
+        ));
+    }
+
+    #[test]
+    fn test_keymap_file_overrides_default_binding() {
+        let ctrl_enter = key_with_mods(KeyCode::Enter, KeyModifiers::CONTROL);
+        let values = ContextValues::from_true_vars(&[ContextVar::Always]);
+        let default = resolve_binding(&[], ctrl_enter, &values).map(|b| b.action);
+        assert_ne!(default, Some(KeyEventAction::ToggleMouse));
+
+        let user_bindings = keymap_bindings(&crate::config_file::parse(
+            r#"
+# my keymap
+[bindings]
+"Ctrl+Enter" = "always=toggleMouse"
+"Ctrl+Nope" = "always=toggleMouse"
+"Alt+Left" = "always=notAnAction"
+"#,
+            "keymap",
+        ));
+        assert_eq!(user_bindings.len(), 1);
+        assert_eq!(
+            resolve_binding(&user_bindings, ctrl_enter, &values).map(|b| b.action),
+            Some(KeyEventAction::ToggleMouse)
+        );
+        // Keys not in the keymap still use the defaults.
+        let ctrl_r = key_with_mods(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(
+            resolve_binding(&user_bindings, ctrl_r, &values).map(|b| b.action),
+            default_action_for(ctrl_r, &[ContextVar::Always])
+        );
+    }
+
+    #[test]
+    fn test_keymap_entries_keep_file_order() {
+        let bindings = keymap_bindings(&crate::config_file::parse(
+            r#"
+Tab = 'always=inlineSuggestionAccept'
+"Ctrl+Enter" = "always=toggleMouse"
+"#,
+            "keymap",
+        ));
+        let actions: Vec<KeyEventAction> = bindings.iter().map(|b| b.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                KeyEventAction::InlineSuggestionAccept,
+                KeyEventAction::ToggleMouse
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ctrl_r_steps_through_fuzzy_history_and_escape_cancels() {
+        let searching = [
+            ContextVar::Always,
+            ContextVar::FuzzyHistorySearch,
+            ContextVar::FuzzyHistorySearchNormalCommands,
+        ];
+        let ctrl_r = key_with_mods(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(
+            default_action_for(ctrl_r, &[ContextVar::Always]),
+            Some(KeyEventAction::RunFuzzyHistorySearch)
+        );
+        assert_eq!(
+            default_action_for(ctrl_r, &searching),
+            Some(KeyEventAction::FuzzyHistorySelectNext)
+        );
+        assert_eq!(
+            default_action_for(key(KeyCode::Esc), &searching),
+            Some(KeyEventAction::FuzzyHistoryCancel)
+        );
+    }
+
+    #[test]
+    fn test_ctrl_y_yanks_and_alt_y_rotates() {
+        let always = [ContextVar::Always];
+        assert_eq!(
+            default_action_for(
+                key_with_mods(KeyCode::Char('y'), KeyModifiers::CONTROL),
+                &always
+            ),
+            Some(KeyEventAction::Yank)
+        );
+        assert_eq!(
+            default_action_for(
+                key_with_mods(KeyCode::Char('y'), KeyModifiers::ALT),
+                &always
+            ),
+            Some(KeyEventAction::YankPop)
+        );
+        assert_eq!(
+            default_action_for(
+                key_with_mods(
+                    KeyCode::Char('z'),
+                    KeyModifiers::CONTROL | KeyModifiers::SHIFT
+                ),
+                &always
+            ),
+            Some(KeyEventAction::Redo)
+        );
+    }
+
+    #[test]
+    fn test_quoted_insert_consumes_the_next_key_literally() {
+        let mut pending = true;
+        let mut buffer = TextBuffer::new("ls");
+        assert!(insert_quoted_key(
+            &mut pending,
+            key(KeyCode::Tab),
+            &mut buffer
+        ));
+        assert_eq!(buffer.buffer(), "ls\t");
+        assert!(!pending);
+        // Only the one key: the next Tab goes to the bindings (completion).
+        assert!(!insert_quoted_key(
+            &mut pending,
+            key(KeyCode::Tab),
+            &mut buffer
+        ));
+        assert_eq!(buffer.buffer(), "ls\t");
+
+        // Enter is inserted instead of submitting.
+        pending = true;
+        assert!(insert_quoted_key(
+            &mut pending,
+            key(KeyCode::Enter),
+            &mut buffer
+        ));
+        assert_eq!(buffer.buffer(), "ls\t\n");
+
+        // A key with no literal text is still swallowed.
+        pending = true;
+        assert!(insert_quoted_key(
+            &mut pending,
+            key(KeyCode::Left),
+            &mut buffer
+        ));
+        assert_eq!(buffer.buffer(), "ls\t\n");
+        assert!(!pending);
+    }
+
+    #[test]
+    fn test_ctrl_q_is_bound_to_quoted_insert() {
+        let ctrl_q = key_with_mods(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert!(
+            DEFAULT_BINDINGS
+                .iter()
+                .any(|b| b.action == KeyEventAction::QuotedInsert && b.matches(ctrl_q))
+        );
+    }
+
+    #[test]
+    fn test_command_jumps_avoid_escape_sequence_introducers() {
+        let always = [ContextVar::Always];
+        let alt = |c| key_with_mods(KeyCode::Char(c), KeyModifiers::ALT);
+        assert_eq!(
+            default_action_for(alt('p'), &always),
+            Some(KeyEventAction::MoveToPrevCommand)
+        );
+        assert_eq!(
+            default_action_for(alt('n'), &always),
+            Some(KeyEventAction::MoveToNextCommand)
+        );
+        for c in ['[', ']'] {
+            assert!(
+                !DEFAULT_BINDINGS.iter().any(|b| b.matches(alt(c))),
+                "Alt+{} is bound",
+                c
+            );
+        }
+    }
 }
 
 #[derive(
@@ -3516,6 +4320,10 @@ pub(crate) enum ContextVar {
     TextSelected,
     #[strum(message = "The command buffer contains at least one newline")]
     MultilineBuffer,
+    #[strum(
+        message = "A multiline paste left only complete commands and no key has been pressed since"
+    )]
+    PastedCompleteCommands,
     #[strum(message = "The command buffer starts with an agent mode prefix")]
     BufferHasAgentModePrefix,
     #[strum(message = "The content mode is normal editing (no overlay is active)")]
@@ -3530,6 +4338,8 @@ pub(crate) enum ContextVar {
     FuzzyHistorySearchNoneSelected,
     #[strum(message = "Agent output selection is active and no suggestion is currently selected")]
     AgentOutputNoneSelected,
+    #[strum(message = "Command palette overlay is active")]
+    CommandPalette,
 }
 
 impl ContextVar {
@@ -3613,7 +4423,7 @@ impl ContextVar {
             ContextVar::AgentModeError => {
                 matches!(app.content_mode, ContentMode::AgentError { .. })
             }
-            ContextVar::InlineSuggestionAvailable => app.inline_history_suggestion.is_some(),
+            ContextVar::InlineSuggestionAvailable => app.inline_suggestion.is_some(),
             ContextVar::CursorAtEnd => app.buffer.is_cursor_at_end(),
             ContextVar::CursorAtEndTrimmed => app.buffer.is_cursor_at_trimmed_end(),
             ContextVar::CursorAtStart => app.buffer.is_cursor_at_start(),
@@ -3624,6 +4434,7 @@ impl ContextVar {
             }
             ContextVar::TextSelected => app.buffer.selection_range().is_some(),
             ContextVar::MultilineBuffer => app.buffer.buffer().contains('\n'),
+            ContextVar::PastedCompleteCommands => app.pasted_complete_commands,
             ContextVar::BufferHasAgentModePrefix => {
                 app.buffer_starts_with_agent_command_prefix().is_some()
             }
@@ -3662,6 +4473,9 @@ impl ContextVar {
                     false
                 }
             }
+            ContextVar::CommandPalette => {
+                matches!(app.content_mode, ContentMode::CommandPalette(_))
+            }
         }
     }
 }
@@ -33,6 +33,16 @@ where
         }
     }
 
+    /// Values with exactly `true_vars` set, for testing binding resolution
+    /// without an [`App`].
+    #[cfg(test)]
+    pub fn from_true_vars(true_vars: &[V]) -> Self {
+        Self {
+            values: V::VARIANTS.iter().map(|v| true_vars.contains(v)).collect(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
     fn index_of(var: V) -> usize {
         V::VARIANTS
             .iter()
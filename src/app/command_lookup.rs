@@ -0,0 +1,97 @@
+use crate::bash_funcs;
+use std::time::{Duration, Instant};
+
+/// How long a command word must stay unchanged before it is looked up.
+pub(crate) const COMMAND_LOOKUP_DEBOUNCE: Duration = Duration::from_millis(120);
+
+/// Debounces the `type` lookup of the command word being typed, so typing
+/// `git` does not call into bash for `g` and `gi` as well. Lookups run on the
+/// main thread under `BASH_LOCK`, since `type` redirects the process-wide
+/// stdout; results land in the [`bash_funcs::get_command_info`] cache and the
+/// buffer is re-formatted when one finishes.
+#[derive(Debug, Default)]
+pub(crate) struct CommandLookupDebouncer {
+    /// The word waiting to be looked up and when it was last requested.
+    pending: Option<(String, Instant)>,
+}
+
+impl CommandLookupDebouncer {
+    /// Note that `cmd` is not cached yet. Requesting a different word restarts
+    /// the debounce window; requesting the same word again does not.
+    pub(crate) fn request(&mut self, cmd: &str, now: Instant) {
+        if self.pending.as_ref().is_some_and(|(p, _)| p == cmd) {
+            return;
+        }
+        self.pending = Some((cmd.to_string(), now));
+    }
+
+    /// Take the pending word if it has been stable for [`COMMAND_LOOKUP_DEBOUNCE`].
+    fn take_due(&mut self, now: Instant) -> Option<String> {
+        let (_, requested_at) = self.pending.as_ref()?;
+        if now.saturating_duration_since(*requested_at) < COMMAND_LOOKUP_DEBOUNCE {
+            return None;
+        }
+        self.pending.take().map(|(cmd, _)| cmd)
+    }
+
+    /// Whether a lookup is waiting, so the event loop keeps ticking.
+    pub(crate) fn is_busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Look up a due word. Returns `true` when a lookup ran and the buffer
+    /// should be re-formatted.
+    pub(crate) fn poll(&mut self, now: Instant) -> bool {
+        let Some(cmd) = self.take_due(now) else {
+            return false;
+        };
+        log::trace!("Looking up command type for '{}'", cmd);
+        let _guard = crate::bash_symbols::BASH_LOCK.lock();
+        bash_funcs::get_command_info(&cmd);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rapid_edits_schedule_one_lookup() {
+        let mut debouncer = CommandLookupDebouncer::default();
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        debouncer.request("g", t0);
+        debouncer.request("gi", ms(50));
+        assert_eq!(debouncer.take_due(ms(100)), None);
+        assert_eq!(debouncer.take_due(ms(169)), None);
+        assert_eq!(debouncer.take_due(ms(170)), Some("gi".to_string()));
+        assert_eq!(debouncer.take_due(ms(500)), None);
+        assert!(!debouncer.is_busy());
+    }
+
+    #[test]
+    fn repeated_request_keeps_debounce_window() {
+        let mut debouncer = CommandLookupDebouncer::default();
+        let t0 = Instant::now();
+        debouncer.request("git", t0);
+        debouncer.request("git", t0 + Duration::from_millis(100));
+        assert_eq!(
+            debouncer.take_due(t0 + COMMAND_LOOKUP_DEBOUNCE),
+            Some("git".to_string())
+        );
+    }
+
+    #[test]
+    fn poll_looks_up_due_word() {
+        let mut debouncer = CommandLookupDebouncer::default();
+        let t0 = Instant::now();
+        debouncer.request("git", t0);
+        assert!(!debouncer.poll(t0 + Duration::from_millis(10)));
+        assert!(debouncer.is_busy());
+        assert!(debouncer.poll(t0 + COMMAND_LOOKUP_DEBOUNCE));
+        assert!(!debouncer.is_busy());
+        assert!(!debouncer.poll(t0 + COMMAND_LOOKUP_DEBOUNCE * 2));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::app::App;
+use crate::app::actions::KeyEventAction;
+use crate::content_utils::{FuzzyMatchThreshold, fuzzy_indices_with_threshold};
+use crossterm::event::KeyEvent;
+use skim::fuzzy_matcher::arinae::ArinaeMatcher;
+use strum::{EnumIter, EnumMessage, IntoEnumIterator};
+
+/// A flyline command that can be run from the command palette.  The strum
+/// `message` is the label shown in (and fuzzy-matched by) the palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumMessage)]
+pub(crate) enum PaletteCommand {
+    #[strum(message = "Search command history")]
+    SearchHistory,
+    #[strum(message = "Search cancelled command history")]
+    SearchCancelledHistory,
+    #[strum(message = "Toggle mouse capture")]
+    ToggleMouse,
+    #[strum(message = "Toggle auto-suggestions while typing")]
+    ToggleAutoSuggest,
+    #[strum(message = "Toggle inline history suggestions")]
+    ToggleInlineHistory,
+    #[strum(message = "Toggle auto-closing of brackets and quotes")]
+    ToggleAutoCloseChars,
+    #[strum(message = "Toggle vi mode")]
+    ToggleViMode,
+    #[strum(message = "Clear command history")]
+    ClearHistory,
+    #[strum(message = "Clear the screen")]
+    ClearScreen,
+    #[strum(message = "Reload settings and completions")]
+    ReloadCompletions,
+    #[strum(message = "Show flyline version")]
+    ShowVersion,
+}
+
+impl PaletteCommand {
+    pub(crate) fn label(&self) -> &'static str {
+        self.get_message().unwrap_or("")
+    }
+
+    /// Text shown in the palette after running the command, which keeps the
+    /// palette open to show it.
+    fn output(&self) -> Option<String> {
+        match self {
+            PaletteCommand::ShowVersion => Some(crate::cli::version_line()),
+            _ => None,
+        }
+    }
+}
+
+/// A palette entry that survived filtering, with the char indices of `label`
+/// matched by the query (for highlighting).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PaletteMatch {
+    pub command: PaletteCommand,
+    pub matching_indices: Vec<usize>,
+}
+
+/// State of the command palette overlay: the query typed so far, the
+/// commands matching it (best match first), the selected row, and the output
+/// of the last command run from it.
+#[derive(Debug, Clone)]
+pub(crate) struct CommandPalette {
+    query: String,
+    matches: Vec<PaletteMatch>,
+    selected_idx: usize,
+    output: Option<String>,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        let mut palette = CommandPalette {
+            query: String::new(),
+            matches: Vec::new(),
+            selected_idx: 0,
+            output: None,
+        };
+        palette.refilter();
+        palette
+    }
+
+    /// The palette to show after running `command`, if it has output.
+    fn after_running(command: PaletteCommand) -> Option<Self> {
+        let output = command.output()?;
+        Some(CommandPalette {
+            output: Some(output),
+            ..CommandPalette::new()
+        })
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    pub fn matches(&self) -> &[PaletteMatch] {
+        &self.matches
+    }
+
+    pub fn selected_idx(&self) -> usize {
+        self.selected_idx
+    }
+
+    pub fn selected_command(&self) -> Option<PaletteCommand> {
+        self.matches.get(self.selected_idx).map(|m| m.command)
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.query.push(c);
+        self.refilter();
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.query.pop().is_some() {
+            self.refilter();
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected_idx = if self.selected_idx > 0 {
+            self.selected_idx - 1
+        } else {
+            self.matches.len() - 1
+        };
+    }
+
+    pub fn move_down(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected_idx = (self.selected_idx + 1) % self.matches.len();
+    }
+
+    /// Recompute the matching commands for the current query and reset the
+    /// selection to the best match.
+    fn refilter(&mut self) {
+        self.output = None;
+        let matcher = ArinaeMatcher::new(skim::CaseMatching::Smart, true);
+        let mut scored: Vec<(i64, PaletteMatch)> = PaletteCommand::iter()
+            .filter_map(|command| {
+                if self.query.is_empty() {
+                    return Some((
+                        0,
+                        PaletteMatch {
+                            command,
+                            matching_indices: vec![],
+                        },
+                    ));
+                }
+                fuzzy_indices_with_threshold(
+                    &matcher,
+                    command.label(),
+                    &self.query,
+                    FuzzyMatchThreshold::Medium,
+                )
+                .map(|(score, matching_indices)| {
+                    (
+                        score,
+                        PaletteMatch {
+                            command,
+                            matching_indices,
+                        },
+                    )
+                })
+            })
+            .collect();
+        // Stable sort keeps declaration order among equal scores.
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        self.matches = scored.into_iter().map(|(_, m)| m).collect();
+        self.selected_idx = 0;
+    }
+}
+
+impl<'a> App<'a> {
+    pub(crate) fn run_palette_command(&mut self, command: PaletteCommand, key: KeyEvent) {
+        log::info!("Running command palette command: {:?}", command);
+        match command {
+            PaletteCommand::SearchHistory => KeyEventAction::RunFuzzyHistorySearch.run(self, key),
+            PaletteCommand::SearchCancelledHistory => {
+                KeyEventAction::RunFuzzyCancelledHistorySearch.run(self, key)
+            }
+            PaletteCommand::ToggleMouse => KeyEventAction::ToggleMouse.run(self, key),
+            PaletteCommand::ToggleAutoSuggest => {
+                self.settings.auto_suggest = !self.settings.auto_suggest;
+                log::info!("auto_suggest set to {}", self.settings.auto_suggest);
+            }
+            PaletteCommand::ToggleInlineHistory => {
+                self.settings.show_inline_history = !self.settings.show_inline_history;
+                log::info!(
+                    "show_inline_history set to {}",
+                    self.settings.show_inline_history
+                );
+            }
+            PaletteCommand::ToggleAutoCloseChars => {
+                self.settings.auto_close_chars = !self.settings.auto_close_chars;
+                log::info!("auto_close_chars set to {}", self.settings.auto_close_chars);
+            }
+            PaletteCommand::ToggleViMode => {
+                self.vi_mode = match self.vi_mode {
+                    Some(_) => None,
+                    None => Some(crate::vi::ViState::default()),
+                };
+                log::info!("vi mode set to {}", self.vi_mode.is_some());
+            }
+            PaletteCommand::ClearHistory => {
+                if let Err(e) = crate::bash_funcs::evaluate_shell_string("history -c") {
+                    log::error!("Failed to clear bash history: {}", e);
+                }
+                self.history_manager.clear();
+            }
+            PaletteCommand::ClearScreen => KeyEventAction::ClearScreen.run(self, key),
+            PaletteCommand::ReloadCompletions => self.reload(),
+            PaletteCommand::ShowVersion => {}
+        }
+        if let Some(palette) = CommandPalette::after_running(command) {
+            self.content_mode = crate::app::ContentMode::CommandPalette(palette);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(palette: &CommandPalette) -> Vec<PaletteCommand> {
+        palette.matches().iter().map(|m| m.command).collect()
+    }
+
+    #[test]
+    fn empty_query_lists_every_command_in_order() {
+        let palette = CommandPalette::new();
+        assert_eq!(
+            commands(&palette),
+            PaletteCommand::iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            palette.selected_command(),
+            Some(PaletteCommand::SearchHistory)
+        );
+    }
+
+    #[test]
+    fn typing_filters_and_resets_selection() {
+        let mut palette = CommandPalette::new();
+        palette.move_down();
+        palette.move_down();
+        for c in "version".chars() {
+            palette.insert_char(c);
+        }
+        assert_eq!(palette.query(), "version");
+        assert_eq!(palette.selected_idx(), 0);
+        assert_eq!(
+            palette.selected_command(),
+            Some(PaletteCommand::ShowVersion)
+        );
+        let m = &palette.matches()[0];
+        let highlighted: String = m
+            .command
+            .label()
+            .chars()
+            .enumerate()
+            .filter(|(i, _)| m.matching_indices.contains(i))
+            .map(|(_, c)| c)
+            .collect();
+        assert_eq!(highlighted, "version");
+    }
+
+    #[test]
+    fn vi_mode_and_clear_history_can_be_filtered_to() {
+        let select = |query: &str| {
+            let mut palette = CommandPalette::new();
+            for c in query.chars() {
+                palette.insert_char(c);
+            }
+            palette.selected_command()
+        };
+        assert_eq!(select("vi mode"), Some(PaletteCommand::ToggleViMode));
+        assert_eq!(select("clear hist"), Some(PaletteCommand::ClearHistory));
+    }
+
+    #[test]
+    fn no_match_has_no_selected_command() {
+        let mut palette = CommandPalette::new();
+        for c in "zzzzqqq".chars() {
+            palette.insert_char(c);
+        }
+        assert!(palette.matches().is_empty());
+        assert_eq!(palette.selected_command(), None);
+        palette.move_down();
+        palette.move_up();
+        assert_eq!(palette.selected_command(), None);
+
+        for _ in 0..7 {
+            palette.delete_char();
+        }
+        assert_eq!(palette.query(), "");
+        assert_eq!(palette.matches().len(), PaletteCommand::iter().count());
+        palette.delete_char();
+        assert_eq!(palette.query(), "");
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut palette = CommandPalette::new();
+        let last = PaletteCommand::iter().last();
+        palette.move_up();
+        assert_eq!(palette.selected_command(), last);
+        palette.move_down();
+        assert_eq!(
+            palette.selected_command(),
+            Some(PaletteCommand::SearchHistory)
+        );
+    }
+
+    #[test]
+    fn show_version_keeps_the_palette_open_with_the_version() {
+        let palette = CommandPalette::after_running(PaletteCommand::ShowVersion).unwrap();
+        let output = palette.output().unwrap();
+        assert!(output.starts_with("flyline version "));
+        assert!(output.contains(env!("CARGO_PKG_VERSION")));
+
+        // Typing a new query clears the output.
+        let mut palette = palette;
+        palette.insert_char('c');
+        assert_eq!(palette.output(), None);
+
+        assert!(CommandPalette::after_running(PaletteCommand::ClearScreen).is_none());
+        assert_eq!(CommandPalette::new().output(), None);
+    }
+
+    #[test]
+    fn every_command_has_a_label() {
+        for command in PaletteCommand::iter() {
+            assert!(!command.label().is_empty());
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use flash::lexer::TokenKind;
 use std::vec;
 
-use crate::snake_animation::SnakeAnimation;
+use crate::snake_animation::{AnimationKind, GlyphAnimation};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
@@ -11,10 +11,12 @@ use crate::dparser::{AnnotatedToken, ClosingAnnotation, ToInclusiveRange};
 use crate::palette::Palette;
 use itertools::{EitherOrBoth, Itertools};
 use ratatui::prelude::*;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex, OnceLock};
 
-// Store it globally so that the animation looks smooth between calls
-static SNAKE_ANIMATION: OnceLock<Mutex<SnakeAnimation>> = OnceLock::new();
+// Store them globally so that the animations look smooth between calls
+static COMMAND_WORD_ANIMATIONS: OnceLock<Mutex<HashMap<AnimationKind, Box<dyn GlyphAnimation>>>> =
+    OnceLock::new();
 
 #[derive(Debug)]
 pub struct FormattedBuffer {
@@ -23,12 +25,77 @@ pub struct FormattedBuffer {
 }
 
 impl FormattedBuffer {
+    /// Warn about `>` redirects that noclobber will refuse. Only the buffer
+    /// being edited gets these, not history entries or suggestions.
+    pub fn add_noclobber_tooltips(&mut self, noclobber: bool) {
+        add_noclobber_tooltips(&mut self.parts, noclobber);
+    }
+
+    /// Highlight trailing whitespace when `show` (`FLYLINE_SHOW_TRAILING_WS=1`)
+    /// is set. Like the noclobber tooltips, only the buffer being edited gets
+    /// this.
+    pub fn mark_trailing_whitespace(
+        &mut self,
+        show: bool,
+        cursor_byte_pos: usize,
+        palette: &Palette,
+    ) {
+        if show {
+            mark_trailing_whitespace(&mut self.parts, cursor_byte_pos, palette);
+        }
+    }
+
     pub fn get_part_from_byte_pos(&self, byte_pos: usize) -> Option<&FormattedBufferPart> {
         self.parts
             .iter()
             .find(|part| part.token.token.byte_range().contains(&byte_pos))
     }
 
+    /// Animate each command word that starts with one of the `triggers`
+    /// (e.g. `python`) with the configured animation. The longest matching
+    /// trigger wins.
+    pub fn apply_command_word_animations(&mut self, triggers: &HashMap<String, AnimationKind>) {
+        for part in self.parts.iter_mut() {
+            if part.token.annotations.command_word.is_none() {
+                continue;
+            }
+            let word = &part.token.token.value;
+            let Some(kind) = triggers
+                .iter()
+                .filter(|(trigger, _)| !trigger.is_empty() && word.starts_with(trigger.as_str()))
+                .max_by_key(|(trigger, _)| trigger.len())
+                .map(|(_, kind)| *kind)
+            else {
+                continue;
+            };
+
+            let normal_string = word.clone();
+            let style = part.span.style;
+            part.set_alternative_span(Arc::new(move |now| {
+                let mut animations = COMMAND_WORD_ANIMATIONS
+                    .get_or_init(|| Mutex::new(HashMap::new()))
+                    .lock()
+                    .unwrap();
+                let anim = animations
+                    .entry(kind)
+                    .or_insert_with(|| kind.new_animation());
+                anim.update_anim(now);
+                Span::styled(anim.apply_to_string(&normal_string), style)
+            }));
+        }
+    }
+
+    /// The highlighted buffer as text with ANSI SGR escape codes, for
+    /// consumers that do not draw with ratatui. Parts with an alternative
+    /// span are written as it currently looks.
+    pub fn to_ansi_string(&self) -> String {
+        let now = std::time::Instant::now();
+        self.parts
+            .iter()
+            .map(|part| crate::content_utils::span_to_ansi(&part.get_possible_animated_span(now)))
+            .collect()
+    }
+
     /// Create a `FormattedBuffer` from a raw string and cursor position. Only intended for use in tests.
     #[cfg(test)]
     pub fn from(input: &str, cursor_pos: usize, selection_byte: Option<usize>) -> Self {
@@ -149,6 +216,14 @@ fn token_to_style(
         return palette.opening_and_closing_pair();
     }
 
+    // An opening bracket or quote with no closing counterpart gets the error style.
+    // Unclosed keywords (`if`, `while`, ...) have no bracket depth and are left alone.
+    if token.annotations.opening == Some(crate::dparser::OpeningState::Unmatched)
+        && token.annotations.bracket_depth.is_some()
+    {
+        return palette.unmatched_bracket();
+    }
+
     // Rainbow-colour all opening and closing delimiter tokens by nesting depth.
     if let Some(depth) = token.annotations.bracket_depth {
         return palette.rainbow_bracket(depth);
@@ -165,10 +240,12 @@ fn token_to_style(
     }
 
     if token.annotations.command_word.is_some() {
-        if recognised_command == Some(true) {
-            return palette.recognised_command();
-        }
-        return palette.unrecognised_command();
+        return match recognised_command {
+            Some(true) => palette.recognised_command(),
+            Some(false) => palette.unrecognised_command(),
+            // Still being looked up.
+            None => palette.normal_text(),
+        };
     }
 
     if token.annotations.is_inside_single_quotes || token.token.kind == TokenKind::SingleQuote {
@@ -190,13 +267,25 @@ fn token_to_style(
     palette.normal_text()
 }
 
+/// How command words get their `type` information while formatting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandLookup {
+    /// Look the command up now (results are cached per word).
+    Blocking,
+    /// Only use cached results. Uncached command words are pending: they get
+    /// no tooltip and the normal text style until their debounced lookup
+    /// finishes.
+    CachedOnly,
+}
+
 #[derive(Debug)]
 struct WordInfo {
     pub tooltip: Option<String>,
-    pub is_recognised_command: bool,
+    /// `None` for a command word whose lookup is still pending.
+    pub is_recognised_command: Option<bool>,
 }
 
-fn get_word_info(token: &AnnotatedToken) -> Option<WordInfo> {
+fn get_word_info(token: &AnnotatedToken, command_lookup: CommandLookup) -> Option<WordInfo> {
     if token.annotations.is_env_var && token.token.kind.is_word() {
         let env_var_name = &token.token.value;
 
@@ -204,13 +293,24 @@ fn get_word_info(token: &AnnotatedToken) -> Option<WordInfo> {
 
         return Some(WordInfo {
             tooltip: Some(tooltip),
-            is_recognised_command: false,
+            is_recognised_command: Some(false),
         });
     } else if let Some(value) = &token.annotations.command_word {
-        let command_info = bash_funcs::get_command_info(value);
+        let command_info = match command_lookup {
+            CommandLookup::Blocking => bash_funcs::get_command_info(value),
+            CommandLookup::CachedOnly => match bash_funcs::cached_command_info(value) {
+                Some(info) => info,
+                None => {
+                    return Some(WordInfo {
+                        tooltip: None,
+                        is_recognised_command: None,
+                    });
+                }
+            },
+        };
         return Some(WordInfo {
             tooltip: Some(command_info.to_description()),
-            is_recognised_command: command_info.is_known(),
+            is_recognised_command: Some(command_info.is_known()),
         });
     }
     None
@@ -224,10 +324,13 @@ impl FormattedBufferPart {
         selection_byte_pos_in_token: Option<usize>,
         palette: &Palette,
         recognised_env_var: Option<bool>,
+        command_lookup: CommandLookup,
     ) -> Self {
-        let word_info = get_word_info(token);
+        let word_info = get_word_info(token, command_lookup);
         let tooltip = word_info.as_ref().and_then(|info| info.tooltip.clone());
-        let recognised_command = word_info.as_ref().map(|info| info.is_recognised_command);
+        let recognised_command = word_info
+            .as_ref()
+            .and_then(|info| info.is_recognised_command);
 
         let style = token_to_style(
             token,
@@ -255,29 +358,10 @@ impl FormattedBufferPart {
         let cursor_grapheme_idx = cursor_byte_pos_in_token.map(byte_pos_to_grapheme_idx);
         let selection_byte_grapheme_idx = selection_byte_pos_in_token.map(byte_pos_to_grapheme_idx);
 
-        let animated_span_fn: Option<
-            Arc<dyn Fn(std::time::Instant) -> Span<'static> + Send + Sync>,
-        > = if token.annotations.command_word.is_some() && token.token.value.starts_with("python") {
-            let normal_string = token.token.value.clone();
-            let recognised_style = palette.recognised_command();
-
-            Some(Arc::new(move |now| {
-                let mut anim = SNAKE_ANIMATION
-                    .get_or_init(|| Mutex::new(SnakeAnimation::new()))
-                    .lock()
-                    .unwrap();
-                anim.update_anim(now);
-                let snake_str = anim.apply_to_string(&normal_string);
-                Span::styled(snake_str, recognised_style)
-            }))
-        } else {
-            None
-        };
-
         Self {
             token: token.clone(),
             span,
-            animated_span_fn,
+            animated_span_fn: None,
             cursor_grapheme_idx,
             selection_byte_grapheme_idx,
             tooltip,
@@ -288,6 +372,19 @@ impl FormattedBufferPart {
         &self.span
     }
 
+    /// Draw the span returned by `alternative` instead of the normal span
+    /// while animating. It must keep the normal span's graphemes.
+    pub fn set_alternative_span(
+        &mut self,
+        alternative: Arc<dyn Fn(std::time::Instant) -> Span<'static> + Send + Sync>,
+    ) {
+        self.animated_span_fn = Some(alternative);
+    }
+
+    pub fn has_alternative_span(&self) -> bool {
+        self.animated_span_fn.is_some()
+    }
+
     pub fn get_possible_animated_span(&self, now: std::time::Instant) -> Span<'static> {
         if let Some(anim_fn) = &self.animated_span_fn {
             let anim_span = anim_fn(now);
@@ -405,6 +502,285 @@ pub fn format_buffer(
     buffer_byte_length: usize,
     app_is_running: bool,
     palette: &Palette,
+) -> FormattedBuffer {
+    format_buffer_with_lookup(
+        annotated_tokens,
+        cursor_byte_pos,
+        selection_byte_pos,
+        buffer_byte_length,
+        app_is_running,
+        palette,
+        CommandLookup::Blocking,
+    )
+}
+
+/// `input` highlighted with `palette` as ANSI text, styled as in the history
+/// previews. Used by `flyline highlight`.
+pub fn highlight_to_ansi(input: &str, palette: &Palette) -> String {
+    let tokens = crate::dparser::DParser::parse_and_annotate(input);
+    format_buffer(&tokens, input.len(), None, input.len(), false, palette).to_ansi_string()
+}
+
+/// Heredoc delimiters that name the language of the body. Bodies under one of
+/// these are styled with [`Palette::heredoc_body`] rather than bash rules.
+const HEREDOC_LANGUAGE_HINTS: &[&str] = &[
+    "SQL", "JSON", "PY", "PYTHON", "JS", "YAML", "YML", "TOML", "HTML", "XML", "CSS",
+];
+
+/// For each token, whether it sits in the body of a heredoc whose delimiter is
+/// a known language hint. An unterminated heredoc's body runs to the end of
+/// the buffer.
+fn heredoc_language_body_mask(annotated_tokens: &[AnnotatedToken]) -> Vec<bool> {
+    let mut mask = vec![false; annotated_tokens.len()];
+    // Bodies of heredocs opened on the same line follow one another.
+    let mut previous_body_end = 0;
+    for (idx, tok) in annotated_tokens.iter().enumerate() {
+        let delimiter = match &tok.token.kind {
+            TokenKind::HereDoc { delimiter, .. } | TokenKind::HereDocDash { delimiter, .. } => {
+                delimiter
+            }
+            _ => continue,
+        };
+        let body_end = match tok.annotations.opening {
+            Some(crate::dparser::OpeningState::Matched(closing_idx)) => closing_idx,
+            _ => annotated_tokens.len(),
+        };
+        let Some(newline_idx) = annotated_tokens[idx..]
+            .iter()
+            .position(|t| t.token.kind == TokenKind::Newline)
+            .map(|offset| idx + offset)
+        else {
+            continue;
+        };
+        let body_start = (newline_idx + 1).max(previous_body_end);
+        previous_body_end = body_end;
+
+        if HEREDOC_LANGUAGE_HINTS
+            .iter()
+            .any(|hint| hint.eq_ignore_ascii_case(delimiter))
+            && body_start < body_end
+        {
+            mask[body_start..body_end].fill(true);
+        }
+    }
+    mask
+}
+
+/// Where the scan in [`first_syntax_error`] is within the current command list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandListState {
+    /// A command is required next, e.g. at the start of the buffer or after
+    /// `if`, `then` or `&&`.
+    Start,
+    /// After a `;`, `&` or newline that ended a command: keywords such as
+    /// `then` or `fi` may follow, but another operator may not.
+    AfterSeparator,
+    InCommand,
+}
+
+/// Show the value of each closed `$(( ... ))` as the tooltip of its tokens.
+/// Inner expansions are handled first so their tokens show their own value,
+/// and their value is substituted into the outer expression. Expressions that
+/// fail to evaluate, e.g. because they use variables, get no tooltip.
+fn add_arithmetic_tooltips(parts: &mut [FormattedBufferPart]) {
+    // Opening index -> (closing index, value) of each evaluated expansion.
+    let mut values: HashMap<usize, (usize, i64)> = HashMap::new();
+    for open_idx in (0..parts.len()).rev() {
+        let token = &parts[open_idx].token;
+        if token.token.kind != TokenKind::ArithSubst {
+            continue;
+        }
+        let Some(crate::dparser::OpeningState::Matched(close_idx)) = token.annotations.opening
+        else {
+            continue;
+        };
+        if close_idx >= parts.len() {
+            continue;
+        }
+        let mut expr = String::new();
+        let mut idx = open_idx + 1;
+        while idx < close_idx {
+            if let Some(&(inner_close, inner_value)) = values.get(&idx) {
+                expr.push_str(&format!("({})", inner_value));
+                idx = inner_close + 1;
+            } else {
+                expr.push_str(&parts[idx].token.token.value);
+                idx += 1;
+            }
+        }
+        let Some(value) = crate::arith::evaluate(&expr) else {
+            continue;
+        };
+        values.insert(open_idx, (close_idx, value));
+        for part in &mut parts[open_idx..=close_idx] {
+            if part.tooltip.is_none() {
+                part.tooltip = Some(format!("= {}", value));
+            }
+        }
+    }
+}
+
+/// Whether `set -o noclobber` is on, i.e. `SHELLOPTS` lists `noclobber`.
+pub fn noclobber_is_set() -> bool {
+    bash_funcs::get_envvar_value("SHELLOPTS")
+        .is_some_and(|opts| opts.split(':').any(|opt| opt == "noclobber"))
+}
+
+/// Warn on each `>` whose target is an existing regular file, which bash
+/// refuses to overwrite under noclobber. `>>` and `>|` are fine, and targets
+/// with quotes or expansions are not checked.
+fn add_noclobber_tooltips(parts: &mut [FormattedBufferPart], noclobber: bool) {
+    if !noclobber {
+        return;
+    }
+    for redirect_idx in 0..parts.len() {
+        let redirect = &parts[redirect_idx].token;
+        if redirect.token.kind != TokenKind::Great
+            || redirect.annotations.is_inside_single_quotes
+            || redirect.annotations.is_inside_double_quotes
+            || redirect.annotations.is_comment
+        {
+            continue;
+        }
+        let Some(target_start) = (redirect_idx + 1..parts.len())
+            .find(|&idx| !parts[idx].token.token.kind.is_whitespace())
+        else {
+            continue;
+        };
+        // The lexer may split a path into several adjacent word tokens.
+        let target_end = (target_start..parts.len())
+            .find(|&idx| !parts[idx].token.token.kind.is_word())
+            .unwrap_or(parts.len());
+        let path: String = parts[target_start..target_end]
+            .iter()
+            .map(|p| p.token.token.value.as_str())
+            .collect();
+        if path.is_empty() || !std::path::Path::new(&path).is_file() {
+            continue;
+        }
+        let warning = format!("noclobber: {} exists and will not be overwritten", path);
+        for idx in std::iter::once(redirect_idx).chain(target_start..target_end) {
+            parts[idx].tooltip.get_or_insert_with(|| warning.clone());
+        }
+    }
+}
+
+/// Style the whitespace at the end of each line with
+/// [`Palette::trailing_whitespace`]. The line holding the cursor is left
+/// alone, since a trailing space there is usually mid-typing.
+fn mark_trailing_whitespace(
+    parts: &mut [FormattedBufferPart],
+    cursor_byte_pos: usize,
+    palette: &Palette,
+) {
+    let mut line_start_idx = 0;
+    for line_end_idx in 0..=parts.len() {
+        if parts
+            .get(line_end_idx)
+            .is_some_and(|p| p.token.token.kind != TokenKind::Newline)
+        {
+            continue;
+        }
+        let line = line_start_idx..line_end_idx;
+        line_start_idx = line_end_idx + 1;
+
+        let run_start = (line.start..line.end)
+            .rev()
+            .take_while(|&idx| parts[idx].token.token.kind.is_whitespace())
+            .last();
+        let Some(run_start) = run_start else {
+            continue;
+        };
+        let line_bytes = parts[line.start].token.token.byte_range().start
+            ..=parts[line.end - 1].token.token.byte_range().end;
+        if line_bytes.contains(&cursor_byte_pos) {
+            continue;
+        }
+        for part in &mut parts[run_start..line.end] {
+            part.span.style = palette.trailing_whitespace();
+        }
+    }
+}
+
+/// Find the first token bash would reject with "syntax error near unexpected
+/// token", such as the `then` in `if then fi` or the second `&&` in
+/// `a && && b`. Incomplete input is not an error.
+fn first_syntax_error(annotated_tokens: &[AnnotatedToken]) -> Option<usize> {
+    let mut state = CommandListState::Start;
+    for (idx, tok) in annotated_tokens.iter().enumerate() {
+        let annotations = &tok.annotations;
+        if annotations.is_inside_single_quotes
+            || annotations.is_inside_double_quotes
+            || annotations.is_comment
+        {
+            continue;
+        }
+        let kind = &tok.token.kind;
+        let is_operator = matches!(
+            kind,
+            TokenKind::And
+                | TokenKind::Or
+                | TokenKind::Pipe
+                | TokenKind::Semicolon
+                | TokenKind::Background
+        );
+        let is_keyword = matches!(
+            kind,
+            TokenKind::Then
+                | TokenKind::Do
+                | TokenKind::Elif
+                | TokenKind::Else
+                | TokenKind::Fi
+                | TokenKind::Done
+        );
+        match state {
+            CommandListState::Start if is_operator || is_keyword => return Some(idx),
+            CommandListState::AfterSeparator if is_operator => return Some(idx),
+            _ => {}
+        }
+
+        state = match kind {
+            TokenKind::Whitespace(_) | TokenKind::Comment => state,
+            TokenKind::Newline if state == CommandListState::Start => state,
+            TokenKind::Semicolon
+            | TokenKind::Background
+            | TokenKind::Newline
+            | TokenKind::DoubleSemicolon => CommandListState::AfterSeparator,
+            TokenKind::And
+            | TokenKind::Or
+            | TokenKind::Pipe
+            | TokenKind::LParen
+            | TokenKind::LBrace
+            | TokenKind::CmdSubst
+            | TokenKind::ProcessSubstIn
+            | TokenKind::ProcessSubstOut => CommandListState::Start,
+            TokenKind::Backtick if annotations.opening.is_some() => CommandListState::Start,
+            // Inside a command these are plain arguments, e.g. `echo then`.
+            TokenKind::If
+            | TokenKind::While
+            | TokenKind::Until
+            | TokenKind::Then
+            | TokenKind::Do
+            | TokenKind::Elif
+            | TokenKind::Else
+                if state != CommandListState::InCommand =>
+            {
+                CommandListState::Start
+            }
+            _ => CommandListState::InCommand,
+        };
+    }
+    None
+}
+
+pub fn format_buffer_with_lookup(
+    annotated_tokens: &[AnnotatedToken],
+    cursor_byte_pos: usize,
+    selection_byte_pos: Option<usize>,
+    buffer_byte_length: usize,
+    app_is_running: bool,
+    palette: &Palette,
+    command_lookup: CommandLookup,
 ) -> FormattedBuffer {
     let check_highlight = |inclusive: bool| {
         annotated_tokens
@@ -463,7 +839,10 @@ pub fn format_buffer(
         }
     }
 
-    let spans: Vec<FormattedBufferPart> = annotated_tokens
+    let heredoc_language_body = heredoc_language_body_mask(annotated_tokens);
+    let syntax_error_idx = first_syntax_error(annotated_tokens);
+
+    let mut spans: Vec<FormattedBufferPart> = annotated_tokens
         .iter()
         .enumerate()
         .map(|(idx, tok)| {
@@ -486,16 +865,30 @@ pub fn format_buffer(
             } else {
                 None
             };
-            FormattedBufferPart::new(
+            let mut part = FormattedBufferPart::new(
                 tok,
                 highlight,
                 cursor_pos_in_token,
                 selection_pos_in_token,
                 palette,
                 recognised_env_var,
-            )
+                command_lookup,
+            );
+            // Unquoted heredocs still expand variables, so keep those styled.
+            if heredoc_language_body[idx] && !tok.annotations.is_env_var {
+                part.span.style = palette.heredoc_body();
+            }
+            if syntax_error_idx == Some(idx) {
+                part.span.style = palette.syntax_error();
+                part.tooltip = Some(format!(
+                    "syntax error near unexpected token `{}'",
+                    tok.token.value
+                ));
+            }
+            part
         })
         .collect();
+    add_arithmetic_tooltips(&mut spans);
 
     // if log::log_enabled!(log::Level::Trace) {
     //     for part in &spans {
@@ -545,6 +938,7 @@ pub fn format_agent_buffer(
                 selection_pos_in_token,
                 palette,
                 None,
+                CommandLookup::Blocking,
             );
 
             if tok.token.kind.is_word() && !found_first_word {
@@ -770,6 +1164,58 @@ mod tests {
         );
     }
 
+    // ── command word animations ───────────────────────────────────────────
+
+    #[test]
+    fn configured_trigger_word_gets_alternative_span() {
+        let triggers = HashMap::from([
+            ("cargo".to_string(), AnimationKind::Snake),
+            ("py".to_string(), AnimationKind::Snake),
+        ]);
+        let mut fb = FormattedBuffer::from("cargo build && python3 x.py; rustc cargo", 0, None);
+        fb.apply_command_word_animations(&triggers);
+
+        let animated: Vec<&str> = fb
+            .parts
+            .iter()
+            .filter(|p| p.has_alternative_span())
+            .map(|p| p.token.token.value.as_str())
+            .collect();
+        assert_eq!(animated, vec!["cargo", "python3"]);
+
+        let cargo = &fb.parts[0];
+        let now = std::time::Instant::now();
+        let animated_span = cargo.get_possible_animated_span(now);
+        assert_eq!(animated_span.content.chars().count(), "cargo".len());
+        assert_eq!(animated_span.style, cargo.normal_span().style);
+    }
+
+    #[test]
+    fn no_alternative_span_without_triggers() {
+        let mut fb = FormattedBuffer::from("python3 -m http.server", 0, None);
+        fb.apply_command_word_animations(&HashMap::new());
+        assert!(fb.parts.iter().all(|p| !p.has_alternative_span()));
+    }
+
+    // ── to_ansi_string ────────────────────────────────────────────────────
+
+    #[test]
+    fn to_ansi_string_colours_keyword() {
+        let fb = FormattedBuffer::from("if true; then :; fi", 4, None);
+        let ansi = fb.to_ansi_string();
+        assert!(ansi.starts_with("\x1b[33;1mif\x1b[0m"), "{:?}", ansi);
+        assert!(ansi.ends_with("\x1b[33;1mfi\x1b[0m"), "{:?}", ansi);
+    }
+
+    #[test]
+    fn highlight_to_ansi_matches_the_formatted_buffer() {
+        let input = "if true; then :; fi";
+        assert_eq!(
+            highlight_to_ansi(input, &Palette::dark()),
+            FormattedBuffer::from(input, input.len(), None).to_ansi_string()
+        );
+    }
+
     // ── format_buffer selection bookkeeping ───────────────────────────────
 
     #[test]
@@ -830,6 +1276,192 @@ mod tests {
         }
     }
 
+    /// An unclosed opening bracket gets the error style instead of its rainbow
+    /// colour, while balanced brackets keep theirs.
+    #[test]
+    fn unmatched_opening_bracket_uses_error_style() {
+        let input = "echo $(ls) (true";
+        let fb = FormattedBuffer::from(input, 0, None);
+        let palette = Palette::dark();
+
+        let style_of = |value: &str| {
+            fb.parts
+                .iter()
+                .find(|p| p.token.token.value == value)
+                .map(|p| p.normal_span().style)
+                .unwrap()
+        };
+
+        assert_eq!(style_of("("), palette.unmatched_bracket());
+        assert_ne!(style_of("$("), palette.unmatched_bracket());
+        assert_ne!(style_of(")"), palette.unmatched_bracket());
+    }
+
+    /// A heredoc body under a language-hint delimiter is not highlighted as
+    /// bash, while one under an unknown delimiter keeps the usual styling.
+    #[test]
+    fn heredoc_language_hint_body_is_not_styled_as_bash() {
+        let palette = Palette::dark();
+        let style_of = |input: &str, value: &str| {
+            let fb = FormattedBuffer::from(input, 0, None);
+            fb.parts
+                .iter()
+                .find(|p| p.token.token.value == value)
+                .map(|p| p.normal_span().style)
+                .unwrap()
+        };
+
+        let sql = "cat <<SQL\nselect * from t where x in (1) then\nSQL\necho done";
+        for value in ["select", "in", "then", "("] {
+            assert_eq!(style_of(sql, value), palette.heredoc_body(), "{value}");
+        }
+        assert_ne!(style_of(sql, "cat"), palette.heredoc_body());
+        assert_ne!(style_of(sql, "echo"), palette.heredoc_body());
+
+        let unterminated = "cat <<-json\n{ \"a\": 1";
+        assert_eq!(style_of(unterminated, "1"), palette.heredoc_body());
+
+        let unknown = "cat <<EOF\nselect\nEOF";
+        assert_ne!(style_of(unknown, "select"), palette.heredoc_body());
+    }
+
+    #[test]
+    fn alias_command_word_tooltip_shows_expansion() {
+        let fb = FormattedBuffer::from("gst --short", 1, None);
+        let gst = parts_with_value(&fb, "gst");
+        assert_eq!(gst.len(), 1);
+        assert_eq!(gst[0].tooltip.as_deref(), Some("alias: git status"));
+    }
+
+    #[test]
+    fn arithmetic_expansion_tooltip_shows_value() {
+        fn arith_openings(fb: &FormattedBuffer) -> Vec<&FormattedBufferPart> {
+            fb.parts
+                .iter()
+                .filter(|p| p.token.token.kind == TokenKind::ArithSubst)
+                .collect()
+        }
+        let tooltip_of = |input: &str| {
+            let fb = FormattedBuffer::from(input, 0, None);
+            arith_openings(&fb)[0].tooltip.clone()
+        };
+
+        assert_eq!(tooltip_of("echo $((1+2*3))").as_deref(), Some("= 7"));
+        assert_eq!(tooltip_of("echo $((0x10))").as_deref(), Some("= 16"));
+        assert_eq!(tooltip_of("echo $((2#1010))").as_deref(), Some("= 10"));
+        assert_eq!(tooltip_of("echo $(( x + 1 ))"), None);
+        assert_eq!(tooltip_of("echo $((1 +"), None);
+        assert_eq!(tooltip_of("echo $((1 / 0))"), None);
+
+        // Tokens of an inner expansion show the inner value.
+        let fb = FormattedBuffer::from("echo $(( 2 * $((1+2)) ))", 0, None);
+        let opens = arith_openings(&fb);
+        assert_eq!(opens[0].tooltip.as_deref(), Some("= 6"));
+        assert_eq!(opens[1].tooltip.as_deref(), Some("= 3"));
+
+        // An outer expansion can't be evaluated when an inner one can't.
+        let fb = FormattedBuffer::from("echo $(( 2 * $((x)) ))", 0, None);
+        let opens = arith_openings(&fb);
+        assert_eq!(opens[0].tooltip, None);
+        assert_eq!(opens[1].tooltip, None);
+    }
+
+    #[test]
+    fn noclobber_warns_on_overwriting_redirect() {
+        let dir = std::env::temp_dir().join(format!("flyline_noclobber_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("existing.txt");
+        std::fs::write(&existing, "").unwrap();
+        let existing = existing.to_str().unwrap();
+
+        let tooltips = |input: &str, noclobber: bool| {
+            let mut fb = FormattedBuffer::from(input, 0, None);
+            add_noclobber_tooltips(&mut fb.parts, noclobber);
+            fb.parts
+                .iter()
+                .filter_map(|p| p.tooltip.clone())
+                .collect::<Vec<_>>()
+        };
+        let warning = format!("noclobber: {} exists and will not be overwritten", existing);
+
+        let warnings = tooltips(&format!("echo x > {}", existing), true);
+        assert!(warnings.len() >= 2);
+        assert!(warnings.iter().all(|w| *w == warning));
+        assert!(tooltips(&format!("echo x >> {}", existing), true).is_empty());
+        assert!(tooltips(&format!("echo x >| {}", existing), true).is_empty());
+        assert!(tooltips(&format!("echo x > {}.new", existing), true).is_empty());
+        assert!(tooltips(&format!("echo x > {}", existing), false).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn trailing_whitespace_marked_except_on_cursor_line() {
+        let palette = Palette::dark();
+        let marked_if = |show: bool, input: &str, cursor: usize| {
+            let mut fb = FormattedBuffer::from(input, cursor, None);
+            fb.mark_trailing_whitespace(show, cursor, &palette);
+            fb.parts
+                .iter()
+                .filter(|p| p.normal_span().style == palette.trailing_whitespace())
+                .flat_map(|p| p.token.token.byte_range())
+                .collect::<Vec<_>>()
+        };
+        let marked = |input: &str, cursor: usize| marked_if(true, input, cursor);
+        let input = "echo a  \necho b \necho c ";
+        // Cursor on the last line: the runs on the other lines are marked.
+        assert_eq!(marked(input, input.len()), vec![6, 7, 15]);
+        // Cursor on the first line leaves that line's run alone.
+        assert_eq!(marked(input, 2), vec![15, 23]);
+        // Spaces between words are never marked.
+        assert_eq!(marked("echo  a", 0), vec![]);
+        // Nothing is marked unless FLYLINE_SHOW_TRAILING_WS=1.
+        assert_eq!(marked_if(false, input, input.len()), vec![]);
+    }
+
+    #[test]
+    fn syntax_error_is_underlined_with_tooltip() {
+        let palette = Palette::dark();
+        let fb = FormattedBuffer::from("if then fi", 0, None);
+        let errors: Vec<&FormattedBufferPart> = fb
+            .parts
+            .iter()
+            .filter(|p| p.normal_span().style == palette.syntax_error())
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].token.token.value, "then");
+        assert_eq!(
+            errors[0].tooltip.as_deref(),
+            Some("syntax error near unexpected token `then'")
+        );
+    }
+
+    #[test]
+    fn first_syntax_error_positions() {
+        let error_at = |input: &str| {
+            let tokens = crate::dparser::DParser::parse_and_annotate(input);
+            first_syntax_error(&tokens).map(|idx| tokens[idx].token.byte_range().start)
+        };
+
+        assert_eq!(error_at("ls && && ls"), Some(6));
+        assert_eq!(error_at("| grep x"), Some(0));
+        assert_eq!(error_at("ls; ;"), Some(4));
+        assert_eq!(error_at("for x in a; do done"), Some(15));
+
+        for valid in [
+            "if true; then echo then; fi",
+            "if true\nthen\n  ls\nfi",
+            "for x in a b; do echo $x; done",
+            "case x in a) ;; esac",
+            "echo $(ls) && ls | wc -l &",
+            "echo 'a && && b' # ; ;",
+            "ls &&",
+            "",
+        ] {
+            assert_eq!(error_at(valid), None, "{valid:?}");
+        }
+    }
+
     /// Depth cycles modulo 4: depth 4 is styled the same as depth 0.
     #[test]
     fn rainbow_bracket_style_cycles_modulo_4() {
@@ -1,5 +1,7 @@
 pub(crate) mod actions;
 pub(crate) mod auto_close;
+mod command_lookup;
+mod command_palette;
 pub(crate) mod formatted_buffer;
 mod tab_completion;
 mod ui;
@@ -32,11 +34,16 @@ pub enum RightClickCopyTarget {
 use crate::active_suggestions::{ActiveSuggestions, ActiveSuggestionsBuilder, COLUMN_PADDING};
 use crate::agent_mode::{AiOutputSelection, parse_ai_output};
 use crate::app::actions::KeyEventAction;
-use crate::app::formatted_buffer::{FormattedBuffer, format_agent_buffer, format_buffer};
+use crate::app::command_lookup::CommandLookupDebouncer;
+use crate::app::command_palette::CommandPalette;
+use crate::app::formatted_buffer::{
+    CommandLookup, FormattedBuffer, format_agent_buffer, format_buffer_with_lookup,
+};
 use crate::content_builder::{Contents, SpanTag, Tag, TaggedLine, TaggedSpan};
-use crate::cursor::{Cursor, CursorBackend};
+use crate::cursor::Cursor;
 use crate::dparser::{AnnotatedToken, ToInclusiveRange};
 use crate::history::{HistoryEntry, HistoryEntryFormatted, HistoryManager};
+use crate::history_expansion;
 use crate::iter_first_last::FirstLast;
 use crate::kill_on_drop_child::KillOnDropChild;
 use crate::mouse_state::{MouseState, PointerShape, XtShiftEscape};
@@ -44,6 +51,8 @@ use crate::palette::{ButtonState, Palette};
 use crate::prompt_manager::PromptManager;
 use crate::settings::{self, MatrixAnimation, MouseMode, Settings};
 use crate::shell_integration;
+use crate::snake_animation::SpinnerAnimation;
+use crate::suggestion_source::{self, FileMatchDebouncer};
 use crate::text_buffer::{SubString, TextBuffer};
 use crate::{bash_funcs, dparser};
 use crate::{bash_symbols, command_acceptance};
@@ -68,6 +77,84 @@ const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 /// Frame rate (fps) used when the user has been idle for longer than [`IDLE_TIMEOUT`].
 const IDLE_FRAME_RATE: f64 = 0.2;
 
+/// Poll timeout used once animation ticks are paused.  Nothing is redrawn on
+/// these wake-ups; they only let the main loop notice terminating signals and
+/// finished background work.  Matches the [`IDLE_FRAME_RATE`] tick so pausing
+/// never makes the loop slower to react than it was while idle.
+const PAUSED_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Input that arrives within this window of the first event of a frame is
+/// handled before drawing, so bursts (key repeat, pastes without bracketed
+/// paste, slow links) cost one redraw instead of one per event.
+const REDRAW_COALESCE_WINDOW: Duration = Duration::from_millis(16);
+
+/// Whether the main loop should stop producing animation ticks and wait for
+/// the next input event instead.  Ticks pause when nothing on screen is
+/// animating and either animations are suppressed (disabled, or the terminal
+/// is unfocused) or the user has been idle for [`IDLE_TIMEOUT`] and a frame
+/// has been drawn in the idle state (so the cursor is shown unfocused).
+fn should_pause_ticks(
+    idle_for: Duration,
+    drawn_while_idle: bool,
+    has_active_animation: bool,
+    animations_suppressed: bool,
+) -> bool {
+    !has_active_animation
+        && (animations_suppressed || (idle_for >= IDLE_TIMEOUT && drawn_while_idle))
+}
+
+/// Whether the prompt is drawn dimmed after `idle_for` without input, given
+/// the `FLYLINE_IDLE_DIM_SECS` timeout.
+fn is_idle_dimmed(idle_for: Duration, dim_after: Option<Duration>) -> bool {
+    dim_after.is_some_and(|dim_after| idle_for >= dim_after)
+}
+
+/// Where the main loop reads input from.
+trait EventSource<E> {
+    fn poll_event(&mut self, timeout: Duration) -> std::io::Result<Option<E>>;
+}
+
+/// The terminal's input queue.
+struct TerminalEvents;
+
+impl EventSource<CrosstermEvent> for TerminalEvents {
+    fn poll_event(&mut self, timeout: Duration) -> std::io::Result<Option<CrosstermEvent>> {
+        poll_terminal_event(timeout)
+    }
+}
+
+/// What the main loop hands input to.
+trait FrameInput<E> {
+    /// Handle `event` and return whether it needs a redraw.
+    fn handle_event(&mut self, event: E) -> bool;
+    fn is_running(&self) -> bool;
+}
+
+/// Wait up to `timeout` for input, then handle it along with the events
+/// already queued behind it, for at most `budget`, so a burst costs one
+/// redraw.  Returns whether to redraw, or `None` if nothing arrived.
+/// Draining stops once `input` stops running so input typed after e.g. Enter
+/// is left for the next reader.
+fn handle_frame_input<E>(
+    events: &mut impl EventSource<E>,
+    input: &mut impl FrameInput<E>,
+    timeout: Duration,
+    budget: Duration,
+) -> std::io::Result<Option<bool>> {
+    let Some(event) = events.poll_event(timeout)? else {
+        return Ok(None);
+    };
+    let start = std::time::Instant::now();
+    let mut redraw = input.handle_event(event);
+    while input.is_running() && start.elapsed() < budget {
+        match events.poll_event(Duration::ZERO) {
+            Ok(Some(event)) => redraw |= input.handle_event(event),
+            _ => break,
+        }
+    }
+    Ok(Some(redraw))
+}
+
 fn restore_terminal(extended_key_codes: bool) {
     crossterm::terminal::disable_raw_mode().unwrap_or_else(|e| {
         // Likely from the master pty fd being closed.
@@ -80,6 +167,7 @@ fn restore_terminal(extended_key_codes: bool) {
         crossterm::event::DisableMouseCapture,
         XtShiftEscape::Disable,
         PointerShape::Default,
+        crossterm::cursor::SetCursorStyle::DefaultUserShape,
     )
     .unwrap_or_else(|e| {
         log::error!("Failed to restore terminal features: {}", e);
@@ -197,6 +285,15 @@ impl AppRunningState {
     }
 }
 
+/// Time left before `get_command` gives up and hands an empty command back to
+/// bash, or `None` when there is no timeout. `idle_for` is the time since the
+/// last keypress or paste. The main loop checks this between events, so the
+/// timeout can't fire while the loop is blocked, e.g. in a synchronous call
+/// into bash.
+fn get_command_time_left(idle_for: Duration, timeout: Option<Duration>) -> Option<Duration> {
+    timeout.map(|timeout| timeout.saturating_sub(idle_for))
+}
+
 pub fn get_command(settings: &mut Settings) -> ExitState {
     // If stdin is closed, bash expects us to just return EOF a few times
     if let Some(reason) = stdin_unavailable_reason() {
@@ -319,6 +416,8 @@ pub(crate) enum ContentMode {
         start_time: std::time::Instant,
         auto_started: bool,
         last_active_suggestions: Option<Box<ActiveSuggestions>>,
+        /// Drawn after the word under the cursor once the wait is noticeable.
+        spinner: SpinnerAnimation,
     },
     /// AI command is running as a child process.  The child is polled each
     /// event-loop iteration with `try_wait`; on drop it is killed and reaped.
@@ -337,6 +436,8 @@ pub(crate) enum ContentMode {
         raw_output: String,
         suggested_setup_command: Option<String>,
     },
+    /// Command palette overlay: the user is fuzzy-searching flyline commands.
+    CommandPalette(CommandPalette),
     /// User is navigating the CWD path segments displayed in the prompt.
     /// The inner value is the currently highlighted segment index (0 = rightmost/current dir).
     PromptDirSelect(usize),
@@ -372,14 +473,28 @@ pub(crate) struct App<'a> {
     pub(super) prompt_manager: PromptManager,
     /// Parsed bash history available at startup.
     pub(super) history_manager: HistoryManager,
-    pub(super) buffer_before_history_navigation: Option<String>,
-    pub(super) inline_history_suggestion: Option<(HistoryEntry, String)>,
+    /// Hooks that rewrite the command just before it is submitted.
+    pub(super) submit_transforms: crate::transforms::TransformRegistry,
+    /// Buffer contents and cursor byte position when Up/Down history
+    /// navigation started, restored on navigating past the newest entry.
+    pub(super) buffer_before_history_navigation: Option<(String, usize)>,
+    /// Buffer contents when fuzzy history search was opened, restored if the
+    /// search is cancelled with Escape.
+    pub(super) buffer_before_fuzzy_search: Option<String>,
+    /// Text to show after the cursor, and the history entry it came from
+    /// (`None` when it came from completion).
+    pub(super) inline_suggestion: Option<(Option<HistoryEntry>, String)>,
     /// Buffer contents at the time the user last dismissed the inline suggestion.
     /// While the buffer equals this value the suggestion is suppressed.
     pub(super) dismissed_inline_suggestion_buffer: Option<String>,
     /// Word-under-cursor at the time the user dismissed tab completion with Escape.
     /// While the new word-under-cursor equals this value, auto-suggest is suppressed.
     pub(super) dismissed_tab_completion_wuc: Option<String>,
+    /// Set by quoted-insert: the next key is inserted literally instead of
+    /// being dispatched to a binding.
+    pub(super) quoted_insert_pending: bool,
+    /// Modal editing state; `None` unless vi mode is enabled.
+    pub(super) vi_mode: Option<crate::vi::ViState>,
     /// Buffer contents at the time the user last dismissed the agent prompts fuzzy history search.
     pub(super) dismissed_agent_prompts_buffer: Option<String>,
     pub(super) mouse_state: MouseState,
@@ -391,18 +506,53 @@ pub(crate) struct App<'a> {
     /// Timestamp of the last draw operation.
     pub(super) last_draw_time: std::time::Instant,
     pub(super) needs_screen_cleared: bool,
+    /// Bar cursor last requested from the terminal, see [`CursorConfig::terminal_bar`].
+    ///
+    /// [`CursorConfig::terminal_bar`]: crate::cursor::CursorConfig::terminal_bar
+    pub(super) terminal_bar: Option<bool>,
     /// Last key event, context expression, and action dispatched.
     pub(super) last_key: Option<LastKeyPress>,
     /// Last mouse event received.
     pub(super) last_mouse: Option<LastMouseEvent>,
     /// Last processed key event sequence number for triggers.
     pub(super) last_processed_key_sequence: u64,
+    /// Set by a multiline paste that left only complete commands, so the
+    /// next Enter runs them wherever the cursor is. Cleared by any key.
+    pub(super) pasted_complete_commands: bool,
     /// Position of the right click popup, if active.
     pub(super) right_click_popup_pos: Option<crate::content_builder::Coord>,
     /// Target content to copy/cut determined at right-click depress time.
     pub(super) right_click_copy_target: Option<RightClickCopyTarget>,
     /// Timestamp of the last keypress or mouse event; used for idle-based matrix animation.
     pub(super) last_activity_time: std::time::Instant,
+    /// Timestamp of the last keypress or paste; `FLYLINE_GETCOMMAND_TIMEOUT`
+    /// counts from here.
+    pub(super) last_input_time: std::time::Instant,
+    /// Debounced background `type` lookups for command words being typed.
+    pub(super) command_lookup: CommandLookupDebouncer,
+    /// Debounced file matching for the completion suggestion source.
+    pub(super) file_match: FileMatchDebouncer,
+    /// `settings.show_animations`, unless overridden by `settings.no_animations`.
+    pub(super) animations_enabled: bool,
+    /// Whether `set -o noclobber` was on when this prompt started.
+    pub(super) noclobber: bool,
+}
+
+/// The app as the main loop's input handler.
+struct AppInput<'x, 'a> {
+    app: &'x mut App<'a>,
+    last_terminal_size: &'x mut Size,
+}
+
+impl FrameInput<CrosstermEvent> for AppInput<'_, '_> {
+    fn handle_event(&mut self, event: CrosstermEvent) -> bool {
+        self.app
+            .handle_terminal_event(event, self.last_terminal_size)
+    }
+
+    fn is_running(&self) -> bool {
+        self.app.mode.is_running()
+    }
 }
 
 impl<'a> App<'a> {
@@ -410,24 +560,13 @@ impl<'a> App<'a> {
         let unfinished_from_prev_command =
             unsafe { crate::bash_symbols::current_command_line_count } > 0;
         let initial_buf_val = settings.initial_buffer.take().unwrap_or_default();
-        let buffer = TextBuffer::new(&initial_buf_val);
+        let mut buffer = TextBuffer::new(&initial_buf_val);
+        buffer.set_word_chars(settings.word_chars.clone());
         let formatted_buffer_cache = FormattedBuffer::default();
+        let animations_enabled = settings.show_animations && !settings.no_animations;
 
         bash_funcs::reset_caches();
-
-        // Join any previous warming thread to prevent multiple active warming threads
-        crate::threads::join_threads_by_tag(crate::threads::ThreadTag::Warming);
-
-        let warming_handle = std::thread::Builder::new()
-            .name("flyline-warming".to_string())
-            .spawn(|| {
-                let _timer = crate::perf::PerfTimer::start("warming_thread");
-                let start = std::time::Instant::now();
-                crate::bash_funcs::warm_completion_caches();
-                log::info!("Warming thread finished in {:?}", start.elapsed());
-            })
-            .unwrap();
-        crate::threads::register_thread(crate::threads::ThreadTag::Warming, warming_handle);
+        Self::spawn_warming_thread(bash_funcs::warm_completion_caches);
 
         let mut app = App {
             mode: AppRunningState::Running,
@@ -436,6 +575,8 @@ impl<'a> App<'a> {
             dparser_tokens_cache: Vec::new(),
             cursor: Cursor::new(),
             term_has_focus: true,
+            animations_enabled,
+            noclobber: formatted_buffer::noclobber_is_set(),
             unfinished_from_prev_command,
             prompt_manager: time_it!(
                 "startup: prompt manager",
@@ -452,13 +593,18 @@ impl<'a> App<'a> {
                         .cloned()
                         .collect::<Vec<_>>(),
                     settings.last_app_closed_at,
+                    &settings.colour_palette,
                 )
             ),
             history_manager: time_it!("startup: history manager", HistoryManager::new(settings)),
+            submit_transforms: crate::transforms::load_at_startup(),
             buffer_before_history_navigation: None,
-            inline_history_suggestion: None,
+            buffer_before_fuzzy_search: None,
+            inline_suggestion: None,
             dismissed_inline_suggestion_buffer: None,
             dismissed_tab_completion_wuc: None,
+            quoted_insert_pending: false,
+            vi_mode: crate::vi::ViState::from_env(),
             dismissed_agent_prompts_buffer: None,
             mouse_state: time_it!(
                 "startup: mouse state",
@@ -470,18 +616,53 @@ impl<'a> App<'a> {
             settings,
             last_draw_time: std::time::Instant::now(),
             needs_screen_cleared: false,
+            terminal_bar: None,
             last_key: None,
             last_mouse: None,
             last_processed_key_sequence: 0,
+            pasted_complete_commands: false,
             right_click_popup_pos: None,
             right_click_copy_target: None,
             last_activity_time: std::time::Instant::now(),
+            last_input_time: std::time::Instant::now(),
+            command_lookup: CommandLookupDebouncer::default(),
+            file_match: FileMatchDebouncer::default(),
         };
 
         app.on_possible_buffer_change();
         app
     }
 
+    /// Warm the completion caches on a background thread so the first tab
+    /// completion of the prompt doesn't pay for it.
+    fn spawn_warming_thread(warm: fn()) {
+        // Join any previous warming thread to prevent multiple active warming threads
+        crate::threads::join_threads_by_tag(crate::threads::ThreadTag::Warming);
+
+        let warming_handle = std::thread::Builder::new()
+            .name("flyline-warming".to_string())
+            .spawn(move || {
+                let _timer = crate::perf::PerfTimer::start("warming_thread");
+                let start = std::time::Instant::now();
+                warm();
+                log::info!("Warming thread finished in {:?}", start.elapsed());
+            })
+            .unwrap();
+        crate::threads::register_thread(crate::threads::ThreadTag::Warming, warming_handle);
+    }
+
+    /// Re-read the settings from the environment and config files (theme,
+    /// keymap, abbreviations) and rebuild the completion sources (aliases,
+    /// functions, builtins, PATH executables, commit messages) without
+    /// waiting for the next prompt, then redraw the whole screen.
+    pub(crate) fn reload(&mut self) {
+        log::info!("Reloading settings and completion caches");
+        self.settings.load_config();
+        Self::spawn_warming_thread(bash_funcs::reload_completion_caches);
+        self.on_possible_buffer_change();
+        self.needs_screen_cleared = true;
+    }
+
     /// Return a mutable reference to the history manager for the given fuzzy source.
     pub(crate) fn select_fuzzy_history_manager_mut(
         &mut self,
@@ -575,6 +756,7 @@ impl<'a> App<'a> {
 
         let mut redraw = true;
         let mut last_terminal_size = terminal.size().unwrap();
+        let get_command_timeout = self.settings.get_command_timeout;
 
         'main_loop: loop {
             if self.poll_agent() {
@@ -586,6 +768,15 @@ impl<'a> App<'a> {
             if self.poll_flycomp() {
                 redraw = true;
             }
+            if self.command_lookup.poll(std::time::Instant::now()) {
+                self.refresh_formatted_buffer();
+                redraw = true;
+            }
+            if self.file_match.poll(std::time::Instant::now()) {
+                self.update_inline_suggestion();
+                self.refresh_formatted_buffer();
+                redraw = true;
+            }
 
             if redraw {
                 let frame_area = terminal.get_frame().area();
@@ -595,6 +786,10 @@ impl<'a> App<'a> {
 
                 let desired_height = if self.needs_screen_cleared {
                     self.needs_screen_cleared = false;
+                    // Forget what was drawn so every cell is written again.
+                    terminal.clear().unwrap_or_else(|e| {
+                        log::error!("Failed to clear terminal: {}", e);
+                    });
                     last_terminal_size.height
                 } else {
                     content.height().min(last_terminal_size.height)
@@ -606,6 +801,12 @@ impl<'a> App<'a> {
                         log::error!("Failed to set viewport height: {}", e);
                     });
 
+                let terminal_bar = self.settings.cursor_config.terminal_bar();
+                if terminal_bar != self.terminal_bar {
+                    crate::cursor::set_terminal_bar(terminal_bar);
+                    self.terminal_bar = terminal_bar;
+                }
+
                 let prev_contents = std::mem::take(&mut self.last_contents);
                 let draw_result = {
                     let _timer = crate::perf::PerfTimer::start("draw");
@@ -649,7 +850,8 @@ impl<'a> App<'a> {
                 break;
             }
 
-            let is_idle = self.last_activity_time.elapsed() >= IDLE_TIMEOUT;
+            let idle_for = self.last_activity_time.elapsed();
+            let is_idle = idle_for >= IDLE_TIMEOUT;
             let effective_fps = if is_idle {
                 IDLE_FRAME_RATE.min(self.settings.frame_rate as f64)
             } else {
@@ -657,53 +859,54 @@ impl<'a> App<'a> {
             };
             let min_refresh_rate: Duration = Duration::from_millis((1000.0 / effective_fps) as u64);
 
-            redraw = match poll_terminal_event(min_refresh_rate) {
-                Ok(Some(event)) => {
-                    let r = match event {
-                        CrosstermEvent::Key(key) => {
-                            self.last_activity_time = std::time::Instant::now();
-                            self.handle_key_event(key);
-                            true
-                        }
-                        CrosstermEvent::Mouse(mouse) => {
-                            self.last_activity_time = std::time::Instant::now();
-                            self.on_mouse(mouse)
-                        }
-                        CrosstermEvent::Resize(new_cols, new_rows) => {
-                            // log::trace!("Terminal resized to {}x{}", new_cols, new_rows);
-                            last_terminal_size = Size {
-                                width: new_cols,
-                                height: new_rows,
-                            };
-                            true
-                        }
-                        CrosstermEvent::FocusLost => {
-                            // log::trace!("Terminal focus lost");
-                            self.term_has_focus = false;
-                            false
-                        }
-                        CrosstermEvent::FocusGained => {
-                            // log::trace!("Terminal focus gained");
-                            self.term_has_focus = true;
-                            if self.settings.mouse_mode == MouseMode::Smart {
-                                log::debug!(
-                                    "Enabling mouse capture due to terminal focus gain in smart mode"
-                                );
-                                self.mouse_state.enable();
-                            }
-                            false
-                        }
-                        CrosstermEvent::Paste(pasted) => {
-                            log::trace!("Pasted content: {}", pasted);
-                            self.buffer.delete_selection();
-                            self.buffer.insert_str(&pasted);
-                            self.on_possible_buffer_change();
-                            true
-                        }
-                    };
-                    r
-                }
-                Ok(None) => true,
+            let ticks_paused = should_pause_ticks(
+                idle_for,
+                self.last_draw_time
+                    .saturating_duration_since(self.last_activity_time)
+                    >= IDLE_TIMEOUT,
+                self.has_active_animation(),
+                !self.animations_enabled || !self.term_has_focus,
+            );
+            // Wake up when the prompt is due to dim, even if ticks are paused.
+            let dim_after = self.settings.idle_dim_after;
+            let drawn_dimmed = is_idle_dimmed(
+                self.last_draw_time
+                    .saturating_duration_since(self.last_activity_time),
+                dim_after,
+            );
+            let time_until_dim = dim_after
+                .filter(|_| !drawn_dimmed)
+                .map(|dim_after| dim_after.saturating_sub(idle_for));
+            let input_idle_for = self.last_input_time.elapsed();
+            let time_left = get_command_time_left(input_idle_for, get_command_timeout);
+            if time_left == Some(Duration::ZERO) {
+                log::warn!(
+                    "No input for {:?} (FLYLINE_GETCOMMAND_TIMEOUT); returning an empty command",
+                    input_idle_for
+                );
+                self.mode = AppRunningState::Exiting(ExitState::WithoutCommand);
+                break 'main_loop;
+            }
+            let poll_timeout = if ticks_paused {
+                PAUSED_POLL_TIMEOUT
+            } else {
+                min_refresh_rate
+            }
+            .min(time_until_dim.unwrap_or(Duration::MAX))
+            .min(time_left.unwrap_or(Duration::MAX));
+
+            let mut input = AppInput {
+                app: &mut self,
+                last_terminal_size: &mut last_terminal_size,
+            };
+            redraw = match handle_frame_input(
+                &mut TerminalEvents,
+                &mut input,
+                poll_timeout,
+                REDRAW_COALESCE_WINDOW,
+            ) {
+                Ok(Some(redraw)) => redraw,
+                Ok(None) => !ticks_paused,
                 Err(err) => {
                     log::info!(
                         "Terminal input problem, setting mode to exiting with EOF: {}",
@@ -714,12 +917,18 @@ impl<'a> App<'a> {
                 }
             };
 
-            if std::time::Instant::now().duration_since(self.last_draw_time) > min_refresh_rate {
+            if !ticks_paused
+                && std::time::Instant::now().duration_since(self.last_draw_time) > min_refresh_rate
+            {
                 // redraw periodically to update animations even when no events are occurring
                 // (e.g. cursor blinking, matrix animation)
                 redraw = true;
             }
 
+            if !drawn_dimmed && is_idle_dimmed(self.last_activity_time.elapsed(), dim_after) {
+                redraw = true;
+            }
+
             // Check if a terminating signal has been received.
             // In bash >= 4.4 (readline 6.0+), rl_signal_event_hook is set when
             // bash receives a terminating signal.
@@ -769,6 +978,75 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Apply one terminal event and return whether it needs a redraw.
+    fn handle_terminal_event(
+        &mut self,
+        event: CrosstermEvent,
+        last_terminal_size: &mut Size,
+    ) -> bool {
+        match event {
+            CrosstermEvent::Key(key) => {
+                self.last_activity_time = std::time::Instant::now();
+                self.last_input_time = self.last_activity_time;
+                self.handle_key_event(key);
+                self.pasted_complete_commands = false;
+                true
+            }
+            CrosstermEvent::Mouse(mouse) => {
+                self.last_activity_time = std::time::Instant::now();
+                self.on_mouse(mouse)
+            }
+            CrosstermEvent::Resize(new_cols, new_rows) => {
+                // log::trace!("Terminal resized to {}x{}", new_cols, new_rows);
+                *last_terminal_size = Size {
+                    width: new_cols,
+                    height: new_rows,
+                };
+                true
+            }
+            CrosstermEvent::FocusLost => {
+                // log::trace!("Terminal focus lost");
+                self.term_has_focus = false;
+                // Draw once so the cursor is shown unfocused before ticks pause.
+                true
+            }
+            CrosstermEvent::FocusGained => {
+                // log::trace!("Terminal focus gained");
+                self.term_has_focus = true;
+                if self.settings.mouse_mode == MouseMode::Smart {
+                    log::debug!("Enabling mouse capture due to terminal focus gain in smart mode");
+                    self.mouse_state.enable();
+                }
+                false
+            }
+            CrosstermEvent::Paste(pasted) => {
+                log::trace!("Pasted content: {}", pasted);
+                self.last_input_time = std::time::Instant::now();
+                self.buffer.insert_pasted(&pasted);
+                self.pasted_complete_commands =
+                    command_acceptance::is_complete_pasted_block(&pasted, self.buffer.buffer());
+                self.on_possible_buffer_change();
+                true
+            }
+        }
+    }
+
+    /// Whether anything on screen changes without user input, which keeps
+    /// animation ticks running even when the user is idle.
+    fn has_active_animation(&self) -> bool {
+        let animating = self.animations_enabled && self.term_has_focus;
+        (animating && self.settings.matrix_animation != MatrixAnimation::Off)
+            || matches!(
+                self.content_mode,
+                ContentMode::AgentModeWaiting { .. }
+                    | ContentMode::TabCompletionWaiting { .. }
+                    | ContentMode::TabCompletionRunningFlycomp { .. }
+            )
+            || self.command_lookup.is_busy()
+            || self.file_match.is_busy()
+            || self.prompt_manager.needs_periodic_redraw(animating)
+    }
+
     fn toggle_mouse_state(&mut self) {
         self.mouse_state.toggle();
         if !self.mouse_state.is_enabled() {
@@ -956,6 +1234,7 @@ impl<'a> App<'a> {
             let new_command = entry.command.clone();
             self.buffer.replace_buffer(new_command.as_str());
         }
+        self.buffer_before_fuzzy_search = None;
         self.content_mode = ContentMode::Normal;
     }
 
@@ -1238,7 +1517,7 @@ impl<'a> App<'a> {
         word_under_cursor: String,
         use_sandbox: bool,
     ) {
-        let poss_alias = crate::bash_funcs::find_alias(&command_word);
+        let poss_alias = crate::bash_funcs::find_alias_or_log(&command_word);
         let alias_def = poss_alias
             .as_deref()
             .filter(|alias| !alias.is_empty())
@@ -1423,13 +1702,52 @@ impl<'a> App<'a> {
     fn try_submit_current_buffer(&mut self) {
         let complete_command = command_acceptance::will_bash_accept_buffer(self.buffer.buffer());
         if self.unfinished_from_prev_command || complete_command {
-            self.mode =
-                AppRunningState::Exiting(ExitState::WithCommand(self.buffer.buffer().to_string()));
+            let expanded = self.expand_history_designators();
+            if expanded.is_some() {
+                self.settings.history_expanded = true;
+            }
+            let rewritten =
+                rewrite_for_submit(self.buffer.buffer(), expanded, &self.submit_transforms);
+            // Show the command that will actually run in the final render.
+            if rewritten != self.buffer.buffer() {
+                self.buffer.replace_buffer(&rewritten);
+                self.on_possible_buffer_change();
+            }
+            let command = if self.settings.trim_on_submit {
+                command_acceptance::trim_trailing_whitespace(self.buffer.buffer())
+            } else {
+                self.buffer.buffer().to_string()
+            };
+            self.mode = AppRunningState::Exiting(ExitState::WithCommand(command));
         } else {
             self.buffer.insert_newline();
         }
     }
 
+    /// The buffer with `!!`, `!$` and friends expanded, or `None` when there
+    /// is nothing to expand. On failure the buffer is submitted as typed and
+    /// bash reports the error.
+    fn expand_history_designators(&self) -> Option<String> {
+        if !history_expansion::is_enabled() || !self.buffer.buffer().contains('!') {
+            return None;
+        }
+        match history_expansion::history_expand(self.buffer.buffer(), &self.history_manager) {
+            Ok(expanded) if expanded != self.buffer.buffer() => {
+                log::info!(
+                    "History expansion: {:?} -> {:?}",
+                    self.buffer.buffer(),
+                    expanded
+                );
+                Some(expanded)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("History expansion failed: {}", e);
+                None
+            }
+        }
+    }
+
     fn on_possible_buffer_change(&mut self) {
         if let ContentMode::AgentOutputSelection(ref mut selection) = self.content_mode {
             let current_buf = self.buffer.buffer();
@@ -1711,6 +2029,25 @@ impl<'a> App<'a> {
 
         self.dparser_tokens_cache = new_tokens;
 
+        // Command words typed since the last lookup are resolved in the background
+        // once they stop changing; see `refresh_formatted_buffer`.
+        if self.mode.is_running()
+            && let Some(uncached) = self
+                .dparser_tokens_cache
+                .iter()
+                .filter_map(|tok| tok.annotations.command_word.as_deref())
+                .filter(|word| bash_funcs::cached_command_info(word).is_none())
+                .last()
+        {
+            self.command_lookup
+                .request(uncached, std::time::Instant::now());
+        }
+
+        self.update_inline_suggestion();
+        self.refresh_formatted_buffer();
+    }
+
+    fn update_inline_suggestion(&mut self) {
         let history_buffer = self.buffer.buffer();
 
         // If the buffer has changed since the user dismissed the suggestion, re-enable it.
@@ -1722,16 +2059,26 @@ impl<'a> App<'a> {
             self.dismissed_inline_suggestion_buffer = None;
         }
 
-        self.inline_history_suggestion = if !self.settings.show_inline_history
+        self.inline_suggestion = if !self.settings.show_inline_history
             || history_buffer.is_empty()
             || self.dismissed_inline_suggestion_buffer.is_some()
         {
             None
         } else {
-            self.history_manager
-                .get_command_suggestion_suffix(history_buffer)
+            suggestion_source::inline_suggestion(
+                &self.settings.suggestion_sources,
+                history_buffer,
+                &mut self.history_manager,
+                &mut self.file_match,
+                std::time::Instant::now(),
+            )
         };
+    }
 
+    /// Re-style the parsed buffer and update the tooltip. While running, command
+    /// words not yet looked up are styled as pending instead of blocking on
+    /// bash.
+    fn refresh_formatted_buffer(&mut self) {
         self.formatted_buffer_cache = if matches!(
             self.content_mode,
             ContentMode::FuzzyHistorySearch(FuzzyHistorySource::AgentPrompts)
@@ -1747,17 +2094,33 @@ impl<'a> App<'a> {
                 &self.settings.colour_palette,
             )
         } else {
-            format_buffer(
+            let command_lookup = if self.mode.is_running() {
+                CommandLookup::CachedOnly
+            } else {
+                CommandLookup::Blocking
+            };
+            let mut formatted = format_buffer_with_lookup(
                 &self.dparser_tokens_cache,
                 self.buffer.cursor_byte_pos(),
                 self.buffer.selection_byte(),
                 self.buffer.buffer().len(),
                 self.mode.is_running(),
                 &self.settings.colour_palette,
-            )
+                command_lookup,
+            );
+            formatted.add_noclobber_tooltips(self.noclobber);
+            formatted.mark_trailing_whitespace(
+                self.settings.show_trailing_whitespace,
+                self.buffer.cursor_byte_pos(),
+                &self.settings.colour_palette,
+            );
+            formatted
         };
+        self.formatted_buffer_cache
+            .apply_command_word_animations(&self.settings.command_word_animations);
 
         let cursor_byte_pos = self.buffer.cursor_byte_pos();
+        let show_command_preview = self.settings.show_command_preview;
         self.tooltip = self
             .formatted_buffer_cache
             .parts
@@ -1771,7 +2134,16 @@ impl<'a> App<'a> {
                     .to_inclusive()
                     .contains(&cursor_byte_pos)
                 {
-                    part.tooltip.clone()
+                    let tooltip = part.tooltip.clone()?;
+                    let preview = part
+                        .token
+                        .annotations
+                        .command_word
+                        .as_deref()
+                        .filter(|_| show_command_preview)
+                        .and_then(bash_funcs::cached_command_info)
+                        .and_then(|info| info.definition_preview());
+                    Some(with_command_preview(tooltip, preview.as_deref()))
                 } else {
                     None
                 }
@@ -1779,6 +2151,29 @@ impl<'a> App<'a> {
     }
 }
 
+/// Append a command's definition preview to its tooltip on a second line.
+fn with_command_preview(tooltip: String, preview: Option<&str>) -> String {
+    match preview {
+        Some(preview) => format!("{}\n{}", tooltip, preview),
+        None => tooltip,
+    }
+}
+
+/// The command line submitted for `buffer`: its history expansion, if any,
+/// run through the submit transforms. Abbreviations were already expanded
+/// when Enter was pressed.
+fn rewrite_for_submit(
+    buffer: &str,
+    history_expanded: Option<String>,
+    transforms: &crate::transforms::TransformRegistry,
+) -> String {
+    let command = history_expanded.as_deref().unwrap_or(buffer);
+    if transforms.is_empty() {
+        return command.to_string();
+    }
+    transforms.apply(command)
+}
+
 pub fn signal_to_str(sig: libc::c_int) -> &'static str {
     match sig {
         libc::SIGHUP => "SIGHUP",
@@ -1799,3 +2194,211 @@ pub fn signal_to_str(sig: libc::c_int) -> &'static str {
         _ => "Unknown signal",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_pause_after_idle_timeout_once_idle_frame_is_drawn() {
+        assert!(!should_pause_ticks(Duration::ZERO, false, false, false));
+        assert!(!should_pause_ticks(
+            IDLE_TIMEOUT - Duration::from_millis(1),
+            true,
+            false,
+            false
+        ));
+        // The unfocused cursor has not been drawn yet.
+        assert!(!should_pause_ticks(IDLE_TIMEOUT, false, false, false));
+        assert!(should_pause_ticks(IDLE_TIMEOUT, true, false, false));
+        assert!(should_pause_ticks(
+            Duration::from_secs(3600),
+            true,
+            false,
+            false
+        ));
+    }
+
+    #[test]
+    fn idle_dim_flips_after_configured_ticks() {
+        // 20 fps ticks with FLYLINE_IDLE_DIM_SECS=2.
+        let tick = Duration::from_millis(50);
+        let dim_after = Some(Duration::from_secs(2));
+        let first_dim_tick = (0..100)
+            .find(|&ticks| is_idle_dimmed(tick * ticks, dim_after))
+            .unwrap();
+        assert_eq!(first_dim_tick, 40);
+        assert!(is_idle_dimmed(tick * 1000, dim_after));
+
+        // Disabled by default and with FLYLINE_IDLE_DIM_SECS=0.
+        assert!(!is_idle_dimmed(Duration::from_secs(3600), None));
+    }
+
+    #[test]
+    fn ticks_keep_running_while_animating() {
+        assert!(!should_pause_ticks(
+            Duration::from_secs(3600),
+            true,
+            true,
+            false
+        ));
+        assert!(!should_pause_ticks(Duration::ZERO, false, true, true));
+    }
+
+    #[test]
+    fn ticks_pause_immediately_when_animations_are_suppressed() {
+        assert!(should_pause_ticks(Duration::ZERO, false, false, true));
+    }
+
+    #[test]
+    fn paused_loop_wakes_as_often_as_the_idle_tick() {
+        let idle_tick = Duration::from_secs_f64(1.0 / IDLE_FRAME_RATE);
+        assert!(PAUSED_POLL_TIMEOUT <= idle_tick);
+    }
+
+    #[test]
+    fn command_preview_goes_on_its_own_line() {
+        let info = bash_funcs::CommandWordInfo::Function {
+            command: "mkcd".to_string(),
+            source_file: Some("/home/me/.bashrc".to_string()),
+            line: Some(12),
+        };
+        assert_eq!(
+            with_command_preview(info.to_description(), info.definition_preview().as_deref()),
+            "function /home/me/.bashrc:12\nmkcd () { mkdir -p \"$1\" && cd \"$1\"; }"
+        );
+
+        let alias = bash_funcs::get_command_info("gst");
+        assert_eq!(alias.definition_preview(), None);
+        assert_eq!(
+            with_command_preview(
+                alias.to_description(),
+                alias.definition_preview().as_deref()
+            ),
+            "alias: git status"
+        );
+    }
+
+    #[test]
+    fn submit_transforms_run_after_history_expansion() {
+        let mut transforms = crate::transforms::TransformRegistry::default();
+        transforms.register(
+            "no-pager",
+            Box::new(|command| {
+                command
+                    .strip_prefix("git ")
+                    .map(|rest| format!("git --no-pager {}", rest))
+            }),
+        );
+        assert_eq!(
+            rewrite_for_submit("!!", Some("git log".to_string()), &transforms),
+            "git --no-pager log"
+        );
+        assert_eq!(
+            rewrite_for_submit("git diff", None, &transforms),
+            "git --no-pager diff"
+        );
+        let none = crate::transforms::TransformRegistry::default();
+        assert_eq!(
+            rewrite_for_submit("!!", Some("ls".to_string()), &none),
+            "ls"
+        );
+    }
+
+    #[test]
+    fn get_command_falls_back_once_the_timeout_passes() {
+        let timeout = Some(Duration::from_secs(2));
+        assert_eq!(get_command_time_left(Duration::ZERO, None), None);
+        assert_eq!(get_command_time_left(Duration::from_secs(3600), None), None);
+        assert_eq!(
+            get_command_time_left(Duration::from_millis(500), timeout),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            get_command_time_left(Duration::from_secs(2), timeout),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            get_command_time_left(Duration::from_secs(5), timeout),
+            Some(Duration::ZERO)
+        );
+    }
+
+    /// Input already queued in the terminal.
+    struct QueuedEvents(std::collections::VecDeque<char>);
+
+    impl EventSource<char> for QueuedEvents {
+        fn poll_event(&mut self, _timeout: Duration) -> std::io::Result<Option<char>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    /// Stands in for the app and terminal in `App::run`: records handled
+    /// input, stops running at `stop_at`, and counts frames drawn.
+    #[derive(Default)]
+    struct MockTerminal {
+        handled: Vec<char>,
+        stop_at: Option<char>,
+        draws: usize,
+    }
+
+    impl FrameInput<char> for MockTerminal {
+        fn handle_event(&mut self, event: char) -> bool {
+            self.handled.push(event);
+            true
+        }
+
+        fn is_running(&self) -> bool {
+            self.stop_at.is_none_or(|c| !self.handled.contains(&c))
+        }
+    }
+
+    impl MockTerminal {
+        fn run_frame(&mut self, events: &mut QueuedEvents, budget: Duration) {
+            if handle_frame_input(events, self, Duration::ZERO, budget).unwrap() == Some(true) {
+                self.draws += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn queued_key_events_are_drawn_once() {
+        let mut events = QueuedEvents("hello".chars().collect());
+        let mut terminal = MockTerminal::default();
+
+        terminal.run_frame(&mut events, REDRAW_COALESCE_WINDOW);
+
+        assert_eq!(terminal.handled, vec!['h', 'e', 'l', 'l', 'o']);
+        assert!(events.0.is_empty());
+        assert_eq!(terminal.draws, 1);
+    }
+
+    #[test]
+    fn draining_stops_when_the_app_stops_running() {
+        let mut events = QueuedEvents("ls\nnext".chars().collect());
+        let mut terminal = MockTerminal {
+            stop_at: Some('\n'),
+            ..Default::default()
+        };
+
+        terminal.run_frame(&mut events, REDRAW_COALESCE_WINDOW);
+
+        assert_eq!(terminal.handled, vec!['l', 's', '\n']);
+        assert_eq!(events.0.iter().collect::<String>(), "next");
+        assert_eq!(terminal.draws, 1);
+    }
+
+    #[test]
+    fn no_draining_without_budget() {
+        let mut events = QueuedEvents("ab".chars().collect());
+        let mut terminal = MockTerminal::default();
+
+        terminal.run_frame(&mut events, Duration::ZERO);
+        terminal.run_frame(&mut events, Duration::ZERO);
+        // Nothing left to read: no frame is drawn.
+        terminal.run_frame(&mut events, Duration::ZERO);
+
+        assert_eq!(terminal.handled, vec!['a', 'b']);
+        assert_eq!(terminal.draws, 2);
+    }
+}
@@ -11,7 +11,12 @@ use crate::app::{App, ContentMode, FlycompPromptSelection, TabCompletionHandle};
 use crate::bash_funcs::{self, QuoteType};
 use crate::content_utils::{self, ansi_string_to_spans};
 use crate::globbing::PathPatternExpansion;
+use crate::help_completion;
 use crate::iter_first_last::FirstLast;
+use crate::kill_completion::{ProcessArg, SignalForm};
+use crate::project_completion::{self, ProjectCompletion};
+use crate::settings::Settings;
+use crate::snake_animation::SpinnerAnimation;
 use crate::tab_completion_context::CompType;
 use crate::text_buffer::SubString;
 use crate::users;
@@ -48,11 +53,14 @@ use skim::fuzzy_matcher::arinae::ArinaeMatcher;
 //   if (iscompgen && iscompleting == 0 && rl_completion_found_quote == 0
 //   && rl_filename_dequoting_function) { ... }
 
+/// Run the compspec for `initial_command_word`, through the completion cache
+/// when `use_cache` is set.
 fn run_comp_spec_completion(
     completion_context: &tab_completion_context::CompletionContext,
     initial_command_word: &str,
+    use_cache: bool,
 ) -> Option<ActiveSuggestionsBuilder> {
-    let poss_alias = bash_funcs::find_alias(initial_command_word);
+    let poss_alias = bash_funcs::find_alias_or_log(initial_command_word);
     log::debug!(
         "Checking for alias for command word '{}': {:?}",
         initial_command_word,
@@ -81,13 +89,23 @@ fn run_comp_spec_completion(
     if alias_expanded_command_word == "flyline" {
         run_flyline_compspec(alias_expanded_completion_context)
     } else {
-        let poss_completions = bash_funcs::run_programmable_completions(
-            alias_expanded_full_command,
-            &alias_expanded_command_word,
-            alias_expanded_word_under_cursor,
-            alias_expanded_cursor_byte_pos,
-            alias_expanded_word_under_cursor_end,
-        );
+        let poss_completions = if use_cache {
+            bash_funcs::run_programmable_completions_cached(
+                alias_expanded_full_command,
+                &alias_expanded_command_word,
+                alias_expanded_word_under_cursor,
+                alias_expanded_cursor_byte_pos,
+                alias_expanded_word_under_cursor_end,
+            )
+        } else {
+            bash_funcs::run_programmable_completions(
+                alias_expanded_full_command,
+                &alias_expanded_command_word,
+                alias_expanded_word_under_cursor,
+                alias_expanded_cursor_byte_pos,
+                alias_expanded_word_under_cursor_end,
+            )
+        };
 
         match poss_completions {
             Ok(comp_result) => {
@@ -185,6 +203,38 @@ fn run_flyline_compspec(
     }
 }
 
+/// Cache entries filled while completing in the forked child. The child
+/// `_exit`s afterwards, so they are sent back with the result and merged
+/// into the parent's caches, which later forks inherit.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct ChildCacheFills {
+    programmable: Vec<bash_funcs::CompletionCacheEntry>,
+    help_options: Vec<(String, Vec<String>)>,
+    makefile_targets: Vec<crate::make_completion::CacheEntry>,
+    project_names: Vec<project_completion::CacheEntry>,
+    commit_messages: Vec<crate::git_completion::CacheEntry>,
+}
+
+impl ChildCacheFills {
+    fn take() -> Self {
+        Self {
+            programmable: bash_funcs::take_filled_completion_cache(),
+            help_options: help_completion::take_filled_cache(),
+            makefile_targets: crate::make_completion::take_filled_cache(),
+            project_names: project_completion::take_filled_cache(),
+            commit_messages: crate::git_completion::take_filled_cache(),
+        }
+    }
+
+    fn merge(self) {
+        bash_funcs::merge_completion_cache(self.programmable);
+        help_completion::merge_cache(self.help_options);
+        crate::make_completion::merge_cache(self.makefile_targets);
+        project_completion::merge_cache(self.project_names);
+        crate::git_completion::merge_cache(self.commit_messages);
+    }
+}
+
 /// Top-level completion entry point used by `start_tab_complete` and tests.
 ///
 /// Calls `gen_completions_uncomitted` (which may yield a partially-processed
@@ -197,11 +247,13 @@ fn run_flyline_compspec(
 /// expectations are deterministic.
 pub(crate) fn gen_completions_internal(
     completion_context: &tab_completion_context::CompletionContext,
+    settings: &Settings,
     auto_started: bool,
     will_run_flycomp_if_prog_comp_is_useless: bool,
 ) -> Option<ActiveSuggestionsBuilder> {
     let mut builder = gen_completions_uncomitted(
         completion_context,
+        settings,
         auto_started,
         will_run_flycomp_if_prog_comp_is_useless,
     )?;
@@ -228,12 +280,15 @@ pub(crate) fn gen_completions_internal(
 
 fn gen_completions_uncomitted(
     completion_context: &tab_completion_context::CompletionContext,
+    settings: &Settings,
     auto_started: bool,
     will_run_flycomp_if_prog_comp_is_useless: bool,
 ) -> Option<ActiveSuggestionsBuilder> {
     log::debug!("Completion context: {:#?}", completion_context);
 
     let word_under_cursor = &completion_context.word_under_cursor;
+    let only_path_fallbacks =
+        unknown_command_arg(completion_context) && !looks_like_path(word_under_cursor.as_ref());
 
     for comp_type in &completion_context.comp_types() {
         log::debug!("Processing completion type: {:?}", comp_type);
@@ -244,8 +299,11 @@ fn gen_completions_uncomitted(
             }
             CompType::FirstWord => {
                 log::debug!("CompType::FirstWord for: {}", word_under_cursor.as_ref());
-                let completions =
-                    tab_complete_first_word(word_under_cursor.as_ref(), word_under_cursor.as_ref());
+                let completions = tab_complete_first_word(
+                    word_under_cursor.as_ref(),
+                    word_under_cursor.as_ref(),
+                    settings,
+                );
                 log::debug!(
                     "CompType::FirstWord found {} completions for prefix: {}",
                     completions.len(),
@@ -280,9 +338,11 @@ fn gen_completions_uncomitted(
                 // https://www.reddit.com/r/bash/comments/eqwitd/programmable_completion_on_expanded_aliases_not/
                 // Since aliases are the highest priority in command word resolution,
                 // If it is an alias, lets expand it here for better completion results.
-                if let Some(mut builder) =
-                    run_comp_spec_completion(completion_context, initial_command_word)
-                {
+                if let Some(mut builder) = run_comp_spec_completion(
+                    completion_context,
+                    initial_command_word,
+                    settings.completion_cache,
+                ) {
                     log::debug!(
                         "CompType::CommandComp found {} completions for command word: {}",
                         builder.len(),
@@ -297,11 +357,48 @@ fn gen_completions_uncomitted(
                         return Some(builder.with_comp_type(comp_type.clone()));
                     }
                 }
+
+                if initial_command_word == "make" && !word_under_cursor.as_ref().starts_with('-') {
+                    let completions = tab_complete_make_targets(word_under_cursor.as_ref());
+                    log::debug!(
+                        "CompType::CommandComp found {} Makefile targets",
+                        completions.len()
+                    );
+                    if !completions.is_empty() {
+                        return Some(
+                            ActiveSuggestionsBuilder::from_processed(completions)
+                                .with_comp_type(comp_type.clone()),
+                        );
+                    }
+                }
+
+                if word_under_cursor.as_ref().starts_with('-') && settings.help_completion {
+                    let completions =
+                        tab_complete_help_options(initial_command_word, word_under_cursor.as_ref());
+                    log::debug!(
+                        "CompType::CommandComp found {} --help options for {}",
+                        completions.len(),
+                        initial_command_word
+                    );
+                    if !completions.is_empty() {
+                        return Some(
+                            ActiveSuggestionsBuilder::from_processed(completions)
+                                .with_comp_type(comp_type.clone()),
+                        );
+                    }
+                }
             }
 
             CompType::FuzzyCommandComp {
                 command_word: initial_command_word,
             } => {
+                if unknown_command_arg(completion_context) {
+                    log::debug!(
+                        "Skipping FuzzyCommandComp because {} is not a known command",
+                        initial_command_word
+                    );
+                    continue;
+                }
                 let original_wuc = word_under_cursor.as_ref();
                 log::debug!("CompType::FuzzyCommandComp for: {}", original_wuc);
 
@@ -315,9 +412,11 @@ fn gen_completions_uncomitted(
 
                 let fuzzy_completion_context = completion_context.with_wuc_replaced(&new_wuc);
 
-                if let Some(mut builder) =
-                    run_comp_spec_completion(&fuzzy_completion_context, initial_command_word)
-                {
+                if let Some(mut builder) = run_comp_spec_completion(
+                    &fuzzy_completion_context,
+                    initial_command_word,
+                    settings.completion_cache,
+                ) {
                     let matcher = ArinaeMatcher::new(skim::CaseMatching::Smart, true);
                     let pattern = original_wuc.strip_prefix(&new_wuc).unwrap_or(original_wuc);
 
@@ -367,10 +466,97 @@ fn gen_completions_uncomitted(
                 }
             }
 
+            CompType::AssignmentValue { var_name } => {
+                log::debug!(
+                    "CompType::AssignmentValue for {}",
+                    word_under_cursor.as_ref()
+                );
+                let completions = tab_complete_assignment_value(
+                    var_name,
+                    completion_context.context_until_cursor(),
+                    completion_context.word_left_of_cursor(),
+                );
+                log::debug!(
+                    "CompType::AssignmentValue found {} completions for {}",
+                    completions.len(),
+                    var_name
+                );
+                if !completions.is_empty() {
+                    return Some(
+                        ActiveSuggestionsBuilder::from_processed(completions)
+                            .with_comp_type(comp_type.clone()),
+                    );
+                }
+            }
+            CompType::GitCommitMessage => {
+                log::debug!(
+                    "CompType::GitCommitMessage for {}",
+                    word_under_cursor.as_ref()
+                );
+                let completions = tab_complete_git_commit_message(word_under_cursor.as_ref());
+                log::debug!(
+                    "CompType::GitCommitMessage found {} completions for prefix: {}",
+                    completions.len(),
+                    word_under_cursor.as_ref()
+                );
+                if !completions.is_empty() {
+                    return Some(
+                        ActiveSuggestionsBuilder::from_processed(completions)
+                            .with_comp_type(comp_type.clone())
+                            .with_nosort(true),
+                    );
+                }
+            }
+            CompType::KillSignal { form } => {
+                log::debug!("CompType::KillSignal for {}", word_under_cursor.as_ref());
+                let completions = tab_complete_kill_signal(*form, word_under_cursor.as_ref());
+                log::debug!(
+                    "CompType::KillSignal found {} completions for prefix: {}",
+                    completions.len(),
+                    word_under_cursor.as_ref()
+                );
+                if !completions.is_empty() {
+                    return Some(
+                        ActiveSuggestionsBuilder::from_processed(completions)
+                            .with_comp_type(comp_type.clone())
+                            .with_nosort(true),
+                    );
+                }
+            }
+            CompType::ProcessArg { arg } => {
+                log::debug!("CompType::ProcessArg for {}", word_under_cursor.as_ref());
+                let completions = tab_complete_process_arg(*arg, word_under_cursor.as_ref());
+                log::debug!(
+                    "CompType::ProcessArg found {} completions for prefix: {}",
+                    completions.len(),
+                    word_under_cursor.as_ref()
+                );
+                if !completions.is_empty() {
+                    return Some(
+                        ActiveSuggestionsBuilder::from_processed(completions)
+                            .with_comp_type(comp_type.clone())
+                            .with_nosort(true),
+                    );
+                }
+            }
+            CompType::ProjectName { kind } => {
+                log::debug!("CompType::ProjectName for {}", word_under_cursor.as_ref());
+                let completions = tab_complete_project_name(*kind, word_under_cursor.as_ref());
+                log::debug!(
+                    "CompType::ProjectName found {} completions for prefix: {}",
+                    completions.len(),
+                    word_under_cursor.as_ref()
+                );
+                if !completions.is_empty() {
+                    return Some(
+                        ActiveSuggestionsBuilder::from_processed(completions)
+                            .with_comp_type(comp_type.clone()),
+                    );
+                }
+            }
             CompType::EnvVariable => {
                 log::debug!("CompType::EnvVariable for {}", word_under_cursor.as_ref());
-                let matching_vars =
-                    bash_funcs::get_all_variables_with_prefix(word_under_cursor.as_ref());
+                let matching_vars = tab_complete_env_variable(word_under_cursor.as_ref());
                 log::debug!(
                     "CompType::EnvVariable found {} completions for prefix: {}",
                     matching_vars.len(),
@@ -378,10 +564,8 @@ fn gen_completions_uncomitted(
                 );
                 if !matching_vars.is_empty() {
                     return Some(
-                        ActiveSuggestionsBuilder::from_processed(
-                            ProcessedSuggestion::from_string_vec(matching_vars, "", " "),
-                        )
-                        .with_comp_type(comp_type.clone()),
+                        ActiveSuggestionsBuilder::from_processed(matching_vars)
+                            .with_comp_type(comp_type.clone()),
                     );
                 }
             }
@@ -427,9 +611,10 @@ fn gen_completions_uncomitted(
                     continue;
                 }
                 log::debug!("CompType::GlobExpansion for {}", word_under_cursor.as_ref());
-                let (completions, comp_res_flags) = tab_complete_glob_expansion(
+                let (completions, comp_res_flags, _) = tab_complete_glob_expansion(
                     word_under_cursor.as_ref(),
                     word_under_cursor.as_ref(),
+                    settings.max_glob_results,
                 );
 
                 log::debug!(
@@ -501,13 +686,25 @@ fn gen_completions_uncomitted(
                     "CompType::FilenameExpansion for: {}",
                     word_under_cursor.as_ref()
                 );
-                let (completions, _comp_res_flags) = tab_complete_glob_expansion(
+                let (mut completions, comp_res_flags, truncated_to) = tab_complete_glob_expansion(
                     &(completion_context.word_left_of_cursor().to_string()
                         + "*"
                         + completion_context.word_right_of_cursor()),
                     word_under_cursor.as_ref(),
+                    settings.max_glob_results,
                 );
 
+                if completes_directories_only(completion_context) {
+                    completions.retain(|sug| sug.full_path.as_ref().is_some_and(|p| p.is_dir()));
+                    completions.extend(tab_complete_cdpath(
+                        completion_context.word_left_of_cursor(),
+                        word_under_cursor.as_ref(),
+                        comp_res_flags,
+                    ));
+                    completions.sort_by(|a, b| a.match_text().cmp(b.match_text()));
+                    completions.dedup_by(|a, b| a.raw_text == b.raw_text);
+                }
+
                 log::debug!(
                     "CompType::FilenameExpansion found {} completions for pattern: {}",
                     completions.len(),
@@ -519,7 +716,8 @@ fn gen_completions_uncomitted(
                             .with_insert_common_prefix(
                                 completion_context.word_right_of_cursor().is_empty(),
                             )
-                            .with_comp_type(comp_type.clone()),
+                            .with_comp_type(comp_type.clone())
+                            .with_truncated_to(truncated_to),
                     );
                 }
             }
@@ -530,6 +728,13 @@ fn gen_completions_uncomitted(
                     );
                     continue;
                 }
+                if only_path_fallbacks {
+                    log::debug!(
+                        "Skipping FuzzyFilenameExpansion because the command is unknown and {} does not look like a path",
+                        word_under_cursor.as_ref()
+                    );
+                    continue;
+                }
                 log::debug!(
                     "CompType::FuzzyFilenameExpansion for: {}",
                     word_under_cursor.as_ref()
@@ -558,6 +763,88 @@ fn gen_completions_uncomitted(
     None
 }
 
+/// Complete the variable name in `$NAME` or `${NAME}`. The braced form keeps
+/// its closing `}` as the suffix; the bare form gets no suffix so the user
+/// can carry on typing e.g. `$HOME/`.
+fn tab_complete_env_variable(word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    let (prefix, name_prefix, suffix) = match word_under_cursor.strip_prefix("${") {
+        Some(rest) => ("${", rest.trim_end_matches('}'), "}"),
+        None => (
+            "$",
+            word_under_cursor
+                .strip_prefix('$')
+                .unwrap_or(word_under_cursor),
+            "",
+        ),
+    };
+    bash_funcs::get_all_variables_with_prefix(name_prefix)
+        .into_iter()
+        .map(|var| {
+            let name = var.strip_prefix('$').unwrap_or(&var).to_string();
+            ProcessedSuggestion::new(name, prefix, suffix)
+        })
+        .collect()
+}
+
+/// Whether the cursor is on an argument of a command bash doesn't know about.
+/// Its compspec (if any) won't help, so only filename completion is useful.
+fn unknown_command_arg(completion_context: &tab_completion_context::CompletionContext) -> bool {
+    let words: Vec<&str> = completion_context
+        .context
+        .as_ref()
+        .split_whitespace()
+        .collect();
+    let Some(command_idx) = command_word_index(&words) else {
+        return false;
+    };
+    let until_cursor = completion_context.context_until_cursor();
+    let words_before_cursor = if until_cursor.ends_with(char::is_whitespace) {
+        until_cursor.split_whitespace().count()
+    } else {
+        until_cursor.split_whitespace().count().saturating_sub(1)
+    };
+    words_before_cursor > command_idx
+        && !bash_funcs::get_command_info(words[command_idx]).is_known()
+}
+
+/// Index of the command name in `words`, skipping leading `NAME=value`
+/// assignments and redirections (`FOO=1 2>/dev/null mycmd`).
+fn command_word_index(words: &[&str]) -> Option<usize> {
+    let mut idx = 0;
+    while let Some(word) = words.get(idx) {
+        if crate::assignment_completion::split_assignment(word).is_some() {
+            idx += 1;
+        } else if let Some(takes_target) = redirection_takes_next_word(word) {
+            idx += if takes_target { 2 } else { 1 };
+        } else {
+            return Some(idx);
+        }
+    }
+    None
+}
+
+/// `Some(true)` for a redirection operator whose target is the next word
+/// (`>`, `2>>`), `Some(false)` when the target is attached (`>out`, `2>&1`),
+/// `None` when `word` isn't a redirection.
+fn redirection_takes_next_word(word: &str) -> Option<bool> {
+    let op = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    let op = op
+        .strip_prefix('&')
+        .filter(|rest| rest.starts_with('>'))
+        .unwrap_or(op);
+    if !op.starts_with(['<', '>']) {
+        return None;
+    }
+    Some(op.trim_start_matches(['<', '>', '&', '|']).is_empty())
+}
+
+/// Whether `word` is clearly a path (`./`, `../`, `/`, `~/` or containing a
+/// `/`), as opposed to an arbitrary argument.
+fn looks_like_path(word: &str) -> bool {
+    let word = word.trim_start_matches(['"', '\'']);
+    word.starts_with('.') || word.starts_with('~') || word.contains('/')
+}
+
 fn filter_out_non_executables(paths: Vec<UnprocessedSuggestion>) -> Vec<UnprocessedSuggestion> {
     paths
         .into_iter()
@@ -575,8 +862,7 @@ fn filter_out_non_executables(paths: Vec<UnprocessedSuggestion>) -> Vec<Unproces
                     return true;
                 }
                 if meta.is_file() {
-                    use std::os::unix::fs::PermissionsExt;
-                    return meta.permissions().mode() & 0o111 != 0;
+                    return crate::users::is_executable_by_current_user(&meta);
                 }
             }
             true
@@ -584,7 +870,11 @@ fn filter_out_non_executables(paths: Vec<UnprocessedSuggestion>) -> Vec<Unproces
         .collect()
 }
 
-fn tab_complete_first_word(command: &str, word_under_cursor: &str) -> ActiveSuggestionsBuilder {
+fn tab_complete_first_word(
+    command: &str,
+    word_under_cursor: &str,
+    settings: &Settings,
+) -> ActiveSuggestionsBuilder {
     log::debug!("Generating first word completions for: '{}'", command);
     if command.is_empty() {
         return ActiveSuggestionsBuilder::new();
@@ -592,8 +882,11 @@ fn tab_complete_first_word(command: &str, word_under_cursor: &str) -> ActiveSugg
 
     if command.starts_with('.') || command.contains('/') || command.starts_with('~') {
         // Path to executable
-        let (files, _comp_res_flags) =
-            tab_complete_glob_expansion(&(command.to_string() + "*"), word_under_cursor);
+        let (files, _comp_res_flags, _) = tab_complete_glob_expansion(
+            &(command.to_string() + "*"),
+            word_under_cursor,
+            settings.max_glob_results,
+        );
         let executable_files = filter_out_non_executables(files);
         return ActiveSuggestionsBuilder::from_unprocessed(executable_files);
     }
@@ -602,7 +895,9 @@ fn tab_complete_first_word(command: &str, word_under_cursor: &str) -> ActiveSugg
     let mut seen: HashSet<String> = HashSet::new();
     for poss_info in bash_funcs::get_possible_command_words() {
         let cmd_name = poss_info.command();
-        if cmd_name.starts_with(command) && seen.insert(cmd_name.to_string()) {
+        if settings.completion_case.matches_prefix(cmd_name, command)
+            && seen.insert(cmd_name.to_string())
+        {
             res.push(poss_info);
         }
     }
@@ -611,12 +906,32 @@ fn tab_complete_first_word(command: &str, word_under_cursor: &str) -> ActiveSugg
         return ActiveSuggestionsBuilder::new();
     }
 
+    sort_first_word_prefix_matches(&mut res);
+    ActiveSuggestionsBuilder::from_processed(processed_suggestions_from_command_info(res))
+}
+
+/// Shortest commands first, so exactly what was typed (or the closest
+/// thing to it) is at the top.
+fn sort_first_word_prefix_matches(res: &mut [bash_funcs::CommandWordInfo]) {
     res.sort_by(|a, b| {
         let a_cmd = a.command();
         let b_cmd = b.command();
         a_cmd.len().cmp(&b_cmd.len()).then(a_cmd.cmp(b_cmd))
     });
-    ActiveSuggestionsBuilder::from_processed(processed_suggestions_from_command_info(res))
+}
+
+/// Whether swapping one pair of adjacent characters in `typed` makes it a
+/// prefix of `candidate`, e.g. `gti` for `git`.
+fn is_transposed_prefix(candidate: &str, typed: &str) -> bool {
+    let chars: Vec<char> = typed.chars().collect();
+    (0..chars.len().saturating_sub(1)).any(|i| {
+        if chars[i] == chars[i + 1] {
+            return false;
+        }
+        let mut swapped = chars.clone();
+        swapped.swap(i, i + 1);
+        candidate.starts_with(&swapped.iter().collect::<String>())
+    })
 }
 
 fn processed_suggestions_from_command_info(
@@ -654,27 +969,45 @@ fn tab_complete_fuzzy_first_word(command: &str) -> ActiveSuggestionsBuilder {
         return ActiveSuggestionsBuilder::from_unprocessed(executable_files);
     }
 
+    let res = rank_fuzzy_first_words(bash_funcs::get_possible_command_words(), command);
+    ActiveSuggestionsBuilder::from_processed(processed_suggestions_from_command_info(res))
+}
+
+/// Fuzzy-match command words against `command`, best first. Commands that
+/// `command` would prefix after fixing one swapped pair of letters come
+/// before the rest, since that is the most common typo.
+fn rank_fuzzy_first_words(
+    candidates: impl Iterator<Item = bash_funcs::CommandWordInfo>,
+    command: &str,
+) -> Vec<bash_funcs::CommandWordInfo> {
     let matcher = ArinaeMatcher::new(skim::CaseMatching::Smart, true);
+    let mut transposed = vec![];
     let mut scored = vec![];
 
     let mut seen: HashSet<String> = HashSet::new();
-    for poss_info in bash_funcs::get_possible_command_words() {
+    for poss_info in candidates {
         let cmd_name = poss_info.command();
-        if seen.insert(cmd_name.to_string())
-            && let Some(score) = content_utils::fuzzy_match_with_threshold(
-                &matcher,
-                cmd_name,
-                command,
-                content_utils::FuzzyMatchThreshold::High,
-            )
-        {
+        if !seen.insert(cmd_name.to_string()) {
+            continue;
+        }
+        if is_transposed_prefix(cmd_name, command) {
+            transposed.push(poss_info);
+        } else if let Some(score) = content_utils::fuzzy_match_with_threshold(
+            &matcher,
+            cmd_name,
+            command,
+            content_utils::FuzzyMatchThreshold::High,
+        ) {
             scored.push((score, poss_info));
         }
     }
 
+    sort_first_word_prefix_matches(&mut transposed);
     scored.sort_by(|a, b| b.0.cmp(&a.0));
-    let res = scored.into_iter().map(|(_, info)| info).collect();
-    ActiveSuggestionsBuilder::from_processed(processed_suggestions_from_command_info(res))
+    transposed
+        .into_iter()
+        .chain(scored.into_iter().map(|(_, info)| info))
+        .collect()
 }
 
 /// Core glob expansion logic that works with an already-expanded PathPatternExpansion.
@@ -686,10 +1019,13 @@ fn tab_complete_with_expanded_pattern(
     comp_resultflags: bash_funcs::CompletionFlags,
     wuc: &str,
     should_skip_hidden: bool,
-) -> Vec<UnprocessedSuggestion> {
+    max_results: usize,
+) -> (Vec<UnprocessedSuggestion>, bool) {
     let mut results = Vec::new();
 
-    const MAX_GLOB_RESULTS: usize = 10_000;
+    // Stop walking eventually even for patterns like `/**/*`; the most
+    // relevant `max_results` are picked from what was found.
+    let scan_limit = max_results.saturating_mul(10);
 
     let glob_patterns = expanded.glob_pattern();
 
@@ -701,10 +1037,10 @@ fn tab_complete_with_expanded_pattern(
             continue;
         };
         for path in paths.filter_map(Result::ok) {
-            if results.len() >= MAX_GLOB_RESULTS {
+            if results.len() >= scan_limit {
                 log::debug!(
-                    "Reached maximum glob results limit of {}. Stopping further processing.",
-                    MAX_GLOB_RESULTS
+                    "Reached glob scan limit of {}. Stopping further processing.",
+                    scan_limit
                 );
                 break 'outer;
             }
@@ -745,13 +1081,32 @@ fn tab_complete_with_expanded_pattern(
     }
 
     results.sort_by(|a, b| a.match_text().cmp(b.match_text()));
-    results
+    // Overlapping brace alternatives (e.g. `{foo1,foo*}`) can match the same file twice.
+    results.dedup_by(|a, b| a.raw_text == b.raw_text);
+
+    let truncated = results.len() > max_results;
+    if truncated {
+        // Keep the shortest matches, which are closest to the pattern.
+        results.sort_by(|a, b| {
+            (a.raw_text.len(), a.match_text()).cmp(&(b.raw_text.len(), b.match_text()))
+        });
+        results.truncate(max_results);
+        results.sort_by(|a, b| a.match_text().cmp(b.match_text()));
+    }
+    (results, truncated)
 }
 
+/// Matches of `pattern`, the flags to process them with, and the number shown
+/// when there were more than `max_results` matches.
 fn tab_complete_glob_expansion(
     pattern: &str,
     word_under_cursor: &str,
-) -> (Vec<UnprocessedSuggestion>, bash_funcs::CompletionFlags) {
+    max_results: usize,
+) -> (
+    Vec<UnprocessedSuggestion>,
+    bash_funcs::CompletionFlags,
+    Option<usize>,
+) {
     let mut comp_resultflags = bash_funcs::CompletionFlags::default();
     // We will handle it ourselves because the prefix should not be quoted but the found filename should be.
     // e.g. my_command $PWD/fi<TAB> should expand to:
@@ -765,10 +1120,101 @@ fn tab_complete_glob_expansion(
     log::debug!("found quote type: {:?}", comp_resultflags.quote_type);
 
     let expanded = PathPatternExpansion::new(pattern);
-    let completions =
-        tab_complete_with_expanded_pattern(&expanded, comp_resultflags, word_under_cursor, true);
+    let (completions, truncated) = tab_complete_with_expanded_pattern(
+        &expanded,
+        comp_resultflags,
+        word_under_cursor,
+        true,
+        max_results,
+    );
 
-    (completions, comp_resultflags)
+    (
+        completions,
+        comp_resultflags,
+        truncated.then_some(max_results),
+    )
+}
+
+/// Commands whose only argument is a directory.
+const DIRECTORY_COMMANDS: &[&str] = &["cd", "pushd"];
+
+/// Whether file matches are noise for the command being completed, e.g. `cd`.
+fn completes_directories_only(
+    completion_context: &tab_completion_context::CompletionContext,
+) -> bool {
+    completion_context
+        .context
+        .as_ref()
+        .split_whitespace()
+        .next()
+        .is_some_and(|word| DIRECTORY_COMMANDS.contains(&word))
+}
+
+/// Directories under each entry of `CDPATH` that start with `word_left_of_cursor`,
+/// presented relative to that entry (like `cd` resolves them).
+fn tab_complete_cdpath(
+    word_left_of_cursor: &str,
+    word_under_cursor: &str,
+    comp_resultflags: bash_funcs::CompletionFlags,
+) -> Vec<UnprocessedSuggestion> {
+    // Like bash, only relative names that don't start with `.` or `..` use CDPATH.
+    // Quoted words are left to the normal filename expansion.
+    if comp_resultflags.quote_type.is_some()
+        || word_left_of_cursor.starts_with(['/', '~', '$'])
+        || word_left_of_cursor.starts_with("./")
+        || word_left_of_cursor.starts_with("../")
+    {
+        return vec![];
+    }
+    let Some(cdpath) = bash_funcs::get_envvar_value("CDPATH") else {
+        return vec![];
+    };
+
+    let prefix = bash_funcs::dequoting_function_rust(word_left_of_cursor);
+    let wants_hidden = prefix
+        .rsplit('/')
+        .next()
+        .is_some_and(|s| s.starts_with('.'));
+    let mut results = vec![];
+    for root in cdpath
+        .split(':')
+        .filter(|root| !root.is_empty() && *root != ".")
+    {
+        let root = PathBuf::from(bash_funcs::fully_expand_path(root));
+        let pattern = format!(
+            "{}/{}*",
+            glob::Pattern::escape(&root.to_string_lossy()),
+            glob::Pattern::escape(&prefix)
+        );
+        let Ok(paths) = glob::glob(&pattern) else {
+            continue;
+        };
+        for path in paths.filter_map(Result::ok).filter(|path| path.is_dir()) {
+            let Ok(relative) = path.strip_prefix(&root) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy();
+            let is_hidden = relative
+                .rsplit('/')
+                .next()
+                .is_some_and(|s| s.starts_with('.'));
+            if is_hidden && !wants_hidden {
+                continue;
+            }
+            results.push(UnprocessedSuggestion {
+                raw_text: bash_funcs::quoting_function_rust(
+                    &relative,
+                    QuoteType::Backslash,
+                    false,
+                    false,
+                ),
+                full_path: Some(path.clone()),
+                flags: comp_resultflags,
+                word_under_cursor: word_under_cursor.to_string(),
+            });
+        }
+    }
+    results
 }
 
 /// List all files in the directory implied by `word_under_cursor` and return
@@ -942,6 +1388,133 @@ fn fuzzy_glob_recursive(
     out
 }
 
+/// Complete the value of `var_name=` for a known variable. `assignment` is the
+/// `NAME=value` text up to the cursor; the part of `word_left_of_cursor` before
+/// the typed value (e.g. `=` or `NAME=`) is kept in each suggestion.
+fn tab_complete_assignment_value(
+    var_name: &str,
+    assignment: &str,
+    word_left_of_cursor: &str,
+) -> Vec<ProcessedSuggestion> {
+    let Some((_, typed)) = crate::assignment_completion::split_assignment(assignment) else {
+        return vec![];
+    };
+    let kept = word_left_of_cursor.strip_suffix(typed).unwrap_or("");
+
+    crate::assignment_completion::value_candidates(var_name, typed)
+        .into_iter()
+        .map(|value| ProcessedSuggestion::new(format!("{}{}", kept, value), "", " "))
+        .collect()
+}
+
+fn tab_complete_kill_signal(form: SignalForm, word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    crate::kill_completion::signal_candidates(form, word_under_cursor)
+        .into_iter()
+        .map(|(signal, description)| {
+            ProcessedSuggestion::new(signal, "", " ").with_description(
+                SuggestionDescription::Static(vec![ratatui::text::Span::raw(description)]),
+            )
+        })
+        .collect()
+}
+
+/// Candidates for a process argument: running processes for a PID, shown as
+/// `PID command` but inserting only the PID; running command names for a
+/// name pattern; or user or group names.
+fn tab_complete_process_arg(arg: ProcessArg, word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    let names = match arg {
+        ProcessArg::Pid => {
+            let processes = crate::kill_completion::running_processes();
+            return crate::kill_completion::pid_candidates(&processes, word_under_cursor)
+                .into_iter()
+                .map(|(pid, command)| {
+                    ProcessedSuggestion::new(pid, "", " ").with_description(
+                        SuggestionDescription::Static(vec![ratatui::text::Span::raw(command)]),
+                    )
+                })
+                .collect();
+        }
+        ProcessArg::Name => crate::kill_completion::name_candidates(
+            &crate::kill_completion::running_processes(),
+            word_under_cursor,
+        ),
+        ProcessArg::User => crate::users::get_all_users()
+            .iter()
+            .map(|user| user.username.clone())
+            .filter(|name| name.starts_with(word_under_cursor))
+            .collect(),
+        ProcessArg::Group => crate::users::get_all_groups()
+            .iter()
+            .filter(|name| name.starts_with(word_under_cursor))
+            .cloned()
+            .collect(),
+    };
+    names
+        .into_iter()
+        .map(|name| ProcessedSuggestion::new(name, "", " "))
+        .collect()
+}
+
+/// Fallback for commands without a compspec: long options scraped from
+/// `<command> --help`.
+fn tab_complete_help_options(command: &str, word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    help_completion::help_options(command)
+        .into_iter()
+        .filter(|option| option.starts_with(word_under_cursor))
+        .map(|option| ProcessedSuggestion::new(option, "", " "))
+        .collect()
+}
+
+/// Fallback for `make` without a compspec: targets of the makefile in the
+/// current directory.
+fn tab_complete_make_targets(word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return vec![];
+    };
+    crate::make_completion::makefile_targets(&cwd)
+        .into_iter()
+        .filter(|target| target.starts_with(word_under_cursor))
+        .map(|target| ProcessedSuggestion::new(target, "", " "))
+        .collect()
+}
+
+fn tab_complete_project_name(
+    kind: ProjectCompletion,
+    word_under_cursor: &str,
+) -> Vec<ProcessedSuggestion> {
+    let Ok(cwd) = std::env::current_dir() else {
+        return vec![];
+    };
+    project_completion::names(kind, &cwd)
+        .into_iter()
+        .filter(|name| name.starts_with(word_under_cursor))
+        .map(|name| ProcessedSuggestion::new(name, "", " "))
+        .collect()
+}
+
+/// Offer recent commit messages, quoted, for the value of `git commit -m`.
+/// The word under the cursor may already carry an opening quote, in which case
+/// the same quote style is used.
+fn tab_complete_git_commit_message(word_under_cursor: &str) -> Vec<ProcessedSuggestion> {
+    let quote_type = match bash_funcs::find_quote_type(word_under_cursor) {
+        Some(QuoteType::DoubleQuote) => QuoteType::DoubleQuote,
+        _ => QuoteType::SingleQuote,
+    };
+    let typed = bash_funcs::dequoting_function_rust(word_under_cursor);
+
+    crate::git_completion::recent_commit_messages()
+        .into_iter()
+        .filter(|message| message.starts_with(&typed))
+        .map(|message| {
+            ProcessedSuggestion::new(
+                bash_funcs::quoting_function_rust(&message, quote_type, true, true),
+                "",
+                " ",
+            )
+        })
+        .collect()
+}
+
 fn tab_complete_hostname_expansion(pattern: &str) -> Vec<ProcessedSuggestion> {
     let at_idx = if let Some(idx) = pattern.rfind('@') {
         idx
@@ -970,28 +1543,27 @@ fn tab_complete_hostname_expansion(pattern: &str) -> Vec<ProcessedSuggestion> {
 }
 
 fn tab_complete_tilde_expansion(pattern: &str) -> Vec<ProcessedSuggestion> {
-    let user_pattern = if let Some(stripped) = pattern.strip_prefix('~') {
-        stripped
-    } else {
+    tilde_user_suggestions(pattern, users::get_all_users())
+}
+
+/// `~username` — complete to `~user/` for each matching user, described by
+/// their home directory. No trailing space: the user likely continues the path.
+fn tilde_user_suggestions(pattern: &str, all_users: &[users::User]) -> Vec<ProcessedSuggestion> {
+    let Some(user_pattern) = pattern.strip_prefix('~') else {
         return vec![];
     };
 
-    // `~username` — find matching users from the users module
-    let mut suggestions = Vec::new();
-
-    for user in users::get_all_users() {
-        if user.username.starts_with(user_pattern) {
-            suggestions.push(ProcessedSuggestion::new(
-                if user.home_dir.ends_with('/') {
-                    user.home_dir.clone()
-                } else {
-                    format!("{}/", user.home_dir)
-                },
-                "",
-                "",
-            ));
-        }
-    }
+    let mut suggestions: Vec<ProcessedSuggestion> = all_users
+        .iter()
+        .filter(|user| user.username.starts_with(user_pattern))
+        .map(|user| {
+            ProcessedSuggestion::new(format!("~{}/", user.username), "", "").with_description(
+                SuggestionDescription::Static(vec![ratatui::text::Span::raw(
+                    user.home_dir.clone(),
+                )]),
+            )
+        })
+        .collect();
 
     suggestions.sort_by(|a, b| a.s.cmp(&b.s));
     suggestions.dedup_by(|a, b| a.s == b.s);
@@ -1134,6 +1706,8 @@ impl App<'_> {
             return;
         }
 
+        let builder = builder.with_trailing(self.settings.completion_trailing);
+
         if auto_started {
             if builder.is_empty() {
                 self.content_mode = ContentMode::Normal;
@@ -1165,6 +1739,11 @@ impl App<'_> {
             );
             self.content_mode = ContentMode::TabCompletion(Box::new(suggestions));
         } else {
+            let builder = if self.settings.confirm_single_completion {
+                builder.with_confirm_single_completion()
+            } else {
+                builder
+            };
             let outcome = apply_tab_complete_to_buffer(&mut self.buffer, &builder, &wuc_substring);
             match outcome {
                 TabCompleteBufferOutcome::SoloAccepted => {
@@ -1273,6 +1852,7 @@ impl App<'_> {
             let thread_start = std::time::Instant::now();
             let result = gen_completions_internal(
                 &completion_context_owned,
+                &self.settings,
                 auto_started,
                 will_run_flycomp_if_prog_comp_is_useless,
             );
@@ -1281,7 +1861,11 @@ impl App<'_> {
             log::info!("Child process completed");
             let child_logs = crate::logging::take_logs();
 
-            let data = (result.map(|r| (r, elapsed)), child_logs);
+            let data = (
+                result.map(|r| (r, elapsed)),
+                child_logs,
+                ChildCacheFills::take(),
+            );
             if let Ok(serialized) = serde_json::to_vec(&data) {
                 let mut file = unsafe { std::fs::File::from_raw_fd(write_fd) };
                 use std::io::Write;
@@ -1315,6 +1899,7 @@ impl App<'_> {
                     let payload: Option<(
                         Option<(ActiveSuggestionsBuilder, std::time::Duration)>,
                         Vec<String>,
+                        ChildCacheFills,
                     )> = if std::io::Read::read_exact(&mut file, &mut len_buf).is_err() {
                         None
                     } else {
@@ -1327,11 +1912,13 @@ impl App<'_> {
                         }
                     };
 
-                    let (completion_res, child_logs) = if let Some((res, logs)) = payload {
-                        (res, logs)
-                    } else {
-                        (None, vec![])
-                    };
+                    let (completion_res, child_logs) =
+                        if let Some((res, logs, cache_fills)) = payload {
+                            cache_fills.merge();
+                            (res, logs)
+                        } else {
+                            (None, vec![])
+                        };
 
                     // Replay child logs inside the parent process
                     for log_line in child_logs {
@@ -1389,6 +1976,7 @@ impl App<'_> {
                         start_time,
                         auto_started,
                         last_active_suggestions,
+                        spinner: SpinnerAnimation::new(),
                     };
                 }
                 Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
@@ -1419,6 +2007,7 @@ impl App<'_> {
 mod tab_completion_tests {
     use super::*;
     use crate::active_suggestions::{FilteredItem, ProcessedSuggestion, UnprocessedSuggestion};
+    use crate::settings::CompletionTrailing;
     use crate::tab_completion_context::{CompletionContext, get_completion_context};
     use crate::text_buffer::TextBuffer;
     use rusty_fork::rusty_fork_test;
@@ -1465,7 +2054,9 @@ mod tab_completion_tests {
     ) -> Option<(ActiveSuggestionsBuilder, CompletionContext<'static>)> {
         crate::logging::init_for_tests_once();
         let comp_context = get_completion_context(buffer.buffer(), buffer.cursor_byte_pos());
-        let Some(builder) = gen_completions_internal(&comp_context, false, false) else {
+        let Some(builder) =
+            gen_completions_internal(&comp_context, &Settings::default(), false, false)
+        else {
             return None;
         };
         Some((builder, comp_context.into_owned()))
@@ -1558,6 +2149,19 @@ mod tab_completion_tests {
     }
 
     rusty_fork_test! {
+        #[test]
+        fn command_word_skips_assignments_and_redirections() {
+            let idx = |line: &str| command_word_index(&line.split_whitespace().collect::<Vec<_>>());
+            assert_eq!(idx("mycmd x"), Some(0));
+            assert_eq!(idx("FOO=1 mycmd x"), Some(1));
+            assert_eq!(idx("FOO=1 BAR= mycmd"), Some(2));
+            assert_eq!(idx("2>/dev/null mycmd"), Some(1));
+            assert_eq!(idx("> out.txt FOO=1 mycmd"), Some(3));
+            assert_eq!(idx("2>&1 &> log mycmd"), Some(3));
+            assert_eq!(idx("FOO=1"), None);
+            assert_eq!(idx("echo FOO=1"), Some(0));
+        }
+
         // ------- dummy git completion (clap-based, no bash symbols) -------
 
         #[test]
@@ -1567,6 +2171,108 @@ mod tab_completion_tests {
             assert_eq!(names, vec!["us@localhost"]);
         }
 
+        #[test]
+        fn git_commit_message_completion() {
+            let repo = std::env::temp_dir().join(format!("flyline_test_repo_{}", rand::random::<u32>()));
+            std::fs::create_dir_all(repo.join(".git")).unwrap();
+            std::env::set_current_dir(&repo).unwrap();
+
+            let actual = run_completion("git commit -m \"Fix");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["\"Fix cursor flicker on resize\""]);
+
+            let actual = run_completion("gcm Handle");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["'Handle '\\''quoted'\\'' args in completions'"]);
+
+            let _ = std::fs::remove_dir_all(repo);
+        }
+
+        #[test]
+        fn first_word_completion_case_modes() {
+            use crate::settings::CompletionCase;
+
+            let names = |typed: &str, completion_case: CompletionCase| -> Vec<String> {
+                let settings = Settings {
+                    completion_case,
+                    ..Settings::default()
+                };
+                tab_complete_first_word(typed, typed, &settings)
+                    .processed
+                    .into_iter()
+                    .map(|s| s.s)
+                    .collect()
+            };
+
+            assert_eq!(names("fu", CompletionCase::Sensitive), vec!["function"]);
+            assert!(names("FU", CompletionCase::Sensitive).is_empty());
+
+            assert_eq!(names("FU", CompletionCase::Insensitive), vec!["function"]);
+            assert_eq!(names("wHi", CompletionCase::Insensitive), vec!["while"]);
+
+            assert_eq!(names("fu", CompletionCase::Smart), vec!["function"]);
+            assert!(names("Fu", CompletionCase::Smart).is_empty());
+
+            assert_eq!(CompletionCase::load_at_startup(), CompletionCase::Sensitive);
+        }
+
+        #[test]
+        fn tilde_user_completion() {
+            let user = |username: &str, home_dir: &str| users::User {
+                username: username.to_string(),
+                home_dir: home_dir.to_string(),
+            };
+            let all_users = vec![
+                user("partial-match", "/srv/partial"),
+                user("parker", "/home/parker"),
+                user("root", "/root"),
+            ];
+            let names = |pattern: &str| -> Vec<String> {
+                tilde_user_suggestions(pattern, &all_users)
+                    .into_iter()
+                    .map(|s| s.formatted())
+                    .collect()
+            };
+
+            assert_eq!(names("~par"), vec!["~parker/", "~partial-match/"]);
+            assert_eq!(names("~roo"), vec!["~root/"]);
+            assert!(names("~nobody").is_empty());
+            assert!(names("par").is_empty());
+        }
+
+        #[test]
+        fn kill_signal_completion() {
+            let actual = run_completion("kill -TE");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["-TERM"]);
+
+            let actual = run_completion("kill -s HU");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["HUP"]);
+        }
+
+        #[test]
+        fn assignment_value_completion() {
+            let actual = run_completion("EDITOR=vi");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["vi", "vim"]);
+
+            // The `=` (or `SHELL=`) left of the cursor is kept in each suggestion.
+            let actual = run_completion("SHELL=");
+            let values: Vec<&str> = actual.iter().map(|s| s.s.rsplit('=').next().unwrap()).collect();
+            assert_eq!(values, vec!["/bin/bash", "/bin/sh", "/usr/bin/bash", "/usr/bin/zsh"]);
+            assert!(actual.iter().all(|s| s.s.starts_with("SHELL=") || s.s.starts_with('=')));
+        }
+
+        #[test]
+        fn unknown_assignment_value_completes_paths() {
+            cd_to_example_fs();
+
+            let actual = run_completion("DATA=./fi");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["./file\\ with\\ spaces.txt"]);
+        }
+
         #[test]
         fn test_tilde_dot_completions() {
             let temp_home = std::env::temp_dir().join(format!("flyline_test_home_{}", rand::random::<u32>()));
@@ -1597,6 +2303,62 @@ mod tab_completion_tests {
             let _ = std::fs::remove_dir_all(temp_home);
         }
 
+        #[test]
+        fn cd_completes_directories_and_cdpath_entries() {
+            let temp = std::env::temp_dir().join(format!("flyline_test_cdpath_{}", rand::random::<u32>()));
+            let cwd = temp.join("cwd");
+            let root_a = temp.join("root_a");
+            let root_b = temp.join("root_b");
+            for dir in [
+                cwd.join("proj_local"),
+                root_a.join("projects"),
+                root_a.join(".proj_hidden"),
+                root_b.join("proj_b/nested"),
+            ] {
+                std::fs::create_dir_all(dir).unwrap();
+            }
+            std::fs::write(cwd.join("proj_file.txt"), "").unwrap();
+            std::fs::write(root_b.join("proj_notes.txt"), "").unwrap();
+            std::env::set_current_dir(&cwd).unwrap();
+
+            // Without CDPATH only local directories are offered, never files.
+            let actual = run_completion("cd proj");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["proj_local/"]);
+
+            // Other commands still see files.
+            let actual = run_completion("ls proj");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["proj_file.txt", "proj_local/"]);
+
+            unsafe {
+                std::env::set_var(
+                    "FLYLINE_TEST_CDPATH",
+                    format!("{}:{}", root_a.display(), root_b.display()),
+                );
+            }
+
+            let actual = run_completion("pushd proj");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["proj_b/", "proj_local/", "projects/"]);
+
+            let actual = run_completion("cd proj_b/");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["proj_b/nested/"]);
+
+            let actual = run_completion("cd .proj");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec![".proj_hidden/"]);
+
+            // Explicitly relative paths don't consult CDPATH.
+            let actual = run_completion("cd ./proj");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["./proj_local/"]);
+
+            unsafe { std::env::remove_var("FLYLINE_TEST_CDPATH"); }
+            let _ = std::fs::remove_dir_all(temp);
+        }
+
         #[test]
         fn git_top_level_subcommand_a_completes_to_add() {
             cd_to_example_fs();
@@ -1684,6 +2446,43 @@ mod tab_completion_tests {
             assert_eq!(builder.processed[0].s, "sym_link_to_foo/");
         }
 
+        #[test]
+        fn env_variable_completion() {
+            let formatted: Vec<String> = run_completion("echo $HO")
+                .iter()
+                .map(ProcessedSuggestion::formatted)
+                .collect();
+            assert_eq!(formatted, vec!["$HOME"]);
+
+            let formatted: Vec<String> = tab_complete_env_variable("${PA")
+                .iter()
+                .map(ProcessedSuggestion::formatted)
+                .collect();
+            assert_eq!(formatted, vec!["${PATH}"]);
+
+            let formatted: Vec<String> = tab_complete_env_variable("${US}")
+                .iter()
+                .map(ProcessedSuggestion::formatted)
+                .collect();
+            assert_eq!(formatted, vec!["${USER}"]);
+        }
+
+        #[test]
+        fn unknown_command_only_gets_path_completion() {
+            cd_to_example_fs();
+            // The same typo that falls back to fuzzy filenames for `git`
+            // produces nothing for a command bash doesn't know.
+            assert!(get_builder("mycmd symlinktfoo").is_none());
+
+            let (builder, _) = get_builder("mycmd ./symlinktfoo").unwrap();
+            assert_eq!(builder.comp_type, CompType::FuzzyFilenameExpansion);
+            assert_eq!(builder.len(), 1);
+            assert!(builder.processed[0].s.ends_with("sym_link_to_foo/"));
+
+            let (builder, _) = get_builder("mycmd ./ab").unwrap();
+            assert_eq!(builder.comp_type, CompType::FilenameExpansion);
+        }
+
         #[test]
         fn docker_completions_with_inline_descriptions() {
             cd_to_example_fs();
@@ -1727,7 +2526,7 @@ mod tab_completion_tests {
             let comp_context =
                 get_completion_context(buffer.buffer(), buffer.cursor_byte_pos());
             let wuc = comp_context.word_under_cursor.clone();
-            let builder = gen_completions_internal(&comp_context, false, false).expect("some completions");
+            let builder = gen_completions_internal(&comp_context, &Settings::default(), false, false).expect("some completions");
             assert_eq!(builder.comp_type, CompType::CommandComp { command_word: "gd".to_string() });
             assert_eq!(builder.len(), 1, "expected solo suggestion, got {:?}", builder.processed);
             let outcome = apply_tab_complete_to_buffer(&mut buffer, &builder, &wuc);
@@ -1735,6 +2534,33 @@ mod tab_completion_tests {
             assert_eq!(buffer.buffer(), "gd --staged ");
         }
 
+        #[test]
+        fn alias_to_bare_git_completes_subcommands() {
+            // `g` is aliased to `git`, so the alias is longer than the word
+            // typed and every position after it shifts right.
+            cd_to_example_fs();
+            let actual = run_completion("g a");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert!(names.contains(&"add"), "expected `add` in {:?}", names);
+        }
+
+        #[test]
+        fn alias_to_bare_git_completes_with_cursor_mid_word() {
+            cd_to_example_fs();
+            let buffer = TextBuffer::new_with_cursor("g co█mmit");
+            let actual = run_completion_from_buffer(&buffer);
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert!(names.contains(&"commit"), "expected `commit` in {:?}", names);
+        }
+
+        #[test]
+        fn alias_to_bare_git_completes_subcommand_flags() {
+            cd_to_example_fs();
+            let actual = run_completion("g diff --stag");
+            let names: Vec<&str> = actual.iter().map(|s| s.s.as_str()).collect();
+            assert_eq!(names, vec!["--staged"]);
+        }
+
         // ------- filename completion against tests/example_fs ------------
 
         #[test]
@@ -1755,6 +2581,24 @@ mod tab_completion_tests {
             );
         }
 
+        #[test]
+        fn filename_completion_inside_double_quotes_is_not_backslash_escaped() {
+            cd_to_example_fs();
+            let actual = run_completion("mycmd \"file w");
+            assert_eq!(actual.len(), 1, "got {:?}", actual);
+            assert!(actual[0].s.contains("file with spaces.txt"), "got {:?}", actual);
+            assert!(!actual[0].s.contains('\\'), "got {:?}", actual);
+        }
+
+        #[test]
+        fn filename_completion_inside_single_quotes_is_not_backslash_escaped() {
+            cd_to_example_fs();
+            let actual = run_completion("mycmd 'many s");
+            assert_eq!(actual.len(), 1, "got {:?}", actual);
+            assert!(actual[0].s.contains("many spaces here/"), "got {:?}", actual);
+            assert!(!actual[0].s.contains('\\'), "got {:?}", actual);
+        }
+
         #[test]
         fn programmable_completion_infers_filename_mode_in_example_fs() {
             cd_to_example_fs();
@@ -1806,6 +2650,19 @@ mod tab_completion_tests {
             );
         }
 
+        #[test]
+        fn overlapping_brace_alternatives_are_deduplicated() {
+            cd_to_example_braces_fs();
+            assert_completions(
+                "mycmd $PWD/foo{1,*}/barA",
+                &[ProcessedSuggestion::new(
+                    "$PWD/foo1/barA $PWD/foo2/barA $PWD/foo3/barA ",
+                    "",
+                    "",
+                )],
+            );
+        }
+
         #[test]
         fn glob_expansion_keeps_parent_path_for_each_match() {
             cd_to_example_fs();
@@ -1833,6 +2690,31 @@ mod tab_completion_tests {
             );
         }
 
+        #[test]
+        fn glob_expansion_of_multiple_files_leaves_cursor_at_end() {
+            let dir = std::env::temp_dir().join(format!("flyline_test_glob_{}", rand::random::<u32>()));
+            std::fs::create_dir_all(&dir).unwrap();
+            for name in ["a.rs", "b.rs", "c.txt"] {
+                std::fs::write(dir.join(name), "").unwrap();
+            }
+            std::env::set_current_dir(&dir).unwrap();
+
+            let mut buffer = TextBuffer::new_with_cursor("mycmd *.rs█");
+            let (builder, comp_context) = get_builder_from_buffer(&buffer).unwrap();
+            assert_eq!(builder.comp_type, CompType::GlobExpansion);
+            let outcome = apply_tab_complete_to_buffer(&mut buffer, &builder, &comp_context.word_under_cursor);
+            assert!(matches!(outcome, TabCompleteBufferOutcome::SoloAccepted));
+            assert_eq!(buffer.buffer(), "mycmd a.rs b.rs ");
+            assert_eq!(buffer.cursor_byte_pos(), buffer.buffer().len());
+
+            // Completing again on the expanded buffer must not glob a second time.
+            let (builder, _) = get_builder_from_buffer(&buffer).unwrap();
+            assert_ne!(builder.comp_type, CompType::GlobExpansion);
+            assert_eq!(buffer.buffer(), "mycmd a.rs b.rs ");
+
+            let _ = std::fs::remove_dir_all(dir);
+        }
+
         #[test]
         fn fuzzy_globbing_recurses_across_path_segments() {
             cd_to_example_fuzzy_glob_fs();
@@ -1961,6 +2843,40 @@ mod tab_completion_tests {
             assert_eq!(buffer.buffer(), "mycmd bar.txt ");
         }
 
+        #[test]
+        fn finish_tab_complete_respects_completion_trailing() {
+            cd_to_example_fs();
+            let accept = |input: &str, trailing: CompletionTrailing| {
+                let mut buffer = TextBuffer::new(input);
+                let (builder, comp_context) = get_builder_from_buffer(&buffer).unwrap();
+                let builder = builder.with_trailing(trailing);
+                let outcome = apply_tab_complete_to_buffer(&mut buffer, &builder, &comp_context.word_under_cursor);
+                assert!(matches!(outcome, TabCompleteBufferOutcome::SoloAccepted));
+                buffer.buffer().to_string()
+            };
+
+            assert_eq!(accept("mycmd bar.tx", CompletionTrailing::Space), "mycmd bar.txt ");
+            assert_eq!(accept("mycmd bar.tx", CompletionTrailing::SlashOnly), "mycmd bar.txt");
+            assert_eq!(accept("mycmd bar.tx", CompletionTrailing::None), "mycmd bar.txt");
+
+            assert_eq!(accept("mycmd fo", CompletionTrailing::Space), "mycmd foo/");
+            assert_eq!(accept("mycmd fo", CompletionTrailing::SlashOnly), "mycmd foo/");
+            assert_eq!(accept("mycmd fo", CompletionTrailing::None), "mycmd foo");
+        }
+
+        #[test]
+        fn finish_tab_complete_confirm_single_completion_leaves_buffer() {
+            cd_to_example_fs();
+            let mut buffer = TextBuffer::new("mycmd bar.tx");
+            let (builder, comp_context) = get_builder_from_buffer(&buffer).unwrap();
+            assert_eq!(builder.len(), 1, "expected exactly one suggestion");
+
+            let builder = builder.with_confirm_single_completion();
+            let outcome = apply_tab_complete_to_buffer(&mut buffer, &builder, &comp_context.word_under_cursor);
+            assert!(matches!(outcome, TabCompleteBufferOutcome::Pending { ref final_wuc } if final_wuc.as_ref() == "bar.tx"));
+            assert_eq!(buffer.buffer(), "mycmd bar.tx");
+        }
+
         #[test]
         fn finish_tab_complete_auto_accepts_solo_unprocessed_suggestion() {
             let mut buffer = TextBuffer::new("mycmd bar.tx");
@@ -2078,5 +2994,84 @@ mod tab_completion_tests {
             items.sort();
             assert_eq!(items, vec!["foo1/", "foo2/", "foo3/"]);
         }
+
+        #[test]
+        fn fuzzy_first_word_ranks_transposed_typo_first() {
+            let candidates = ["grep", "tig", "gzip", "git"].into_iter().map(|name| {
+                bash_funcs::CommandWordInfo::Keyword {
+                    command: name.to_string(),
+                    usage: None,
+                }
+            });
+            let ranked = rank_fuzzy_first_words(candidates, "gti");
+            assert_eq!(ranked.first().map(|info| info.command()), Some("git"));
+        }
+
+        #[test]
+        fn first_word_prefix_matches_sort_shortest_first() {
+            let mut res: Vec<_> = ["grep", "gzip", "git", "gcc"]
+                .into_iter()
+                .map(|name| bash_funcs::CommandWordInfo::Keyword {
+                    command: name.to_string(),
+                    usage: None,
+                })
+                .collect();
+            sort_first_word_prefix_matches(&mut res);
+            let names: Vec<&str> = res.iter().map(|info| info.command()).collect();
+            assert_eq!(names, vec!["gcc", "git", "grep", "gzip"]);
+        }
+
+        #[test]
+        fn transposed_prefix_detection() {
+            assert!(is_transposed_prefix("git", "gti"));
+            assert!(is_transposed_prefix("make", "mkae"));
+            assert!(is_transposed_prefix("ls", "sl"));
+            assert!(!is_transposed_prefix("git", "git"));
+            assert!(!is_transposed_prefix("grep", "g"));
+            assert!(!is_transposed_prefix("git", "tgi"));
+        }
+
+        // ------- glob result cap -----------------------------------------
+
+        #[test]
+        fn glob_results_are_capped_to_shortest_matches_with_note() {
+            let dir = std::env::temp_dir().join(format!("flyline_glob_cap_{}", rand::random::<u32>()));
+            std::fs::create_dir_all(&dir).unwrap();
+            for i in 0..20 {
+                std::fs::write(dir.join(format!("longer_name_{:02}", i)), "").unwrap();
+            }
+            for i in 0..10 {
+                std::fs::write(dir.join(format!("a{}", i)), "").unwrap();
+            }
+
+            let expanded = PathPatternExpansion::new(&format!("{}/*", dir.display()));
+            let flags = bash_funcs::CompletionFlags::default();
+            let (results, truncated) =
+                tab_complete_with_expanded_pattern(&expanded, flags, "", true, 10);
+            assert!(truncated);
+            let names: Vec<String> = results
+                .iter()
+                .map(|sug| sug.raw_text.rsplit('/').next().unwrap().to_string())
+                .collect();
+            let expected: Vec<String> = (0..10).map(|i| format!("a{}", i)).collect();
+            assert_eq!(names, expected);
+
+            let (results, truncated) =
+                tab_complete_with_expanded_pattern(&expanded, flags, "", true, 30);
+            assert!(!truncated);
+            assert_eq!(results.len(), 30);
+
+            let active = ActiveSuggestions::new(
+                ActiveSuggestionsBuilder::from_unprocessed(results).with_truncated_to(Some(10)),
+                SubString::new("", "").unwrap(),
+                std::time::Duration::from_secs(0),
+                false,
+                crate::settings::SuggestionSortOrder::default(),
+                crate::settings::FuzzyMode::default(),
+            );
+            assert_eq!(active.truncation_note().as_deref(), Some("(showing first 10)"));
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
     }
 }
@@ -1,12 +1,19 @@
 use super::*;
+use crate::app::command_palette::PaletteCommand;
 use crate::content_builder::Coord;
 use crate::content_utils::{
     gaussian_wave_animated, split_line_to_terminal_rows, ts_to_timeago_string_5chars,
 };
+use crate::snake_animation::GlyphAnimation;
 use crate::tutorial;
 use ratatui::prelude::*;
+use strum::IntoEnumIterator;
+use unicode_segmentation::UnicodeSegmentation;
 
 const LOADING_TEXT: &str = "Loading completions…";
+/// How long completions must be running before the spinner appears, so fast
+/// ones don't flicker.
+const COMPLETION_SPINNER_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
 
 pub(crate) struct DrawnContent {
     pub(crate) contents: Contents,
@@ -110,6 +117,29 @@ impl DrawnContent {
 }
 
 impl<'a> App<'a> {
+    /// Write the history index and age of the inline suggestion after its
+    /// last line. Writes nothing when `show_meta` is false.
+    fn render_history_suggestion_meta(
+        content: &mut Contents,
+        sug: &HistoryEntry,
+        show_meta: bool,
+        palette: &Palette,
+    ) {
+        if !show_meta {
+            return;
+        }
+        let mut extra_info_text = format!(" #idx={}", sug.index);
+        if let Some(ts) = sug.timestamp {
+            let time_ago_str = ts_to_timeago_string_5chars(ts);
+            extra_info_text.push_str(&format!(" {}", time_ago_str.trim_start()));
+        }
+
+        content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
+            Span::from(extra_info_text).style(palette.inline_suggestion()),
+            Tag::HistorySuggestion,
+        ));
+    }
+
     fn render_history_entry(
         content: &mut Contents,
         formatted_entry: &HistoryEntryFormatted,
@@ -544,7 +574,7 @@ impl<'a> App<'a> {
         content.prompt_start = Some(content.cursor_position());
 
         let (mut lprompt, rprompt, fill_span) = self.prompt_manager.get_ps1_lines(
-            self.settings.show_animations,
+            self.animations_enabled,
             self.mouse_state.is_enabled(),
             self.mode.is_running(),
         );
@@ -615,6 +645,36 @@ impl<'a> App<'a> {
             }
         }
 
+        if self.mode.is_running()
+            && let Some(vi) = &self.vi_mode
+            && let Some(last_line) = lprompt.last_mut()
+        {
+            last_line.spans.push(TaggedSpan::new(
+                Span::styled(
+                    vi.mode.indicator(),
+                    self.settings.colour_palette.secondary_text(),
+                ),
+                Tag::Ps1Prompt,
+            ));
+        }
+
+        // Drop the right prompt on the input row once the first line of input
+        // would run into it.
+        let last_prompt_row = lprompt.len().max(rprompt.len()).saturating_sub(1);
+        if let Some(r_line) = rprompt.get_mut(last_prompt_row) {
+            let l_width = lprompt.get(last_prompt_row).map_or(0, |l| l.width());
+            let input_width: usize = self
+                .formatted_buffer_cache
+                .parts
+                .iter()
+                .take_while(|part| part.token.token.kind != TokenKind::Newline)
+                .map(|part| part.normal_span().width())
+                .sum();
+            if !rprompt_fits_beside_input(l_width, input_width, r_line.width(), content.width) {
+                *r_line = TaggedLine::default();
+            }
+        }
+
         let empty_tagged_line = TaggedLine::default();
         for (_, is_last, either_or_both) in
             lprompt.iter().zip_longest(rprompt.iter()).flag_first_last()
@@ -653,17 +713,28 @@ impl<'a> App<'a> {
             .count()
             + 1;
         let max_digits = total_lines.to_string().len();
+        let ps2_line = self
+            .prompt_manager
+            .get_ps2_line(self.animations_enabled, self.mouse_state.is_enabled());
+        // Tab stops are measured from where each buffer line starts.
+        let mut line_start_col = content.cursor_position().col;
+        let mut line_start_row = content.cursor_position().row;
 
         for part in self.formatted_buffer_cache.parts.iter() {
-            let animation_time = if self.mode.is_running() && self.settings.show_animations {
+            let animation_time = if self.mode.is_running() && self.animations_enabled {
                 Some(now)
             } else {
                 None
             };
 
-            for (mut sub_span, tags, is_cursor, _is_sel_byte, is_in_selection) in
+            for (sub_span, tags, is_cursor, _is_sel_byte, is_in_selection) in
                 part.get_spans(animation_time, selection_range.clone())
             {
+                let (mut sub_span, tags) = expand_tabs(
+                    sub_span,
+                    tags,
+                    content.cursor_position().col.saturating_sub(line_start_col) as usize,
+                );
                 if is_in_selection {
                     sub_span.style = self
                         .settings
@@ -685,17 +756,34 @@ impl<'a> App<'a> {
             }
 
             if part.token.token.kind == TokenKind::Newline {
+                if self.settings.reorder_bidi {
+                    reorder_bidi_line(
+                        &mut content,
+                        Coord::new(line_start_row, line_start_col),
+                        &mut cursor_pos_maybe,
+                    );
+                }
                 line_idx += 1;
                 content.newline();
-                let line_num_str = format!("{}", line_idx + 1);
-                let padded_line_num = format!("{:>width$}", line_num_str, width = max_digits);
-                let ps2 = Span::styled(
-                    format!("{}∙", padded_line_num),
+                for span in continuation_prefix(
+                    ps2_line.as_ref(),
+                    line_idx,
+                    max_digits,
                     self.settings.colour_palette.secondary_text(),
-                );
-                content.write_tagged_span(&TaggedSpan::new(ps2, Tag::Ps2Prompt));
+                ) {
+                    content.write_tagged_span(&span);
+                }
+                line_start_col = content.cursor_position().col;
+                line_start_row = content.cursor_position().row;
             }
         }
+        if self.settings.reorder_bidi {
+            reorder_bidi_line(
+                &mut content,
+                Coord::new(line_start_row, line_start_col),
+                &mut cursor_pos_maybe,
+            );
+        }
         if self.formatted_buffer_cache.draw_cursor_at_end {
             let space = StyledGrapheme::new(" ", Style::default());
             content.move_to_next_insertion_point(&space, false, None);
@@ -719,13 +807,13 @@ impl<'a> App<'a> {
             && let Some(cursor_pos) = cursor_pos_maybe
         {
             self.cursor.update_logical_pos(cursor_pos);
-            let cursor_render_pos = if self.settings.show_animations {
+            let cursor_render_pos = if self.animations_enabled {
                 self.cursor.get_render_pos(&self.settings.cursor_config)
             } else {
                 cursor_pos
             };
             let cursor_style = {
-                if self.settings.cursor_config.backend == CursorBackend::Terminal {
+                if self.settings.cursor_config.shows_terminal_cursor() {
                     None
                 } else {
                     let focused = self.term_has_focus
@@ -733,6 +821,7 @@ impl<'a> App<'a> {
                             self.content_mode,
                             ContentMode::PromptDirSelect(_)
                                 | ContentMode::TabCompletionAskForFlycomp { .. }
+                                | ContentMode::CommandPalette(_)
                         )
                         && self.last_activity_time.elapsed() < IDLE_TIMEOUT;
                     let selection_active = self.buffer.selection_range().is_some();
@@ -746,7 +835,7 @@ impl<'a> App<'a> {
                     } else {
                         None
                     };
-                    if self.settings.show_animations {
+                    if self.animations_enabled {
                         self.cursor.get_style(
                             focused,
                             &self.settings.cursor_config,
@@ -763,12 +852,41 @@ impl<'a> App<'a> {
                 }
             };
 
-            content.set_term_cursor_pos(cursor_render_pos, cursor_style);
+            content.set_term_cursor_pos_with_shape(
+                cursor_render_pos,
+                cursor_style,
+                self.settings.cursor_config.shape,
+            );
         }
 
-        if let Some((sug, suf)) = &self.inline_history_suggestion
+        // Preview the rest of the highlighted completion as a ghost after the
+        // cursor. Nothing is inserted until the suggestion is accepted.
+        let completion_preview = match &self.content_mode {
+            ContentMode::TabCompletion(active_suggestions)
+                if self.mode.is_running() && self.buffer.is_cursor_at_end() =>
+            {
+                active_suggestions
+                    .selected_formatted()
+                    .and_then(|formatted| {
+                        completion_preview_ghost(
+                            &formatted,
+                            &active_suggestions.word_under_cursor.s,
+                        )
+                        .map(str::to_string)
+                    })
+            }
+            _ => None,
+        };
+
+        if let Some(preview) = &completion_preview {
+            content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
+                Span::from(preview.to_owned()).style(self.settings.colour_palette.secondary_text()),
+                Tag::HistorySuggestion,
+            ));
+        } else if let Some((sug, suf)) = &self.inline_suggestion
             && self.mode.is_running()
         {
+            let show_suggestion_meta = self.settings.show_suggestion_meta;
             suf.lines()
                 .collect::<Vec<_>>()
                 .iter()
@@ -784,32 +902,63 @@ impl<'a> App<'a> {
                         Tag::HistorySuggestion,
                     ));
 
-                    if is_last {
-                        let mut extra_info_text = format!(" #idx={}", sug.index);
-                        if let Some(ts) = sug.timestamp {
-                            let time_ago_str = ts_to_timeago_string_5chars(ts);
-                            extra_info_text.push_str(&format!(" {}", time_ago_str.trim_start()));
-                        }
-
+                    if is_last && let Some(sug) = sug {
+                        Self::render_history_suggestion_meta(
+                            &mut content,
+                            sug,
+                            show_suggestion_meta,
+                            &self.settings.colour_palette,
+                        );
+                    }
+                    if is_last && self.settings.run_tutorial {
                         content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
-                            Span::from(extra_info_text)
-                                .style(self.settings.colour_palette.inline_suggestion()),
-                            Tag::HistorySuggestion,
+                            Span::styled(
+                                " 💡 Press → or End to accept",
+                                self.settings.colour_palette.tutorial_hint(),
+                            ),
+                            Tag::Tutorial,
                         ));
-
-                        if self.settings.run_tutorial {
-                            content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
-                                Span::styled(
-                                    " 💡 Press → or End to accept",
-                                    self.settings.colour_palette.tutorial_hint(),
-                                ),
-                                Tag::Tutorial,
-                            ));
-                        }
                     }
                 });
         }
 
+        // Preview what an abbreviation will expand to on Space or Enter.
+        if self.inline_suggestion.is_none()
+            && completion_preview.is_none()
+            && self.mode.is_running()
+            && self.buffer.is_cursor_at_end()
+            && let Some((_, expansion)) = crate::abbreviations::expansion_at_cursor(
+                &self.settings.abbreviations,
+                self.buffer.buffer(),
+                self.buffer.cursor_byte_pos(),
+            )
+        {
+            content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
+                Span::from(format!(" → {}", expansion))
+                    .style(self.settings.colour_palette.secondary_text()),
+                Tag::HistorySuggestion,
+            ));
+        }
+
+        // Show a spinner after the word while slow completions are running.
+        if self.mode.is_running()
+            && let ContentMode::TabCompletionWaiting {
+                spinner,
+                start_time,
+                ..
+            } = &mut self.content_mode
+            && now.duration_since(*start_time) >= COMPLETION_SPINNER_DELAY
+        {
+            spinner.update_anim(now);
+            content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
+                Span::styled(
+                    format!(" {}", spinner.frame()),
+                    self.settings.colour_palette.secondary_text(),
+                ),
+                Tag::Normal,
+            ));
+        }
+
         let rows_before = content.cursor_position().row;
         let rows_left_before_end_of_screen: u16 = terminal_height.saturating_sub(rows_before + 1);
 
@@ -1227,14 +1376,20 @@ impl<'a> App<'a> {
             ContentMode::Normal if self.mode.is_running() => {
                 if let Some(tooltip) = &self.tooltip {
                     content.newline();
-                    let tooltip_line = Line::from(Span::styled(
-                        tooltip.clone(),
-                        self.settings.colour_palette.secondary_text(),
-                    ));
-
                     let max_tool_tip_rows: u16 = 3;
 
-                    let rows = split_line_to_terminal_rows(&tooltip_line, content.width);
+                    // Each line of the tooltip (e.g. a command's description
+                    // and its definition preview) starts a new row.
+                    let rows: Vec<_> = tooltip
+                        .lines()
+                        .flat_map(|line| {
+                            let tooltip_line = Line::from(Span::styled(
+                                line.to_string(),
+                                self.settings.colour_palette.secondary_text(),
+                            ));
+                            split_line_to_terminal_rows(&tooltip_line, content.width)
+                        })
+                        .collect();
                     let truncated = rows.len() > max_tool_tip_rows as usize;
                     for (i, row) in rows
                         .into_iter()
@@ -1362,6 +1517,69 @@ impl<'a> App<'a> {
                         .write_tagged_line(&TaggedLine::from_line(line.clone(), Tag::Normal), true);
                 }
             }
+            ContentMode::CommandPalette(palette) if self.mode.is_running() => {
+                content.newline();
+                content.write_tagged_span(&TaggedSpan::new(
+                    Span::styled("> ", self.settings.colour_palette.secondary_text()),
+                    Tag::Normal,
+                ));
+                content.write_tagged_span(&TaggedSpan::new(
+                    Span::raw(palette.query().to_string()),
+                    Tag::Normal,
+                ));
+                for (row_idx, palette_match) in palette.matches().iter().enumerate() {
+                    content.newline();
+                    let is_selected = palette.selected_idx() == row_idx;
+                    if is_selected {
+                        content.set_focus_row(content.cursor_position().row);
+                    }
+                    let (indicator, indicator_style) = if is_selected {
+                        (
+                            "▐",
+                            self.settings
+                                .colour_palette
+                                .matching_char()
+                                .remove_modifier(Modifier::UNDERLINED),
+                        )
+                    } else {
+                        (" ", self.settings.colour_palette.secondary_text())
+                    };
+                    content.write_tagged_span(&TaggedSpan::new(
+                        Span::styled(indicator, indicator_style),
+                        Tag::Normal,
+                    ));
+                    for (char_idx, c) in palette_match.command.label().chars().enumerate() {
+                        let style = if palette_match.matching_indices.contains(&char_idx) {
+                            self.settings.colour_palette.matching_char()
+                        } else {
+                            Style::default()
+                        };
+                        let style = if is_selected {
+                            Palette::convert_to_highlighted(style)
+                        } else {
+                            style
+                        };
+                        content.write_tagged_span(&TaggedSpan::new(
+                            Span::styled(c.to_string(), style),
+                            Tag::Normal,
+                        ));
+                    }
+                    content.fill_line(Tag::Normal);
+                }
+                content.newline();
+                let footer = match palette.output() {
+                    Some(output) => output.to_string(),
+                    None => format!(
+                        "# Commands: {}/{}",
+                        palette.matches().len(),
+                        PaletteCommand::iter().count()
+                    ),
+                };
+                content.write_tagged_span(&TaggedSpan::new(
+                    Span::styled(footer, self.settings.colour_palette.secondary_text()),
+                    Tag::Normal,
+                ));
+            }
             ContentMode::AgentError {
                 message,
                 raw_output,
@@ -1411,6 +1629,15 @@ impl<'a> App<'a> {
             content.apply_matrix_anim(now, viewport_top, terminal_height);
         }
 
+        if self.mode.is_running()
+            && super::is_idle_dimmed(
+                self.last_activity_time.elapsed(),
+                self.settings.idle_dim_after,
+            )
+        {
+            content.dim();
+        }
+
         if !self.mode.is_running() {
             content.move_to_final_line();
             content.newline();
@@ -1490,10 +1717,20 @@ impl<'a> App<'a> {
 
         content
     }
-    pub(crate) fn ui(&mut self, frame: &mut Frame, content: Contents) {
+    pub(crate) fn ui(&mut self, frame: &mut Frame, mut content: Contents) {
         let frame_area = frame.area();
         frame.buffer_mut().reset();
 
+        // The terminal may have been resized after the content was built.
+        if content.width != frame_area.width {
+            log::debug!(
+                "Reflowing content from width {} to {}",
+                content.width,
+                frame_area.width
+            );
+            content.reflow(frame_area.width);
+        }
+
         let content_visible_row_range = content.get_row_range_to_show(frame_area.height);
 
         for row_idx in 0..frame_area.height {
@@ -1514,6 +1751,23 @@ impl<'a> App<'a> {
             };
         }
 
+        // Mark the visible window's position on the right edge, skipping the
+        // prompt rows so RPROMPT stays readable.
+        if let Some(thumb) = content.scroll_indicator_thumb(&content_visible_row_range)
+            && let Some(x) = frame_area.width.checked_sub(1)
+        {
+            let first_row_after_prompt = content.prompt_end.map_or(0, |end| end.row + 1);
+            for row_idx in thumb {
+                if content_visible_row_range.start + row_idx < first_row_after_prompt {
+                    continue;
+                }
+                frame.buffer_mut().content
+                    [row_idx as usize * frame_area.width as usize + x as usize]
+                    .set_symbol("▐")
+                    .set_style(self.settings.colour_palette.scroll_indicator());
+            }
+        }
+
         let drawn_content = DrawnContent {
             contents: content,
             viewport_start: frame_area.y,
@@ -1521,8 +1775,7 @@ impl<'a> App<'a> {
         };
 
         if let Some(term_em_cursor) = drawn_content.term_em_cursor_pos()
-            && (self.settings.cursor_config.backend == CursorBackend::Terminal
-                || !self.mode.is_running())
+            && (self.settings.cursor_config.shows_terminal_cursor() || !self.mode.is_running())
             && !(self.mouse_state.is_left_button_down()
                 && self.buffer.selection_range().is_some()
                 && matches!(
@@ -1554,13 +1807,28 @@ impl<'a> App<'a> {
             let mut selected_grid_row: Option<u16> = None;
             let grid_width = width as usize;
 
-            let grid = active_suggestions.into_grid(
+            let mut grid = active_suggestions.into_grid(
                 num_rows_for_suggestions as usize,
                 grid_width,
                 &settings.colour_palette,
                 None,
             );
 
+            let mut num_hidden = active_suggestions.num_hidden_in_grid(&grid);
+            // Make room for the "+N more" cell after the last column.
+            let indicator_width = COLUMN_PADDING
+                + unicode_width::UnicodeWidthStr::width(
+                    more_indicator(active_suggestions.filtered_suggestions_len()).as_str(),
+                );
+            if num_hidden > 0 && grid_width > indicator_width {
+                grid = active_suggestions.into_grid(
+                    num_rows_for_suggestions as usize,
+                    grid_width - indicator_width,
+                    &settings.colour_palette,
+                    None,
+                );
+                num_hidden = active_suggestions.num_hidden_in_grid(&grid);
+            }
             let num_rows = grid.get(0).map_or(0, |col| col.items.len());
 
             for row_idx in 0..num_rows {
@@ -1584,6 +1852,19 @@ impl<'a> App<'a> {
                         }
                     }
                 }
+                if row_idx == 0 && num_hidden > 0 {
+                    content.write_tagged_span(&TaggedSpan::new(
+                        Span::styled(
+                            format!(
+                                "{}{}",
+                                " ".repeat(COLUMN_PADDING),
+                                more_indicator(num_hidden)
+                            ),
+                            settings.colour_palette.secondary_text(),
+                        ),
+                        Tag::TabSuggestion,
+                    ));
+                }
                 content.newline();
             }
 
@@ -1619,6 +1900,16 @@ impl<'a> App<'a> {
             Tag::TabSuggestion,
         ));
 
+        if let Some(note) = active_suggestions.truncation_note() {
+            content.write_tagged_span(&TaggedSpan::new(
+                Span::styled(
+                    format!("{}; ", note),
+                    settings.colour_palette.secondary_text(),
+                ),
+                Tag::TabSuggestion,
+            ));
+        }
+
         content.write_tagged_span(&TaggedSpan::new(
             Span::styled(
                 format!(
@@ -2068,6 +2359,111 @@ impl<'a> App<'a> {
     }
 }
 
+/// Replace each tab in `span` with spaces up to the next tab stop, where `col`
+/// is the span's column relative to the start of its buffer line. Each space
+/// keeps the tab's tag so clicks on it still map to the tab's byte.
+fn expand_tabs(span: Span<'static>, tags: Vec<Tag>, col: usize) -> (Span<'static>, Vec<Tag>) {
+    if !span.content.contains('\t') {
+        return (span, tags);
+    }
+    let mut col = col;
+    let mut content = String::with_capacity(span.content.len());
+    let mut expanded_tags = Vec::with_capacity(tags.len());
+    for (idx, grapheme) in span.content.graphemes(true).enumerate() {
+        let tag = tags.get(idx).copied().unwrap_or(Tag::Normal);
+        let next_col = crate::text_buffer::next_display_col(col, grapheme);
+        if grapheme == "\t" {
+            for _ in col..next_col {
+                content.push(' ');
+                expanded_tags.push(tag);
+            }
+        } else {
+            content.push_str(grapheme);
+            expanded_tags.push(tag);
+        }
+        col = next_col;
+    }
+    (Span::styled(content, span.style), expanded_tags)
+}
+
+/// Draw the buffer line that started at `line_start` in visual order if it
+/// holds right-to-left text, moving `cursor_pos` along with its cell. Lines
+/// that wrapped are left in logical order. Only used with `FLYLINE_BIDI=1`.
+fn reorder_bidi_line(content: &mut Contents, line_start: Coord, cursor_pos: &mut Option<Coord>) {
+    let line_end = content.cursor_position();
+    if line_end.row != line_start.row {
+        return;
+    }
+    let Some(new_cols) = content.reorder_bidi(line_start.row, line_start.col..line_end.col) else {
+        return;
+    };
+    if let Some(pos) = cursor_pos
+        && pos.row == line_start.row
+        && let Some(&col) = pos
+            .col
+            .checked_sub(line_start.col)
+            .and_then(|i| new_cols.get(i as usize))
+    {
+        pos.col = col;
+    }
+}
+
+/// The prefix drawn at the start of continuation line `line_idx` (0-based):
+/// the expanded `PS2` if the user customised it, otherwise the line number.
+fn continuation_prefix(
+    ps2: Option<&TaggedLine<'static>>,
+    line_idx: usize,
+    max_digits: usize,
+    line_num_style: Style,
+) -> Vec<TaggedSpan<'static>> {
+    match ps2 {
+        // Widgets inside PS2 keep their own tags so they stay clickable.
+        Some(line) => line
+            .spans
+            .iter()
+            .map(|span| match span.tag {
+                crate::content_builder::SpanTag::Constant(Tag::Ps1Prompt) => {
+                    TaggedSpan::new(span.span.clone(), Tag::Ps2Prompt)
+                }
+                _ => span.clone(),
+            })
+            .collect(),
+        None => {
+            let padded_line_num = format!("{:>width$}", line_idx + 1, width = max_digits);
+            vec![TaggedSpan::new(
+                Span::styled(format!("{}∙", padded_line_num), line_num_style),
+                Tag::Ps2Prompt,
+            )]
+        }
+    }
+}
+
+/// The cell after the last grid column counting suggestions out of view.
+fn more_indicator(num_hidden: usize) -> String {
+    format!("+{} more", num_hidden)
+}
+
+/// The part of a completion's `formatted` text still to come after the typed
+/// `word_under_cursor`. `None` when the completion does not extend the word,
+/// e.g. a fuzzy match, since a ghost could not show it faithfully.
+fn completion_preview_ghost<'a>(formatted: &'a str, word_under_cursor: &str) -> Option<&'a str> {
+    formatted
+        .strip_prefix(word_under_cursor)
+        .filter(|rest| !rest.is_empty())
+}
+
+/// Whether a right prompt of `r_width` fits on the input row after a left
+/// prompt of `l_width` and `input_width` cells of input, leaving one cell for
+/// the cursor.
+fn rprompt_fits_beside_input(
+    l_width: u16,
+    input_width: usize,
+    r_width: u16,
+    term_width: u16,
+) -> bool {
+    l_width as usize + input_width + 1 + r_width as usize <= term_width as usize
+}
+
 fn auto_suggestions_popup_anchor_col(
     cursor_col: usize,
     word_under_cursor: &crate::text_buffer::SubString,
@@ -2103,6 +2499,174 @@ mod tests {
     use crate::palette::Palette;
     use crate::text_buffer::SubString;
 
+    /// Draw `$ echo hello\nls` at width 8, tagging each buffer character with
+    /// its byte offset the way the buffer renderer does:
+    ///
+    /// ```text
+    /// $ echo h
+    /// ello
+    /// ls
+    /// ```
+    fn wrapped_multiline_drawn() -> DrawnContent {
+        let mut contents = Contents::new(8);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("$ "), Tag::Ps1Prompt));
+        for (byte, c) in "echo hello\nls".char_indices() {
+            if c == '\n' {
+                contents.newline();
+            } else {
+                contents.write_tagged_span(&TaggedSpan::new(
+                    Span::raw(c.to_string()),
+                    Tag::Command(byte),
+                ));
+            }
+        }
+        DrawnContent {
+            contents,
+            viewport_start: 0,
+            content_visible_row_range: 0..3,
+        }
+    }
+
+    /// Cells of a wrapped, multiline buffer map back to the byte offsets they
+    /// were drawn from; clicks on the prompt map to nothing.
+    #[test]
+    fn test_get_tagged_cell_maps_wrapped_multiline_buffer() {
+        let drawn = wrapped_multiline_drawn();
+
+        assert_eq!(
+            drawn.get_tagged_cell(3, 0),
+            Some((Tag::Command(1), Tag::Command(1)))
+        );
+        assert_eq!(
+            drawn.get_tagged_cell(1, 1),
+            Some((Tag::Command(7), Tag::Command(7)))
+        );
+        // Past the end of a wrapped row lands on its last byte.
+        assert_eq!(
+            drawn.get_tagged_cell(6, 1),
+            Some((Tag::Blank, Tag::Command(9)))
+        );
+        assert_eq!(
+            drawn.get_tagged_cell(0, 2),
+            Some((Tag::Command(11), Tag::Command(11)))
+        );
+        assert_eq!(drawn.get_tagged_cell(0, 0), None);
+    }
+
+    /// Dragging from one wrapped row to the next selects the bytes between the
+    /// two cells, in either direction.
+    #[test]
+    fn test_drag_selection_across_wrapped_rows() {
+        let drawn = wrapped_multiline_drawn();
+        let byte_at = |x, y| match drawn.get_tagged_cell(x, y) {
+            Some((_, Tag::Command(byte))) => byte,
+            other => panic!("no command cell at ({x}, {y}): {other:?}"),
+        };
+
+        let drag = |from: usize, to: usize| {
+            let mut buffer = TextBuffer::new("echo hello\nls");
+            buffer.try_move_cursor_to_byte_pos(from, false);
+            buffer.start_selection_if_none();
+            buffer.try_move_cursor_to_byte_pos(to, false);
+            buffer.selected_text()
+        };
+
+        // From "h" of "hello" on the first row up to "l" on the wrapped row.
+        assert_eq!(drag(byte_at(7, 0), byte_at(2, 1)).as_deref(), Some("he"));
+        assert_eq!(drag(byte_at(2, 1), byte_at(7, 0)).as_deref(), Some("he"));
+        // Across the newline onto the last line.
+        assert_eq!(
+            drag(byte_at(2, 0), byte_at(1, 2)).as_deref(),
+            Some("echo hello\nl")
+        );
+    }
+
+    /// Draw `$ ` + a two-line buffer with `prefix` before the second line and
+    /// the cursor on its last character, returning the contents and where the
+    /// cursor landed.
+    fn draw_two_line_buffer(
+        ps2: Option<&TaggedLine<'static>>,
+    ) -> (Contents, crate::content_builder::Coord) {
+        let mut contents = Contents::new(40);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("$ "), Tag::Ps1Prompt));
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("echo \\"), Tag::Command(0)));
+        contents.newline();
+        for span in continuation_prefix(ps2, 1, 1, Style::default()) {
+            contents.write_tagged_span(&span);
+        }
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("h"), Tag::Command(7)));
+        let cursor = contents.cursor_position();
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("i"), Tag::Command(8)));
+        (contents, cursor)
+    }
+
+    fn row_text(contents: &Contents, row: usize) -> String {
+        contents.buf[row]
+            .iter()
+            .map(|cell| cell.cell.symbol())
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn test_continuation_line_shows_ps2_and_offsets_cursor() {
+        let ps2 = TaggedLine::from(TaggedSpan::new(Span::raw("... "), Tag::Ps1Prompt));
+        let (contents, cursor) = draw_two_line_buffer(Some(&ps2));
+
+        assert_eq!(row_text(&contents, 0), "$ echo \\");
+        assert_eq!(row_text(&contents, 1), "... hi");
+        assert_eq!(contents.buf[1][0].tag, Tag::Ps2Prompt);
+        assert_eq!(cursor.row, 1);
+        assert_eq!(cursor.col, 4);
+    }
+
+    #[test]
+    fn test_continuation_line_defaults_to_line_numbers() {
+        let (contents, cursor) = draw_two_line_buffer(None);
+
+        assert_eq!(row_text(&contents, 1), "2∙hi");
+        assert_eq!(cursor.row, 1);
+        assert_eq!(cursor.col, 2);
+    }
+
+    #[test]
+    fn test_expand_tabs_to_tab_stops() {
+        let tags = vec![Tag::Command(0), Tag::Command(1), Tag::Command(2)];
+        let (span, expanded) = expand_tabs(Span::raw("a\tb"), tags.clone(), 0);
+        assert_eq!(span.content, "a       b");
+        assert_eq!(expanded.len(), 9);
+        assert_eq!(expanded[0], Tag::Command(0));
+        assert!(expanded[1..8].iter().all(|t| *t == Tag::Command(1)));
+        assert_eq!(expanded[8], Tag::Command(2));
+
+        // A tab-indented line that starts mid tab stop only pads to the stop.
+        let (span, _) = expand_tabs(Span::raw("\t"), vec![Tag::Command(3)], 5);
+        assert_eq!(span.content, "   ");
+
+        let (span, same) = expand_tabs(Span::raw("no tabs"), tags.clone(), 3);
+        assert_eq!(span.content, "no tabs");
+        assert_eq!(same, tags);
+    }
+
+    #[test]
+    fn test_completion_preview_ghost() {
+        assert_eq!(completion_preview_ghost("foo.txt ", "fo"), Some("o.txt "));
+        assert_eq!(completion_preview_ghost("./food/", "./fo"), Some("od/"));
+        // Fuzzy matches and already-complete words have no ghost.
+        assert_eq!(completion_preview_ghost("bar_foo", "fo"), None);
+        assert_eq!(completion_preview_ghost("foo", "foo"), None);
+    }
+
+    #[test]
+    fn test_rprompt_fits_beside_input() {
+        // "$ " + "echo hi" + cursor + "12:00" == 15 cells.
+        assert!(rprompt_fits_beside_input(2, 7, 5, 15));
+        assert!(!rprompt_fits_beside_input(2, 8, 5, 15));
+        assert!(!rprompt_fits_beside_input(2, 100, 0, 15));
+        assert!(rprompt_fits_beside_input(0, 0, 0, 1));
+    }
+
     #[test]
     fn test_auto_suggestions_popup_anchor_col_uses_cursor_col_for_empty_wuc() {
         let anchor =
@@ -2123,6 +2687,24 @@ mod tests {
         assert_eq!(anchor, 0);
     }
 
+    #[test]
+    fn test_render_history_suggestion_meta_can_be_disabled() {
+        let palette = Palette::default();
+        let sug = HistoryEntry::new(None, 42, "ls -la".to_string());
+        let render = |show_meta: bool| {
+            let mut content = Contents::new(20);
+            content.write_tagged_span_dont_overwrite(&TaggedSpan::new(
+                Span::raw("ls -la"),
+                Tag::HistorySuggestion,
+            ));
+            App::render_history_suggestion_meta(&mut content, &sug, show_meta, &palette);
+            content.get_buffer_lines()
+        };
+
+        assert_eq!(render(true), vec!["ls -la #idx=42      "]);
+        assert_eq!(render(false), vec!["ls -la              "]);
+    }
+
     #[test]
     fn test_render_history_entry_wrapping_and_ellipsis() {
         let palette = Palette::default();
@@ -2312,6 +2894,95 @@ mod tests {
         );
     }
 
+    /// With more columns than fit, the grid scrolls to keep the selected
+    /// column visible and reports how many suggestions are hidden.
+    #[test]
+    fn test_suggestion_grid_scrolls_to_selected_column() {
+        use crate::active_suggestions::{
+            ActiveSuggestions, ActiveSuggestionsBuilder, ProcessedSuggestion,
+        };
+
+        let builder = ActiveSuggestionsBuilder::from_processed(
+            (0..30).map(|i| ProcessedSuggestion::new(format!("s{:02}", i), "", "")),
+        );
+        let mut active = ActiveSuggestions::new(
+            builder,
+            crate::text_buffer::SubString::new("", "").unwrap(),
+            std::time::Duration::from_millis(0),
+            false,
+            crate::settings::SuggestionSortOrder::default(),
+            crate::settings::FuzzyMode::default(),
+        );
+        let palette = Palette::dark();
+        let shown = |grid: &[crate::active_suggestions::ColumnInfo]| -> usize {
+            grid.iter().map(|col| col.items.len()).sum()
+        };
+
+        // 2 rows per column: 15 columns, only a few of which fit in 20 cells.
+        active.selected_coord = Some((0, 0));
+        let grid = active.into_grid(2, 20, &palette, None);
+        assert_eq!(grid[0].global_col_idx, 0);
+        assert!(shown(&grid) < 30);
+        assert_eq!(active.num_hidden_in_grid(&grid), 30 - shown(&grid));
+        assert_eq!(active.last_num_data_cols, 15);
+
+        active.selected_coord = Some((10, 1));
+        let grid = active.into_grid(2, 20, &palette, None);
+        assert!(grid[0].global_col_idx > 0);
+        assert!(
+            grid.iter()
+                .any(|col| col.global_col_idx == 10 && col.is_selected_col)
+        );
+        assert_eq!(active.num_hidden_in_grid(&grid), 30 - shown(&grid));
+
+        // Back at the start the window scrolls back.
+        active.selected_coord = Some((0, 0));
+        let grid = active.into_grid(2, 20, &palette, None);
+        assert_eq!(grid[0].global_col_idx, 0);
+    }
+
+    /// Suggestions scrolled out of view are counted in a cell after the last
+    /// grid column, not in the footer.
+    #[test]
+    fn test_user_suggestions_grid_shows_more_indicator() {
+        use crate::active_suggestions::{
+            ActiveSuggestions, ActiveSuggestionsBuilder, ProcessedSuggestion,
+        };
+        use crate::settings::Settings;
+
+        let mut settings = Settings::default();
+        settings.num_suggestion_rows = 2;
+        let mut content = Contents::new(40);
+        let builder = ActiveSuggestionsBuilder::from_processed(
+            (0..30).map(|i| ProcessedSuggestion::new(format!("s{:02}", i), "", "")),
+        );
+        let mut active = ActiveSuggestions::new(
+            builder,
+            crate::text_buffer::SubString::new("", "").unwrap(),
+            std::time::Duration::from_millis(0),
+            false,
+            crate::settings::SuggestionSortOrder::default(),
+            crate::settings::FuzzyMode::default(),
+        );
+        active.selected_coord = Some((0, 0));
+
+        App::render_user_suggestions(&settings, &mut active, &mut content, 40, 20, None);
+
+        let lines = content.get_buffer_lines();
+        let shown = lines[1..3]
+            .iter()
+            .flat_map(|line| line.split_whitespace())
+            .filter(|word| word.starts_with('s'))
+            .count();
+        assert!(shown < 30);
+        assert!(
+            lines[1].trim_end().ends_with(&more_indicator(30 - shown)),
+            "{:?}",
+            lines
+        );
+        assert!(!lines[3].contains("more"), "{:?}", lines);
+    }
+
     #[test]
     fn test_render_auto_suggestions_selected_wrapping_and_ellipsis() {
         use crate::active_suggestions::{
@@ -2345,6 +3016,8 @@ mod tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: crate::settings::CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let mut active = ActiveSuggestions::new(
@@ -2427,6 +3100,8 @@ mod tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: crate::settings::CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let mut active = ActiveSuggestions::new(
@@ -2496,6 +3171,8 @@ mod tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: crate::settings::CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let mut active = ActiveSuggestions::new(
@@ -2584,6 +3261,8 @@ mod tests {
             nosort: false,
             compspec_was_useful: Some(true),
             should_run_flycomp: false,
+            trailing: crate::settings::CompletionTrailing::default(),
+            truncated_to: None,
         };
 
         let mut active = ActiveSuggestions::new(
@@ -0,0 +1,274 @@
+//! A small evaluator for the integer arithmetic inside `$(( ... ))`.
+//!
+//! Supports the operators `+ - * / % ** << >> & | ^ ~` with bash's precedence,
+//! parentheses, and bash's number formats (`0x1f`, `017`, `2#101`). Values
+//! wrap on overflow like bash's 64-bit `intmax_t`. Anything else, including
+//! variable references, is an error so the caller shows nothing.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tok {
+    Num(i64),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+/// Operators, longest first so `**` is not read as two `*`.
+const OPERATORS: &[&str] = &[
+    "**", "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~",
+];
+
+/// Evaluate `expr` with bash's integer arithmetic rules. Returns `None` on a
+/// parse error, division by zero, a negative exponent, or any variable.
+pub fn evaluate(expr: &str) -> Option<i64> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.bit_or()?;
+    (parser.pos == parser.tokens.len()).then_some(value)
+}
+
+fn tokenize(expr: &str) -> Option<Vec<Tok>> {
+    let mut tokens = vec![];
+    let mut rest = expr;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '@' || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Tok::Num(parse_number(&rest[..len])?));
+            rest = &rest[len..];
+        } else if c == '(' {
+            tokens.push(Tok::LParen);
+            rest = &rest[1..];
+        } else if c == ')' {
+            tokens.push(Tok::RParen);
+            rest = &rest[1..];
+        } else {
+            let op = OPERATORS.iter().find(|op| rest.starts_with(**op))?;
+            tokens.push(Tok::Op(*op));
+            rest = &rest[op.len()..];
+        }
+    }
+    Some(tokens)
+}
+
+/// Parse a decimal, `0x` hex, leading-`0` octal or `base#digits` literal.
+fn parse_number(literal: &str) -> Option<i64> {
+    let (base, digits) = if let Some((base, digits)) = literal.split_once('#') {
+        let base: u32 = base.parse().ok()?;
+        if !(2..=64).contains(&base) {
+            return None;
+        }
+        (base, digits)
+    } else if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        (16, hex)
+    } else if literal.len() > 1
+        && let Some(octal) = literal.strip_prefix('0')
+    {
+        (8, octal)
+    } else {
+        (10, literal)
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(0i64, |acc, c| {
+        let digit = digit_value(c, base)?;
+        Some(acc.wrapping_mul(base as i64).wrapping_add(digit as i64))
+    })
+}
+
+/// The value of `c` as a digit in `base`. Up to base 36 letters are case
+/// insensitive; above that `a-z`, `A-Z`, `@` and `_` stand for 10 to 63.
+fn digit_value(c: char, base: u32) -> Option<u32> {
+    let value = match c {
+        '0'..='9' => c as u32 - '0' as u32,
+        'a'..='z' => c as u32 - 'a' as u32 + 10,
+        'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+        'A'..='Z' => c as u32 - 'A' as u32 + 36,
+        '@' => 62,
+        '_' => 63,
+        _ => return None,
+    };
+    (value < base).then_some(value)
+}
+
+struct Parser {
+    tokens: Vec<Tok>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Tok> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    /// Consume the next token if it is one of `ops`.
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Tok::Op(op)) if ops.contains(&op) => {
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// A left associative chain of `ops` over operands parsed by `next`.
+    fn binary(
+        &mut self,
+        ops: &[&str],
+        next: fn(&mut Self) -> Option<i64>,
+        apply: fn(&str, i64, i64) -> Option<i64>,
+    ) -> Option<i64> {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            let rhs = next(self)?;
+            lhs = apply(op, lhs, rhs)?;
+        }
+        Some(lhs)
+    }
+
+    fn bit_or(&mut self) -> Option<i64> {
+        self.binary(&["|"], Self::bit_xor, |_, a, b| Some(a | b))
+    }
+
+    fn bit_xor(&mut self) -> Option<i64> {
+        self.binary(&["^"], Self::bit_and, |_, a, b| Some(a ^ b))
+    }
+
+    fn bit_and(&mut self) -> Option<i64> {
+        self.binary(&["&"], Self::shift, |_, a, b| Some(a & b))
+    }
+
+    fn shift(&mut self) -> Option<i64> {
+        self.binary(&["<<", ">>"], Self::additive, |op, a, b| match op {
+            "<<" => Some(a.wrapping_shl(b as u32)),
+            _ => Some(a.wrapping_shr(b as u32)),
+        })
+    }
+
+    fn additive(&mut self) -> Option<i64> {
+        self.binary(&["+", "-"], Self::multiplicative, |op, a, b| match op {
+            "+" => Some(a.wrapping_add(b)),
+            _ => Some(a.wrapping_sub(b)),
+        })
+    }
+
+    fn multiplicative(&mut self) -> Option<i64> {
+        self.binary(&["*", "/", "%"], Self::power, |op, a, b| match op {
+            "*" => Some(a.wrapping_mul(b)),
+            _ if b == 0 => None,
+            "/" => Some(a.wrapping_div(b)),
+            _ => Some(a.wrapping_rem(b)),
+        })
+    }
+
+    /// `**` is right associative and binds looser than unary minus, so
+    /// `-2**2` is 4 as in bash.
+    fn power(&mut self) -> Option<i64> {
+        let base = self.unary()?;
+        if self.eat_op(&["**"]).is_none() {
+            return Some(base);
+        }
+        let exponent = self.power()?;
+        if exponent < 0 {
+            return None;
+        }
+        let (mut result, mut base, mut exponent) = (1i64, base, exponent as u64);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.wrapping_mul(base);
+            }
+            base = base.wrapping_mul(base);
+            exponent >>= 1;
+        }
+        Some(result)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        match self.eat_op(&["-", "+", "~"]) {
+            Some("-") => Some(self.unary()?.wrapping_neg()),
+            Some("+") => self.unary(),
+            Some(_) => Some(!self.unary()?),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Option<i64> {
+        match self.peek()? {
+            Tok::Num(value) => {
+                self.pos += 1;
+                Some(value)
+            }
+            Tok::LParen => {
+                self.pos += 1;
+                let value = self.bit_or()?;
+                (self.peek() == Some(Tok::RParen)).then(|| {
+                    self.pos += 1;
+                    value
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(evaluate("1+2*3"), Some(7));
+        assert_eq!(evaluate("(1+2)*3"), Some(9));
+        assert_eq!(evaluate("10 - 4 - 3"), Some(3));
+        assert_eq!(evaluate("2**3**2"), Some(512));
+        assert_eq!(evaluate("-2**2"), Some(4));
+        assert_eq!(evaluate("1 << 4 | 1"), Some(17));
+        assert_eq!(evaluate("6 & 3 ^ 1"), Some(3));
+        assert_eq!(evaluate("~0"), Some(-1));
+        assert_eq!(evaluate("-7 / 2"), Some(-3));
+        assert_eq!(evaluate("-7 % 2"), Some(-1));
+        assert_eq!(evaluate(" 256 >> 2 "), Some(64));
+    }
+
+    #[test]
+    fn number_bases() {
+        assert_eq!(evaluate("0x10"), Some(16));
+        assert_eq!(evaluate("0XfF"), Some(255));
+        assert_eq!(evaluate("010"), Some(8));
+        assert_eq!(evaluate("0"), Some(0));
+        assert_eq!(evaluate("2#1010"), Some(10));
+        assert_eq!(evaluate("16#ff"), Some(255));
+        assert_eq!(evaluate("64#_"), Some(63));
+        assert_eq!(evaluate("64#A"), Some(36));
+        assert_eq!(evaluate("09"), None);
+        assert_eq!(evaluate("2#102"), None);
+        assert_eq!(evaluate("65#1"), None);
+    }
+
+    #[test]
+    fn overflow_wraps() {
+        assert_eq!(evaluate("9223372036854775807 + 1"), Some(i64::MIN));
+        assert_eq!(evaluate("2**64"), Some(0));
+    }
+
+    #[test]
+    fn errors_evaluate_to_none() {
+        assert_eq!(evaluate(""), None);
+        assert_eq!(evaluate("1 / 0"), None);
+        assert_eq!(evaluate("5 % 0"), None);
+        assert_eq!(evaluate("2 ** -1"), None);
+        assert_eq!(evaluate("x + 1"), None);
+        assert_eq!(evaluate("$x + 1"), None);
+        assert_eq!(evaluate("(1 + 2"), None);
+        assert_eq!(evaluate("1 2"), None);
+        assert_eq!(evaluate("1 ? 2 : 3"), None);
+    }
+}
@@ -0,0 +1,117 @@
+/// Where the candidate values for a known variable come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueSource {
+    /// Executable files found on `PATH`.
+    Executables,
+    /// Login shells listed in `/etc/shells`.
+    Shells,
+}
+
+/// Variables whose assigned value flyline knows how to complete.
+const KNOWN_VARIABLES: &[(&str, ValueSource)] = &[
+    ("EDITOR", ValueSource::Executables),
+    ("VISUAL", ValueSource::Executables),
+    ("GIT_EDITOR", ValueSource::Executables),
+    ("PAGER", ValueSource::Executables),
+    ("MANPAGER", ValueSource::Executables),
+    ("GIT_PAGER", ValueSource::Executables),
+    ("SHELL", ValueSource::Shells),
+];
+
+fn value_source(var_name: &str) -> Option<ValueSource> {
+    KNOWN_VARIABLES
+        .iter()
+        .find_map(|(name, source)| (*name == var_name).then_some(*source))
+}
+
+/// Split `NAME=value` into its name and value. Returns `None` unless `NAME`
+/// is a valid shell identifier.
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if !(first.is_ascii_alphabetic() || first == '_')
+        || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    Some((name, value))
+}
+
+/// Whether `var_name` has a known set of values to complete after `=`.
+pub fn is_known_variable(var_name: &str) -> bool {
+    value_source(var_name).is_some()
+}
+
+/// Parse the contents of `/etc/shells`, skipping comments and blank lines.
+pub fn parse_etc_shells(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(not(test))]
+fn read_etc_shells() -> String {
+    std::fs::read_to_string("/etc/shells").unwrap_or_else(|e| {
+        log::debug!("Failed to read /etc/shells: {}", e);
+        String::new()
+    })
+}
+
+#[cfg(test)]
+fn read_etc_shells() -> String {
+    crate::bash_funcs::test_fixtures::test_etc_shells().to_string()
+}
+
+/// Candidate values for `var_name` that start with `prefix`, sorted and
+/// deduplicated. Empty if the variable is not known.
+pub fn value_candidates(var_name: &str, prefix: &str) -> Vec<String> {
+    let mut candidates = match value_source(var_name) {
+        Some(ValueSource::Executables) => crate::bash_funcs::get_executables_on_path(),
+        Some(ValueSource::Shells) => parse_etc_shells(&read_etc_shells()),
+        None => return vec![],
+    };
+    candidates.retain(|c| c.starts_with(prefix));
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_assignment_requires_identifier() {
+        assert_eq!(split_assignment("EDITOR=vi"), Some(("EDITOR", "vi")));
+        assert_eq!(split_assignment("_X1="), Some(("_X1", "")));
+        assert_eq!(split_assignment("A=b=c"), Some(("A", "b=c")));
+        assert_eq!(split_assignment("=vi"), None);
+        assert_eq!(split_assignment("1A=vi"), None);
+        assert_eq!(split_assignment("--opt=vi"), None);
+        assert_eq!(split_assignment("EDITOR"), None);
+    }
+
+    #[test]
+    fn parse_etc_shells_skips_comments() {
+        let shells = parse_etc_shells(crate::bash_funcs::test_fixtures::test_etc_shells());
+        assert_eq!(
+            shells,
+            vec!["/bin/sh", "/bin/bash", "/usr/bin/bash", "/usr/bin/zsh"]
+        );
+    }
+
+    #[test]
+    fn value_candidates_by_variable() {
+        assert_eq!(value_candidates("EDITOR", "vi"), vec!["vi", "vim"]);
+        assert_eq!(value_candidates("PAGER", "le"), vec!["less"]);
+        assert_eq!(
+            value_candidates("SHELL", "/usr"),
+            vec!["/usr/bin/bash", "/usr/bin/zsh"]
+        );
+        assert!(value_candidates("UNKNOWN_VAR", "").is_empty());
+    }
+}
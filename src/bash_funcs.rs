@@ -10,21 +10,41 @@ use libc::c_char;
 use libc::c_int;
 use lscolors::LsColors;
 use std::collections::HashMap;
-#[cfg(not(test))]
 use std::collections::HashSet;
 #[cfg(not(test))]
 use std::io::Read;
 #[cfg(not(test))]
-use std::os::unix::fs::PermissionsExt;
-#[cfg(not(test))]
 use std::os::unix::io::FromRawFd;
 use std::path::Path;
-#[cfg(not(test))]
 use std::path::PathBuf;
 use std::sync::{LazyLock, Mutex};
-#[cfg(not(test))]
 use std::time::SystemTime;
 
+/// Why a call into bash couldn't be made or its result couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BashFuncError {
+    /// The argument contains a NUL byte, so it can't be passed as a C string.
+    InteriorNul(String),
+    /// Bash returned text that isn't valid UTF-8.
+    InvalidUtf8(String),
+}
+
+impl std::fmt::Display for BashFuncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BashFuncError::InteriorNul(arg) => write!(f, "{:?} contains a NUL byte", arg),
+            BashFuncError::InvalidUtf8(what) => write!(f, "{} is not valid UTF-8", what),
+        }
+    }
+}
+
+impl std::error::Error for BashFuncError {}
+
+/// `s` as a C string for passing to bash.
+fn c_string(s: &str) -> Result<std::ffi::CString, BashFuncError> {
+    std::ffi::CString::new(s).map_err(|_| BashFuncError::InteriorNul(s.to_string()))
+}
+
 #[cfg(not(test))]
 fn with_redirected_stdout<F, R>(func: F) -> (R, String)
 where
@@ -142,35 +162,55 @@ impl CommandWordInfo {
             },
         }
     }
+
+    /// A one-line preview of what the command runs, shown under the tooltip
+    /// when `flyline editor --show-command-preview` is on. Aliases already
+    /// show their expansion in [`Self::to_description`]. Looked up on demand
+    /// rather than cached, so lookups don't pay for it when the preview is off.
+    pub fn definition_preview(&self) -> Option<String> {
+        match self {
+            CommandWordInfo::Function { command, .. } => function_definition_preview(command),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(not(test))]
-pub fn find_alias(cmd: &str) -> Option<String> {
+pub fn find_alias(cmd: &str) -> Result<Option<String>, BashFuncError> {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    let cmd_c_str = c_string(cmd)?;
     unsafe {
-        let alias_ptr =
-            bash_symbols::get_alias_value(std::ffi::CString::new(cmd).unwrap().as_ptr());
+        let alias_ptr = bash_symbols::get_alias_value(cmd_c_str.as_ptr());
         if alias_ptr.is_null() {
-            return None;
+            return Ok(None);
         }
 
-        let c_str = std::ffi::CStr::from_ptr(alias_ptr);
-        if let Ok(str_slice) = c_str.to_str() {
-            return Some(str_slice.to_string());
-        }
+        std::ffi::CStr::from_ptr(alias_ptr)
+            .to_str()
+            .map(|s| Some(s.to_string()))
+            .map_err(|_| BashFuncError::InvalidUtf8(format!("alias {}", cmd)))
     }
-    None
 }
 
 #[cfg(test)]
-pub fn find_alias(cmd: &str) -> Option<String> {
-    test_fixtures::test_aliases()
-        .iter()
-        .find_map(|(name, value)| (*name == cmd).then(|| (*value).to_string()))
+pub fn find_alias(cmd: &str) -> Result<Option<String>, BashFuncError> {
+    c_string(cmd)?;
+    Ok(test_fixtures::test_aliases()
+        .into_iter()
+        .find_map(|(name, value)| (name == cmd).then_some(value)))
+}
+
+/// The expansion of the alias `cmd`, or `None` if there isn't one or it
+/// can't be read.
+pub fn find_alias_or_log(cmd: &str) -> Option<String> {
+    find_alias(cmd).unwrap_or_else(|e| {
+        log::warn!("Failed to look up alias: {}", e);
+        None
+    })
 }
 
 #[cfg(not(test))]
-fn get_command_info_uncached(cmd: &str) -> CommandWordInfo {
+fn get_command_info_uncached(cmd: &str) -> Result<CommandWordInfo, BashFuncError> {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
     // If the command word looks like a filename (contains '/' or starts with
     // '~'), expand it first so that tilde and variable expansion are resolved
@@ -184,16 +224,16 @@ fn get_command_info_uncached(cmd: &str) -> CommandWordInfo {
     };
 
     // Call the `type` builtin to check if the command exists
-    let cmd_c_str = std::ffi::CString::new(cmd).unwrap();
+    let cmd_c_str = c_string(cmd)?;
 
     let (_, command_type_output) = with_redirected_stdout(|| unsafe {
         bash_symbols::describe_command(cmd_c_str.as_ptr(), bash_symbols::CDescFlag::Type as c_int)
     });
     let command_type_str = command_type_output.trim();
 
-    match command_type_str {
+    Ok(match command_type_str {
         "alias" => {
-            let expansion = find_alias(cmd).unwrap_or_else(|| cmd.to_string());
+            let expansion = find_alias(cmd)?.unwrap_or_else(|| cmd.to_string());
             CommandWordInfo::Alias {
                 command: cmd.to_string(),
                 expansion,
@@ -245,82 +285,158 @@ fn get_command_info_uncached(cmd: &str) -> CommandWordInfo {
                 path: output.trim().to_string(),
             }
         }
-        "function" => unsafe {
-            let func_def_ptr = bash_symbols::find_function_def(cmd_c_str.as_ptr());
-            if !func_def_ptr.is_null() {
-                let func_def = &*func_def_ptr;
-                let line = if func_def.line > 0 {
-                    Some(func_def.line)
-                } else {
-                    None
-                };
-                let source_file = if func_def.source_file.is_null() {
-                    None
-                } else {
-                    std::ffi::CStr::from_ptr(func_def.source_file)
-                        .to_str()
-                        .ok()
-                        .map(|s| s.to_string())
-                };
-                CommandWordInfo::Function {
-                    command: cmd.to_string(),
-                    source_file,
-                    line,
-                }
-            } else {
-                CommandWordInfo::Function {
-                    command: cmd.to_string(),
-                    source_file: None,
-                    line: None,
-                }
-            }
-        },
+        "function" => get_function_info(cmd.to_string()),
         _ => CommandWordInfo::Unknown {
             command: cmd.to_string(),
         },
+    })
+}
+
+/// Where the shell function `name` was defined. Callers must hold
+/// `BASH_LOCK`.
+#[cfg(not(test))]
+fn get_function_info(name: String) -> CommandWordInfo {
+    let Ok(name_c) = c_string(&name) else {
+        return CommandWordInfo::Function {
+            command: name,
+            source_file: None,
+            line: None,
+        };
+    };
+    let func_def_ptr = unsafe { bash_symbols::find_function_def(name_c.as_ptr()) };
+    if func_def_ptr.is_null() {
+        return CommandWordInfo::Function {
+            command: name,
+            source_file: None,
+            line: None,
+        };
     }
+    let func_def = unsafe { &*func_def_ptr };
+    let line = if func_def.line > 0 {
+        Some(func_def.line)
+    } else {
+        None
+    };
+    let source_file = if func_def.source_file.is_null() {
+        None
+    } else {
+        unsafe { std::ffi::CStr::from_ptr(func_def.source_file) }
+            .to_str()
+            .ok()
+            .map(|s| s.to_string())
+    };
+    CommandWordInfo::Function {
+        command: name,
+        source_file,
+        line,
+    }
+}
+
+/// The first line of the shell function `name`'s definition as `declare -f`
+/// prints it, or `None` if there is no such function.
+#[cfg(not(test))]
+pub fn function_definition_preview(name: &str) -> Option<String> {
+    let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    let name_c = c_string(name).ok()?;
+    let func_def_ptr = unsafe { bash_symbols::find_function_def(name_c.as_ptr()) };
+    if func_def_ptr.is_null() {
+        return None;
+    }
+    let func_def = unsafe { &*func_def_ptr };
+    if func_def.command.is_null() {
+        return None;
+    }
+    // The returned buffer belongs to bash and is reused by the next call.
+    let text_ptr = unsafe {
+        bash_symbols::named_function_string(name_c.as_ptr() as *mut c_char, func_def.command, 0)
+    };
+    if text_ptr.is_null() {
+        return None;
+    }
+    unsafe { std::ffi::CStr::from_ptr(text_ptr) }
+        .to_str()
+        .ok()
+        .and_then(|text| text.lines().next())
+        .map(|first_line| first_line.trim().to_string())
+}
+
+#[cfg(test)]
+pub fn function_definition_preview(name: &str) -> Option<String> {
+    test_fixtures::test_functions()
+        .iter()
+        .find_map(|(func, definition)| (*func == name).then(|| (*definition).to_string()))
 }
 
 static CALL_TYPE_CACHE: Mutex<Option<HashMap<String, CommandWordInfo>>> = Mutex::new(None);
 
-#[cfg(not(test))]
+/// What kind of command `cmd` is. A command word bash can't look up, e.g.
+/// one containing a NUL byte, is logged and reported as unknown.
 pub fn get_command_info(cmd: &str) -> CommandWordInfo {
-    let mut cache_guard = CALL_TYPE_CACHE.lock().unwrap();
-    let cache = cache_guard.get_or_insert_with(HashMap::new);
+    try_get_command_info(cmd).unwrap_or_else(|e| {
+        log::warn!("Failed to look up command: {}", e);
+        CommandWordInfo::Unknown {
+            command: cmd.to_string(),
+        }
+    })
+}
 
-    if let Some(res) = cache.get(cmd) {
-        res.clone()
-    } else {
-        let result = get_command_info_uncached(cmd);
-        cache.insert(cmd.to_string(), result.clone());
-        result
-    }
+#[cfg(not(test))]
+pub fn try_get_command_info(cmd: &str) -> Result<CommandWordInfo, BashFuncError> {
+    if let Some(res) = cached_command_info(cmd) {
+        return Ok(res);
+    }
+    // Don't hold the cache lock during the lookup, which may be slow.
+    let result = get_command_info_uncached(cmd)?;
+    CALL_TYPE_CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(cmd.to_string(), result.clone());
+    Ok(result)
+}
+
+/// The result of an earlier [`get_command_info`] call for `cmd`, without
+/// calling into bash.
+#[cfg(not(test))]
+pub fn cached_command_info(cmd: &str) -> Option<CommandWordInfo> {
+    CALL_TYPE_CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(cmd).cloned())
 }
 
 #[cfg(test)]
-pub fn get_command_info(cmd: &str) -> CommandWordInfo {
+pub fn cached_command_info(cmd: &str) -> Option<CommandWordInfo> {
+    Some(get_command_info(cmd))
+}
+
+#[cfg(test)]
+pub fn try_get_command_info(cmd: &str) -> Result<CommandWordInfo, BashFuncError> {
+    // Like the real lookup, a command word bash can't be given is an error.
+    c_string(cmd)?;
     // The test environment models a tiny world: `git` is the only "real"
     // executable on PATH, so it gets reported as a File at /usr/bin/git.
     // Everything else is unknown — tests that need additional command types
     // can extend this match arm.
     if cmd == "git" {
-        return CommandWordInfo::File {
+        return Ok(CommandWordInfo::File {
             command: "git".to_string(),
             path: "/usr/bin/git".to_string(),
-        };
+        });
     }
     if let Some(expansion) = test_fixtures::test_aliases()
-        .iter()
-        .find_map(|(name, value)| (*name == cmd).then(|| (*value).to_string()))
+        .into_iter()
+        .find_map(|(name, value)| (name == cmd).then_some(value))
     {
-        return CommandWordInfo::Alias {
+        return Ok(CommandWordInfo::Alias {
             command: cmd.to_string(),
             expansion,
-        };
+        });
     }
-    CommandWordInfo::Unknown {
+    Ok(CommandWordInfo::Unknown {
         command: cmd.to_string(),
-    }
+    })
 }
 
 #[cfg(not(test))]
@@ -372,6 +488,8 @@ pub fn reset_caches() {
     let mut cache_guard = CALL_TYPE_CACHE.lock().unwrap();
     *cache_guard = None;
 
+    PROGRAMMABLE_COMPLETION_CACHE.lock().unwrap().clear();
+
     let mut cache_guard = SHELL_VAR_CACHE.lock().unwrap();
     *cache_guard = None;
 
@@ -402,8 +520,12 @@ pub fn get_all_aliases() -> Vec<String> {
             let alias = &*ptr;
             if !alias.name.is_null() {
                 let c_str = std::ffi::CStr::from_ptr(alias.name);
-                if let Ok(str_slice) = c_str.to_str() {
-                    aliases.push(str_slice.to_string());
+                match c_str.to_str() {
+                    Ok(str_slice) => aliases.push(str_slice.to_string()),
+                    Err(_) => log::warn!(
+                        "Skipping alias: {}",
+                        BashFuncError::InvalidUtf8(format!("alias {:?}", c_str))
+                    ),
                 }
             }
             offset += 1;
@@ -416,8 +538,8 @@ pub fn get_all_aliases() -> Vec<String> {
 #[cfg(test)]
 pub fn get_all_aliases() -> Vec<String> {
     test_fixtures::test_aliases()
-        .iter()
-        .map(|(name, _)| (*name).to_string())
+        .into_iter()
+        .map(|(name, _)| name)
         .collect()
 }
 
@@ -437,7 +559,13 @@ pub fn get_all_reserved_words() -> Vec<String> {
 pub fn get_all_variables_with_prefix(prefix: &str) -> Vec<String> {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
     let mut variables = Vec::new();
-    let prefix_c_str = std::ffi::CString::new(prefix.strip_prefix('$').unwrap_or(prefix)).unwrap();
+    let prefix_c_str = match c_string(prefix.strip_prefix('$').unwrap_or(prefix)) {
+        Ok(prefix_c_str) => prefix_c_str,
+        Err(e) => {
+            log::warn!("Can't list variables: {}", e);
+            return variables;
+        }
+    };
 
     unsafe {
         let var_ptr = bash_symbols::all_variables_matching_prefix(prefix_c_str.as_ptr());
@@ -639,6 +767,7 @@ impl Default for CompletionFlags {
     }
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProgrammableCompleteReturn {
     pub completions: Vec<String>,
     pub flags: CompletionFlags,
@@ -931,6 +1060,140 @@ pub fn evaluate_shell_string(_script: &str) -> Result<()> {
     Ok(())
 }
 
+const COMPLETION_CACHE_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct CompletionCacheKey {
+    full_command: String,
+    word_under_cursor: String,
+    cursor_byte_pos: usize,
+}
+
+/// Least-recently-used cache of programmable completion results, so pressing
+/// Tab again on an unchanged command doesn't re-run an expensive compspec.
+/// Cleared by [`reset_caches`] at each prompt.
+///
+/// Completions run in a forked child that exits afterwards, so the keys it
+/// fills are recorded for [`take_filled_completion_cache`] to send back to the
+/// parent, where [`merge_completion_cache`] keeps them for later forks.
+struct CompletionCache {
+    entries: std::collections::VecDeque<(CompletionCacheKey, ProgrammableCompleteReturn)>,
+    filled: Vec<CompletionCacheKey>,
+}
+
+pub(crate) type CompletionCacheEntry = (CompletionCacheKey, ProgrammableCompleteReturn);
+
+impl CompletionCache {
+    const fn new() -> Self {
+        Self {
+            entries: std::collections::VecDeque::new(),
+            filled: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, key: &CompletionCacheKey) -> Option<ProgrammableCompleteReturn> {
+        let idx = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(idx)?;
+        let value = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(value)
+    }
+
+    fn insert(&mut self, key: CompletionCacheKey, value: ProgrammableCompleteReturn) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= COMPLETION_CACHE_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, value));
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.filled.clear();
+    }
+
+    /// The entries filled by [`Self::get_or_run`] since the last call.
+    fn take_filled(&mut self) -> Vec<CompletionCacheEntry> {
+        std::mem::take(&mut self.filled)
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.entries.iter().find(|(k, _)| *k == key)?.1.clone();
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    fn merge(&mut self, entries: Vec<CompletionCacheEntry>) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+
+    /// Return the cached result for `key`, or run `complete` and cache its
+    /// result. Errors are not cached.
+    fn get_or_run(
+        &mut self,
+        key: CompletionCacheKey,
+        complete: impl FnOnce() -> Result<ProgrammableCompleteReturn>,
+    ) -> Result<ProgrammableCompleteReturn> {
+        if let Some(hit) = self.get(&key) {
+            log::debug!(
+                "Programmable completions for '{}' served from cache",
+                key.full_command
+            );
+            return Ok(hit);
+        }
+        let result = complete()?;
+        self.filled.push(key.clone());
+        self.insert(key, result.clone());
+        Ok(result)
+    }
+}
+
+static PROGRAMMABLE_COMPLETION_CACHE: Mutex<CompletionCache> = Mutex::new(CompletionCache::new());
+
+/// [`run_programmable_completions`] through the completion cache.
+pub fn run_programmable_completions_cached(
+    full_command: &str,
+    command_word: &str,
+    word_under_cursor: &str,
+    cursor_byte_pos: usize,
+    word_under_cursor_byte_end: usize,
+) -> Result<ProgrammableCompleteReturn> {
+    let run = || {
+        run_programmable_completions(
+            full_command,
+            command_word,
+            word_under_cursor,
+            cursor_byte_pos,
+            word_under_cursor_byte_end,
+        )
+    };
+    let key = CompletionCacheKey {
+        full_command: full_command.to_string(),
+        word_under_cursor: word_under_cursor.to_string(),
+        cursor_byte_pos,
+    };
+    // The lock is held while the compspec runs; completions are serialised by
+    // the bash lock anyway.
+    PROGRAMMABLE_COMPLETION_CACHE
+        .lock()
+        .unwrap()
+        .get_or_run(key, run)
+}
+
+/// Programmable completion results computed in this process since the last
+/// call. Called in the forked completion child before it exits.
+pub(crate) fn take_filled_completion_cache() -> Vec<CompletionCacheEntry> {
+    PROGRAMMABLE_COMPLETION_CACHE.lock().unwrap().take_filled()
+}
+
+/// Keep results sent back by a forked completion child, so the next fork
+/// starts with them.
+pub(crate) fn merge_completion_cache(entries: Vec<CompletionCacheEntry>) {
+    PROGRAMMABLE_COMPLETION_CACHE.lock().unwrap().merge(entries);
+}
+
 #[cfg(not(test))]
 pub fn run_programmable_completions(
     full_command: &str,                // "git commi asdf" with cursor just after com
@@ -963,7 +1226,9 @@ pub fn run_programmable_completions(
     }
 
     unsafe {
-        let full_command_cstr = std::ffi::CString::new(full_command).unwrap();
+        let full_command_cstr = c_string(full_command)?;
+        let command_word_cstr = c_string(command_word)?;
+        let word_under_cursor_cstr = c_string(word_under_cursor)?;
         bash_symbols::rl_line_buffer = bash_symbols::locked_xmalloc_cstr(&full_command_cstr); // git commi asdf
         bash_symbols::rl_point = cursor_byte_pos as std::ffi::c_int; // 7 ("git com|mi asdf")
         bash_symbols::set_readline_state(bash_symbols::RL_STATE_COMPLETING);
@@ -990,8 +1255,8 @@ pub fn run_programmable_completions(
         let foundcs: std::ffi::c_int = 0;
 
         let list_of_strs = bash_symbols::programmable_completions(
-            std::ffi::CString::new(command_word).unwrap().as_ptr(),
-            std::ffi::CString::new(word_under_cursor).unwrap().as_ptr(),
+            command_word_cstr.as_ptr(),
+            word_under_cursor_cstr.as_ptr(),
             0,
             word_under_cursor_byte_end as std::ffi::c_int,
             &foundcs as *const std::ffi::c_int as *mut std::ffi::c_int,
@@ -1179,8 +1444,8 @@ pub fn print_copt_flags(flag: c_int) {
 #[cfg(not(test))]
 pub fn get_shell_var(var_name: &str) -> Option<ShellVar> {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    let var_cstr = c_string(var_name).ok()?;
     unsafe {
-        let var_cstr = std::ffi::CString::new(var_name).unwrap();
         let value_ptr = bash_symbols::find_variable(var_cstr.as_ptr());
         if value_ptr.is_null() {
             return None;
@@ -1218,6 +1483,38 @@ pub fn get_hostname() -> String {
     "test-host".to_string()
 }
 
+/// Append the lines added to bash's history this session to `$HISTFILE`,
+/// like `history -a`. bash applies `HISTCONTROL` and `HISTIGNORE` when it
+/// adds lines, and counts the appended lines as written so `histappend`
+/// doesn't write them again at exit.
+#[cfg(not(test))]
+pub fn append_session_history() {
+    let Some(hist_file) = get_envvar_value("HISTFILE").filter(|f| !f.is_empty()) else {
+        return;
+    };
+    let Ok(hist_file) = c_string(&hist_file) else {
+        return;
+    };
+    let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    // maybe_append_history doesn't keep or free the filename.
+    unsafe { bash_symbols::maybe_append_history(hist_file.as_ptr() as *mut c_char) };
+}
+
+#[cfg(test)]
+pub fn append_session_history() {}
+
+/// `$?`: the exit status of the last command bash ran.
+#[cfg(not(test))]
+pub fn get_last_exit_code() -> i32 {
+    let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    unsafe { bash_symbols::last_command_exit_value }
+}
+
+#[cfg(test)]
+pub fn get_last_exit_code() -> i32 {
+    0
+}
+
 #[cfg(not(test))]
 pub fn get_cwd() -> String {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
@@ -1245,13 +1542,13 @@ pub fn get_cwd() -> String {
 #[cfg(not(test))]
 pub fn expand_filename(filename: &str) -> String {
     let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    let Ok(filename_cstr) = c_string(filename) else {
+        return filename.to_string();
+    };
     unsafe {
         // expand_string_to_string returns an allocated string via string_list (using xmalloc)
         // (see mirror-bash/subst.c:3859 / 3869). We must free it with locked_xfree.
-        let expanded_string = bash_symbols::expand_string_to_string(
-            std::ffi::CString::new(filename).unwrap().as_ptr(),
-            0,
-        );
+        let expanded_string = bash_symbols::expand_string_to_string(filename_cstr.as_ptr(), 0);
 
         if expanded_string.is_null() {
             return filename.to_string();
@@ -1285,7 +1582,7 @@ pub fn expand_filename(filename: &str) -> String {
 
     let home = get_envvar_value("HOME").unwrap_or_default();
 
-    // Tilde expansion: leading `~/` only (we don't try to support `~user`).
+    // Tilde expansion: leading `~/` only (`~user` is resolved by `fully_expand_path`).
     let mut expanded = if let Some(rest) = filename.strip_prefix("~/") {
         format!("{}/{}", home, rest)
     } else if filename == "~" {
@@ -1314,7 +1611,10 @@ pub fn fully_expand_path(p: &str) -> String {
     let bash_expanded = if p.is_empty() {
         String::new()
     } else {
-        expand_filename(&dequoting_function_rust(p))
+        let dequoted = dequoting_function_rust(p);
+        // Resolve `~user` ourselves so it works the same with and without bash.
+        let dequoted = crate::users::expand_tilde_user(&dequoted).unwrap_or(dequoted);
+        expand_filename(&dequoted)
     };
 
     // Make the path absolute (prepend cwd when relative or empty).
@@ -1344,7 +1644,7 @@ pub fn resolve_completion_script_path(
     flycomp_output: Option<&str>,
 ) -> std::path::PathBuf {
     // Resolve the alias-expanded target command name
-    let poss_alias = find_alias(command_word);
+    let poss_alias = find_alias_or_log(command_word);
     let alias_def = poss_alias
         .as_deref()
         .filter(|alias| !alias.is_empty())
@@ -1609,7 +1909,6 @@ static DEFINED_RESERVED_WORDS: Mutex<Option<Vec<CommandWordInfo>>> = Mutex::new(
 static DEFINED_SHELL_FUNCTIONS: Mutex<Option<Vec<CommandWordInfo>>> = Mutex::new(None);
 static DEFINED_BUILTINS: Mutex<Option<Vec<CommandWordInfo>>> = Mutex::new(None);
 
-#[cfg(not(test))]
 fn get_cached_aliases() -> Vec<CommandWordInfo> {
     let mut guard = DEFINED_ALIASES.lock().unwrap();
     guard
@@ -1617,7 +1916,7 @@ fn get_cached_aliases() -> Vec<CommandWordInfo> {
             get_all_aliases()
                 .into_iter()
                 .map(|name| {
-                    let expansion = find_alias(&name).unwrap_or_else(|| name.clone());
+                    let expansion = find_alias_or_log(&name).unwrap_or_else(|| name.clone());
                     CommandWordInfo::Alias {
                         command: name,
                         expansion,
@@ -1652,37 +1951,7 @@ fn get_cached_shell_functions() -> Vec<CommandWordInfo> {
         .get_or_insert_with(|| {
             get_all_shell_functions()
                 .into_iter()
-                .map(|name| unsafe {
-                    let name_c = std::ffi::CString::new(name.clone()).unwrap();
-                    let func_def_ptr = bash_symbols::find_function_def(name_c.as_ptr());
-                    if !func_def_ptr.is_null() {
-                        let func_def = &*func_def_ptr;
-                        let line = if func_def.line > 0 {
-                            Some(func_def.line)
-                        } else {
-                            None
-                        };
-                        let source_file = if func_def.source_file.is_null() {
-                            None
-                        } else {
-                            std::ffi::CStr::from_ptr(func_def.source_file)
-                                .to_str()
-                                .ok()
-                                .map(|s| s.to_string())
-                        };
-                        CommandWordInfo::Function {
-                            command: name,
-                            source_file,
-                            line,
-                        }
-                    } else {
-                        CommandWordInfo::Function {
-                            command: name,
-                            source_file: None,
-                            line: None,
-                        }
-                    }
-                })
+                .map(get_function_info)
                 .collect()
         })
         .clone()
@@ -1706,7 +1975,6 @@ fn get_cached_builtins() -> Vec<CommandWordInfo> {
 
 /// Per-directory executable cache entry: the directory's last-modified time and
 /// the list of executable filenames found in that directory.
-#[cfg(not(test))]
 struct DirExecutables {
     mtime: Option<SystemTime>,
     names: Vec<String>,
@@ -1720,16 +1988,19 @@ struct DirExecutables {
 /// 2. Newly-added directories are scanned and inserted.
 /// 3. For each remaining directory the last-modified time is compared to the
 ///    cached value; if it has changed the directory is re-scanned.
-#[cfg(not(test))]
+///
+/// The first full scan happens on the warming thread. Until it has finished,
+/// completion sees no executables rather than walking `PATH` itself.
 struct ExecutablesOnPath {
     cache: HashMap<PathBuf, DirExecutables>,
+    scanned: bool,
 }
 
-#[cfg(not(test))]
 impl ExecutablesOnPath {
     fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            scanned: false,
         }
     }
 
@@ -1770,6 +2041,7 @@ impl ExecutablesOnPath {
                 }
             }
         }
+        self.scanned = true;
     }
 
     /// Iterate over the names of all cached executables.
@@ -1792,13 +2064,10 @@ impl ExecutablesOnPath {
             for entry in entries.flatten() {
                 if let Ok(metadata) = std::fs::metadata(entry.path())
                     && metadata.is_file()
+                    && crate::users::is_executable_by_current_user(&metadata)
+                    && let Some(file_name) = entry.file_name().to_str()
                 {
-                    let permissions = metadata.permissions();
-                    if permissions.mode() & 0o111 != 0 {
-                        if let Some(file_name) = entry.file_name().to_str() {
-                            names.push(file_name.to_string());
-                        }
-                    }
+                    names.push(file_name.to_string());
                 }
             }
         }
@@ -1806,10 +2075,24 @@ impl ExecutablesOnPath {
     }
 }
 
-#[cfg(not(test))]
 static EXECUTABLES_ON_PATH: LazyLock<Mutex<ExecutablesOnPath>> =
     LazyLock::new(|| Mutex::new(ExecutablesOnPath::new()));
 
+/// Executables on `PATH`, or none if the warming thread has not finished its
+/// first scan yet. Never blocks on a scan in progress.
+fn cached_executables() -> Vec<CommandWordInfo> {
+    match EXECUTABLES_ON_PATH.try_lock() {
+        Ok(mut exe_guard) if exe_guard.scanned => {
+            exe_guard.update_cache();
+            exe_guard.iter_info().collect()
+        }
+        _ => {
+            log::debug!("PATH scan still in progress, skipping executables");
+            vec![]
+        }
+    }
+}
+
 pub(crate) static LS_COLORS: LazyLock<Option<LsColors>> =
     LazyLock::new(|| get_envvar_value("LS_COLORS").map(|s| LsColors::from_string(&s)));
 
@@ -1820,10 +2103,7 @@ pub fn get_possible_command_words() -> impl Iterator<Item = CommandWordInfo> {
     let reserved_words = get_cached_reserved_words();
     let shell_functions = get_cached_shell_functions();
     let builtins = get_cached_builtins();
-    let mut exe_guard = EXECUTABLES_ON_PATH.lock().unwrap();
-    exe_guard.update_cache();
-    let executables: Vec<CommandWordInfo> = exe_guard.iter_info().collect();
-    drop(exe_guard);
+    let executables = cached_executables();
 
     aliases
         .into_iter()
@@ -1843,20 +2123,51 @@ pub fn get_possible_command_words() -> impl Iterator<Item = CommandWordInfo> {
         })
 }
 
+/// Names of the executable files in the directories on `PATH`.
 #[cfg(not(test))]
+pub fn get_executables_on_path() -> Vec<String> {
+    cached_executables()
+        .into_iter()
+        .map(|info| info.command().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+pub fn get_executables_on_path() -> Vec<String> {
+    test_fixtures::test_executables_on_path()
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
 pub fn warm_completion_caches() {
-    let _guard = crate::bash_symbols::BASH_LOCK.lock();
-    let _ = get_cached_aliases();
-    let _ = get_cached_reserved_words();
-    let _ = get_cached_shell_functions();
-    let _ = get_cached_builtins();
+    #[cfg(not(test))]
+    {
+        let _guard = crate::bash_symbols::BASH_LOCK.lock();
+        let _ = get_cached_aliases();
+        let _ = get_cached_reserved_words();
+        let _ = get_cached_shell_functions();
+        let _ = get_cached_builtins();
+    }
+    // Scan PATH without holding the bash lock so the cheap sources above are
+    // usable straight away on slow or network filesystems.
     if let Ok(mut exe_guard) = EXECUTABLES_ON_PATH.lock() {
         exe_guard.update_cache();
     }
 }
 
-#[cfg(test)]
-pub fn warm_completion_caches() {}
+/// Drop every cached completion source (aliases, functions, builtins,
+/// executables on PATH, commit messages) and rebuild them from the current
+/// shell state. Unlike the per-prompt [`reset_caches`], this also forces a
+/// full re-scan of PATH directories whose mtime has not changed.
+pub fn reload_completion_caches() {
+    reset_caches();
+    crate::git_completion::clear_cache();
+    if let Ok(mut exe_guard) = EXECUTABLES_ON_PATH.lock() {
+        *exe_guard = ExecutablesOnPath::new();
+    }
+    warm_completion_caches();
+}
 
 #[cfg(not(test))]
 pub fn read_terminating_signal() -> c_int {
@@ -1945,6 +2256,184 @@ pub fn unset_env_var(name: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rusty_fork::rusty_fork_test;
+
+    fn completion_key(full_command: &str, cursor_byte_pos: usize) -> CompletionCacheKey {
+        CompletionCacheKey {
+            full_command: full_command.to_string(),
+            word_under_cursor: full_command[..cursor_byte_pos]
+                .rsplit(' ')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+            cursor_byte_pos,
+        }
+    }
+
+    #[test]
+    fn test_command_word_with_nul_is_an_error_not_a_panic() {
+        assert_eq!(
+            try_get_command_info("ls\0-la"),
+            Err(BashFuncError::InteriorNul("ls\0-la".to_string()))
+        );
+        assert_eq!(
+            get_command_info("ls\0-la"),
+            CommandWordInfo::Unknown {
+                command: "ls\0-la".to_string()
+            }
+        );
+        assert!(find_alias("gst\0").is_err());
+        assert_eq!(find_alias_or_log("gst\0"), None);
+        assert_eq!(find_alias_or_log("gst"), Some("git status".to_string()));
+        assert_eq!(
+            BashFuncError::InvalidUtf8("alias x".to_string()).to_string(),
+            "alias x is not valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn test_completion_cache_serves_repeated_request() {
+        let mut cache = CompletionCache::new();
+        let mut runs = 0;
+        for _ in 0..3 {
+            let res = cache
+                .get_or_run(completion_key("git che", 7), || {
+                    runs += 1;
+                    run_programmable_completions("git che", "git", "che", 7, 7)
+                })
+                .unwrap();
+            assert_eq!(
+                res.completions,
+                run_programmable_completions("git che", "git", "che", 7, 7)
+                    .unwrap()
+                    .completions
+            );
+        }
+        assert_eq!(runs, 1);
+
+        // A different cursor position is a different request.
+        cache
+            .get_or_run(completion_key("git che", 5), || {
+                runs += 1;
+                run_programmable_completions("git che", "git", "c", 5, 7)
+            })
+            .unwrap();
+        assert_eq!(runs, 2);
+    }
+
+    #[test]
+    fn test_completion_cache_does_not_cache_errors() {
+        let mut cache = CompletionCache::new();
+        let mut runs = 0;
+        for _ in 0..2 {
+            let res = cache.get_or_run(completion_key("nope x", 6), || {
+                runs += 1;
+                Err(anyhow::anyhow!("no compspec"))
+            });
+            assert!(res.is_err());
+        }
+        assert_eq!(runs, 2);
+    }
+
+    #[test]
+    fn test_completion_cache_fills_survive_in_parent() {
+        // The child fills its copy of the cache and sends the fills back
+        // through the pipe as JSON.
+        let mut child = CompletionCache::new();
+        child
+            .get_or_run(completion_key("git che", 7), || {
+                run_programmable_completions("git che", "git", "che", 7, 7)
+            })
+            .unwrap();
+        let fills = child.take_filled();
+        assert_eq!(fills.len(), 1);
+        assert!(child.take_filled().is_empty());
+        let fills: Vec<CompletionCacheEntry> =
+            serde_json::from_slice(&serde_json::to_vec(&fills).unwrap()).unwrap();
+
+        let mut parent = CompletionCache::new();
+        parent.merge(fills);
+        let res = parent
+            .get_or_run(completion_key("git che", 7), || {
+                panic!("parent should be served from the merged fills")
+            })
+            .unwrap();
+        assert_eq!(
+            res.completions,
+            run_programmable_completions("git che", "git", "che", 7, 7)
+                .unwrap()
+                .completions
+        );
+        // Merged entries are not sent back again.
+        assert!(parent.take_filled().is_empty());
+    }
+
+    fn cached_command_names(infos: Vec<CommandWordInfo>) -> Vec<String> {
+        let mut names: Vec<String> = infos
+            .iter()
+            .map(|info| info.command().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn add_executable(dir: &Path, name: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn test_reload_picks_up_changed_aliases_and_executables() {
+            let dir = std::env::temp_dir().join(format!("flyline_reload_path_{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            add_executable(&dir, "tool_one");
+            unsafe {
+                std::env::set_var("FLYLINE_TEST_PATH", &dir);
+                std::env::set_var("FLYLINE_TEST_ALIASES", "ll=ls -l\n");
+            }
+            reload_completion_caches();
+            assert_eq!(cached_command_names(get_cached_aliases()), vec!["ll"]);
+            assert_eq!(cached_command_names(cached_executables()), vec!["tool_one"]);
+
+            // Change both sources, keeping the directory's mtime so the
+            // per-prompt PATH check alone would not notice the new file.
+            let mtime = dir.metadata().unwrap().modified().unwrap();
+            add_executable(&dir, "tool_two");
+            std::fs::File::open(&dir).unwrap().set_modified(mtime).unwrap();
+            unsafe { std::env::set_var("FLYLINE_TEST_ALIASES", "la=ls -a\n"); }
+            assert_eq!(cached_command_names(get_cached_aliases()), vec!["ll"]);
+            assert_eq!(cached_command_names(cached_executables()), vec!["tool_one"]);
+
+            reload_completion_caches();
+            assert_eq!(cached_command_names(get_cached_aliases()), vec!["la"]);
+            assert_eq!(
+                cached_command_names(cached_executables()),
+                vec!["tool_one", "tool_two"]
+            );
+
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn test_completion_cache_evicts_least_recently_used() {
+        let mut cache = CompletionCache::new();
+        let value = || ProgrammableCompleteReturn::new(vec![], CompletionFlags::default(), true);
+        for i in 0..COMPLETION_CACHE_CAPACITY {
+            cache.insert(completion_key(&format!("cmd {i}"), 4), value());
+        }
+        // Touch the oldest entry so the second-oldest is evicted instead.
+        assert!(cache.get(&completion_key("cmd 0", 4)).is_some());
+        cache.insert(completion_key("cmd new", 4), value());
+
+        assert_eq!(cache.entries.len(), COMPLETION_CACHE_CAPACITY);
+        assert!(cache.get(&completion_key("cmd 0", 4)).is_some());
+        assert!(cache.get(&completion_key("cmd 1", 4)).is_none());
+        assert!(cache.get(&completion_key("cmd new", 4)).is_some());
+    }
 
     #[test]
     fn test_quote_function() {
@@ -1999,6 +2488,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quote_function_filename_with_space_and_dollar() {
+        let name = "my $file.txt";
+        assert_eq!(
+            quoting_function_rust(name, QuoteType::Backslash, true, true),
+            r#"my\ \$file.txt"#
+        );
+        assert_eq!(
+            quoting_function_rust(name, QuoteType::DoubleQuote, true, true),
+            r#""my \$file.txt""#
+        );
+        assert_eq!(
+            quoting_function_rust(name, QuoteType::SingleQuote, true, true),
+            r#"'my $file.txt'"#
+        );
+        // Single quotes can't be escaped, so close, escape and reopen.
+        assert_eq!(
+            quoting_function_rust("it's $5", QuoteType::SingleQuote, true, true),
+            r#"'it'\''s $5'"#
+        );
+        // Continuing a word that already has its opening quote.
+        assert_eq!(
+            quoting_function_rust(name, QuoteType::DoubleQuote, false, false),
+            r#"my \$file.txt"#
+        );
+    }
+
     #[test]
     fn test_dequoting_function() {
         assert_eq!(dequoting_function_rust(r#"qwe\ asd"#), r#"qwe asd"#);
@@ -2208,12 +2724,58 @@ pub(crate) mod test_fixtures {
 
     /// Aliases visible to the test build of flyline. Shared between
     /// `find_alias` and `get_all_aliases` so the two stay in sync.
-    pub(crate) fn test_aliases() -> &'static [(&'static str, &'static str)] {
-        &[
-            ("gst", "git status"),
-            ("gcm", "git commit -m"),
-            ("gd", "git diff"),
-        ]
+    /// `FLYLINE_TEST_ALIASES` (newline-separated `name=expansion` pairs)
+    /// replaces the defaults, so tests can change the aliases mid-run.
+    pub(crate) fn test_aliases() -> Vec<(String, String)> {
+        match std::env::var("FLYLINE_TEST_ALIASES") {
+            Ok(aliases) => aliases
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            Err(_) => [
+                ("gst", "git status"),
+                ("gcm", "git commit -m"),
+                ("gd", "git diff"),
+                ("g", "git"),
+            ]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        }
+    }
+
+    /// Shell functions and the first line of their definitions, backing the
+    /// test build of `function_definition_preview`.
+    pub(crate) fn test_functions() -> &'static [(&'static str, &'static str)] {
+        &[("mkcd", "mkcd () { mkdir -p \"$1\" && cd \"$1\"; }")]
+    }
+
+    /// Sample `git log --format=%s` output backing the test build of the
+    /// git commit message completer. Includes a blank line and a repeated
+    /// subject to exercise parsing.
+    pub(crate) fn test_git_log_output() -> &'static str {
+        "Fix cursor flicker on resize\n\
+         Add fuzzy history search\n\
+         \n\
+         Bump version to 0.4.0\n\
+         Fix cursor flicker on resize\n\
+         Handle 'quoted' args in completions\n"
+    }
+
+    /// Executables on `PATH` visible to the test build.
+    pub(crate) fn test_executables_on_path() -> &'static [&'static str] {
+        &["emacs", "git", "less", "ls", "more", "nano", "vi", "vim"]
+    }
+
+    /// Sample `/etc/shells` contents, including a comment and a blank line.
+    pub(crate) fn test_etc_shells() -> &'static str {
+        "# /etc/shells: valid login shells\n\
+         /bin/sh\n\
+         /bin/bash\n\
+         \n\
+         /usr/bin/bash\n\
+         /usr/bin/zsh\n"
     }
 
     /// Hardcoded set of environment variables visible to test code. The
@@ -2227,14 +2789,21 @@ pub(crate) mod test_fixtures {
             .map(|p| p.to_string_lossy().into_owned())
             .unwrap_or_default();
         let home = std::env::var("FLYLINE_TEST_HOME").unwrap_or_else(|_| "/home/john".to_string());
-        vec![
+        let mut vars = vec![
             ("HOME".to_string(), home),
             ("PWD".to_string(), pwd),
-            ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+            (
+                "PATH".to_string(),
+                std::env::var("FLYLINE_TEST_PATH").unwrap_or_else(|_| "/usr/bin:/bin".to_string()),
+            ),
             ("SHELL".to_string(), "/bin/bash".to_string()),
             ("TERM".to_string(), "xterm-256color".to_string()),
             ("USER".to_string(), "john".to_string()),
-        ]
+        ];
+        if let Ok(cdpath) = std::env::var("FLYLINE_TEST_CDPATH") {
+            vars.push(("CDPATH".to_string(), cdpath));
+        }
+        vars
     }
 
     /// Tiny clap definition used to drive the test build of
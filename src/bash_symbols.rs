@@ -60,6 +60,7 @@ pub struct BashBuiltin {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuiltinExitCode {
     ExecutionSuccess = 0,
+    ExecutionFailure = 1,
     BadSyntax = 257,    // shell syntax error
     Usage = 258,        // syntax error in usage
     RedirFail = 259,    // redirection failed
@@ -179,6 +180,16 @@ unsafe extern "C" {
     // extern FUNCTION_DEF *find_function_def (const char *);
     pub fn find_function_def(name: *const c_char) -> *mut FunctionDef;
 
+    // print_cmd.c
+    /* Returns a static buffer holding the text of the function NAME with body
+    COMMAND. Don't free it. */
+    // extern char *named_function_string (char *, COMMAND *, int);
+    pub fn named_function_string(
+        name: *mut c_char,
+        command: *mut libc::c_void,
+        flags: c_int,
+    ) -> *mut c_char;
+
     // from type.def
     // int describe_command (char *command, int dflags)
     pub fn describe_command(command: *const c_char, dflags: c_int) -> c_int;
@@ -336,6 +347,18 @@ unsafe extern "C" {
     // extern HIST_ENTRY **history_list (void);
     pub fn history_list() -> *mut *mut HistoryEntry;
 
+    // int history_base;
+    #[link_name = "history_base"]
+    pub static history_base: c_int;
+
+    // bashhist.c
+    // int maybe_append_history (char *filename);
+    pub fn maybe_append_history(filename: *mut c_char) -> c_int;
+
+    // int history_expansion_inhibited;
+    #[link_name = "history_expansion_inhibited"]
+    pub static mut history_expansion_inhibited: c_int;
+
     // y.tab.c
     // char *current_readline_prompt
     #[link_name = "current_readline_prompt"]
@@ -0,0 +1,49 @@
+use unicode_bidi::BidiInfo;
+
+/// The visual order of a line made of `graphemes`: the logical index of
+/// the grapheme drawn at each visual position. `None` when the line has no
+/// right-to-left text and so is drawn as written.
+pub fn visual_order(graphemes: &[&str]) -> Option<Vec<usize>> {
+    let text = graphemes.concat();
+    let info = BidiInfo::new(&text, None);
+    if !info.has_rtl() {
+        return None;
+    }
+
+    let mut byte_levels = info.levels.clone();
+    for para in &info.paragraphs {
+        let range = para.range.clone();
+        byte_levels[range.clone()]
+            .copy_from_slice(&info.reordered_levels(para, range.clone())[range]);
+    }
+    let levels: Vec<_> = graphemes
+        .iter()
+        .scan(0, |offset, g| {
+            // Empty symbols (e.g. reset cells) take the level of what precedes them.
+            let level = byte_levels.get(*offset).or(byte_levels.last()).copied();
+            *offset += g.len();
+            level
+        })
+        .collect();
+    Some(BidiInfo::reorder_visual(&levels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    #[test]
+    fn ltr_line_keeps_logical_order() {
+        assert_eq!(visual_order(&["a", "b", " ", "c"]), None);
+    }
+
+    #[test]
+    fn rtl_run_is_reversed_inside_ltr_line() {
+        let graphemes: Vec<&str> = "abc مرحبا def".graphemes(true).collect();
+        assert_eq!(
+            visual_order(&graphemes),
+            Some(vec![0, 1, 2, 3, 8, 7, 6, 5, 4, 9, 10, 11, 12])
+        );
+    }
+}
@@ -15,9 +15,26 @@ use crate::{
     app::actions::{self},
     bash_funcs, bash_symbols, content_utils,
     cursor::{self, CursorStyleConfig},
-    dparser, logging, palette, settings, tutorial,
+    dparser, logging, palette, settings,
+    snake_animation::AnimationKind,
+    tutorial,
 };
 
+/// The first line of `flyline --version`.
+pub(crate) fn version_line() -> String {
+    format!(
+        "flyline version {} ({}) git:{} built:{}",
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        env!("GIT_HASH"),
+        env!("BUILD_TIME"),
+    )
+}
+
 fn get_styles() -> clap::builder::Styles {
     clap::builder::Styles::styled()
         .header(
@@ -58,6 +75,23 @@ fn parse_matrix_animation(s: &str) -> Result<settings::MatrixAnimation, String>
     }
 }
 
+/// Parse `WORD=ANIMATION`, where `ANIMATION` is an animation name or `none`.
+fn parse_command_animation(s: &str) -> Result<(String, Option<AnimationKind>), String> {
+    let (word, animation) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `WORD=ANIMATION`, got `{s}`"))?;
+    if word.is_empty() {
+        return Err(format!("expected a trigger word before `=`, got `{s}`"));
+    }
+    let kind = match animation {
+        "none" => None,
+        _ => Some(<AnimationKind as clap::ValueEnum>::from_str(
+            animation, true,
+        )?),
+    };
+    Ok((word.to_string(), kind))
+}
+
 fn parse_effect_speed(s: &str) -> Result<f32, String> {
     let val: f32 = s.parse().map_err(|e| format!("invalid float: {e}"))?;
     if (0.0..=10.0).contains(&val) {
@@ -89,6 +123,10 @@ struct FlylineArgs {
     /// value is equivalent to `on`.
     #[arg(long = "matrix-animation", default_missing_value = "on", num_args = 0..=1, value_parser = parse_matrix_animation)]
     matrix_animation: Option<settings::MatrixAnimation>,
+    /// Animate command words starting with WORD, e.g. `cargo=snake`. Use `WORD=none` to
+    /// remove a trigger. May be repeated. `python=snake` is set by default.
+    #[arg(long = "set-command-animation", value_name = "WORD=ANIMATION", value_parser = parse_command_animation)]
+    command_animations: Vec<(String, Option<AnimationKind>)>,
     /// Render frame rate in frames per second (1–120, default 24)
     #[arg(long = "set-frame-rate", value_name = "FPS", value_parser = clap::value_parser!(u8).range(1..=120))]
     frame_rate: Option<u8>,
@@ -385,6 +423,7 @@ enum Commands {
     ///   flyline set-cursor --effect blink --effect-speed 2.0
     ///   flyline set-cursor --effect fade --effect-easing in-out-sine
     ///   flyline set-cursor --interpolate none
+    ///   flyline set-cursor --shape bar --effect blink --effect-period 1000
     #[command(name = "set-cursor", verbatim_doc_comment)]
     SetCursor {
         /// Cursor rendering backend.  `flyline` renders a custom cursor (the default);
@@ -412,6 +451,13 @@ enum Commands {
         /// Easing function for the cursor effect intensity.  Default is `linear`.
         #[arg(long, value_name = "EASING", add = ArgValueCompleter::new(possible_effect_easing_completions))]
         effect_easing: Option<cursor::CursorEasing>,
+        /// Length of one fade or blink cycle in milliseconds, overriding
+        /// --effect-speed.  `0` keeps the cursor at full intensity.
+        #[arg(long, value_name = "MS")]
+        effect_period: Option<u64>,
+        /// Cursor shape: `block` (default), `bar`, or `underline`.
+        #[arg(long)]
+        shape: Option<cursor::CursorShape>,
     },
     /// Manage keybindings.
     ///
@@ -476,20 +522,25 @@ enum Commands {
     /// Configure the inline editor.
     ///
     /// Controls behaviours of the buffer editor: automatic closing of bracket
-    /// pairs and quotes, inline history suggestions, and whether mouse clicks
-    /// and drags change the buffer cursor and selection.
+    /// pairs and quotes, inline history suggestions, whether mouse clicks
+    /// and drags change the buffer cursor and selection, and whether trailing
+    /// whitespace is trimmed from submitted commands, and whether command
+    /// tooltips preview shell function definitions.
     ///
     /// Examples:
     ///   flyline editor --auto-close-chars false
     ///   flyline editor --show-inline-history false
     ///   flyline editor --select-with-mouse false
+    ///   flyline editor --trim-on-submit
+    ///   flyline editor --show-command-preview
     ///   flyline editor --auto-close-chars true --select-with-mouse true
     #[command(name = "editor", verbatim_doc_comment)]
     Editor {
         /// Enable automatic closing character insertion (e.g. insert `)` after `(`).
         #[arg(long = "auto-close-chars", default_missing_value = "true", num_args = 0..=1)]
         auto_close_chars: Option<bool>,
-        /// Show inline history suggestions.
+        /// Show inline history suggestions. Set FLYLINE_SUGGEST_META=0 to hide
+        /// the `#idx=` and age shown after the suggestion.
         #[arg(long = "show-inline-history", default_missing_value = "true", num_args = 0..=1)]
         show_inline_history: Option<bool>,
         /// Whether mouse clicks and drags on the command buffer change the
@@ -497,6 +548,16 @@ enum Commands {
         /// mouse interaction with the buffer does not change the selection.
         #[arg(long = "select-with-mouse", default_missing_value = "true", num_args = 0..=1)]
         select_with_mouse: Option<bool>,
+        /// Strip trailing whitespace from each line of a command when it is submitted.
+        /// Whitespace inside quoted strings and heredocs is preserved. Default is `false`,
+        /// or `FLYLINE_TRIM_ON_SUBMIT=1` to turn it on.
+        #[arg(long = "trim-on-submit", default_missing_value = "true", num_args = 0..=1)]
+        trim_on_submit: Option<bool>,
+        /// Show the first line of a shell function's definition in the tooltip
+        /// of a command word. Alias tooltips always show the expansion.
+        /// Default is `false`.
+        #[arg(long = "show-command-preview", default_missing_value = "true", num_args = 0..=1)]
+        show_command_preview: Option<bool>,
     },
     /// Configure suggestion behavior.
     ///
@@ -519,9 +580,14 @@ enum Commands {
         /// How to sort suggestions when fuzzy scores are tied (mtime, alphabetical).
         #[arg(long = "sort-order", value_name = "ORDER")]
         sort_order: Option<settings::SuggestionSortOrder>,
-        /// Maximum number of suggestion rows to render for tab-completion lists.
+        /// Maximum number of suggestion rows to render for tab-completion lists (default 8).
+        /// `FLYLINE_MAX_SUGGESTION_ROWS` sets this too.
         #[arg(long = "num-suggestion-rows", value_name = "NUM")]
         num_suggestion_rows: Option<u16>,
+        /// Show the menu even when there is only one candidate, instead of accepting it immediately.
+        /// `FLYLINE_CONFIRM_SINGLE_COMPLETION=1` turns this on too.
+        #[arg(long = "confirm-single-completion", default_missing_value = "true", num_args = 0..=1)]
+        confirm_single_completion: Option<bool>,
         /// Directory where flycomp output should be saved.
         /// You should source the completions from this directory in your bashrc so flyline can use them next time.
         #[arg(long = "flycomp-output", value_name = "DIR")]
@@ -561,6 +627,94 @@ enum Commands {
     ///   flyline upgrade
     #[command(name = "upgrade", verbatim_doc_comment)]
     Upgrade,
+    /// Check whether each command in a script is complete.
+    ///
+    /// Reads FILE (or stdin) and prints, per top-level command, whether
+    /// flyline considers it complete and which quotes or brackets are left
+    /// open. Exits non-zero if any command is incomplete.
+    ///
+    /// Examples:
+    ///   flyline check-syntax ~/.bashrc
+    ///   flyline check-syntax --json < script.sh
+    #[command(name = "check-syntax", verbatim_doc_comment)]
+    CheckSyntax {
+        /// Script to check. Reads stdin when omitted.
+        #[arg(value_name = "FILE")]
+        file: Option<std::path::PathBuf>,
+        /// Print the report as JSON.
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Print how flyline tokenizes and annotates a command.
+    ///
+    /// Prints one tab separated line per token: index, byte range, token
+    /// kind, value, and annotations such as `opening=<idx>` (the index of
+    /// the matching closing token) or `command_word="git"`. Useful for
+    /// reproducing highlighting or completion issues.
+    ///
+    /// Examples:
+    ///   flyline dump-parse 'echo "$(ls)"'
+    ///   flyline dump-parse "$(cat cmd.sh)" | grep opening
+    #[command(name = "dump-parse", verbatim_doc_comment)]
+    DumpParse {
+        /// The command line to parse.
+        #[arg(value_name = "COMMAND")]
+        command: String,
+    },
+    /// Print a command with flyline's syntax highlighting as ANSI escapes.
+    ///
+    /// Uses the current colour palette, so other tools can show commands
+    /// the way flyline draws them.
+    ///
+    /// Examples:
+    ///   flyline highlight 'for f in *.rs; do wc -l "$f"; done'
+    ///   flyline highlight "$(fc -ln -1)"
+    #[command(name = "highlight", verbatim_doc_comment)]
+    Highlight {
+        /// The command line to highlight.
+        #[arg(value_name = "COMMAND")]
+        command: String,
+    },
+    /// Print a shell completion script for the flyline builtin.
+    ///
+    /// Flyline completes its own arguments while it is running. The script
+    /// is for shells where it isn't, e.g. with `bash --noprofile`.
+    ///
+    /// Examples:
+    ///   flyline completions bash > ~/.local/share/bash-completion/completions/flyline
+    ///   source <(flyline completions bash)
+    #[command(name = "completions", verbatim_doc_comment)]
+    Completions {
+        /// Shell to generate the script for.
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+}
+
+/// The file this code was loaded from, i.e. the path given to `enable -f`.
+/// Several copies of flyline (a release download, a local build) are easy to
+/// mix up, so `--version` reports which one bash actually loaded.
+fn loaded_library_path() -> Option<std::path::PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    let addr = loaded_library_path as *const libc::c_void;
+    if unsafe { libc::dladdr(addr, &mut info) } == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(info.dli_fname) };
+    if name.is_empty() {
+        return None;
+    }
+    let path = std::path::Path::new(std::ffi::OsStr::from_bytes(name.to_bytes()));
+    Some(std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// The completion script for `shell`, generated from the [`FlylineArgs`] parser.
+fn completion_script(shell: clap_complete::Shell) -> String {
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut FlylineArgs::command(), "flyline", &mut out);
+    String::from_utf8_lossy(&out).into_owned()
 }
 
 #[derive(Subcommand, Debug)]
@@ -604,7 +758,7 @@ enum KeySubcommands {
     ///   agentModeWaiting, agentOutputSelection, agentModeError,
     ///   inlineSuggestionAvailable, cursorAtEnd, cursorAtEndTrimmed,
     ///   cursorAtStart, promptDirSelection, textSelected, multilineBuffer,
-    ///   bufferHasAgentModePrefix, editingBufferMode.
+    ///   pastedCompleteCommands, bufferHasAgentModePrefix, editingBufferMode.
     ///
     /// Examples:
     ///   flyline key bind Ctrl+Enter always=submitOrNewline
@@ -831,10 +985,15 @@ enum PromptWidgetSubcommands {
     /// The widget text inherits (and may override) the style of the prompt span
     /// it is embedded in, just like other widgets.
     ///
+    /// Set FLYLINE_SLOW_MS to a number of milliseconds to dim durations shorter
+    /// than that and highlight longer ones.
+    ///
     /// Examples:
     ///   flyline create-prompt-widget last-command-duration
     ///   # Now use FLYLINE_LAST_COMMAND_DURATION in your prompt:
     ///   RPS1=' FLYLINE_LAST_COMMAND_DURATION'
+    ///   # Highlight commands that took 5 seconds or more:
+    ///   FLYLINE_SLOW_MS=5000
     ///
     ///   flyline create-prompt-widget last-command-duration --name MY_DURATION
     #[command(name = "last-command-duration", verbatim_doc_comment)]
@@ -844,6 +1003,24 @@ enum PromptWidgetSubcommands {
         #[arg(long, default_value = "FLYLINE_LAST_COMMAND_DURATION")]
         name: String,
     },
+    /// Show the previous command's exit code and duration in the prompt.
+    ///
+    /// Instances of NAME in prompt strings are replaced with a tick or cross,
+    /// the exit code ($?) and the same duration as last-command-duration,
+    /// for example: ✓ 0 1.2s, ✗ 127 3ms. Success is shown in green and
+    /// failure in red.
+    ///
+    /// Examples:
+    ///   flyline create-prompt-widget last-command-status
+    ///   # Now use FLYLINE_LAST_COMMAND_STATUS in your prompt:
+    ///   RPS1=' FLYLINE_LAST_COMMAND_STATUS'
+    #[command(name = "last-command-status", verbatim_doc_comment)]
+    LastCommandStatus {
+        /// Name to embed in prompt strings as the widget placeholder.
+        /// Defaults to `FLYLINE_LAST_COMMAND_STATUS`.
+        #[arg(long, default_value = "FLYLINE_LAST_COMMAND_STATUS")]
+        name: String,
+    },
 }
 impl Flyline {
     pub(crate) fn call(&mut self, words: *const bash_symbols::WordList) -> c_int {
@@ -873,17 +1050,13 @@ impl Flyline {
                 log::debug!("Parsed flyline arguments: {:?}", parsed);
 
                 if parsed.version {
-                    println!(
-                        "flyline version {} ({}) git:{} built:{}",
-                        env!("CARGO_PKG_VERSION"),
-                        if cfg!(debug_assertions) {
-                            "debug"
-                        } else {
-                            "release"
-                        },
-                        env!("GIT_HASH"),
-                        env!("BUILD_TIME"),
-                    );
+                    println!("{}", version_line());
+                    match loaded_library_path() {
+                        Some(path) => println!("loaded from {}", path.display()),
+                        None => {
+                            eprintln!("flyline: could not determine where flyline was loaded from")
+                        }
+                    }
                 }
 
                 if let Some(path) = parsed.load_zsh_history {
@@ -900,6 +1073,18 @@ impl Flyline {
                     self.settings.matrix_animation = val;
                 }
 
+                for (word, kind) in parsed.command_animations {
+                    log::info!("Command animation for {:?} set to {:?}", word, kind);
+                    match kind {
+                        Some(kind) => {
+                            self.settings.command_word_animations.insert(word, kind);
+                        }
+                        None => {
+                            self.settings.command_word_animations.remove(&word);
+                        }
+                    }
+                }
+
                 if let Some(fps) = parsed.frame_rate {
                     log::info!("Frame rate set to {}", fps);
                     self.settings.frame_rate = fps;
@@ -1071,6 +1256,13 @@ impl Flyline {
                                 settings::PromptWidget::LastCommandDuration { name },
                             );
                         }
+                        PromptWidgetSubcommands::LastCommandStatus { name } => {
+                            log::info!("Registering last-command-status widget '{}'", name);
+                            self.settings.custom_prompt_widgets.insert(
+                                name.clone(),
+                                settings::PromptWidget::LastCommandStatus { name },
+                            );
+                        }
                     },
                     Some(Commands::SetColour {
                         default_theme,
@@ -1235,6 +1427,8 @@ impl Flyline {
                         auto_close_chars,
                         show_inline_history,
                         select_with_mouse,
+                        trim_on_submit,
+                        show_command_preview,
                     }) => {
                         if let Some(enabled) = auto_close_chars {
                             log::info!("Auto closing char set to {}", enabled);
@@ -1248,6 +1442,14 @@ impl Flyline {
                             log::info!("Select with mouse set to {}", enabled);
                             self.settings.select_with_mouse = enabled;
                         }
+                        if let Some(enabled) = trim_on_submit {
+                            log::info!("Trim on submit set to {}", enabled);
+                            self.settings.trim_on_submit = enabled;
+                        }
+                        if let Some(enabled) = show_command_preview {
+                            log::info!("Command preview set to {}", enabled);
+                            self.settings.show_command_preview = enabled;
+                        }
                     }
                     Some(Commands::Suggestions {
                         subcommand,
@@ -1255,6 +1457,7 @@ impl Flyline {
                         use_flycomp,
                         sort_order,
                         num_suggestion_rows,
+                        confirm_single_completion,
                         flycomp_output,
                         flycomp_blacklist,
                     }) => {
@@ -1291,6 +1494,10 @@ impl Flyline {
                             log::info!("Suggestion row limit set to {}", num);
                             self.settings.num_suggestion_rows = num;
                         }
+                        if let Some(enabled) = confirm_single_completion {
+                            log::info!("Confirm single completion set to {}", enabled);
+                            self.settings.confirm_single_completion = enabled;
+                        }
                         if let Some(path) = flycomp_output {
                             log::info!("Flycomp output directory set to '{}'", path);
                             self.settings.flycomp_output = Some(path);
@@ -1323,6 +1530,8 @@ impl Flyline {
                         effect,
                         effect_speed,
                         effect_easing,
+                        effect_period,
+                        shape,
                     }) => {
                         // set backend first since it affects the validity of other options
                         if let Some(b) = backend {
@@ -1332,10 +1541,12 @@ impl Flyline {
                                 && (style.is_some()
                                     || effect.is_some()
                                     || effect_speed.is_some()
-                                    || effect_easing.is_some())
+                                    || effect_easing.is_some()
+                                    || effect_period.is_some()
+                                    || shape.is_some())
                             {
                                 return_usage_error!(
-                                    "flyline set-cursor: --style, --effect, --effect-speed, and --effect-easing require --backend flyline"
+                                    "flyline set-cursor: --style, --effect, --effect-speed, --effect-easing, --effect-period, and --shape require --backend flyline"
                                 );
                             }
                         }
@@ -1436,6 +1647,32 @@ impl Flyline {
                             log::info!("Cursor effect easing set to {:?}", easing);
                             self.settings.cursor_config.effect_easing = easing;
                         }
+
+                        if let Some(ms) = effect_period {
+                            if backend_is_terminal {
+                                return_usage_error!(
+                                    "flyline set-cursor: --effect-period requires --backend flyline"
+                                );
+                            }
+                            if ms == 0 {
+                                log::info!("Cursor effect disabled by a zero period");
+                                self.settings.cursor_config.effect = cursor::CursorEffect::None;
+                            } else {
+                                log::info!("Cursor effect period set to {}ms", ms);
+                                self.settings.cursor_config.effect_period =
+                                    Some(std::time::Duration::from_millis(ms));
+                            }
+                        }
+
+                        if let Some(shape) = shape {
+                            if backend_is_terminal {
+                                return_usage_error!(
+                                    "flyline set-cursor: --shape requires --backend flyline"
+                                );
+                            }
+                            log::info!("Cursor shape set to {:?}", shape);
+                            self.settings.cursor_config.shape = shape;
+                        }
                     }
                     Some(Commands::Perf { subcommand }) => match subcommand {
                         PerfSubcommands::Start => {
@@ -1487,6 +1724,57 @@ impl Flyline {
                         );
                         self.settings.initial_buffer = Some("curl -sSfL https://github.com/HalFrgrd/flyline/releases/latest/download/install.sh | sh".to_string());
                     }
+                    Some(Commands::CheckSyntax { file, json }) => {
+                        let script = match &file {
+                            Some(path) => std::fs::read_to_string(path),
+                            None => std::io::read_to_string(std::io::stdin()),
+                        };
+                        let script = match script {
+                            Ok(s) => s,
+                            Err(e) => {
+                                return_usage_error!("flyline check-syntax: {}", e);
+                            }
+                        };
+                        let reports = crate::command_acceptance::check_script_syntax(&script);
+                        if json {
+                            match serde_json::to_string_pretty(&reports) {
+                                Ok(s) => println!("{}", s),
+                                Err(e) => eprintln!("flyline check-syntax: {}", e),
+                            }
+                        } else {
+                            for report in &reports {
+                                let first_line = report.command.lines().next().unwrap_or("");
+                                if report.complete {
+                                    println!("{}: ok: {}", report.line, first_line);
+                                } else {
+                                    println!(
+                                        "{}: incomplete (open: {}): {}",
+                                        report.line,
+                                        report.open.join(" "),
+                                        first_line
+                                    );
+                                }
+                            }
+                        }
+                        if reports.iter().any(|r| !r.complete) {
+                            return bash_symbols::BuiltinExitCode::ExecutionFailure as c_int;
+                        }
+                    }
+                    Some(Commands::DumpParse { command }) => {
+                        print!("{}", crate::dparser::dump_parse(&command));
+                    }
+                    Some(Commands::Highlight { command }) => {
+                        println!(
+                            "{}",
+                            crate::app::formatted_buffer::highlight_to_ansi(
+                                &command,
+                                &self.settings.colour_palette
+                            )
+                        );
+                    }
+                    Some(Commands::Completions { shell }) => {
+                        print!("{}", completion_script(shell));
+                    }
                 }
 
                 bash_symbols::BuiltinExitCode::ExecutionSuccess as c_int
@@ -1529,6 +1817,35 @@ impl Flyline {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_command_animation() {
+        assert_eq!(
+            parse_command_animation("cargo=snake"),
+            Ok(("cargo".to_string(), Some(AnimationKind::Snake)))
+        );
+        assert_eq!(
+            parse_command_animation("docker=spinner"),
+            Ok(("docker".to_string(), Some(AnimationKind::Spinner)))
+        );
+        assert_eq!(
+            parse_command_animation("python=none"),
+            Ok(("python".to_string(), None))
+        );
+        assert!(parse_command_animation("cargo").is_err());
+        assert!(parse_command_animation("=snake").is_err());
+        assert!(parse_command_animation("cargo=comet").is_err());
+    }
+
+    #[test]
+    fn test_parse_dump_parse() {
+        let args = FlylineArgs::try_parse_from(["flyline", "dump-parse", "echo $(ls)"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Commands::DumpParse { command }) if command == "echo $(ls)"
+        ));
+        assert!(FlylineArgs::try_parse_from(["flyline", "dump-parse"]).is_err());
+    }
+
     #[test]
     fn test_perf_subcommand_completions() {
         let raw_cmd = "flyline perf ";
@@ -1543,4 +1860,29 @@ mod tests {
         assert!(values.contains(&"stop".to_string()));
         assert!(values.contains(&"dump".to_string()));
     }
+
+    #[test]
+    fn test_loaded_library_path_is_current_exe() {
+        // Tests link flyline into the test executable rather than a shared
+        // object, so the loader reports the executable itself.
+        let exe = std::fs::canonicalize(std::env::current_exe().unwrap()).unwrap();
+        assert_eq!(loaded_library_path(), Some(exe));
+    }
+
+    #[test]
+    fn test_bash_completion_script_lists_subcommands() {
+        let script = completion_script(clap_complete::Shell::Bash);
+        assert!(script.contains("flyline"));
+        for sub in [
+            "set-style",
+            "create-prompt-widget",
+            "check-syntax",
+            "completions",
+        ] {
+            assert!(
+                script.contains(sub),
+                "expected {sub} in bash completion script"
+            );
+        }
+    }
 }
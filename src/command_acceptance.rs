@@ -1,4 +1,4 @@
-use crate::dparser::{DParser, collect_tokens_include_whitespace};
+use crate::dparser::{DParser, OpeningState, collect_tokens_include_whitespace};
 use flash::lexer::{Token, TokenKind};
 
 pub fn will_bash_accept_buffer(buffer: &str) -> bool {
@@ -15,40 +15,226 @@ pub fn will_bash_accept_buffer(buffer: &str) -> bool {
         }
     }
 
-    if let Some(last_token) = tokens
+    let mut parser = DParser::new(tokens);
+    parser.walk_to_end();
+
+    // An unclosed nesting covers any dangling connective inside it, e.g.
+    // `echo $(foo &&`, so only a complete buffer needs its final token checked.
+    if parser.needs_more_input() {
+        return false;
+    }
+
+    let last_kind = parser
+        .tokens()
         .iter()
         .rev()
-        .skip_while(|t| {
-            matches!(
-                t.kind,
-                TokenKind::Whitespace(_) | TokenKind::Comment | TokenKind::Newline
-            )
+        .find(|t| {
+            !t.annotations.is_comment
+                && !matches!(
+                    t.token.kind,
+                    TokenKind::Whitespace(_) | TokenKind::Comment | TokenKind::Newline
+                )
         })
-        .next()
-    {
-        match &last_token.kind {
-            TokenKind::Pipe | TokenKind::And | TokenKind::Or => {
-                return false;
+        .map(|t| &t.token.kind);
+
+    match last_kind {
+        Some(TokenKind::Pipe | TokenKind::And | TokenKind::Or) => false,
+        Some(TokenKind::Word(s))
+            if s.trim().chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 =>
+        {
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Whether pasting `pasted` left `buffer` as a block of complete commands,
+/// which Enter should run as-is wherever the cursor is. A paste that leaves
+/// something open stays in the editor as a multiline buffer.
+pub fn is_complete_pasted_block(pasted: &str, buffer: &str) -> bool {
+    pasted.trim_end().contains(['\n', '\r']) && will_bash_accept_buffer(buffer)
+}
+
+/// Whether one top-level command of a script is complete, as reported by
+/// `flyline check-syntax`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CommandSyntaxReport {
+    /// 1-based line on which the command starts.
+    pub line: usize,
+    pub command: String,
+    pub complete: bool,
+    /// Opening tokens (quotes, brackets, heredocs, ...) left unclosed.
+    pub open: Vec<String>,
+}
+
+/// Split `script` into top-level commands the way bash reads them, joining
+/// lines until [`will_bash_accept_buffer`] accepts the command, and report
+/// whether each one is complete. Blank and comment-only lines are skipped.
+pub fn check_script_syntax(script: &str) -> Vec<CommandSyntaxReport> {
+    let mut reports = Vec::new();
+    // The command still waiting for more lines, with its starting line.
+    let mut pending: Option<(usize, String)> = None;
+
+    for (idx, line) in script.lines().enumerate() {
+        match pending.as_mut() {
+            Some((_, command)) => {
+                command.push('\n');
+                command.push_str(line);
             }
-            TokenKind::Word(s)
-                if s.trim().chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 =>
-            {
-                return false;
+            None => {
+                let trimmed = line.trim_start();
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                pending = Some((idx + 1, line.to_string()));
             }
-            _ => {}
+        }
+        if pending
+            .as_ref()
+            .is_some_and(|(_, command)| will_bash_accept_buffer(command))
+        {
+            let (line, command) = pending.take().unwrap();
+            reports.push(CommandSyntaxReport {
+                line,
+                command,
+                complete: true,
+                open: vec![],
+            });
         }
     }
 
-    let mut parser = DParser::new(tokens);
+    if let Some((line, command)) = pending {
+        let open = unclosed_openings(&command);
+        reports.push(CommandSyntaxReport {
+            line,
+            command,
+            complete: false,
+            open,
+        });
+    }
+    reports
+}
+
+fn unclosed_openings(buffer: &str) -> Vec<String> {
+    let mut parser = DParser::from(buffer);
     parser.walk_to_end();
+    parser
+        .tokens()
+        .iter()
+        .filter(|t| t.annotations.opening == Some(OpeningState::Unmatched))
+        .map(|t| t.token.value.to_string())
+        .collect()
+}
 
-    !parser.needs_more_input()
+/// Strip trailing whitespace from every line of `buffer`.
+///
+/// Whitespace that is part of a quoted string or a heredoc body is kept, as is
+/// the one whitespace character escaped by a trailing backslash, since removing
+/// it would change what bash runs.
+pub fn trim_trailing_whitespace(buffer: &str) -> String {
+    let tokens = DParser::parse_and_annotate(buffer);
+
+    let is_inside_quotes = |byte_pos: usize| {
+        tokens.iter().any(|t| {
+            if !matches!(t.token.kind, TokenKind::SingleQuote | TokenKind::Quote) {
+                return false;
+            }
+            let close_start = match t.annotations.opening {
+                Some(OpeningState::Matched(close_idx)) => {
+                    tokens[close_idx].token.byte_range().start
+                }
+                Some(OpeningState::Unmatched) => buffer.len(),
+                None => return false,
+            };
+            t.token.byte_range().end <= byte_pos && byte_pos < close_start
+        })
+    };
+    // Newlines inside heredoc bodies and multiline strings are annotated as quoted.
+    let is_quoted_newline = |byte_pos: usize| {
+        tokens.iter().any(|t| {
+            t.token.kind == TokenKind::Newline
+                && t.token.byte_range().start == byte_pos
+                && (t.annotations.is_inside_single_quotes || t.annotations.is_inside_double_quotes)
+        })
+    };
+
+    let mut result = String::with_capacity(buffer.len());
+    let mut line_start = 0;
+    for line in buffer.split_inclusive('\n') {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        let trimmed = content.trim_end();
+        let whitespace_start = line_start + trimmed.len();
+        let line_end = line_start + content.len();
+
+        let keep = trimmed.len() == content.len()
+            || is_inside_quotes(whitespace_start)
+            || (line_end < buffer.len() && is_quoted_newline(line_end));
+
+        if keep {
+            result.push_str(content);
+        } else {
+            result.push_str(trimmed);
+            // An odd run of backslashes escapes only the first whitespace
+            // character after it.
+            let trailing_backslashes = trimmed.chars().rev().take_while(|&c| c == '\\').count();
+            if trailing_backslashes % 2 == 1
+                && let Some(escaped) = content[trimmed.len()..].chars().next()
+            {
+                result.push(escaped);
+            }
+        }
+        if content.len() < line.len() {
+            result.push('\n');
+        }
+        line_start += line.len();
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_check_script_syntax_reports_each_command() {
+        let script =
+            "# rc file\nexport A=1\n\nif true; then\n  echo hi\nfi\necho 'unterminated\nls\n";
+        let reports = check_script_syntax(script);
+        let summary: Vec<(usize, bool)> = reports.iter().map(|r| (r.line, r.complete)).collect();
+        assert_eq!(summary, vec![(2, true), (4, true), (7, false)]);
+        assert_eq!(reports[1].command, "if true; then\n  echo hi\nfi");
+        assert_eq!(reports[2].command, "echo 'unterminated\nls");
+        assert_eq!(reports[2].open, vec!["'"]);
+    }
+
+    #[test]
+    fn test_check_script_syntax_json_report() {
+        let reports = check_script_syntax("ls\necho \"x\n");
+        assert_eq!(reports.len(), 2);
+        assert!(!reports[1].complete);
+        assert_eq!(reports[1].open, vec!["\""]);
+        let json = serde_json::to_value(&reports).unwrap();
+        assert_eq!(json[1]["line"], 2);
+        assert_eq!(json[1]["complete"], false);
+        assert_eq!(json[1]["command"], "echo \"x");
+    }
+
+    #[test]
+    fn test_complete_pasted_block() {
+        let two_commands = "cd /tmp\nls -la\n";
+        assert!(is_complete_pasted_block(two_commands, two_commands));
+        // Pasted into the middle of an existing command.
+        assert!(is_complete_pasted_block(
+            "echo a\necho b",
+            "echo a\necho b && echo c"
+        ));
+
+        let open_subst = "echo start\nfiles=$(";
+        assert!(!is_complete_pasted_block(open_subst, open_subst));
+        // A single line paste is left to the usual Enter handling.
+        assert!(!is_complete_pasted_block("ls -la\n", "ls -la\n"));
+    }
+
     #[test]
     fn test_unclosed_quotes() {
         assert_eq!(will_bash_accept_buffer("echo 'hello"), false);
@@ -339,8 +525,58 @@ mod tests {
         assert_eq!(will_bash_accept_buffer(r#"a [""#), false);
     }
 
+    #[test]
+    fn test_connectives_inside_nestings() {
+        assert_eq!(will_bash_accept_buffer("echo $(foo &&"), false);
+        assert_eq!(will_bash_accept_buffer("echo $(foo && bar)"), true);
+        assert_eq!(will_bash_accept_buffer("echo \"x\" | $(y ||"), false);
+        assert_eq!(will_bash_accept_buffer("echo $(foo) &&"), false);
+        assert_eq!(will_bash_accept_buffer("echo \"a &&\""), true);
+    }
+
     #[test]
     fn test_multiline_ands() {
         assert_eq!(will_bash_accept_buffer("echo && \n"), false);
     }
+
+    #[test]
+    fn test_trim_trailing_whitespace_outside_quotes() {
+        assert_eq!(trim_trailing_whitespace("echo hi   "), "echo hi");
+        assert_eq!(trim_trailing_whitespace("echo hi \t"), "echo hi");
+        assert_eq!(
+            trim_trailing_whitespace("echo a  \necho b  "),
+            "echo a\necho b"
+        );
+        assert_eq!(trim_trailing_whitespace("echo 'a'   "), "echo 'a'");
+        assert_eq!(trim_trailing_whitespace("echo hi"), "echo hi");
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_preserves_quoted() {
+        assert_eq!(
+            trim_trailing_whitespace("echo 'a   \nb'  "),
+            "echo 'a   \nb'"
+        );
+        assert_eq!(
+            trim_trailing_whitespace("echo \"a   \nb\"  "),
+            "echo \"a   \nb\""
+        );
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_preserves_heredoc_body() {
+        assert_eq!(
+            trim_trailing_whitespace("cat <<EOF\nline   \nEOF\n"),
+            "cat <<EOF\nline   \nEOF\n"
+        );
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace_preserves_escaped_space() {
+        assert_eq!(trim_trailing_whitespace("echo a\\ "), "echo a\\ ");
+        assert_eq!(trim_trailing_whitespace("echo a\\  "), "echo a\\ ");
+        assert_eq!(trim_trailing_whitespace("echo a\\\t \t"), "echo a\\\t");
+        // An even run is a literal backslash, so the space is not escaped.
+        assert_eq!(trim_trailing_whitespace("echo a\\\\  "), "echo a\\\\");
+    }
 }
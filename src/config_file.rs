@@ -0,0 +1,105 @@
+//! Loading for flyline's TOML config files (theme, keymap, abbreviations).
+
+use std::path::PathBuf;
+
+/// The config file named by `$env_var`, else `~/.config/flyline/<file_name>`
+/// if it exists.
+fn path(env_var: &str, file_name: &str) -> Option<PathBuf> {
+    if let Some(path) = crate::bash_funcs::get_envvar_value(env_var) {
+        return Some(PathBuf::from(path));
+    }
+    let home = crate::bash_funcs::get_envvar_value("HOME")?;
+    let path = std::path::Path::new(&home)
+        .join(".config/flyline")
+        .join(file_name);
+    path.is_file().then_some(path)
+}
+
+/// The entries of the config file found by [`path`], in file order. `what`
+/// names the file in log messages. A missing file gives no entries; an
+/// unreadable or invalid one is logged and gives no entries.
+pub fn load(env_var: &str, file_name: &str, what: &str) -> Vec<(String, String)> {
+    let Some(path) = path(env_var, file_name) else {
+        return vec![];
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::warn!("Failed to read {} file {}: {}", what, path.display(), e);
+            return vec![];
+        }
+    };
+    log::info!("Loading {} from {}", what, path.display());
+    parse(&contents, what)
+}
+
+/// Parse `contents` as TOML and return its `key = "value"` entries in file
+/// order, including those inside `[table]`s. Values that aren't strings are
+/// logged and skipped, and invalid TOML is logged and gives no entries.
+pub fn parse(contents: &str, what: &str) -> Vec<(String, String)> {
+    let table = match contents.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(e) => {
+            log::warn!("Invalid {} file: {}", what, e);
+            return vec![];
+        }
+    };
+    let mut entries = vec![];
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(inner) => {
+                for (key, value) in inner {
+                    push_string_entry(&mut entries, key, value, what);
+                }
+            }
+            value => push_string_entry(&mut entries, key, value, what),
+        }
+    }
+    entries
+}
+
+fn push_string_entry(
+    entries: &mut Vec<(String, String)>,
+    key: String,
+    value: toml::Value,
+    what: &str,
+) {
+    match value {
+        toml::Value::String(value) => entries.push((key, value)),
+        _ => log::warn!("{} entry {:?} must be a quoted string", what, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_entries_keep_file_order_and_include_tables() {
+        let entries = parse(
+            r#"
+# comment
+zeta = "last letter"
+"Ctrl+=" = 'quoted key'
+count = 3
+
+[palette]
+alpha = "in a table"
+"#,
+            "test",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("zeta".to_string(), "last letter".to_string()),
+                ("Ctrl+=".to_string(), "quoted key".to_string()),
+                ("alpha".to_string(), "in a table".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_toml_gives_no_entries() {
+        assert!(parse("bad line\nok = \"fine\"", "test").is_empty());
+    }
+}
@@ -8,6 +8,7 @@ use std::sync::Mutex;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use crate::cursor::CursorShape;
 use crate::palette::{ButtonState, Palette};
 use crate::unicode_helpers::{Directions, PipeStyle, pipe};
 
@@ -293,6 +294,83 @@ impl Contents {
         self.buf.len() as u16
     }
 
+    /// Re-wrap the already-built rows to `new_width`.
+    ///
+    /// Used when the terminal was resized between building the content and drawing it.
+    /// Each row is wrapped on its own (rows are never joined back together), multi-width
+    /// graphemes are kept whole, and the cursor, terminal cursor, focus row and prompt
+    /// markers are moved to the cell they were on.
+    pub fn reflow(&mut self, new_width: u16) {
+        if new_width == 0 || new_width == self.width {
+            return;
+        }
+        let new_w = new_width as usize;
+        let old_buf = std::mem::take(&mut self.buf);
+
+        // For each old row: the new position of every used cell, and the position
+        // just after the last used cell (where trailing blank columns map to).
+        let mut row_maps: Vec<(Vec<Coord>, Coord)> = Vec::with_capacity(old_buf.len());
+
+        for row in old_buf {
+            let used = row
+                .iter()
+                .rposition(|c| c.tag != Tag::Blank)
+                .map_or(0, |i| i + 1);
+            let mut col_map = Vec::with_capacity(used);
+            let mut current: Vec<TaggedCell> = Vec::with_capacity(new_w);
+
+            let mut col = 0;
+            while col < used {
+                let mut group = 1;
+                while col + group < used && row[col + group].tag == Tag::MultiWidthContinuation {
+                    group += 1;
+                }
+                if !current.is_empty() && current.len() + group > new_w {
+                    current.resize(new_w, TaggedCell::default());
+                    self.buf.push(std::mem::take(&mut current));
+                }
+                for cell in &row[col..col + group] {
+                    col_map.push(Coord::new(self.buf.len() as u16, current.len() as u16));
+                    current.push(cell.clone());
+                }
+                col += group;
+            }
+
+            let end = Coord::new(self.buf.len() as u16, current.len() as u16);
+            current.resize(new_w, TaggedCell::default());
+            self.buf.push(current);
+            row_maps.push((col_map, end));
+        }
+
+        let new_height = self.buf.len() as u16;
+        let map = |c: Coord| -> Coord {
+            match row_maps.get(c.row as usize) {
+                Some((col_map, end)) => match col_map.get(c.col as usize) {
+                    Some(mapped) => *mapped,
+                    None => {
+                        let extra = c.col.saturating_sub(col_map.len() as u16);
+                        Coord::new(end.row, (end.col + extra).min(new_width))
+                    }
+                },
+                None => Coord::new(
+                    new_height + (c.row - row_maps.len() as u16),
+                    c.col.min(new_width),
+                ),
+            }
+        };
+
+        self.cursor_pos = map(self.cursor_pos);
+        self.term_cursor_pos = self.term_cursor_pos.map(map);
+        self.prompt_start = self.prompt_start.map(map);
+        self.prompt_end = self.prompt_end.map(map);
+        self.focus_row = self.focus_row.map(|row| map(Coord::new(row, 0)).row);
+        self.width = new_width;
+
+        for _ in self.buf.len()..(self.cursor_pos.row as usize + 1) {
+            self.increase_buf_single_row();
+        }
+    }
+
     #[cfg(test)]
     pub fn get_buffer_lines(&self) -> Vec<String> {
         self.buf
@@ -634,11 +712,36 @@ impl Contents {
         }
     }
 
+    /// Place the terminal cursor. A styled cursor covers the whole grapheme
+    /// under it, so it spans both cells of a wide character like `世`.
     pub fn set_term_cursor_pos(&mut self, cursor: Coord, style: Option<ratatui::style::Style>) {
+        self.set_term_cursor_pos_with_shape(cursor, style, CursorShape::Block);
+    }
+
+    /// Like [`Self::set_term_cursor_pos`], drawing the cursor as `shape`.
+    pub fn set_term_cursor_pos_with_shape(
+        &mut self,
+        cursor: Coord,
+        style: Option<ratatui::style::Style>,
+        shape: CursorShape,
+    ) {
         self.term_cursor_pos = Some(cursor);
-        if let Some(style) = style {
-            self.set_style(Rect::new(cursor.col, cursor.row, 1, 1), style);
-        }
+        let Some(style) = style else {
+            return;
+        };
+        let area = shape.rect(cursor, self.grapheme_width_at(cursor));
+        self.set_style(area, shape.style(style));
+    }
+
+    /// Display width of the grapheme starting at `pos`, at least 1 and
+    /// clipped to the right edge.
+    fn grapheme_width_at(&self, pos: Coord) -> u16 {
+        let width = self
+            .buf
+            .get(pos.row as usize)
+            .and_then(|row| row.get(pos.col as usize))
+            .map_or(1, |tagged_cell| tagged_cell.cell.symbol().width() as u16);
+        width.clamp(1, self.width.saturating_sub(pos.col).max(1))
     }
 
     pub fn get_row_range_to_show(&self, term_height: u16) -> std::ops::Range<u16> {
@@ -654,6 +757,63 @@ impl Contents {
         range.start as u16..range.end as u16
     }
 
+    /// Viewport rows (0 = top of the viewport) covered by the scroll indicator
+    /// thumb when `visible_rows` doesn't show all the content, else `None`.
+    pub fn scroll_indicator_thumb(
+        &self,
+        visible_rows: &std::ops::Range<u16>,
+    ) -> Option<std::ops::Range<u16>> {
+        scroll_indicator_thumb(visible_rows, self.height())
+    }
+
+    /// Redraw the cells of `row` in `cols` in visual order when they hold
+    /// right-to-left text. Cells are written in logical order, so this
+    /// returns where each of them ended up: the new column of the cell
+    /// that was at `cols.start + i` is at index `i`.
+    pub fn reorder_bidi(&mut self, row: u16, cols: std::ops::Range<u16>) -> Option<Vec<u16>> {
+        let row_cells = self.buf.get_mut(row as usize)?;
+        let cols = cols.start as usize..(cols.end as usize).min(row_cells.len());
+        if cols.is_empty() {
+            return None;
+        }
+        // A grapheme is its cell plus any continuation cells of a wide symbol.
+        let mut groups: Vec<std::ops::Range<usize>> = vec![];
+        for col in cols.clone() {
+            match groups.last_mut() {
+                Some(group) if row_cells[col].tag == Tag::MultiWidthContinuation => {
+                    group.end = col + 1
+                }
+                _ => groups.push(col..col + 1),
+            }
+        }
+        let symbols: Vec<&str> = groups
+            .iter()
+            .map(|group| row_cells[group.start].cell.symbol())
+            .collect();
+        let order = crate::bidi::visual_order(&symbols)?;
+
+        let logical = row_cells[cols.clone()].to_vec();
+        let mut new_cols = vec![0; cols.len()];
+        let mut col = cols.start;
+        for logical_idx in order {
+            for old_col in groups[logical_idx].clone() {
+                row_cells[col] = logical[old_col - cols.start].clone();
+                new_cols[old_col - cols.start] = col as u16;
+                col += 1;
+            }
+        }
+        Some(new_cols)
+    }
+
+    /// Draw everything written so far with the dim attribute, leaving the
+    /// colours and text untouched.
+    pub fn dim(&mut self) {
+        let dim = ratatui::style::Style::new().add_modifier(ratatui::style::Modifier::DIM);
+        for tagged_cell in self.buf.iter_mut().flatten() {
+            tagged_cell.cell.set_style(dim);
+        }
+    }
+
     pub fn apply_matrix_anim(
         &mut self,
         now: std::time::Instant,
@@ -1205,6 +1365,24 @@ impl Contents {
     }
 }
 
+/// The thumb is as tall, relative to the viewport, as the viewport is
+/// relative to the content, and sits at the matching offset.
+fn scroll_indicator_thumb(
+    visible_rows: &std::ops::Range<u16>,
+    total_rows: u16,
+) -> Option<std::ops::Range<u16>> {
+    let visible = visible_rows.len() as f64;
+    let total = total_rows as f64;
+    if visible == 0.0 || total <= visible {
+        return None;
+    }
+    let thumb_size = ((visible / total) * visible).round().max(1.0);
+    let thumb_start = (visible_rows.start as f64 / (total - visible) * (visible - thumb_size))
+        .round()
+        .min(visible - thumb_size);
+    Some(thumb_start as u16..(thumb_start + thumb_size) as u16)
+}
+
 static MATRIX_ANIM_STATE: Mutex<Option<MatrixAnimState>> = Mutex::new(None);
 
 #[derive(Debug, Clone)]
@@ -1364,6 +1542,26 @@ mod tests {
         assert_eq!(contents.buf[0][0].cell.style().fg, Some(Color::Red));
     }
 
+    #[test]
+    fn test_reorder_bidi_places_cursor_cell() {
+        let mut contents = Contents::new(20);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("abc مرحبا def"), Tag::Normal));
+        let end = contents.cursor_position().col;
+        assert_eq!(end, 13);
+
+        let new_cols = contents.reorder_bidi(0, 0..end).unwrap();
+        assert_eq!(contents.get_buffer_lines()[0].trim_end(), "abc ابحرم def");
+        // A cursor on the first Arabic letter is drawn on its visual cell.
+        assert_eq!(new_cols[4], 8);
+        assert_eq!(new_cols[8], 4);
+        assert_eq!(new_cols[0], 0);
+        assert_eq!(new_cols[12], 12);
+
+        let mut ltr = Contents::new(20);
+        ltr.write_tagged_span(&TaggedSpan::new(Span::raw("abc def"), Tag::Normal));
+        assert_eq!(ltr.reorder_bidi(0, 0..7), None);
+    }
+
     #[test]
     fn test_wrapping() {
         let mut contents = Contents::new(5);
@@ -1382,6 +1580,128 @@ mod tests {
         assert_eq!(row2, "d    ");
     }
 
+    #[test]
+    fn test_scroll_indicator_follows_cursor_near_bottom() {
+        let mut contents = Contents::new(10);
+        for _ in 0..39 {
+            contents.newline();
+        }
+        contents.set_term_cursor_pos(Coord::new(38, 0), None);
+        assert_eq!(contents.height(), 40);
+
+        let visible = contents.get_row_range_to_show(10);
+        assert_eq!(visible, 30..40);
+        // 10 of 40 rows visible: a thumb of 10 * 10/40 = 2.5 -> 3 rows,
+        // pushed to the bottom of the viewport.
+        assert_eq!(contents.scroll_indicator_thumb(&visible), Some(7..10));
+    }
+
+    #[test]
+    fn test_scroll_indicator_thumb_math() {
+        assert_eq!(scroll_indicator_thumb(&(0..10), 10), None);
+        assert_eq!(scroll_indicator_thumb(&(0..10), 5), None);
+        assert_eq!(scroll_indicator_thumb(&(0..10), 40), Some(0..3));
+        assert_eq!(scroll_indicator_thumb(&(15..25), 40), Some(4..7));
+        // Very tall content still gets a one-row thumb.
+        assert_eq!(scroll_indicator_thumb(&(990..1000), 1000), Some(9..10));
+    }
+
+    #[test]
+    fn test_cursor_style_covers_wide_grapheme() {
+        let style = ratatui::style::Style::new().bg(ratatui::style::Color::White);
+        let mut contents = Contents::new(10);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("a世b"), Tag::Normal));
+        contents.set_term_cursor_pos(Coord::new(0, 1), Some(style));
+
+        let bgs: Vec<_> = contents.buf[0][..4].iter().map(|c| c.cell.bg).collect();
+        let white = ratatui::style::Color::White;
+        assert_eq!(bgs[0], ratatui::style::Color::Reset);
+        assert_eq!(bgs[1], white);
+        assert_eq!(bgs[2], white);
+        assert_eq!(bgs[3], ratatui::style::Color::Reset);
+    }
+
+    #[test]
+    fn test_cursor_style_on_narrow_grapheme_and_past_end() {
+        let style = ratatui::style::Style::new().bg(ratatui::style::Color::White);
+        let mut contents = Contents::new(10);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("ab"), Tag::Normal));
+        contents.set_term_cursor_pos(Coord::new(0, 2), Some(style));
+
+        let bgs: Vec<_> = contents.buf[0][..4].iter().map(|c| c.cell.bg).collect();
+        let white = ratatui::style::Color::White;
+        let reset = ratatui::style::Color::Reset;
+        assert_eq!(bgs, vec![reset, reset, white, reset]);
+    }
+
+    #[test]
+    fn test_reflow_to_narrower_width() {
+        let mut contents = Contents::new(20);
+        contents.write_tagged_line(
+            &TaggedLine::from(TaggedSpan::new(
+                Span::raw("abcdefghijklmnopqrstuvwxyz"),
+                Tag::Normal,
+            )),
+            true,
+        );
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("echo hi"), Tag::Command(0)));
+        contents.set_term_cursor_pos(contents.cursor_position(), None);
+        assert_eq!(contents.height(), 3);
+        assert_eq!(contents.cursor_position(), Coord::new(2, 7));
+
+        contents.reflow(10);
+
+        assert_eq!(contents.width, 10);
+        assert_eq!(
+            contents.get_buffer_lines(),
+            vec!["abcdefghij", "klmnopqrst", "uvwxyz    ", "echo hi   ",]
+        );
+        assert_eq!(contents.cursor_position(), Coord::new(3, 7));
+        assert_eq!(contents.term_cursor_pos, Some(Coord::new(3, 7)));
+        assert_eq!(contents.buf[3][0].tag, Tag::Command(0));
+    }
+
+    #[test]
+    fn test_reflow_keeps_wide_graphemes_whole() {
+        let mut contents = Contents::new(20);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("ab中文cd"), Tag::Normal));
+        contents.reflow(3);
+
+        assert_eq!(
+            contents.get_buffer_lines(),
+            vec!["ab ", "中  ", "文 c", "d  "]
+        );
+        assert_eq!(contents.buf[1][1].tag, Tag::MultiWidthContinuation);
+        assert_eq!(contents.cursor_position(), Coord::new(3, 1));
+    }
+
+    #[test]
+    fn test_reflow_same_width_is_noop() {
+        let mut contents = Contents::new(10);
+        contents.write_tagged_span(&TaggedSpan::new(Span::raw("hello world"), Tag::Normal));
+        let before = contents.get_buffer_lines();
+        contents.reflow(10);
+        assert_eq!(contents.get_buffer_lines(), before);
+        assert_eq!(contents.cursor_position(), Coord::new(1, 1));
+    }
+
+    #[test]
+    fn test_lrjustified_right_aligns_rprompt() {
+        let mut contents = Contents::new(20);
+        contents.write_tagged_line_lrjustified(
+            &TaggedLine::from_line(Line::from("$ "), Tag::Ps1Prompt),
+            &TaggedLine::from_line(Line::from(" "), Tag::Ps1Prompt),
+            &TaggedLine::from_line(Line::from("12:34:56"), Tag::Ps1Prompt),
+            true,
+        );
+
+        assert_eq!(contents.get_buffer_lines(), vec!["$           12:34:56"]);
+        assert_eq!(contents.buf[0][12].cell.symbol(), "1");
+        assert_eq!(contents.buf[0][19].cell.symbol(), "6");
+        // The cursor is left after the left prompt, ready for the input.
+        assert_eq!(contents.cursor_position(), Coord::new(0, 2));
+    }
+
     #[test]
     fn test_dont_overwrite() {
         let mut contents = Contents::new(10);
@@ -3,9 +3,10 @@ use crate::content_builder::Coord;
 use clap::ValueEnum;
 use easing_function::Easing as _;
 use easing_function::easings::StandardEasing;
+use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::Span;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use strum::{AsRefStr, EnumString, VariantArray};
 
 /// Cursor intensity used when the terminal has lost focus (or in modes where
@@ -162,6 +163,19 @@ pub fn cursor_effect_animation_frames(
     frames
 }
 
+/// Set the terminal cursor to a blinking (DECSCUSR 5) or steady (6) bar, or
+/// back to the user's default shape for `None`.
+pub fn set_terminal_bar(bar: Option<bool>) {
+    let style = match bar {
+        Some(true) => crossterm::cursor::SetCursorStyle::BlinkingBar,
+        Some(false) => crossterm::cursor::SetCursorStyle::SteadyBar,
+        None => crossterm::cursor::SetCursorStyle::DefaultUserShape,
+    };
+    crossterm::execute!(std::io::stdout(), style).unwrap_or_else(|e| {
+        log::error!("Failed to set cursor shape: {}", e);
+    });
+}
+
 /// Visual effect applied to the cursor.
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CursorEffect {
@@ -174,6 +188,47 @@ pub enum CursorEffect {
     None,
 }
 
+/// Which part of the cell(s) under the cursor is drawn.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorShape {
+    /// The whole grapheme under the cursor (default).
+    #[default]
+    Block,
+    /// A thin bar before the grapheme. Cells can't hold a bar, so this
+    /// shows the terminal's own cursor as a bar (DECSCUSR 5/6).
+    Bar,
+    /// A line under the whole grapheme.
+    Underline,
+}
+
+impl CursorShape {
+    /// The cells to style for a cursor at `pos` over a grapheme
+    /// `grapheme_width` columns wide.
+    pub fn rect(self, pos: Coord, grapheme_width: u16) -> Rect {
+        let width = match self {
+            CursorShape::Block | CursorShape::Underline => grapheme_width.max(1),
+            CursorShape::Bar => 1,
+        };
+        Rect::new(pos.col, pos.row, width, 1)
+    }
+
+    /// Turn a block cursor `style` into the style for this shape. An
+    /// underline cursor keeps the text colours and draws the line in the
+    /// block's background colour.
+    pub fn style(self, style: Style) -> Style {
+        match self {
+            CursorShape::Block | CursorShape::Bar => style,
+            CursorShape::Underline => {
+                let underline = Style::new().add_modifier(Modifier::UNDERLINED);
+                match style.bg.or(style.fg) {
+                    Some(colour) => underline.underline_color(colour),
+                    None => underline,
+                }
+            }
+        }
+    }
+}
+
 /// How the cursor should be styled.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum CursorStyleConfig {
@@ -206,6 +261,74 @@ pub struct CursorConfig {
     pub effect_speed: f32,
     /// Easing function applied to the effect intensity curve.  Default: `Linear`.
     pub effect_easing: CursorEasing,
+    /// Which part of the cell is drawn.  Default: `Block`.
+    pub shape: CursorShape,
+    /// Length of one fade or blink cycle.  Overrides `effect_speed` when set.
+    pub effect_period: Option<Duration>,
+}
+
+impl CursorConfig {
+    /// Apply `$FLYLINE_CURSOR_SHAPE` (`block`, `bar` or `underline`) and
+    /// `$FLYLINE_CURSOR_BLINK_MS`. A blink period of `0` turns the effect off
+    /// so the cursor stays at full intensity.
+    pub fn load_env(&mut self) {
+        if let Some(shape) = crate::bash_funcs::get_envvar_value("FLYLINE_CURSOR_SHAPE") {
+            match CursorShape::from_str(shape.trim(), true) {
+                Ok(shape) => self.shape = shape,
+                Err(_) => log::warn!("Unknown FLYLINE_CURSOR_SHAPE {:?}", shape),
+            }
+        }
+        if let Some(period) = crate::bash_funcs::get_envvar_value("FLYLINE_CURSOR_BLINK_MS") {
+            match period.trim().parse::<u64>() {
+                Ok(0) => self.effect = CursorEffect::None,
+                Ok(ms) => self.effect_period = Some(Duration::from_millis(ms)),
+                Err(_) => log::warn!("Invalid FLYLINE_CURSOR_BLINK_MS {:?}", period),
+            }
+        }
+    }
+
+    /// Whether the terminal's own cursor is shown instead of one drawn by
+    /// flyline.
+    pub fn shows_terminal_cursor(&self) -> bool {
+        self.backend == CursorBackend::Terminal || self.shape == CursorShape::Bar
+    }
+
+    /// The bar flyline asks the terminal for: `Some(true)` for a blinking bar,
+    /// `Some(false)` for a steady one, `None` to leave the terminal's default
+    /// shape alone.
+    pub fn terminal_bar(&self) -> Option<bool> {
+        (self.backend == CursorBackend::Flyline && self.shape == CursorShape::Bar)
+            .then_some(self.effect == CursorEffect::Blink)
+    }
+
+    /// Length of one effect cycle in seconds, from `effect_period` or else
+    /// derived from `effect_speed`.
+    fn effect_period_secs(&self) -> f32 {
+        match self.effect_period {
+            Some(period) => period.as_secs_f32().max(f32::EPSILON),
+            None => {
+                let speed = self.effect_speed.max(f32::EPSILON);
+                match self.effect {
+                    CursorEffect::Blink => 1.0 / speed,
+                    _ => std::f32::consts::TAU / (CURSOR_FADE_ANGULAR_SPEED * speed),
+                }
+            }
+        }
+    }
+
+    /// Cursor intensity ∈ [0, 1] `elapsed` seconds into the effect, or `None`
+    /// in the off half of a blink.
+    fn effect_intensity(&self, elapsed: f32) -> Option<f32> {
+        let phase = (elapsed / self.effect_period_secs()).fract();
+        match self.effect {
+            CursorEffect::None => Some(1.0),
+            CursorEffect::Fade => {
+                let raw = (phase * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+                Some(fade_intensity(raw, self.effect_easing))
+            }
+            CursorEffect::Blink => (phase < 0.5).then_some(1.0),
+        }
+    }
 }
 
 impl Default for CursorConfig {
@@ -218,6 +341,8 @@ impl Default for CursorConfig {
             effect: CursorEffect::Fade,
             effect_speed: 1.0,
             effect_easing: CursorEasing::Linear,
+            shape: CursorShape::Block,
+            effect_period: None,
         }
     }
 }
@@ -296,20 +421,7 @@ impl Cursor {
             return Some(CURSOR_INTENSITY_UNFOCUSED as f32 / 255.0);
         }
 
-        match config.effect {
-            CursorEffect::None => Some(1.0),
-            CursorEffect::Fade => {
-                let elapsed = self.time_of_change.elapsed().as_secs_f32();
-                // Raw value in [0, 1] from a sine wave, scaled by effect_speed.
-                let raw = (elapsed * 4.0 * config.effect_speed).sin() * 0.5 + 0.5;
-                Some(fade_intensity(raw, config.effect_easing))
-            }
-            CursorEffect::Blink => {
-                let elapsed = self.time_of_change.elapsed().as_secs_f32();
-                let phase = (elapsed * config.effect_speed).fract();
-                if phase < 0.5 { Some(1.0) } else { None }
-            }
-        }
+        config.effect_intensity(self.time_of_change.elapsed().as_secs_f32())
     }
 
     /// Build a ratatui `Style` from a normalised intensity and the cursor style config.
@@ -359,3 +471,91 @@ impl Cursor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effect_intensity_follows_configured_period() {
+        let config = CursorConfig {
+            effect_period: Some(Duration::from_millis(800)),
+            ..CursorConfig::default()
+        };
+        let at = |secs: f32| config.effect_intensity(secs).unwrap();
+        // Fade: a sine wave over the period, mapped to [0.2, 1.0].
+        assert!((at(0.0) - 0.6).abs() < 1e-3);
+        assert!((at(0.2) - 1.0).abs() < 1e-3);
+        assert!((at(0.6) - 0.2).abs() < 1e-3);
+        assert!((at(0.8) - 0.6).abs() < 1e-3);
+
+        let blink = CursorConfig {
+            effect: CursorEffect::Blink,
+            ..config.clone()
+        };
+        assert_eq!(blink.effect_intensity(0.1), Some(1.0));
+        assert_eq!(blink.effect_intensity(0.5), None);
+        assert_eq!(blink.effect_intensity(0.9), Some(1.0));
+
+        let steady = CursorConfig {
+            effect: CursorEffect::None,
+            ..config
+        };
+        assert_eq!(steady.effect_intensity(0.5), Some(1.0));
+    }
+
+    #[test]
+    fn default_periods_match_effect_speed() {
+        let blink = CursorConfig {
+            effect: CursorEffect::Blink,
+            effect_speed: 2.0,
+            ..CursorConfig::default()
+        };
+        assert!((blink.effect_period_secs() - 0.5).abs() < 1e-6);
+        let fade = CursorConfig::default();
+        assert!((fade.effect_period_secs() - std::f32::consts::TAU / 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shape_rects() {
+        let pos = Coord::new(3, 7);
+        assert_eq!(CursorShape::Block.rect(pos, 2), Rect::new(7, 3, 2, 1));
+        assert_eq!(CursorShape::Underline.rect(pos, 2), Rect::new(7, 3, 2, 1));
+        assert_eq!(CursorShape::Bar.rect(pos, 2), Rect::new(7, 3, 1, 1));
+        assert_eq!(CursorShape::Bar.rect(pos, 1), Rect::new(7, 3, 1, 1));
+    }
+
+    #[test]
+    fn bar_shape_uses_the_terminal_cursor() {
+        let bar = CursorConfig {
+            shape: CursorShape::Bar,
+            effect: CursorEffect::Blink,
+            ..CursorConfig::default()
+        };
+        assert!(bar.shows_terminal_cursor());
+        assert_eq!(bar.terminal_bar(), Some(true));
+        let steady = CursorConfig {
+            effect: CursorEffect::Fade,
+            ..bar.clone()
+        };
+        assert_eq!(steady.terminal_bar(), Some(false));
+        let block = CursorConfig::default();
+        assert!(!block.shows_terminal_cursor());
+        assert_eq!(block.terminal_bar(), None);
+        let terminal = CursorConfig {
+            backend: CursorBackend::Terminal,
+            ..bar
+        };
+        assert!(terminal.shows_terminal_cursor());
+        assert_eq!(terminal.terminal_bar(), None);
+    }
+
+    #[test]
+    fn underline_shape_keeps_text_colours() {
+        let block = Style::new().bg(Color::Rgb(200, 200, 200));
+        let underline = CursorShape::Underline.style(block);
+        assert_eq!(underline.bg, None);
+        assert_eq!(underline.underline_color, Some(Color::Rgb(200, 200, 200)));
+        assert!(underline.add_modifier.contains(Modifier::UNDERLINED));
+    }
+}
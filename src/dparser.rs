@@ -84,6 +84,42 @@ impl Annotations {
         self.is_inside_double_quotes = true;
         self
     }
+
+    /// The set annotations as space separated `name` or `name=value` items,
+    /// in field order.
+    pub fn describe(&self) -> String {
+        let mut items = vec![];
+        if self.is_inside_single_quotes {
+            items.push("single_quoted".to_string());
+        }
+        if self.is_inside_double_quotes {
+            items.push("double_quoted".to_string());
+        }
+        if self.is_env_var {
+            items.push("env_var".to_string());
+        }
+        if self.is_comment {
+            items.push("comment".to_string());
+        }
+        match &self.opening {
+            Some(OpeningState::Unmatched) => items.push("opening=unmatched".to_string()),
+            Some(OpeningState::Matched(idx)) => items.push(format!("opening={}", idx)),
+            None => {}
+        }
+        if let Some(closing) = &self.closing {
+            items.push(format!("closing={}", closing.opening_idx));
+            if closing.is_auto_inserted {
+                items.push("auto_inserted".to_string());
+            }
+        }
+        if let Some(word) = &self.command_word {
+            items.push(format!("command_word={:?}", word));
+        }
+        if let Some(depth) = self.bracket_depth {
+            items.push(format!("depth={}", depth));
+        }
+        items.join(" ")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -842,6 +878,35 @@ impl DParser {
         }
     }
 
+    /// Byte offset of the first argument of the first command in `input`
+    /// (e.g. `4` for `git commit -m x`). `None` if the command has no
+    /// arguments before the next separator or the end of the line.
+    pub fn first_argument_byte_pos(input: &str) -> Option<usize> {
+        let tokens = Self::parse_and_annotate(input);
+        let command_start = tokens
+            .iter()
+            .position(|t| t.annotations.command_word.is_some())?;
+        // A command word can span several tokens (e.g. `$HOME/bin/echo`).
+        let after_command = tokens[command_start..]
+            .iter()
+            .position(|t| t.annotations.command_word.is_none())?
+            + command_start;
+        let first_arg = tokens[after_command..]
+            .iter()
+            .find(|t| !matches!(t.token.kind, TokenKind::Whitespace(_)))?;
+        match first_arg.token.kind {
+            TokenKind::Newline
+            | TokenKind::Comment
+            | TokenKind::Pipe
+            | TokenKind::Semicolon
+            | TokenKind::DoubleSemicolon
+            | TokenKind::And
+            | TokenKind::Or
+            | TokenKind::Background => None,
+            _ => Some(first_arg.token.byte_range().start),
+        }
+    }
+
     pub fn needs_more_input(&self) -> bool {
         self.tokens
             .iter()
@@ -1152,12 +1217,102 @@ impl DParser {
     }
 }
 
+/// One line per token of `input` after a full parse: index, byte range,
+/// kind, value and annotations, tab separated. Used by `flyline dump-parse`.
+pub fn dump_parse(input: &str) -> String {
+    DParser::parse_and_annotate(input)
+        .iter()
+        .enumerate()
+        .map(|(idx, t)| {
+            let range = t.token.byte_range();
+            format!(
+                "{}\t{}..{}\t{:?}\t{:?}\t{}\n",
+                idx,
+                range.start,
+                range.end,
+                t.token.kind,
+                t.token.value,
+                t.annotations.describe()
+            )
+        })
+        .collect()
+}
+
+/// Byte offsets where each top-level command of `input` starts: the first
+/// non-whitespace token, and the first one after every `;`, `&&`, `||`, `|`,
+/// `&` or newline that is not nested inside brackets, quotes or a comment.
+pub fn top_level_command_starts(input: &str) -> Vec<usize> {
+    let mut starts = vec![];
+    let mut depth = 0usize;
+    let mut at_command_start = true;
+    for t in DParser::parse_and_annotate(input) {
+        if t.annotations.closing.is_some() {
+            depth = depth.saturating_sub(1);
+        }
+        let nested = depth > 0
+            || t.annotations.is_inside_single_quotes
+            || t.annotations.is_inside_double_quotes
+            || t.annotations.is_comment;
+        if t.annotations.opening.is_some() {
+            depth += 1;
+        }
+        if t.token.kind.is_whitespace() {
+            continue;
+        }
+        if at_command_start {
+            starts.push(t.token.byte_range().start);
+            at_command_start = false;
+        }
+        if !nested
+            && matches!(
+                t.token.kind,
+                TokenKind::Semicolon
+                    | TokenKind::And
+                    | TokenKind::Or
+                    | TokenKind::Pipe
+                    | TokenKind::Background
+                    | TokenKind::Newline
+            )
+        {
+            at_command_start = true;
+        }
+    }
+    starts
+}
+
 // Implicitly tested by command acceptance and tab_completion_context
 // Just a few tests here
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_top_level_command_starts() {
+        assert_eq!(
+            top_level_command_starts("echo a; ls -l; pwd"),
+            vec![0, 8, 15]
+        );
+        assert_eq!(
+            top_level_command_starts("echo $(a; b) && x | y"),
+            vec![0, 16, 20]
+        );
+        assert_eq!(top_level_command_starts("echo 'a; b'"), vec![0]);
+        assert!(top_level_command_starts("   ").is_empty());
+    }
+
+    #[test]
+    fn test_dump_parse() {
+        let dump = dump_parse("echo $(ls)");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert!(lines[0].starts_with("0\t0..4\t"), "{}", lines[0]);
+        assert!(lines[0].ends_with("\t\"echo\"\tcommand_word=\"echo\""));
+        let open = lines.iter().position(|l| l.contains("\t\"$(\"\t")).unwrap();
+        let close = lines.len() - 1;
+        assert!(lines[close].starts_with(&format!("{}\t9..10\t", close)));
+        assert!(lines[open].contains(&format!("\topening={}", close)));
+        assert!(lines[close].contains(&format!("\tclosing={}", open)));
+    }
+
     #[test]
     fn test_nested_commands() {
         let input = r#"     echo $(ls $(echo nested) | grep pattern) > output.txt       "#;
@@ -1750,6 +1905,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_first_argument_byte_pos() {
+        assert_eq!(DParser::first_argument_byte_pos("git commit -m x"), Some(4));
+        assert_eq!(DParser::first_argument_byte_pos("  ls   -la"), Some(7));
+        assert_eq!(
+            DParser::first_argument_byte_pos("FOO=1 echo 'hi there'"),
+            Some(11)
+        );
+        assert_eq!(
+            DParser::first_argument_byte_pos("$HOME/bin/echo hi"),
+            Some(15)
+        );
+        assert_eq!(DParser::first_argument_byte_pos("ls"), None);
+        assert_eq!(DParser::first_argument_byte_pos("ls "), None);
+        assert_eq!(DParser::first_argument_byte_pos("ls | grep foo"), None);
+        assert_eq!(DParser::first_argument_byte_pos(""), None);
+    }
+
     #[test]
     fn test_assignment_env_var_annotation() {
         // `FOO=1 echo hello`: FOO is the env-var name; echo is the command.
@@ -2462,6 +2635,64 @@ mod tests {
     }
 }
 
+/// Word tokens with backslash-escaped multibyte characters must cover whole
+/// characters: the lexer measures their length while deslashing, and a
+/// range ending mid-character would make slicing the buffer panic.
+#[cfg(test)]
+mod test_multibyte_escapes {
+    use super::*;
+
+    fn word_ranges(input: &str) -> Vec<std::ops::Range<usize>> {
+        let mut parser = DParser::from(input);
+        parser.walk_to_end();
+        parser
+            .tokens()
+            .iter()
+            .filter(|t| t.token.kind.is_word())
+            .map(|t| t.token.byte_range())
+            .collect()
+    }
+
+    fn assert_ranges_on_char_boundaries(input: &str) {
+        let mut parser = DParser::from(input);
+        parser.walk_to_end();
+        for token in parser.tokens() {
+            let range = token.token.byte_range();
+            assert!(
+                input.is_char_boundary(range.start) && input.is_char_boundary(range.end),
+                "{:?} splits a character in {:?}",
+                range,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_escaped_multibyte_char_stays_in_word() {
+        let input = "echo café\\é";
+        assert_ranges_on_char_boundaries(input);
+        assert_eq!(word_ranges(input), vec![0..4, 5..input.len()]);
+    }
+
+    #[test]
+    fn test_escaped_multibyte_char_followed_by_more_words() {
+        let input = "echo \\é\\ñ next";
+        assert_ranges_on_char_boundaries(input);
+        let ranges = word_ranges(input);
+        assert_eq!(ranges.first(), Some(&(0..4)));
+        assert_eq!(ranges.last(), Some(&(input.len() - 4..input.len())));
+        assert_eq!(&input[ranges[1].clone()], "\\é\\ñ");
+    }
+
+    #[test]
+    fn test_escaped_wide_and_emoji_chars() {
+        for input in ["ls 日本\\語", "echo a\\🦀b", "x\\é"] {
+            assert_ranges_on_char_boundaries(input);
+            assert_eq!(word_ranges(input).last().map(|r| r.end), Some(input.len()));
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_keyword_bracket_color {
     use super::*;
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cache that completion code fills in the forked completion child. The
+/// child exits after one completion, so the keys it fills are recorded and
+/// [`Self::take_filled`] hands them back to send to the parent, where
+/// [`Self::merge`] keeps them for later forks.
+#[derive(Debug)]
+pub struct ForkCache<K, V> {
+    entries: HashMap<K, V>,
+    filled: Vec<K>,
+}
+
+impl<K, V> Default for ForkCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            filled: Vec::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ForkCache<K, V> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.filled.push(key.clone());
+        self.entries.insert(key, value);
+    }
+
+    /// The entries inserted since the last call.
+    pub fn take_filled(&mut self) -> Vec<(K, V)> {
+        let mut filled = std::mem::take(&mut self.filled);
+        filled.dedup();
+        filled
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.entries.get(&key)?.clone();
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    /// Forget every entry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.filled.clear();
+    }
+
+    /// Keep entries sent back by a child, without recording them as filled.
+    pub fn merge(&mut self, entries: Vec<(K, V)>) {
+        self.entries.extend(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_are_taken_once_and_merged_without_refilling() {
+        let mut child: ForkCache<String, u32> = ForkCache::default();
+        child.insert("a".to_string(), 1);
+        child.insert("a".to_string(), 2);
+        assert_eq!(child.take_filled(), vec![("a".to_string(), 2)]);
+        assert!(child.take_filled().is_empty());
+
+        let mut parent: ForkCache<String, u32> = ForkCache::default();
+        parent.merge(vec![("a".to_string(), 2)]);
+        assert_eq!(parent.get(&"a".to_string()), Some(&2));
+        assert!(parent.take_filled().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::fork_cache::ForkCache;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+/// How many commit subjects to read from `git log`.
+const MAX_COMMIT_MESSAGES: usize = 200;
+
+/// Returns true if the word being completed is the value of `git commit -m`
+/// (or `--message`). `words_before` are the words of the command before the
+/// word under the cursor, with any alias on the command word already expanded.
+pub fn is_commit_message_arg(words_before: &[&str]) -> bool {
+    let Some((&last, rest)) = words_before.split_last() else {
+        return false;
+    };
+    (last == "-m" || last == "--message")
+        && words_before.first() == Some(&"git")
+        && rest.contains(&"commit")
+}
+
+/// Parse the output of `git log --format=%s` into a list of unique commit
+/// messages, most recent first.
+pub fn parse_git_log_subjects(output: &str) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| seen.insert(*line))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Find the git directory for `start`, walking up through its ancestors.
+/// Handles both `.git` directories and `.git` files (worktrees, submodules).
+fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        }
+        if dot_git.is_file() {
+            let contents = std::fs::read_to_string(&dot_git).ok()?;
+            let git_dir = contents.trim().strip_prefix("gitdir:")?.trim();
+            return Some(dir.join(git_dir));
+        }
+    }
+    None
+}
+
+/// Modification time of the reflog for `HEAD`, which changes on every commit,
+/// checkout and reset. Used to invalidate the cached messages for a repo.
+fn head_mtime(git_dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(git_dir.join("logs").join("HEAD"))
+        .or_else(|_| std::fs::metadata(git_dir.join("HEAD")))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Recent commit messages keyed by git dir, with the `HEAD` reflog mtime
+/// they were read at.
+static COMMIT_MESSAGE_CACHE: LazyLock<
+    Mutex<ForkCache<PathBuf, (Option<SystemTime>, Vec<String>)>>,
+> = LazyLock::new(|| Mutex::new(ForkCache::default()));
+
+pub type CacheEntry = (PathBuf, (Option<SystemTime>, Vec<String>));
+
+/// Commit messages read in this process since the last call, for the forked
+/// completion child to send back.
+pub fn take_filled_cache() -> Vec<CacheEntry> {
+    COMMIT_MESSAGE_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take_filled()
+}
+
+/// Keep commit messages read by a forked completion child.
+pub fn merge_cache(entries: Vec<CacheEntry>) {
+    COMMIT_MESSAGE_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .merge(entries);
+}
+
+#[cfg(not(test))]
+fn read_git_log(cwd: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(["log", "--format=%s", "-n", &MAX_COMMIT_MESSAGES.to_string()])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        log::debug!("git log failed in {}: {:?}", cwd.display(), output.status);
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+fn read_git_log(_cwd: &Path) -> Option<String> {
+    Some(
+        std::env::var("FLYLINE_TEST_GIT_LOG").unwrap_or_else(|_| {
+            crate::bash_funcs::test_fixtures::test_git_log_output().to_string()
+        }),
+    )
+}
+
+/// Forget all cached commit messages so the next lookup re-runs `git log`.
+pub fn clear_cache() {
+    COMMIT_MESSAGE_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+/// Recent commit messages for the repository containing the current working
+/// directory, most recent first. Empty when not inside a git repository.
+pub fn recent_commit_messages() -> Vec<String> {
+    let cwd = PathBuf::from(crate::bash_funcs::get_cwd());
+    let Some(git_dir) = find_git_dir(&cwd) else {
+        return vec![];
+    };
+    let mtime = head_mtime(&git_dir);
+
+    let mut cache = COMMIT_MESSAGE_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_mtime, messages)) = cache.get(&git_dir)
+        && *cached_mtime == mtime
+    {
+        return messages.clone();
+    }
+
+    let messages = read_git_log(&cwd)
+        .map(|output| {
+            let mut messages = parse_git_log_subjects(&output);
+            messages.truncate(MAX_COMMIT_MESSAGES);
+            messages
+        })
+        .unwrap_or_default();
+    log::debug!(
+        "Loaded {} commit messages for {}",
+        messages.len(),
+        git_dir.display()
+    );
+    cache.insert(git_dir, (mtime, messages.clone()));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusty_fork::rusty_fork_test;
+
+    #[test]
+    fn parse_git_log_subjects_dedups_and_skips_blank_lines() {
+        let output = crate::bash_funcs::test_fixtures::test_git_log_output();
+        assert_eq!(
+            parse_git_log_subjects(output),
+            vec![
+                "Fix cursor flicker on resize",
+                "Add fuzzy history search",
+                "Bump version to 0.4.0",
+                "Handle 'quoted' args in completions",
+            ]
+        );
+    }
+
+    #[test]
+    fn is_commit_message_arg_detects_message_flag() {
+        assert!(is_commit_message_arg(&["git", "commit", "-m"]));
+        assert!(is_commit_message_arg(&["git", "commit", "-a", "--message"]));
+        assert!(!is_commit_message_arg(&["git", "commit"]));
+        assert!(!is_commit_message_arg(&["git", "tag", "-m"]));
+        assert!(!is_commit_message_arg(&["hg", "commit", "-m"]));
+        assert!(!is_commit_message_arg(&[]));
+    }
+
+    #[test]
+    fn find_git_dir_walks_up_and_follows_gitdir_files() {
+        let root = std::env::temp_dir().join(format!("flyline_git_dir_{}", std::process::id()));
+        let nested = root.join("repo").join("src").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join("repo").join(".git")).unwrap();
+        assert_eq!(find_git_dir(&nested), Some(root.join("repo").join(".git")));
+
+        let worktree = root.join("worktree");
+        std::fs::create_dir_all(&worktree).unwrap();
+        std::fs::write(worktree.join(".git"), "gitdir: ../repo/.git/worktrees/wt\n").unwrap();
+        assert_eq!(
+            find_git_dir(&worktree),
+            Some(worktree.join("../repo/.git/worktrees/wt"))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn reload_picks_up_new_git_log_output() {
+            let repo = std::env::temp_dir().join(format!("flyline_reload_repo_{}", std::process::id()));
+            std::fs::create_dir_all(repo.join(".git")).unwrap();
+            std::env::set_current_dir(&repo).unwrap();
+
+            unsafe { std::env::set_var("FLYLINE_TEST_GIT_LOG", "First message\n"); }
+            assert_eq!(recent_commit_messages(), vec!["First message"]);
+
+            // HEAD has not moved, so the cached messages are still returned.
+            unsafe { std::env::set_var("FLYLINE_TEST_GIT_LOG", "Second message\n"); }
+            assert_eq!(recent_commit_messages(), vec!["First message"]);
+
+            crate::bash_funcs::reload_completion_caches();
+            assert_eq!(recent_commit_messages(), vec!["Second message"]);
+
+            let _ = std::fs::remove_dir_all(repo);
+        }
+
+        #[test]
+        fn messages_read_in_the_child_are_merged_into_the_parent() {
+            let repo = std::env::temp_dir().join(format!("flyline_fork_repo_{}", std::process::id()));
+            std::fs::create_dir_all(repo.join(".git")).unwrap();
+            std::env::set_current_dir(&repo).unwrap();
+
+            unsafe { std::env::set_var("FLYLINE_TEST_GIT_LOG", "From the child\n"); }
+            assert_eq!(recent_commit_messages(), vec!["From the child"]);
+            let filled = take_filled_cache();
+            assert_eq!(filled.len(), 1);
+            assert!(take_filled_cache().is_empty());
+
+            // A parent that never ran git log reuses what the child read.
+            clear_cache();
+            merge_cache(filled);
+            unsafe { std::env::set_var("FLYLINE_TEST_GIT_LOG", "Not read\n"); }
+            assert_eq!(recent_commit_messages(), vec!["From the child"]);
+
+            let _ = std::fs::remove_dir_all(repo);
+        }
+    }
+}
@@ -491,6 +491,22 @@ mod tests {
         assert_eq!(e.glob_pattern(), vec!["/foo*".to_string()]);
     }
 
+    #[test]
+    fn tilde_user_prefix_is_recontracted() {
+        let expanded_prefix = crate::users::expand_tilde_user("~root").unwrap();
+        let e = PathPatternExpansion {
+            raw_prefix: "~root".to_string(),
+            expanded_prefix: expanded_prefix.clone(),
+            rhs_pattern: "pro*".to_string(),
+        };
+        assert_eq!(e.glob_pattern(), vec![format!("{}/pro*", expanded_prefix)]);
+
+        let (unexpanded, rhs) =
+            e.convert_expanded_match_to_unexpanded(&format!("{}/proj", expanded_prefix), None);
+        assert_eq!(unexpanded, "~root/proj");
+        assert_eq!(rhs, "proj");
+    }
+
     #[test]
     fn expand_braces_no_braces() {
         assert_eq!(expand_braces("plain"), vec!["plain".to_string()]);
@@ -504,4 +520,34 @@ mod tests {
             vec!["xy".to_string(), "xfooy".to_string()],
         );
     }
+
+    #[test]
+    fn expand_braces_escaped_brace_is_literal() {
+        assert_eq!(expand_braces(r"\{a,b}.rs"), vec![r"\{a,b}.rs".to_string()]);
+        assert_eq!(
+            expand_braces(r"\{x}{a,b}"),
+            vec![r"\{x}a".to_string(), r"\{x}b".to_string()],
+        );
+    }
+
+    #[test]
+    fn expand_braces_escaped_comma_does_not_split() {
+        assert_eq!(
+            expand_braces(r"{a\,b,c}"),
+            vec![r"a\,b".to_string(), "c".to_string()],
+        );
+    }
+
+    #[test]
+    fn expand_braces_nested_groups_with_suffix() {
+        assert_eq!(
+            expand_braces("src/{main,lib{,s/{a,b}}}.rs"),
+            vec![
+                "src/main.rs".to_string(),
+                "src/lib.rs".to_string(),
+                "src/libs/a.rs".to_string(),
+                "src/libs/b.rs".to_string(),
+            ],
+        );
+    }
 }
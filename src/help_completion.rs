@@ -0,0 +1,155 @@
+use std::io::Read;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::bash_funcs::{self, CommandWordInfo};
+use crate::fork_cache::ForkCache;
+use crate::kill_on_drop_child::KillOnDropChild;
+
+/// How long `<command> --help` may run before it is killed.
+const HELP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Long options scraped from `--help`, keyed by the executable's path.
+static HELP_OPTIONS_CACHE: LazyLock<Mutex<ForkCache<String, Vec<String>>>> =
+    LazyLock::new(|| Mutex::new(ForkCache::default()));
+
+/// Options scraped in this process since the last call, for the forked
+/// completion child to send back.
+pub fn take_filled_cache() -> Vec<(String, Vec<String>)> {
+    HELP_OPTIONS_CACHE.lock().unwrap().take_filled()
+}
+
+/// Keep options scraped by a forked completion child.
+pub fn merge_cache(entries: Vec<(String, Vec<String>)>) {
+    HELP_OPTIONS_CACHE.lock().unwrap().merge(entries);
+}
+
+/// Scrape the long options (matching `--[a-z][a-z0-9-]+`) out of `--help`
+/// output, in order of first appearance.
+pub fn long_options_from_help(help: &str) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let bytes = help.as_bytes();
+    let mut options = vec![];
+    let mut idx = 0;
+    while let Some(offset) = help[idx..].find("--") {
+        let start = idx + offset;
+        idx = start + 2;
+        // Skip `---` rules and options glued to a preceding word.
+        if start > 0 && (bytes[start - 1] == b'-' || bytes[start - 1].is_ascii_alphanumeric()) {
+            continue;
+        }
+        if !bytes.get(idx).is_some_and(|b| b.is_ascii_lowercase()) {
+            continue;
+        }
+        let len = bytes[idx..]
+            .iter()
+            .take_while(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || **b == b'-')
+            .count();
+        let name = help[idx..idx + len].trim_end_matches('-');
+        idx += len;
+        if name.len() >= 2 && seen.insert(name) {
+            options.push(format!("--{}", name));
+        }
+    }
+    options
+}
+
+/// Run `path --help` and return its stdout, or `None` if it fails to start or
+/// does not finish within [`HELP_TIMEOUT`].
+fn read_help(path: &str) -> Option<String> {
+    let child = std::process::Command::new(path)
+        .arg("--help")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut child = KillOnDropChild::new(child);
+    let mut stdout = child.stdout.take()?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        let _ = tx.send(output);
+    });
+
+    match rx.recv_timeout(HELP_TIMEOUT) {
+        Ok(output) => Some(output),
+        Err(_) => {
+            log::debug!("{} --help timed out", path);
+            None
+        }
+    }
+}
+
+/// Long options for `command`, scraped from `--help` and cached per
+/// executable. Only commands that resolve to a file are run.
+pub fn help_options(command: &str) -> Vec<String> {
+    let CommandWordInfo::File { path, .. } = bash_funcs::get_command_info(command) else {
+        return vec![];
+    };
+
+    if let Some(options) = HELP_OPTIONS_CACHE.lock().unwrap().get(&path) {
+        return options.clone();
+    }
+
+    let options = read_help(&path)
+        .map(|help| long_options_from_help(&help))
+        .unwrap_or_default();
+    log::debug!("Scraped {} options from {} --help", options.len(), path);
+    HELP_OPTIONS_CACHE
+        .lock()
+        .unwrap()
+        .insert(path, options.clone());
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrapes_long_options_from_help_text() {
+        let help = "\
+Usage: frob [OPTION]... FILE
+  -a, --all              do not ignore entries starting with .
+      --block-size=SIZE  scale sizes by SIZE
+  -v, --verbose          explain what is being done
+      --verbose          listed twice
+      --color[=WHEN]     colorize the output; see --help-colors
+  ---------------------
+  --X11                  upper case is not an option
+  --1st                  must start with a letter
+  -x                     short options are ignored
+  foo--bar               glued to a word
+";
+        assert_eq!(
+            long_options_from_help(help),
+            vec![
+                "--all",
+                "--block-size",
+                "--verbose",
+                "--color",
+                "--help-colors",
+            ]
+        );
+    }
+
+    #[test]
+    fn options_merged_from_child_are_served_without_running_help() {
+        // `git` resolves to /usr/bin/git in tests; the merged entry is used
+        // instead of running it.
+        merge_cache(vec![(
+            "/usr/bin/git".to_string(),
+            vec!["--from-child".to_string()],
+        )]);
+        assert_eq!(help_options("git"), vec!["--from-child"]);
+    }
+
+    #[test]
+    fn scrapes_nothing_from_text_without_options() {
+        assert!(long_options_from_help("").is_empty());
+        assert!(long_options_from_help("-- --- -- a").is_empty());
+    }
+}
@@ -1,4 +1,6 @@
 use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Instant;
 use std::vec;
 
@@ -11,13 +13,20 @@ use flash::lexer::TokenKind;
 use itertools::Itertools;
 use ratatui::text::{Line, Span};
 use skim::fuzzy_matcher::arinae::ArinaeMatcher;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
 
 #[derive(Debug, Clone)]
 pub struct HistoryEntry {
     pub timestamp: Option<u64>,
+    /// Zero-based history number: for bash history, `index + 1` is the number
+    /// `history` prints and `!n` refers to. Stable across deduplication.
     pub index: usize,
     pub command: String,
     pub raw_output: Option<String>,
+    /// Directory the command was run from. Only known for commands entered
+    /// during this session; entries loaded from the history file have `None`.
+    pub cwd: Option<PathBuf>,
     syntax_highlighted: OnceCell<Vec<Line<'static>>>,
 }
 
@@ -28,6 +37,7 @@ impl HistoryEntry {
             index,
             command,
             raw_output: None,
+            cwd: None,
             syntax_highlighted: OnceCell::new(),
         }
     }
@@ -60,14 +70,193 @@ impl HistoryEntry {
     }
 }
 
+fn first_word(command: &str) -> Option<&str> {
+    command.split_whitespace().next()
+}
+
+/// How many of `entries` start with each first word.
+fn count_command_uses(entries: &[HistoryEntry]) -> HashMap<String, usize> {
+    let mut uses = HashMap::new();
+    for word in entries
+        .iter()
+        .filter_map(|entry| first_word(&entry.command))
+    {
+        *uses.entry(word.to_string()).or_default() += 1;
+    }
+    uses
+}
+
 #[derive(Debug)]
 pub struct HistoryManager {
     entries: Vec<HistoryEntry>,
     index: usize,
+    /// The `index` the next pushed entry gets, i.e. bash's next history
+    /// number minus one.
+    next_index: usize,
     last_search_prefix: Option<String>,
     last_buffered_command: Option<String>,
     fuzzy_search: FuzzyHistorySearch,
     last_word_insert_index: Option<usize>,
+    /// Patterns from `HISTIGNORE`; commands matching any of them are not recorded.
+    ignore_patterns: Vec<HistIgnorePattern>,
+    /// Duplicate handling from `HISTCONTROL`.
+    histcontrol: HistControl,
+    /// Prefer suggestions run from the current directory
+    /// ([`Settings::history_prefer_cwd`]).
+    prefer_cwd: bool,
+    /// How many entries start with each first word, kept in step with `entries`.
+    command_uses: HashMap<String, usize>,
+}
+
+/// The duplicate-related options of bash's colon-separated `HISTCONTROL`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct HistControl {
+    /// `ignoredups` (or `ignoreboth`): drop a command equal to the one before it.
+    ignore_dups: bool,
+    /// `erasedups`: drop every earlier occurrence of a command.
+    erase_dups: bool,
+    /// `ignorespace` (or `ignoreboth`): don't save commands starting with a space.
+    ignore_space: bool,
+}
+
+impl HistControl {
+    fn parse(histcontrol: &str) -> Self {
+        let mut res = HistControl::default();
+        for option in histcontrol.split(':') {
+            match option.trim() {
+                "ignoredups" => res.ignore_dups = true,
+                "ignorespace" => res.ignore_space = true,
+                "ignoreboth" => {
+                    res.ignore_dups = true;
+                    res.ignore_space = true;
+                }
+                "erasedups" => res.erase_dups = true,
+                _ => {}
+            }
+        }
+        res
+    }
+
+    fn from_env() -> Self {
+        crate::bash_funcs::get_envvar_value("HISTCONTROL")
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Remove duplicates from parsed history according to these options.
+    /// Surviving entries keep their original `index`.
+    fn apply(self, entries: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+        if self.erase_dups {
+            let mut seen = std::collections::HashSet::new();
+            let mut kept: Vec<HistoryEntry> = entries
+                .into_iter()
+                .rev()
+                .filter(|entry| seen.insert(entry.command.clone()))
+                .collect();
+            kept.reverse();
+            kept
+        } else if self.ignore_dups {
+            entries
+                .into_iter()
+                .dedup_by(|a, b| a.command == b.command)
+                .collect()
+        } else {
+            entries
+        }
+    }
+}
+
+/// Number of history entries bash keeps when `HISTSIZE` is unset.
+const DEFAULT_HISTSIZE: usize = 500;
+
+/// How many entries to keep for a `HISTSIZE` value. As in bash, a negative
+/// value means no limit; an unset or non-numeric value falls back to 500.
+fn parse_histsize(histsize: Option<&str>) -> usize {
+    match histsize.and_then(|value| value.trim().parse::<i64>().ok()) {
+        Some(n) if n < 0 => usize::MAX,
+        Some(n) => n as usize,
+        None => DEFAULT_HISTSIZE,
+    }
+}
+
+fn histsize_from_env() -> usize {
+    parse_histsize(crate::bash_funcs::get_envvar_value("HISTSIZE").as_deref())
+}
+
+/// Drop all but the last `histsize` entries. Kept entries keep their
+/// original `index`, i.e. their position in the file.
+fn keep_most_recent(mut entries: Vec<HistoryEntry>, histsize: usize) -> Vec<HistoryEntry> {
+    if entries.len() > histsize {
+        entries.drain(..entries.len() - histsize);
+    }
+    entries
+}
+
+/// Most lines a multiline history entry is joined from.
+const MAX_JOINED_LINES: usize = 100;
+
+/// Most bytes a multiline history entry is joined from.
+const MAX_JOINED_BYTES: usize = 64 * 1024;
+
+/// Drop entries starting with `comment_prefix`; `None` keeps everything.
+fn skip_comment_entries(
+    entries: Vec<HistoryEntry>,
+    comment_prefix: Option<&str>,
+) -> Vec<HistoryEntry> {
+    let Some(prefix) = comment_prefix else {
+        return entries;
+    };
+    entries
+        .into_iter()
+        .filter(|entry| !entry.command.trim_start().starts_with(prefix))
+        .collect()
+}
+
+/// A single entry of bash's colon-separated `HISTIGNORE` list.
+#[derive(Debug, Clone)]
+enum HistIgnorePattern {
+    /// `&` matches the previous history entry.
+    PreviousEntry,
+    Glob(glob::Pattern),
+}
+
+/// Parse a `HISTIGNORE` value such as `ls:cd *:exit`. A backslash escapes the
+/// next character, so `\:` is a literal colon and `\*` a literal star.
+/// Invalid patterns are logged and skipped.
+fn parse_histignore(histignore: &str) -> Vec<HistIgnorePattern> {
+    let mut raw_patterns = vec![String::new()];
+    let mut chars = histignore.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' => raw_patterns.push(String::new()),
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    raw_patterns
+                        .last_mut()
+                        .unwrap()
+                        .push_str(&glob::Pattern::escape(&next.to_string()));
+                }
+            }
+            _ => raw_patterns.last_mut().unwrap().push(c),
+        }
+    }
+
+    raw_patterns
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| {
+            if p == "&" {
+                return Some(HistIgnorePattern::PreviousEntry);
+            }
+            match glob::Pattern::new(&p) {
+                Ok(pattern) => Some(HistIgnorePattern::Glob(pattern)),
+                Err(e) => {
+                    log::warn!("Ignoring invalid HISTIGNORE pattern {:?}: {}", p, e);
+                    None
+                }
+            }
+        })
+        .collect()
 }
 
 pub enum HistorySearchDirection {
@@ -102,12 +291,13 @@ impl HistoryManager {
         entries.push(entry);
     }
 
+    /// Drop adjacent duplicates. Unlike merged histories, kept entries keep
+    /// their bash history numbers.
     fn normalize_entries(entries: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
-        let mut normalized = Vec::with_capacity(entries.len());
-        for entry in entries {
-            Self::push_deduped_entry(&mut normalized, entry);
-        }
-        normalized
+        entries
+            .into_iter()
+            .dedup_by(|a, b| a.command == b.command)
+            .collect()
     }
 
     fn merge_history_entries(
@@ -141,7 +331,7 @@ impl HistoryManager {
     /// Read the user's bash history file into a Vec<String>.
     /// Tries $HISTFILE first, otherwise falls back to $HOME/.bash_history.
     #[allow(dead_code)]
-    fn parse_bash_history_from_file() -> Vec<HistoryEntry> {
+    fn parse_bash_history_from_file(comment_prefix: Option<&str>) -> Vec<HistoryEntry> {
         let hist_path = std::env::var("HISTFILE").unwrap_or_else(|_| {
             let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
             format!("{}/.bash_history", home)
@@ -152,7 +342,13 @@ impl HistoryManager {
         let content = std::fs::read_to_string(hist_path).unwrap_or_default();
         let res = time_it!(
             "parse bash history",
-            HistoryManager::parse_bash_history_str(&content)
+            keep_most_recent(
+                HistControl::from_env().apply(HistoryManager::parse_bash_history_str(
+                    &content,
+                    comment_prefix
+                )),
+                histsize_from_env(),
+            )
         );
 
         log::debug!("Parsed bash history ({} entries)", res.len());
@@ -162,6 +358,9 @@ impl HistoryManager {
     pub fn parse_bash_history_from_memory() -> Vec<HistoryEntry> {
         let mut res = Vec::with_capacity(4096);
         unsafe {
+            // `history_base` is the history number of the first entry in the
+            // list; it grows once HISTSIZE trims the oldest entries.
+            let base = (bash_symbols::history_base.max(1) - 1) as usize;
             let hist_array = bash_symbols::history_list();
             if hist_array.is_null() {
                 log::warn!("History list is null");
@@ -188,8 +387,7 @@ impl HistoryManager {
                         if let Ok(timestamp_str) = timestamp_cstr.to_str() {
                             // If there are no timestamps in the history file,
                             // Bash will use the current time for all entries, which can lead to many identical timestamps.
-                            let ts_str = timestamp_str.trim_start_matches('#').trim();
-                            ts_str.parse::<u64>().ok()
+                            Self::parse_timestamp(timestamp_str)
                         } else {
                             None
                         }
@@ -197,7 +395,7 @@ impl HistoryManager {
                         None
                     };
 
-                    let entry = HistoryEntry::new(timestamp, index as usize, command_str);
+                    let entry = HistoryEntry::new(timestamp, base + index as usize, command_str);
                     res.push(entry);
                 }
 
@@ -253,60 +451,196 @@ impl HistoryManager {
     pub fn new(settings: &Settings) -> HistoryManager {
         // Bash will load the history into memory, so we can read it from there
         // Bash parses it after bashrc is loaded.
-        let bash_entries = Self::parse_bash_history_from_memory();
+        let histcontrol = HistControl::from_env();
+        let bash_entries = skip_comment_entries(
+            Self::parse_bash_history_from_memory(),
+            settings.history_comment_prefix.as_deref(),
+        );
+        let mut next_index = bash_entries.last().map_or(0, |entry| entry.index + 1);
+        let bash_entries = keep_most_recent(histcontrol.apply(bash_entries), histsize_from_env());
         Self::log_recent_entries(&bash_entries, "bash");
 
         // Alternative is to do it ourselves
-        // let bash_entries = Self::parse_bash_history_from_file();
+        // let bash_entries =
+        //     Self::parse_bash_history_from_file(settings.history_comment_prefix.as_deref());
 
         let entries = if let Some(ref zsh_path) = settings.zsh_history_path {
             // As a Zsh user migrating to Bash, I want to have my Zsh history available too
             let zsh_entries = Self::parse_zsh_history(Some(zsh_path.as_str()));
             Self::log_recent_entries(&zsh_entries, "Zsh");
-            Self::merge_history_entries(zsh_entries, bash_entries)
+            // Merged entries are renumbered by position.
+            let merged = Self::merge_history_entries(zsh_entries, bash_entries);
+            next_index = merged.len();
+            merged
         } else {
             Self::normalize_entries(bash_entries)
         };
+        let entries = Self::attach_command_cwds(entries, &settings.command_cwds);
 
         let index = entries.len();
+        let command_uses = time_it!("count command uses", count_command_uses(&entries));
         HistoryManager {
             entries,
             index,
+            next_index,
             last_search_prefix: None,
             last_buffered_command: None,
             fuzzy_search: FuzzyHistorySearch::new(),
             last_word_insert_index: None,
+            ignore_patterns: Self::histignore_from_env(),
+            histcontrol,
+            prefer_cwd: settings.history_prefer_cwd,
+            command_uses,
+        }
+    }
+
+    /// Set `cwd` on the most recent entry of each command submitted earlier
+    /// in this session, since bash's own history doesn't record it.
+    fn attach_command_cwds(
+        mut entries: Vec<HistoryEntry>,
+        command_cwds: &HashMap<String, PathBuf>,
+    ) -> Vec<HistoryEntry> {
+        let mut remaining = command_cwds.len();
+        for entry in entries.iter_mut().rev() {
+            if remaining == 0 {
+                break;
+            }
+            if entry.cwd.is_none()
+                && let Some(cwd) = command_cwds.get(entry.command.trim_end())
+            {
+                entry.cwd = Some(cwd.clone());
+                remaining -= 1;
+            }
         }
+        entries
+    }
+
+    fn histignore_from_env() -> Vec<HistIgnorePattern> {
+        crate::bash_funcs::get_envvar_value("HISTIGNORE")
+            .map(|value| parse_histignore(&value))
+            .unwrap_or_default()
+    }
+
+    /// Replace the `HISTIGNORE` patterns used by `push_entry`.
+    #[cfg(test)]
+    pub fn set_histignore(&mut self, histignore: &str) {
+        self.ignore_patterns = parse_histignore(histignore);
+    }
+
+    fn is_ignored(&self, command: &str) -> bool {
+        self.ignore_patterns.iter().any(|pattern| match pattern {
+            HistIgnorePattern::PreviousEntry => self
+                .entries
+                .last()
+                .is_some_and(|prev| prev.command == command),
+            HistIgnorePattern::Glob(glob) => glob.matches(command),
+        })
     }
 
     /// Create an empty `HistoryManager` that starts with no entries.
-    /// New entries are added at runtime via `push_entry`.
+    /// New entries are added at runtime via `push_entry`. `HISTIGNORE`,
+    /// `HISTCONTROL` and `FLYLINE_HIST_CWD` only apply to the main history,
+    /// so they are not read.
     pub fn new_empty() -> HistoryManager {
         HistoryManager {
             entries: Vec::new(),
             index: 0,
+            next_index: 0,
             last_search_prefix: None,
             last_buffered_command: None,
             fuzzy_search: FuzzyHistorySearch::new(),
             last_word_insert_index: None,
+            ignore_patterns: Vec::new(),
+            histcontrol: HistControl::default(),
+            prefer_cwd: false,
+            command_uses: HashMap::new(),
         }
     }
 
+    /// Forget every entry, like `history -c`. New entries keep counting
+    /// from [`Self::next_index`].
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = 0;
+        self.last_search_prefix = None;
+        self.last_buffered_command = None;
+        self.fuzzy_search = FuzzyHistorySearch::new();
+        self.last_word_insert_index = None;
+        self.command_uses.clear();
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// The `index` the next pushed entry will get.
+    pub fn next_index(&self) -> usize {
+        self.next_index
+    }
+
+    /// How many entries start with each first word.
+    pub fn command_uses(&self) -> &HashMap<String, usize> {
+        &self.command_uses
+    }
+
+    /// Replace the `HISTCONTROL` options used by `push_entry`.
+    #[cfg(test)]
+    pub fn set_histcontrol(&mut self, histcontrol: &str) {
+        self.histcontrol = HistControl::parse(histcontrol);
+    }
+
     /// Push a new entry to the history list.
     /// `self.index` is kept at `entries.len()` (past-the-end), matching the
     /// invariant established by `new()` and `HistoryManager::search_in_history`.
     /// Resets the fuzzy search cache so the new entry is visible immediately.
+    /// Commands matching a `HISTIGNORE` pattern are not recorded, and
+    /// commands starting with a space and duplicates are dropped or erased as
+    /// `HISTCONTROL` asks.
     pub fn push_entry(&mut self, command: String) {
         if command.trim().is_empty() {
             return;
         }
-        let index = self.entries.len();
+        if self.is_ignored(&command) {
+            log::debug!("Not recording {:?}: matches HISTIGNORE", command);
+            return;
+        }
+        if self.histcontrol.ignore_space && command.starts_with(' ') {
+            log::debug!("Not recording {:?}: starts with a space", command);
+            return;
+        }
+        if self.histcontrol.ignore_dups
+            && self
+                .entries
+                .last()
+                .is_some_and(|prev| prev.command == command)
+        {
+            log::debug!("Not recording {:?}: duplicate of previous entry", command);
+            return;
+        }
+        if self.histcontrol.erase_dups {
+            let erased = self.entries.len();
+            self.entries.retain(|entry| entry.command != command);
+            let erased = erased - self.entries.len();
+            if erased > 0
+                && let Some(word) = first_word(&command)
+                && let Some(uses) = self.command_uses.get_mut(word)
+            {
+                *uses = uses.saturating_sub(erased);
+            }
+        }
+        if let Some(word) = first_word(&command) {
+            *self.command_uses.entry(word.to_string()).or_default() += 1;
+        }
+        let index = self.next_index;
+        self.next_index += 1;
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .ok()
             .map(|d| d.as_secs());
-        self.entries
-            .push(HistoryEntry::new(timestamp, index, command));
+        let mut entry = HistoryEntry::new(timestamp, index, command);
+        entry.cwd = std::env::current_dir().ok();
+        self.entries.push(entry);
         self.index = self.entries.len();
         self.last_word_insert_index = None;
         self.fuzzy_search.clear_cache();
@@ -341,38 +675,86 @@ impl HistoryManager {
         self.last_word_insert_index = None;
     }
 
+    /// Parse a bash history timestamp line such as `#1625078400`: a `#`
+    /// followed only by digits to the end of the line. Trailing whitespace
+    /// (e.g. the `\r` of a CRLF file) is allowed; anything else makes the
+    /// line a comment or command, so `#1625078400 notes` is not a timestamp.
     fn parse_timestamp(line: &str) -> Option<u64> {
-        if let Some(stripped) = line.strip_prefix('#') {
-            stripped.trim().parse::<u64>().ok()
-        } else {
-            None
+        let digits = line.strip_prefix('#')?.trim_end();
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
+        digits.parse::<u64>().ok()
     }
 
-    fn parse_bash_history_str(s: &str) -> Vec<HistoryEntry> {
+    /// Parse the contents of a bash history file. When `comment_prefix` is
+    /// set, lines starting with it that are not timestamps are skipped;
+    /// otherwise they are kept as commands.
+    ///
+    /// A command that bash would need more input for (a trailing backslash,
+    /// an open quote, a heredoc, ...) is joined with the following lines into
+    /// one entry, keeping the timestamp written before its first line. A
+    /// timestamp line ends such an entry early, and one that runs past
+    /// [`MAX_JOINED_LINES`] or [`MAX_JOINED_BYTES`] is kept as a single line,
+    /// so one unterminated command can't swallow the rest of the file.
+    fn parse_bash_history_str(s: &str, comment_prefix: Option<&str>) -> Vec<HistoryEntry> {
         let mut res = Vec::<HistoryEntry>::new();
+        let mut my_ts = None;
+        let lines: Vec<&str> = s.lines().collect();
+        let mut next = 0;
 
-        s.lines().fold(None, |my_ts, l| {
+        while let Some(&l) = lines.get(next) {
+            next += 1;
             let l_ts = HistoryManager::parse_timestamp(l);
 
             if l_ts.is_some() {
                 // replace current timestamp
-                l_ts
-            } else if l.trim().is_empty() {
-                // Empty line
-                my_ts
+                my_ts = l_ts;
+            } else if l.trim().is_empty()
+                || comment_prefix.is_some_and(|prefix| l.trim_start().starts_with(prefix))
+            {
+                // Empty or comment line
+            } else if crate::command_acceptance::will_bash_accept_buffer(l) {
+                // It's a complete command line
+                res.push(HistoryEntry::new(my_ts.take(), res.len(), l.to_string()));
             } else {
-                // It's a command line
-                let entry = HistoryEntry::new(my_ts, res.len(), l.to_string());
-                res.push(entry);
-                None
+                // The first line of a multiline command
+                let command = match Self::join_continuation_lines(l, &lines[next..]) {
+                    Some((command, used)) => {
+                        next += used;
+                        command
+                    }
+                    None => l.to_string(),
+                };
+                res.push(HistoryEntry::new(my_ts.take(), res.len(), command));
             }
-            // TODO multiline commands
-        });
+        }
 
         res
     }
 
+    /// Join `first` with lines from `rest` until bash accepts the command, a
+    /// timestamp line or the end of the file is reached. Returns the command
+    /// and how many lines of `rest` it used, or `None` when it would exceed
+    /// [`MAX_JOINED_LINES`] or [`MAX_JOINED_BYTES`].
+    fn join_continuation_lines(first: &str, rest: &[&str]) -> Option<(String, usize)> {
+        let mut command = first.to_string();
+        for (used, line) in rest.iter().take(MAX_JOINED_LINES - 1).enumerate() {
+            if HistoryManager::parse_timestamp(line).is_some() {
+                return Some((command, used));
+            }
+            command.push('\n');
+            command.push_str(line);
+            if command.len() > MAX_JOINED_BYTES {
+                return None;
+            }
+            if crate::command_acceptance::will_bash_accept_buffer(&command) {
+                return Some((command, used + 1));
+            }
+        }
+        (rest.len() < MAX_JOINED_LINES).then_some((command, rest.len()))
+    }
+
     fn parse_zsh_history_str(s: &str) -> Vec<HistoryEntry> {
         let mut res = Vec::<HistoryEntry>::new();
 
@@ -412,16 +794,70 @@ impl HistoryManager {
         res
     }
 
+    /// The most recent entry starting with `command`, and the rest of it.
+    /// With `prefer_cwd` set, an entry run from the current directory is
+    /// preferred over more recent ones from elsewhere.
     pub fn get_command_suggestion_suffix(
         &mut self,
         command: &str,
     ) -> Option<(HistoryEntry, String)> {
-        for entry in self.entries.iter().take(self.index).rev() {
-            if entry.command.starts_with(command) {
-                return Some((entry.clone(), entry.command[command.len()..].to_string()));
+        let cwd = if self.prefer_cwd {
+            std::env::current_dir().ok()
+        } else {
+            None
+        };
+        self.command_suggestion_in_dir(command, cwd.as_deref())
+    }
+
+    fn command_suggestion_in_dir(
+        &self,
+        command: &str,
+        cwd: Option<&std::path::Path>,
+    ) -> Option<(HistoryEntry, String)> {
+        let mut matches = self
+            .entries
+            .iter()
+            .take(self.index)
+            .rev()
+            .filter_map(|entry| {
+                strip_prefix_nfc(&entry.command, command).map(|suffix| (entry, suffix))
+            });
+        let (entry, suffix) = match cwd {
+            Some(cwd) => {
+                let first = matches.next()?;
+                std::iter::once(first)
+                    .chain(matches)
+                    .find(|(entry, _)| entry.cwd.as_deref() == Some(cwd))
+                    .unwrap_or(first)
             }
-        }
-        None
+            None => matches.next()?,
+        };
+        Some((entry.clone(), suffix.to_string()))
+    }
+
+    /// Where an inline suggestion for `typed` that continues with `suffix`
+    /// stops agreeing with the most similar other history entry, as a byte
+    /// offset into `typed` followed by `suffix`. The end of the suggestion
+    /// when no other entry shares more than `typed` with it.
+    pub fn suggestion_divergence(&self, typed: &str, suffix: &str) -> usize {
+        let shared = self
+            .entries
+            .iter()
+            .take(self.index)
+            .filter_map(|entry| strip_prefix_nfc(&entry.command, typed))
+            .filter(|other| *other != suffix)
+            .map(|other| {
+                suffix
+                    .chars()
+                    .zip(other.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum::<usize>()
+            })
+            .max()
+            .filter(|&len| len > 0)
+            .unwrap_or(suffix.len());
+        typed.len() + shared
     }
 
     pub fn search_in_history(
@@ -864,6 +1300,39 @@ pub fn get_last_word(command: &str) -> Option<String> {
     }
 }
 
+/// The rest of `entry` after `prefix`, comparing both in NFC so a composed
+/// `é` matches `e` followed by a combining acute. The suffix is sliced from
+/// `entry` as written.
+fn strip_prefix_nfc<'a>(entry: &'a str, prefix: &str) -> Option<&'a str> {
+    if let Some(suffix) = entry.strip_prefix(prefix) {
+        return Some(suffix);
+    }
+    if entry.is_ascii() && prefix.is_ascii() {
+        return None;
+    }
+    let target: String = prefix.nfc().collect();
+    if entry.nfc().next() != target.chars().next() {
+        return None;
+    }
+    // A decomposed character is at most four code points, so no longer
+    // prefix of `entry` can normalize to `target`.
+    let max_chars = target.chars().count() * 4;
+    entry
+        .char_indices()
+        .skip(1)
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(entry.len()))
+        .take(max_chars)
+        .filter(|&idx| {
+            entry[idx..]
+                .chars()
+                .next()
+                .is_none_or(|c| canonical_combining_class(c) == 0)
+        })
+        .find(|&idx| entry[..idx].nfc().eq(target.chars()))
+        .map(|idx| &entry[idx..])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -875,6 +1344,23 @@ mod tests {
         assert_eq!(HistoryManager::parse_timestamp("#not_a_number"), None);
     }
 
+    #[test]
+    fn test_parse_timestamp_requires_only_digits() {
+        assert_eq!(HistoryManager::parse_timestamp("#12345 "), Some(12345));
+        assert_eq!(HistoryManager::parse_timestamp("#12345\r"), Some(12345));
+        assert_eq!(HistoryManager::parse_timestamp("# 12345"), None);
+        assert_eq!(HistoryManager::parse_timestamp(" #12345"), None);
+        assert_eq!(HistoryManager::parse_timestamp("#12345.678"), None);
+        assert_eq!(
+            HistoryManager::parse_timestamp("#12345 Mon Jul  1 00:00:00 2021"),
+            None
+        );
+        assert_eq!(HistoryManager::parse_timestamp("#12345abc"), None);
+        assert_eq!(HistoryManager::parse_timestamp("#+12345"), None);
+        assert_eq!(HistoryManager::parse_timestamp("#"), None);
+        assert_eq!(HistoryManager::parse_timestamp("#cd /tmp"), None);
+    }
+
     #[test]
     fn test_parse_bash_history() {
         const TEST_HISTORY: &str = r"#1625078400
@@ -889,7 +1375,7 @@ cd /home/user
 #1625078460
 cd /home/user2
 ";
-        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY);
+        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY, None);
         for entry in &entries {
             println!(
                 "Timestamp: {:?}, Command: {}",
@@ -915,6 +1401,200 @@ cd /home/user2
         check(Some(1625078460), 5, "cd /home/user2");
     }
 
+    #[test]
+    fn test_parse_bash_history_skips_comment_lines() {
+        const TEST_HISTORY: &str = "#1625078400 \n\
+                                    ls -al\n\
+                                    #cd /asdf/asdf\n\
+                                    #1625078430 notes\n\
+                                    #1625078460\n\
+                                    pwd\n";
+        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY, Some("#"));
+        let parsed: Vec<(Option<u64>, &str)> = entries
+            .iter()
+            .map(|e| (e.timestamp, e.command.as_str()))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![(Some(1625078400), "ls -al"), (Some(1625078460), "pwd")]
+        );
+    }
+
+    #[test]
+    fn test_comment_lines_are_kept_without_a_prefix() {
+        const TEST_HISTORY: &str = "#1625078400
+                                    #cd /asdf/asdf
+                                    #1625078460
+                                    pwd
+";
+        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY, None);
+        let commands: Vec<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["#cd /asdf/asdf", "pwd"]);
+
+        assert_eq!(skip_comment_entries(entries.clone(), None).len(), 2);
+        let kept = skip_comment_entries(entries, Some("#"));
+        let commands: Vec<&str> = kept.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["pwd"]);
+    }
+
+    #[test]
+    fn test_parse_bash_history_joins_multiline_commands() {
+        const TEST_HISTORY: &str = r"#1625078400
+echo foo\
+bar
+#1625078460
+cat <<EOF
+hello
+
+world
+EOF
+echo 'one
+two'
+ls
+";
+        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY, Some("#"));
+        let parsed: Vec<(Option<u64>, usize, &str)> = entries
+            .iter()
+            .map(|e| (e.timestamp, e.index, e.command.as_str()))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (Some(1625078400), 0, "echo foo\\\nbar"),
+                (Some(1625078460), 1, "cat <<EOF\nhello\n\nworld\nEOF"),
+                (None, 2, "echo 'one\ntwo'"),
+                (None, 3, "ls"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_bash_history_timestamp_ends_unterminated_command() {
+        const TEST_HISTORY: &str = "echo 'oops\n#1625078400\npwd\n";
+        let entries = HistoryManager::parse_bash_history_str(TEST_HISTORY, Some("#"));
+        let parsed: Vec<(Option<u64>, &str)> = entries
+            .iter()
+            .map(|e| (e.timestamp, e.command.as_str()))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![(None, "echo 'oops"), (Some(1625078400), "pwd")]
+        );
+    }
+
+    #[test]
+    fn test_parse_bash_history_unbalanced_line_falls_back_to_single_lines() {
+        let mut history = String::from("echo \"oops\n");
+        for i in 0..MAX_JOINED_LINES {
+            history.push_str(&format!("ls {}\n", i));
+        }
+        let entries = HistoryManager::parse_bash_history_str(&history, None);
+        assert_eq!(entries.len(), MAX_JOINED_LINES + 1);
+        assert_eq!(entries[0].command, "echo \"oops");
+        assert_eq!(entries[1].command, "ls 0");
+        assert_eq!(
+            entries[MAX_JOINED_LINES].command,
+            format!("ls {}", MAX_JOINED_LINES - 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_histsize() {
+        assert_eq!(parse_histsize(Some("10")), 10);
+        assert_eq!(parse_histsize(Some("0")), 0);
+        assert_eq!(parse_histsize(Some("-1")), usize::MAX);
+        assert_eq!(parse_histsize(Some("lots")), DEFAULT_HISTSIZE);
+        assert_eq!(parse_histsize(None), DEFAULT_HISTSIZE);
+    }
+
+    #[test]
+    fn test_histsize_keeps_most_recent_entries() {
+        let content: String = (0..1000).map(|i| format!("echo {}\n", i)).collect();
+        let entries = keep_most_recent(
+            HistoryManager::parse_bash_history_str(&content, Some("#")),
+            parse_histsize(Some("10")),
+        );
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0].index, 990);
+        assert_eq!(entries[0].command, "echo 990");
+        assert_eq!(entries[9].index, 999);
+
+        let mut hm = HistoryManager::new_empty();
+        hm.index = entries.len();
+        hm.entries = entries;
+        let (entry, suffix) = hm.get_command_suggestion_suffix("echo 99").unwrap();
+        assert_eq!(entry.command, "echo 999");
+        assert_eq!(suffix, "9");
+    }
+
+    #[test]
+    fn test_suggestion_prefers_entries_from_current_dir() {
+        let mut hm = HistoryManager::new_empty();
+        let mut in_project = HistoryEntry::new(None, 0, "make test".to_string());
+        in_project.cwd = Some(PathBuf::from("/src/project"));
+        let from_file = HistoryEntry::new(None, 1, "make install".to_string());
+        let mut elsewhere = HistoryEntry::new(None, 2, "make clean".to_string());
+        elsewhere.cwd = Some(PathBuf::from("/tmp"));
+        hm.entries = vec![in_project, from_file, elsewhere];
+        hm.index = hm.entries.len();
+
+        let suggest = |cwd: Option<&str>| {
+            hm.command_suggestion_in_dir("make ", cwd.map(std::path::Path::new))
+                .map(|(entry, _)| entry.command)
+        };
+        assert_eq!(suggest(None).as_deref(), Some("make clean"));
+        assert_eq!(suggest(Some("/src/project")).as_deref(), Some("make test"));
+        assert_eq!(suggest(Some("/home")).as_deref(), Some("make clean"));
+    }
+
+    #[test]
+    fn test_suggestion_matches_composed_and_decomposed_accents() {
+        let mut hm = HistoryManager::new_empty();
+        hm.push_entry("echo cafe\u{301} cr\u{e8}me".to_string());
+
+        let (entry, suffix) = hm.get_command_suggestion_suffix("echo caf\u{e9}").unwrap();
+        assert_eq!(entry.command, "echo cafe\u{301} cr\u{e8}me");
+        assert_eq!(suffix, " cr\u{e8}me");
+
+        let (_, suffix) = hm
+            .get_command_suggestion_suffix("echo cafe\u{301} cre\u{300}")
+            .unwrap();
+        assert_eq!(suffix, "me");
+
+        assert_eq!(strip_prefix_nfc("caf\u{e9}!", "cafe\u{301}"), Some("!"));
+    }
+
+    #[test]
+    fn test_suggestion_divergence_from_similar_entries() {
+        let mut hm = HistoryManager::new_empty();
+        hm.push_entry("git push origin dev".to_string());
+        hm.push_entry("git push origin main".to_string());
+        hm.push_entry("ls -la".to_string());
+
+        assert_eq!(
+            hm.suggestion_divergence("git p", "ush origin main"),
+            "git push origin ".len()
+        );
+        // Nothing similar: the end of the suggestion.
+        assert_eq!(hm.suggestion_divergence("ls", " -la"), "ls -la".len());
+    }
+
+    #[test]
+    fn test_attach_command_cwds_marks_most_recent_occurrence() {
+        let entries = vec![
+            HistoryEntry::new(None, 0, "cargo build".to_string()),
+            HistoryEntry::new(None, 1, "ls".to_string()),
+            HistoryEntry::new(None, 2, "cargo build".to_string()),
+        ];
+        let command_cwds = HashMap::from([("cargo build".to_string(), PathBuf::from("/src"))]);
+        let entries = HistoryManager::attach_command_cwds(entries, &command_cwds);
+        let cwds: Vec<Option<&str>> = entries
+            .iter()
+            .map(|e| e.cwd.as_deref().and_then(|p| p.to_str()))
+            .collect();
+        assert_eq!(cwds, vec![None, None, Some("/src")]);
+    }
+
     #[test]
     fn test_parse_zsh_history() {
         // Test simple format (no timestamps)
@@ -1002,7 +1682,7 @@ git status
     }
 
     #[test]
-    fn test_normalize_entries_dedups_adjacent_and_reindexes() {
+    fn test_normalize_entries_dedups_adjacent_and_keeps_indices() {
         let entries = vec![
             HistoryEntry::new(Some(1), 99, "echo hi".to_string()),
             HistoryEntry::new(Some(2), 42, "echo hi".to_string()),
@@ -1013,9 +1693,9 @@ git status
 
         assert_eq!(normalized.len(), 2);
         assert_eq!(normalized[0].command, "echo hi");
-        assert_eq!(normalized[0].index, 0);
+        assert_eq!(normalized[0].index, 99);
         assert_eq!(normalized[1].command, "pwd");
-        assert_eq!(normalized[1].index, 1);
+        assert_eq!(normalized[1].index, 7);
     }
 
     #[test]
@@ -1070,6 +1750,165 @@ git status
         assert_eq!(hm.get_last_word_insert_command(), None);
     }
 
+    #[test]
+    fn test_histignore_skips_matching_commands() {
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histignore("ls:cd *:exit");
+        for cmd in ["ls", "ls -la", "cd /tmp", "cd", "exit", "echo exit"] {
+            hm.push_entry(cmd.to_string());
+        }
+        let commands: Vec<&str> = hm.entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["ls -la", "cd", "echo exit"]);
+        assert_eq!(hm.index, 3);
+        assert!(hm.entries.iter().enumerate().all(|(i, e)| e.index == i));
+    }
+
+    #[test]
+    fn test_histignore_ampersand_and_escapes() {
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histignore(r"&:echo a\:b:echo \*");
+        for cmd in [
+            "echo one", "echo one", "echo a:b", "echo *", "echo x", "echo one",
+        ] {
+            hm.push_entry(cmd.to_string());
+        }
+        let commands: Vec<&str> = hm.entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["echo one", "echo x", "echo one"]);
+    }
+
+    const REPEATED_LS_HISTORY: &str = "ls -al\nls -al\ncd /tmp\nls -al\nls -al\npwd\n";
+
+    #[test]
+    fn test_histcontrol_parse() {
+        assert_eq!(HistControl::parse(""), HistControl::default());
+        assert!(HistControl::parse("ignorespace:ignoredups").ignore_dups);
+        assert!(HistControl::parse("ignoreboth").ignore_dups);
+        assert!(HistControl::parse("ignoreboth").ignore_space);
+        assert!(!HistControl::parse("ignoredups").ignore_space);
+        let erase = HistControl::parse("erasedups");
+        assert!(erase.erase_dups && !erase.ignore_dups);
+    }
+
+    #[test]
+    fn test_histcontrol_ignoredups_collapses_consecutive_duplicates() {
+        let entries = HistoryManager::parse_bash_history_str(REPEATED_LS_HISTORY, None);
+        let entries = HistControl::parse("ignoredups").apply(entries);
+        let kept: Vec<(usize, &str)> = entries
+            .iter()
+            .map(|e| (e.index, e.command.as_str()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![(0, "ls -al"), (2, "cd /tmp"), (3, "ls -al"), (5, "pwd")]
+        );
+    }
+
+    #[test]
+    fn test_histcontrol_erasedups_keeps_only_latest() {
+        let entries = HistoryManager::parse_bash_history_str(REPEATED_LS_HISTORY, None);
+        let entries = HistControl::parse("erasedups").apply(entries);
+        let kept: Vec<(usize, &str)> = entries
+            .iter()
+            .map(|e| (e.index, e.command.as_str()))
+            .collect();
+        assert_eq!(kept, vec![(2, "cd /tmp"), (4, "ls -al"), (5, "pwd")]);
+    }
+
+    #[test]
+    fn test_histcontrol_unset_keeps_all_entries() {
+        let entries = HistoryManager::parse_bash_history_str(REPEATED_LS_HISTORY, None);
+        assert_eq!(HistControl::default().apply(entries).len(), 6);
+    }
+
+    #[test]
+    fn test_push_entry_respects_histcontrol() {
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histcontrol("ignoredups");
+        for cmd in ["ls -al", "ls -al", "pwd", "ls -al"] {
+            hm.push_entry(cmd.to_string());
+        }
+        let commands: Vec<&str> = hm.entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["ls -al", "pwd", "ls -al"]);
+
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histcontrol("erasedups");
+        for cmd in ["ls -al", "pwd", "ls -al"] {
+            hm.push_entry(cmd.to_string());
+        }
+        let kept: Vec<(usize, &str)> = hm
+            .entries
+            .iter()
+            .map(|e| (e.index, e.command.as_str()))
+            .collect();
+        assert_eq!(kept, vec![(1, "pwd"), (2, "ls -al")]);
+        assert_eq!(hm.index, 2);
+
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histcontrol("ignorespace");
+        hm.push_entry(" echo secret".to_string());
+        hm.push_entry("echo one".to_string());
+        let commands: Vec<&str> = hm.entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["echo one"]);
+    }
+
+    #[test]
+    fn test_command_uses_follow_pushed_and_erased_entries() {
+        let mut hm = HistoryManager::new_empty();
+        hm.set_histcontrol("erasedups");
+        for cmd in ["ls -al", "ls", "pwd", "ls -al"] {
+            hm.push_entry(cmd.to_string());
+        }
+        assert_eq!(hm.command_uses().get("ls"), Some(&2));
+        assert_eq!(hm.command_uses().get("pwd"), Some(&1));
+        assert_eq!(
+            hm.command_uses(),
+            &count_command_uses(hm.entries()),
+            "incremental counts match a fresh count"
+        );
+    }
+
+    #[test]
+    fn test_clear_forgets_entries_and_counts() {
+        let mut hm = HistoryManager::new_empty();
+        hm.push_entry("ls".to_string());
+        hm.push_entry("pwd".to_string());
+        hm.clear();
+        assert!(hm.entries().is_empty());
+        assert!(hm.command_uses().is_empty());
+        hm.push_entry("echo hi".to_string());
+        assert_eq!(hm.entries()[0].index, 2);
+        assert_eq!(hm.command_uses().get("echo"), Some(&1));
+    }
+
+    #[test]
+    fn test_push_entry_continues_bash_history_numbers() {
+        // Loaded history with gaps from deduplication and a size cap.
+        let mut hm = HistoryManager::new_empty();
+        hm.entries = vec![
+            HistoryEntry::new(None, 40, "ls".to_string()),
+            HistoryEntry::new(None, 42, "pwd".to_string()),
+        ];
+        hm.next_index = 43;
+        hm.push_entry("echo hi".to_string());
+        assert_eq!(hm.entries[2].index, 43);
+        assert_eq!(hm.entries[0].index, 40);
+    }
+
+    #[test]
+    fn test_new_empty_ignores_main_history_filters() {
+        let mut hm = HistoryManager::new_empty();
+        for cmd in ["ask", "ask", " ask"] {
+            hm.push_entry(cmd.to_string());
+        }
+        assert_eq!(hm.entries.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_histignore_skips_empty_and_invalid_patterns() {
+        assert!(parse_histignore("").is_empty());
+        assert_eq!(parse_histignore("ls::[:pwd").len(), 2);
+    }
+
     #[test]
     fn test_get_last_word() {
         assert_eq!(get_last_word("echo hello"), Some("hello".to_string()));
@@ -0,0 +1,291 @@
+use anyhow::{Result, anyhow};
+
+use crate::history::HistoryManager;
+
+/// History expansion is on when `set -H` (the default in interactive shells)
+/// adds `histexpand` to `SHELLOPTS`.
+pub fn is_enabled() -> bool {
+    crate::bash_funcs::get_envvar_value("SHELLOPTS")
+        .is_some_and(|opts| opts.split(':').any(|opt| opt == "histexpand"))
+}
+
+/// Expand the event designators `!!`, `!n`, `!-n` and `!string`, and the
+/// shorthands `!$`, `!^` and `!*`, the way bash does before running a command.
+/// Text inside single quotes or after a backslash is left alone. Fails like
+/// bash's "event not found" when a designator matches no history entry.
+/// `!n` and `!-n` use bash's history numbers, so they still find the right
+/// command when duplicates were erased or the history was truncated.
+pub fn history_expand(buffer: &str, history: &HistoryManager) -> Result<String> {
+    let events: Vec<Event> = history
+        .entries()
+        .iter()
+        .map(|entry| (entry.index, entry.command.as_str()))
+        .collect();
+    expand_with_events(buffer, &events, history.next_index())
+}
+
+/// A history entry's zero-based history number and command.
+type Event<'a> = (usize, &'a str);
+
+/// Characters that end a `!string` designator.
+const STRING_DESIGNATOR_END: &[char] = &[';', '&', '|', '(', ')', '<', '>', '"', '\'', '`', ':'];
+
+/// Expand `buffer` against `events`, oldest first. `next_index` is the
+/// zero-based number the line being expanded will get.
+fn expand_with_events(buffer: &str, events: &[Event], next_index: usize) -> Result<String> {
+    let mut out = String::with_capacity(buffer.len());
+    let mut chars = buffer.char_indices();
+    let mut in_single = false;
+    let mut in_double = false;
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if !in_single => {
+                out.push(c);
+                if let Some((_, next)) = chars.next() {
+                    out.push(next);
+                }
+                continue;
+            }
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            '!' if !in_single => {
+                let rest = &buffer[idx + 1..];
+                if let Some((expansion, consumed)) =
+                    expand_designator(rest, in_double, events, next_index)?
+                {
+                    out.push_str(&expansion);
+                    for _ in 0..rest[..consumed].chars().count() {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    Ok(out)
+}
+
+/// Expand the designator at the start of `rest` (the text after a `!`).
+/// Returns the replacement and how many bytes of `rest` it used, or `None`
+/// when the `!` is literal.
+fn expand_designator(
+    rest: &str,
+    in_double: bool,
+    events: &[Event],
+    next_index: usize,
+) -> Result<Option<(String, usize)>> {
+    let Some(first) = rest.chars().next() else {
+        return Ok(None);
+    };
+    if first.is_whitespace() || first == '=' || first == '(' || (in_double && first == '"') {
+        return Ok(None);
+    }
+
+    let previous = || {
+        events
+            .last()
+            .map(|(_, command)| *command)
+            .ok_or_else(|| anyhow!("!{}: event not found", first))
+    };
+
+    let (expansion, consumed) = match first {
+        '!' => (previous()?.to_string(), 1),
+        '$' => (
+            shell_words(previous()?).last().cloned().unwrap_or_default(),
+            1,
+        ),
+        '^' => (
+            shell_words(previous()?).get(1).cloned().unwrap_or_default(),
+            1,
+        ),
+        '*' => (
+            shell_words(previous()?)
+                .get(1..)
+                .unwrap_or_default()
+                .join(" "),
+            1,
+        ),
+        _ => {
+            let negative = first == '-';
+            let digits_start = usize::from(negative);
+            let digits_len = rest[digits_start..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - digits_start);
+            if digits_len > 0 {
+                let consumed = digits_start + digits_len;
+                let n: usize = rest[digits_start..consumed].parse()?;
+                // History numbers count from 1; `!-1` is the previous command,
+                // counted back from the number this line will get.
+                let index = if negative {
+                    next_index.checked_sub(n)
+                } else {
+                    n.checked_sub(1)
+                };
+                let command = index
+                    .and_then(|i| {
+                        events
+                            .iter()
+                            .rev()
+                            .find(|(event_index, _)| *event_index == i)
+                    })
+                    .map(|(_, command)| *command)
+                    .ok_or_else(|| anyhow!("!{}: event not found", &rest[..consumed]))?;
+                (command.to_string(), consumed)
+            } else {
+                let consumed = rest
+                    .find(|c: char| c.is_whitespace() || STRING_DESIGNATOR_END.contains(&c))
+                    .unwrap_or(rest.len());
+                if consumed == 0 {
+                    return Ok(None);
+                }
+                let prefix = &rest[..consumed];
+                let command = events
+                    .iter()
+                    .rev()
+                    .map(|(_, command)| *command)
+                    .find(|command| command.starts_with(prefix))
+                    .ok_or_else(|| anyhow!("!{}: event not found", prefix))?;
+                (command.to_string(), consumed)
+            }
+        }
+    };
+    Ok(Some((expansion, consumed)))
+}
+
+/// Split `command` into words on unquoted whitespace, keeping quotes.
+fn shell_words(command: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if !in_single => {
+                word.push(c);
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+                continue;
+            }
+            '\'' if !in_double => in_single = !in_single,
+            '"' if !in_single => in_double = !in_double,
+            c if c.is_whitespace() && !in_single && !in_double => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HISTORY: &[&str] = &[
+        "ls -al /tmp",
+        "git commit -m 'first try'",
+        "echo one two three",
+    ];
+
+    /// Expand against `commands` numbered from 1, as in a fresh history.
+    fn expand_with_commands(buffer: &str, commands: &[&str]) -> Result<String> {
+        let events: Vec<Event> = commands.iter().copied().enumerate().collect();
+        expand_with_events(buffer, &events, commands.len())
+    }
+
+    fn expand(buffer: &str) -> Result<String> {
+        expand_with_commands(buffer, HISTORY)
+    }
+
+    #[test]
+    fn expands_previous_command() {
+        assert_eq!(expand("sudo !!").unwrap(), "sudo echo one two three");
+    }
+
+    #[test]
+    fn expands_last_first_and_all_arguments() {
+        assert_eq!(expand("cat !$").unwrap(), "cat three");
+        assert_eq!(expand("cat !^").unwrap(), "cat one");
+        assert_eq!(expand("printf !*").unwrap(), "printf one two three");
+    }
+
+    #[test]
+    fn expands_numbered_and_relative_events() {
+        assert_eq!(expand("!1").unwrap(), "ls -al /tmp");
+        assert_eq!(expand("!-1").unwrap(), "echo one two three");
+        assert_eq!(expand("!-3 && pwd").unwrap(), "ls -al /tmp && pwd");
+        assert!(expand("!4").is_err());
+        assert!(expand("!0").is_err());
+        assert!(expand("!-4").is_err());
+    }
+
+    #[test]
+    fn numbered_events_use_history_numbers() {
+        // Entries 2 and 4 were erased as duplicates and 1 fell off the front.
+        let events = [(2, "ls -al /tmp"), (4, "git status"), (5, "make")];
+        let expand = |buffer| expand_with_events(buffer, &events, 6);
+        assert_eq!(expand("!3").unwrap(), "ls -al /tmp");
+        assert_eq!(expand("!6").unwrap(), "make");
+        assert_eq!(expand("!-1").unwrap(), "make");
+        assert_eq!(expand("!-3").unwrap(), "git status");
+        assert!(expand("!4").is_err());
+        assert!(expand("!-2").is_err());
+        assert!(expand("!1").is_err());
+    }
+
+    #[test]
+    fn expands_most_recent_command_with_prefix() {
+        assert_eq!(expand("!git").unwrap(), "git commit -m 'first try'");
+        assert_eq!(expand("!l; pwd").unwrap(), "ls -al /tmp; pwd");
+        assert!(expand("!cargo").is_err());
+    }
+
+    #[test]
+    fn quoted_words_stay_whole() {
+        let commands = ["git commit -m 'first try'"];
+        assert_eq!(
+            expand_with_commands("echo !$", &commands).unwrap(),
+            "echo 'first try'"
+        );
+    }
+
+    #[test]
+    fn leaves_literal_bangs_alone() {
+        for literal in [
+            "echo 'hi!!'",
+            "echo hi\\!\\!",
+            "echo hi !",
+            "[ ! -e f ]",
+            "echo \"hi!\"",
+            "x=!",
+        ] {
+            assert_eq!(expand(literal).unwrap(), literal);
+        }
+    }
+
+    #[test]
+    fn expands_inside_double_quotes() {
+        assert_eq!(
+            expand("echo \"!!\"").unwrap(),
+            "echo \"echo one two three\""
+        );
+    }
+
+    #[test]
+    fn fails_without_history() {
+        assert!(expand_with_commands("!!", &[]).is_err());
+        assert_eq!(expand_with_commands("echo", &[]).unwrap(), "echo");
+    }
+}
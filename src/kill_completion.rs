@@ -0,0 +1,354 @@
+use std::path::Path;
+
+/// Signals offered when completing `kill` arguments, by name (without the
+/// `SIG` prefix) and number on this platform.
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("SYS", libc::SIGSYS),
+];
+
+/// How the signal being completed is written on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SignalForm {
+    /// `kill -TERM` or `kill -15`.
+    Dashed,
+    /// The argument of `kill -s`, e.g. `kill -s TERM`.
+    Name,
+    /// The argument of `kill -n`, e.g. `kill -n 15`.
+    Number,
+}
+
+/// Work out whether the word under the cursor is a signal for `kill`.
+/// `words_before` are the words of the command before the word under the
+/// cursor, with any alias on the command word already expanded.
+pub fn signal_form(words_before: &[&str], word_under_cursor: &str) -> Option<SignalForm> {
+    if words_before.first() != Some(&"kill") || words_before.contains(&"--") {
+        return None;
+    }
+    match words_before.last() {
+        Some(&"-s") => Some(SignalForm::Name),
+        Some(&"-n") => Some(SignalForm::Number),
+        _ if word_under_cursor.starts_with('-') && word_under_cursor != "--" => {
+            Some(SignalForm::Dashed)
+        }
+        _ => None,
+    }
+}
+
+/// Signals matching `typed` as `(completion, description)` pairs. Names are
+/// matched case-insensitively and may be typed with a `SIG` prefix. In the
+/// dashed form a digit after the dash completes signal numbers instead.
+pub fn signal_candidates(form: SignalForm, typed: &str) -> Vec<(String, String)> {
+    let (dash, typed) = match form {
+        SignalForm::Dashed => ("-", typed.strip_prefix('-').unwrap_or(typed)),
+        SignalForm::Name | SignalForm::Number => ("", typed),
+    };
+    let numeric = match form {
+        SignalForm::Number => true,
+        SignalForm::Name => false,
+        SignalForm::Dashed => typed.starts_with(|c: char| c.is_ascii_digit()),
+    };
+
+    if numeric {
+        let mut candidates: Vec<(i32, &str)> = SIGNALS
+            .iter()
+            .map(|(name, number)| (*number, *name))
+            .filter(|(number, _)| number.to_string().starts_with(typed))
+            .collect();
+        candidates.sort();
+        return candidates
+            .into_iter()
+            .map(|(number, name)| (format!("{}{}", dash, number), name.to_string()))
+            .collect();
+    }
+
+    let upper = typed.to_ascii_uppercase();
+    let (sig, name_prefix) = match upper.strip_prefix("SIG") {
+        Some(rest) => ("SIG", rest),
+        None => ("", upper.as_str()),
+    };
+    SIGNALS
+        .iter()
+        .filter(|(name, _)| name.starts_with(name_prefix))
+        .map(|(name, number)| (format!("{}{}{}", dash, sig, name), number.to_string()))
+        .collect()
+}
+
+/// What an argument of `kill`, `pkill` or `renice` selects processes by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ProcessArg {
+    /// A process ID, e.g. `kill 12`. Also used for `renice -g`, whose
+    /// process group IDs are the PIDs of the group leaders.
+    Pid,
+    /// A command name pattern, e.g. `pkill fire`.
+    Name,
+    /// A user name, e.g. `pkill -u ro` or `renice -u ro`.
+    User,
+    /// A group name, e.g. `pkill -G wh`.
+    Group,
+}
+
+/// Work out what the word under the cursor selects processes by, if it is a
+/// process argument of `kill`, `pkill` or `renice`. `words_before` are the
+/// words of the command before the word under the cursor.
+pub fn process_arg(words_before: &[&str], word_under_cursor: &str) -> Option<ProcessArg> {
+    let (command, args) = words_before.split_first()?;
+    let last = args.last().copied();
+    match *command {
+        "kill" => (!matches!(last, Some("-s") | Some("-n"))
+            && word_under_cursor.chars().all(|c| c.is_ascii_digit()))
+        .then_some(ProcessArg::Pid),
+        "pkill" => match last {
+            Some("-u" | "-U" | "--euid" | "--uid") => Some(ProcessArg::User),
+            Some("-G" | "--group") => Some(ProcessArg::Group),
+            // Options taking some other value.
+            Some(
+                "-g" | "-P" | "-s" | "-t" | "-F" | "--signal" | "--pgroup" | "--parent"
+                | "--session" | "--terminal" | "--pidfile",
+            ) => None,
+            _ if word_under_cursor.starts_with('-') => None,
+            _ => Some(ProcessArg::Name),
+        },
+        "renice" => {
+            if matches!(last, Some("-n" | "--priority")) {
+                return None;
+            }
+            // `-p`, `-g` and `-u` set how every later argument is read.
+            let mode = args.iter().rev().find_map(|arg| match *arg {
+                "-p" | "--pid" | "-g" | "--pgrp" => Some(ProcessArg::Pid),
+                "-u" | "--user" => Some(ProcessArg::User),
+                _ => None,
+            });
+            match mode.unwrap_or(ProcessArg::Pid) {
+                ProcessArg::Pid => word_under_cursor
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then_some(ProcessArg::Pid),
+                arg => (!word_under_cursor.starts_with('-')).then_some(arg),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Running processes as `(pid, command name)` pairs, sorted by PID. Read
+/// from `/proc` where it exists, otherwise from `ps`.
+pub fn running_processes() -> Vec<(u32, String)> {
+    let proc_dir = Path::new("/proc");
+    if proc_dir.join("self").exists() {
+        return processes_in(proc_dir);
+    }
+    match std::process::Command::new("ps")
+        .args(["-e", "-o", "pid=,comm="])
+        .output()
+    {
+        Ok(output) => parse_ps_output(&String::from_utf8_lossy(&output.stdout)),
+        Err(e) => {
+            log::debug!("Failed to run ps: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Processes listed in a `/proc`-style directory: each numeric entry is a
+/// PID whose `comm` file holds the command name.
+fn processes_in(proc_dir: &Path) -> Vec<(u32, String)> {
+    let Ok(entries) = std::fs::read_dir(proc_dir) else {
+        return vec![];
+    };
+    let mut processes: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let comm = std::fs::read_to_string(entry.path().join("comm")).ok()?;
+            Some((pid, comm.trim_end().to_string()))
+        })
+        .collect();
+    processes.sort();
+    processes
+}
+
+/// Processes in the output of `ps -o pid=,comm=`.
+fn parse_ps_output(output: &str) -> Vec<(u32, String)> {
+    let mut processes: Vec<(u32, String)> = output
+        .lines()
+        .filter_map(|line| {
+            let (pid, comm) = line.trim_start().split_once(char::is_whitespace)?;
+            Some((pid.parse().ok()?, comm.trim().to_string()))
+        })
+        .collect();
+    processes.sort();
+    processes
+}
+
+/// Processes whose PID starts with `typed`, as `(pid, command name)` pairs.
+pub fn pid_candidates(processes: &[(u32, String)], typed: &str) -> Vec<(String, String)> {
+    processes
+        .iter()
+        .map(|(pid, comm)| (pid.to_string(), comm.clone()))
+        .filter(|(pid, _)| pid.starts_with(typed))
+        .collect()
+}
+
+/// The distinct command names of `processes` starting with `typed`, sorted.
+pub fn name_candidates(processes: &[(u32, String)], typed: &str) -> Vec<String> {
+    let mut names: Vec<String> = processes
+        .iter()
+        .filter(|(_, comm)| comm.starts_with(typed))
+        .map(|(_, comm)| comm.clone())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completions(form: SignalForm, typed: &str) -> Vec<String> {
+        signal_candidates(form, typed)
+            .into_iter()
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    #[test]
+    fn signal_form_detection() {
+        assert_eq!(signal_form(&["kill"], "-TE"), Some(SignalForm::Dashed));
+        assert_eq!(signal_form(&["kill", "-s"], "HU"), Some(SignalForm::Name));
+        assert_eq!(signal_form(&["kill", "-n"], "1"), Some(SignalForm::Number));
+        assert_eq!(signal_form(&["kill"], "123"), None);
+        assert_eq!(signal_form(&["kill", "--"], "-1"), None);
+        assert_eq!(signal_form(&["pkill"], "-TE"), None);
+    }
+
+    #[test]
+    fn dashed_names_and_numbers() {
+        assert_eq!(completions(SignalForm::Dashed, "-TE"), vec!["-TERM"]);
+        assert_eq!(completions(SignalForm::Dashed, "-te"), vec!["-TERM"]);
+        assert_eq!(completions(SignalForm::Dashed, "-SIGKI"), vec!["-SIGKILL"]);
+        assert_eq!(
+            completions(SignalForm::Dashed, &format!("-{}", libc::SIGKILL)),
+            vec![format!("-{}", libc::SIGKILL)]
+        );
+        assert_eq!(completions(SignalForm::Dashed, "-").len(), SIGNALS.len());
+    }
+
+    #[test]
+    fn bare_names_after_dash_s() {
+        assert_eq!(completions(SignalForm::Name, "HU"), vec!["HUP"]);
+        let (_, description) = signal_candidates(SignalForm::Name, "HUP").remove(0);
+        assert_eq!(description, libc::SIGHUP.to_string());
+    }
+
+    #[test]
+    fn process_arg_detection() {
+        use ProcessArg::*;
+        assert_eq!(process_arg(&["kill"], ""), Some(Pid));
+        assert_eq!(process_arg(&["kill", "-9"], "12"), Some(Pid));
+        assert_eq!(process_arg(&["kill"], "-TE"), None);
+        assert_eq!(process_arg(&["kill", "-s"], ""), None);
+        assert_eq!(process_arg(&["ls"], "1"), None);
+
+        assert_eq!(process_arg(&["pkill"], "fire"), Some(Name));
+        assert_eq!(process_arg(&["pkill", "-9"], ""), Some(Name));
+        assert_eq!(process_arg(&["pkill", "-u"], "ro"), Some(User));
+        assert_eq!(process_arg(&["pkill", "-G"], "wh"), Some(Group));
+        assert_eq!(process_arg(&["pkill", "-P"], "1"), None);
+        assert_eq!(process_arg(&["pkill"], "-f"), None);
+
+        assert_eq!(process_arg(&["renice", "-n", "5", "-p"], ""), Some(Pid));
+        assert_eq!(process_arg(&["renice", "5"], "12"), Some(Pid));
+        assert_eq!(process_arg(&["renice", "-n"], "5"), None);
+        assert_eq!(process_arg(&["renice", "-n", "5", "-u"], "ro"), Some(User));
+        assert_eq!(
+            process_arg(&["renice", "-n", "5", "-u", "root"], ""),
+            Some(User)
+        );
+        assert_eq!(process_arg(&["renice", "-n", "5", "-g"], "1"), Some(Pid));
+        assert_eq!(process_arg(&["renice", "-u", "root", "-p"], "1"), Some(Pid));
+    }
+
+    #[test]
+    fn process_names_are_distinct() {
+        let processes = [
+            (1, "init".to_string()),
+            (7, "bash".to_string()),
+            (9, "bash".to_string()),
+            (12, "btop".to_string()),
+        ];
+        assert_eq!(name_candidates(&processes, "b"), vec!["bash", "btop"]);
+        assert_eq!(name_candidates(&processes, "x"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reads_processes_from_proc_dir() {
+        let dir = std::env::temp_dir().join(format!("flyline_proc_{}", std::process::id()));
+        for (entry, comm) in [("1", Some("init\n")), ("42", Some("bash\n")), ("99", None)] {
+            std::fs::create_dir_all(dir.join(entry)).unwrap();
+            if let Some(comm) = comm {
+                std::fs::write(dir.join(entry).join("comm"), comm).unwrap();
+            }
+        }
+        std::fs::create_dir_all(dir.join("self")).unwrap();
+
+        let processes = processes_in(&dir);
+        assert_eq!(
+            processes,
+            vec![(1, "init".to_string()), (42, "bash".to_string())]
+        );
+        assert_eq!(
+            pid_candidates(&processes, "4"),
+            vec![("42".to_string(), "bash".to_string())]
+        );
+        assert_eq!(pid_candidates(&processes, "").len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_ps_output() {
+        assert_eq!(
+            parse_ps_output("    1 init\n  314 my server\n\n"),
+            vec![(1, "init".to_string()), (314, "my server".to_string())]
+        );
+    }
+
+    #[test]
+    fn numbers_are_sorted_and_described_by_name() {
+        let candidates = signal_candidates(SignalForm::Number, "");
+        let numbers: Vec<i32> = candidates.iter().map(|(n, _)| n.parse().unwrap()).collect();
+        let mut sorted = numbers.clone();
+        sorted.sort();
+        assert_eq!(numbers, sorted);
+        assert!(candidates.contains(&(libc::SIGTERM.to_string(), "TERM".to_string())));
+    }
+}
@@ -8,38 +8,53 @@ use ctor::ctor;
 
 #[macro_use]
 pub(crate) mod perf;
+mod abbreviations;
 mod active_suggestions;
 mod agent_mode;
 mod app;
+mod arith;
+mod assignment_completion;
 mod bash_funcs;
 mod bash_symbols;
+mod bidi;
 mod changelog;
 mod cli;
 mod command_acceptance;
+mod config_file;
 mod content_builder;
 mod content_utils;
 mod cursor;
 mod dparser;
+mod fork_cache;
+mod git_completion;
 mod globbing;
+mod help_completion;
 mod history;
+mod history_expansion;
 pub mod hostnames;
 mod iter_first_last;
+mod kill_completion;
 mod kill_on_drop_child;
 mod logging;
+mod make_completion;
 mod mouse_state;
 mod palette;
+mod project_completion;
 mod prompt_manager;
 mod settings;
 mod shell_integration;
 mod snake_animation;
 mod stateful_sliding_window;
+mod suggestion_source;
 mod tab_completion_context;
 mod table;
 mod text_buffer;
 pub(crate) mod threads;
+mod transforms;
 mod tutorial;
 pub mod unicode_helpers;
 mod users;
+mod vi;
 
 // Global state for our custom input stream
 static FLYLINE_INSTANCE_PTR: Mutex<Option<Box<Flyline>>> = Mutex::new(None);
@@ -133,14 +148,26 @@ pub(crate) struct Flyline {
     content: Vec<u8>,
     position: usize,
     settings: settings::Settings,
+    /// bash's `history_expansion_inhibited` from before flyline set it for a
+    /// line it had already history expanded.
+    saved_history_expansion_inhibited: Option<c_int>,
+}
+
+/// Whether each prompt appends the previous commands to the history file
+/// (`FLYLINE_FLUSH_HISTORY=1`), in place of `history -a` in `PROMPT_COMMAND`.
+fn flush_history_enabled() -> bool {
+    bash_funcs::get_envvar_value("FLYLINE_FLUSH_HISTORY").is_some_and(|v| v.trim() == "1")
 }
 
 impl Flyline {
     fn new() -> Self {
+        let mut settings = settings::Settings::default();
+        settings.load_config();
         Self {
             content: vec![],
             position: 0,
-            settings: settings::Settings::default(),
+            settings,
+            saved_history_expansion_inhibited: None,
         }
     }
 
@@ -170,8 +197,28 @@ impl Flyline {
             // thread depends on SIGCHLD disposition at this instant.
             let prev_sigchld = unsafe { libc::signal(libc::SIGCHLD, libc::SIG_DFL) };
 
+            // bash has parsed the previous line by now, so let it history
+            // expand lines again.
+            if let Some(inhibited) = self.saved_history_expansion_inhibited.take() {
+                unsafe { bash_symbols::history_expansion_inhibited = inhibited };
+            }
+
+            if flush_history_enabled() {
+                bash_funcs::append_session_history();
+            }
+
             let result = app::get_command(&mut self.settings);
 
+            // bash history expands each line it reads, which would expand any
+            // `!` left in an already expanded command a second time.
+            if std::mem::take(&mut self.settings.history_expanded) {
+                unsafe {
+                    self.saved_history_expansion_inhibited =
+                        Some(bash_symbols::history_expansion_inhibited);
+                    bash_symbols::history_expansion_inhibited = 1;
+                }
+            }
+
             self.settings.last_app_closed_at = Some(std::time::Instant::now());
 
             unsafe { libc::signal(libc::SIGCHLD, prev_sigchld) };
@@ -197,6 +244,13 @@ impl Flyline {
 
             self.content = match result {
                 app::ExitState::WithCommand(cmd) => {
+                    if !cmd.trim().is_empty()
+                        && let Ok(cwd) = std::env::current_dir()
+                    {
+                        self.settings
+                            .command_cwds
+                            .insert(cmd.trim_end().to_string(), cwd);
+                    }
                     if self.settings.tutorial_step.is_active() && cmd.trim().is_empty() {
                         self.settings.tutorial_step.next();
                         log::info!(
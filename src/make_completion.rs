@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use crate::fork_cache::ForkCache;
+
+/// The files `make` reads when none is given, in the order it looks for them.
+const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// Targets parsed from a makefile, keyed by its path and checked against its
+/// modification time.
+static TARGETS_CACHE: LazyLock<Mutex<ForkCache<PathBuf, (SystemTime, Vec<String>)>>> =
+    LazyLock::new(|| Mutex::new(ForkCache::default()));
+
+pub type CacheEntry = (PathBuf, (SystemTime, Vec<String>));
+
+/// Targets parsed in this process since the last call, for the forked
+/// completion child to send back.
+pub fn take_filled_cache() -> Vec<CacheEntry> {
+    TARGETS_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take_filled()
+}
+
+/// Keep targets parsed by a forked completion child.
+pub fn merge_cache(entries: Vec<CacheEntry>) {
+    TARGETS_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .merge(entries);
+}
+
+/// Target names defined in makefile `contents`, in order of first
+/// appearance. Only lines of the form `name:` with `name` made of
+/// `[a-zA-Z0-9_.-]` count, so pattern rules (`%.o:`), recipe lines and
+/// variable assignments (`CC := gcc`) are skipped, as are special targets
+/// such as `.PHONY` and any other name starting with `.`.
+pub fn targets_from_makefile(contents: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+    for line in contents.lines() {
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if name.is_empty()
+            || name.starts_with('.')
+            || rest.starts_with('=')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        {
+            continue;
+        }
+        if !targets.iter().any(|target| target == name) {
+            targets.push(name.to_string());
+        }
+    }
+    targets
+}
+
+/// Targets of the makefile `make` would read in `dir`, or nothing if there
+/// is none.
+pub fn makefile_targets(dir: &Path) -> Vec<String> {
+    let Some((path, mtime)) = MAKEFILE_NAMES.iter().find_map(|name| {
+        let path = dir.join(name);
+        let mtime = std::fs::metadata(&path).ok()?.modified().ok()?;
+        Some((path, mtime))
+    }) else {
+        return vec![];
+    };
+
+    let mut cache = TARGETS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_mtime, targets)) = cache.get(&path)
+        && *cached_mtime == mtime
+    {
+        return targets.clone();
+    }
+    let targets = match std::fs::read_to_string(&path) {
+        Ok(contents) => targets_from_makefile(&contents),
+        Err(e) => {
+            log::debug!("Failed to read {}: {}", path.display(), e);
+            return vec![];
+        }
+    };
+    cache.insert(path, (mtime, targets.clone()));
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+CC := gcc
+PREFIX=/usr/local
+.PHONY: all clean install
+.DEFAULT_GOAL := all
+
+all: build
+build: main.o util.o
+\t$(CC) -o app $^
+
+%.o: %.c
+\t$(CC) -c $<
+
+clean:
+\trm -f *.o app
+install-docs: docs
+# not-a-target: this is a comment
+build: extra
+";
+
+    #[test]
+    fn parses_targets_skipping_special_and_pattern_rules() {
+        assert_eq!(
+            targets_from_makefile(SAMPLE),
+            vec!["all", "build", "clean", "install-docs"]
+        );
+    }
+
+    #[test]
+    fn reads_makefile_in_dir_and_refreshes_on_change() {
+        let dir = std::env::temp_dir().join(format!("flyline_make_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(makefile_targets(&dir).is_empty());
+
+        let makefile = dir.join("Makefile");
+        std::fs::write(&makefile, "test:\n\tcargo test\n").unwrap();
+        assert_eq!(makefile_targets(&dir), vec!["test"]);
+
+        std::fs::write(&makefile, "test:\nlint:\n").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options()
+            .write(true)
+            .open(&makefile)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(makefile_targets(&dir), vec!["test", "lint"]);
+
+        // GNUmakefile takes precedence, as in make.
+        std::fs::write(dir.join("GNUmakefile"), "gnu:\n").unwrap();
+        assert_eq!(makefile_targets(&dir), vec!["gnu"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn targets_merged_from_child_are_served_while_unchanged() {
+        let dir = std::env::temp_dir().join(format!("flyline_make_merge_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let makefile = dir.join("Makefile");
+        std::fs::write(&makefile, "on-disk:\n").unwrap();
+        let mtime = std::fs::metadata(&makefile).unwrap().modified().unwrap();
+
+        merge_cache(vec![(
+            makefile.clone(),
+            (mtime, vec!["merged".to_string()]),
+        )]);
+        assert_eq!(makefile_targets(&dir), vec!["merged"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
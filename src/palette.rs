@@ -147,6 +147,14 @@ pub enum PaletteStyleKind {
     MatchingChar,
     #[strum(message = "Style for matched opening/closing bracket or quote pairs")]
     OpeningAndClosingPair,
+    #[strum(message = "Style for an opening bracket or quote that is never closed")]
+    UnmatchedBracket,
+    #[strum(
+        message = "Style for heredoc bodies whose delimiter names another language (e.g. <<SQL)"
+    )]
+    HeredocBody,
+    #[strum(message = "Style for the token at which bash would report a syntax error")]
+    SyntaxError,
     #[strum(message = "Default style for unclassified command buffer text")]
     NormalText,
     #[strum(message = "Syntax highlighting for shell comments (text after #)")]
@@ -171,6 +179,18 @@ pub enum PaletteStyleKind {
     BashReserved,
     #[strum(message = "Style for the right click context menu background")]
     RightClickMenu,
+    #[strum(message = "Style for the indicator on the right edge when the content is scrolled")]
+    ScrollIndicator,
+    #[strum(message = "Background for trailing whitespace when FLYLINE_SHOW_TRAILING_WS=1")]
+    TrailingWhitespace,
+    #[strum(message = "Style for the last command's exit status in the prompt when it succeeded")]
+    ExitSuccess,
+    #[strum(message = "Style for the last command's exit status in the prompt when it failed")]
+    ExitFailure,
+    #[strum(message = "Style for the last command's duration when it was under the slow threshold")]
+    DurationFast,
+    #[strum(message = "Style for the last command's duration when it reached the slow threshold")]
+    DurationSlow,
     #[strum(message = "Rainbow bracket/quote colour for nesting depth 1 (outermost)")]
     RainbowBracket1,
     #[strum(message = "Rainbow bracket/quote colour for nesting depth 2")]
@@ -197,6 +217,9 @@ pub struct Palette {
     tutorial_hint: Style,
     matching_char: Style,
     opening_and_closing_pair: Style,
+    unmatched_bracket: Style,
+    heredoc_body: Style,
+    syntax_error: Style,
     normal_text: Style,
     comment: Style,
     env_var: Style,
@@ -209,7 +232,15 @@ pub struct Palette {
     selected_text: Style,
     bash_reserved: Style,
     right_click_menu: Style,
+    scroll_indicator: Style,
+    trailing_whitespace: Style,
+    exit_success: Style,
+    exit_failure: Style,
+    duration_fast: Style,
+    duration_slow: Style,
     rainbow_brackets: [Style; 4],
+    /// Slots set by the theme file, re-applied when the preset changes.
+    theme_file: Vec<(PaletteStyleKind, Style)>,
 }
 
 impl Palette {
@@ -251,6 +282,18 @@ impl Palette {
         self.opening_and_closing_pair
     }
 
+    pub fn unmatched_bracket(&self) -> Style {
+        self.unmatched_bracket
+    }
+
+    pub fn heredoc_body(&self) -> Style {
+        self.heredoc_body
+    }
+
+    pub fn syntax_error(&self) -> Style {
+        self.syntax_error
+    }
+
     pub fn normal_text(&self) -> Style {
         self.normal_text
     }
@@ -299,6 +342,30 @@ impl Palette {
         self.right_click_menu
     }
 
+    pub fn scroll_indicator(&self) -> Style {
+        self.scroll_indicator
+    }
+
+    pub fn trailing_whitespace(&self) -> Style {
+        self.trailing_whitespace
+    }
+
+    pub fn exit_success(&self) -> Style {
+        self.exit_success
+    }
+
+    pub fn exit_failure(&self) -> Style {
+        self.exit_failure
+    }
+
+    pub fn duration_fast(&self) -> Style {
+        self.duration_fast
+    }
+
+    pub fn duration_slow(&self) -> Style {
+        self.duration_slow
+    }
+
     /// Return the rainbow bracket/quote style for the given nesting `depth`.
     /// Cycles through the 4 palette slots using `depth % 4`.
     pub fn rainbow_bracket(&self, depth: usize) -> Style {
@@ -319,6 +386,9 @@ impl Palette {
             PaletteStyleKind::TutorialHint => self.tutorial_hint = style,
             PaletteStyleKind::MatchingChar => self.matching_char = style,
             PaletteStyleKind::OpeningAndClosingPair => self.opening_and_closing_pair = style,
+            PaletteStyleKind::UnmatchedBracket => self.unmatched_bracket = style,
+            PaletteStyleKind::HeredocBody => self.heredoc_body = style,
+            PaletteStyleKind::SyntaxError => self.syntax_error = style,
             PaletteStyleKind::NormalText => self.normal_text = style,
             PaletteStyleKind::Comment => self.comment = style,
             PaletteStyleKind::EnvVar => self.env_var = style,
@@ -331,6 +401,12 @@ impl Palette {
             PaletteStyleKind::SelectedText => self.selected_text = style,
             PaletteStyleKind::BashReserved => self.bash_reserved = style,
             PaletteStyleKind::RightClickMenu => self.right_click_menu = style,
+            PaletteStyleKind::ScrollIndicator => self.scroll_indicator = style,
+            PaletteStyleKind::TrailingWhitespace => self.trailing_whitespace = style,
+            PaletteStyleKind::ExitSuccess => self.exit_success = style,
+            PaletteStyleKind::ExitFailure => self.exit_failure = style,
+            PaletteStyleKind::DurationFast => self.duration_fast = style,
+            PaletteStyleKind::DurationSlow => self.duration_slow = style,
             PaletteStyleKind::RainbowBracket1 => self.rainbow_brackets[0] = style,
             PaletteStyleKind::RainbowBracket2 => self.rainbow_brackets[1] = style,
             PaletteStyleKind::RainbowBracket3 => self.rainbow_brackets[2] = style,
@@ -360,6 +436,14 @@ impl Palette {
                 .fg(Color::Red)
                 .add_modifier(Modifier::BOLD)
                 .add_modifier(Modifier::UNDERLINED),
+            unmatched_bracket: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            heredoc_body: Style::default().fg(Color::LightCyan),
+            syntax_error: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::UNDERLINED),
             normal_text: Style::default(),
             comment: Style::default()
                 .fg(Color::Red)
@@ -382,12 +466,21 @@ impl Palette {
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
             right_click_menu: Style::default().fg(Color::Black).bg(Color::Gray),
+            scroll_indicator: Style::default().fg(Color::DarkGray),
+            trailing_whitespace: Style::default().bg(Color::Red),
+            exit_success: Style::default().fg(Color::Green),
+            exit_failure: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            duration_fast: Style::default().add_modifier(Modifier::DIM),
+            duration_slow: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
             rainbow_brackets: [
                 Style::default().fg(Color::Rgb(255, 215, 0)),   // gold
                 Style::default().fg(Color::Rgb(255, 100, 100)), // coral
                 Style::default().fg(Color::Rgb(100, 200, 255)), // sky-blue
                 Style::default().fg(Color::Rgb(100, 230, 150)), // mint-green
             ],
+            theme_file: Vec::new(),
         }
     }
 
@@ -410,6 +503,14 @@ impl Palette {
                 .fg(Color::Blue)
                 .add_modifier(Modifier::BOLD)
                 .add_modifier(Modifier::UNDERLINED),
+            unmatched_bracket: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            heredoc_body: Style::default().fg(Color::Rgb(0, 110, 110)),
+            syntax_error: Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::UNDERLINED),
             normal_text: Style::default(),
             comment: Style::default()
                 .fg(Color::Gray)
@@ -432,21 +533,36 @@ impl Palette {
                 .fg(Color::Blue)
                 .add_modifier(Modifier::BOLD),
             right_click_menu: Style::default().fg(Color::Black).bg(Color::Gray),
+            scroll_indicator: Style::default().fg(Color::Gray),
+            trailing_whitespace: Style::default().bg(Color::LightRed),
+            exit_success: Style::default().fg(Color::Green).bold(),
+            exit_failure: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            duration_fast: Style::default().fg(Color::DarkGray),
+            duration_slow: Style::default()
+                .fg(Color::Rgb(180, 120, 0))
+                .add_modifier(Modifier::BOLD),
             rainbow_brackets: [
                 Style::default().fg(Color::Rgb(180, 120, 0)), // dark gold
                 Style::default().fg(Color::Rgb(180, 30, 30)), // deep red
                 Style::default().fg(Color::Rgb(30, 100, 200)), // deep blue
                 Style::default().fg(Color::Rgb(30, 130, 60)), // dark green
             ],
+            theme_file: Vec::new(),
         }
     }
 
-    /// Reset all palette slots to the given theme preset.
+    /// Reset all palette slots to the given theme preset, then re-apply the
+    /// slots set by the theme file.
     pub fn apply_theme(&mut self, mode: ColourTheme) {
+        let theme_file = std::mem::take(&mut self.theme_file);
         *self = match mode {
             ColourTheme::Dark => Self::dark(),
             ColourTheme::Light => Self::light(),
         };
+        for &(kind, style) in &theme_file {
+            self.set(kind, style);
+        }
+        self.theme_file = theme_file;
     }
 
     // ── Derived / constant styles ───────────────────────────────────
@@ -476,6 +592,29 @@ impl Palette {
     pub fn cursor_style(intensity: u8) -> Style {
         Style::new().bg(Color::Rgb(intensity, intensity, intensity))
     }
+
+    /// Style for a previous command's exit status.
+    pub fn exit_status_style(&self, exit_code: i32) -> Style {
+        if exit_code == 0 {
+            self.exit_success
+        } else {
+            self.exit_failure
+        }
+    }
+
+    /// Style for a previous-command duration, depending on whether it
+    /// finished under `slow_threshold`.
+    pub fn duration_style(
+        &self,
+        elapsed: std::time::Duration,
+        slow_threshold: std::time::Duration,
+    ) -> Style {
+        if elapsed < slow_threshold {
+            self.duration_fast
+        } else {
+            self.duration_slow
+        }
+    }
 }
 
 impl Default for Palette {
@@ -484,6 +623,54 @@ impl Default for Palette {
     }
 }
 
+// ── Theme file ──────────────────────────────────────────────────────
+
+impl Palette {
+    /// Apply the styles set by the theme file named by `$FLYLINE_THEME`,
+    /// else `~/.config/flyline/theme.toml` if it exists, replacing those set
+    /// by an earlier read. The file maps palette style names to style
+    /// strings, e.g.
+    ///
+    /// ```toml
+    /// recognised-command = "bold #00ff88"
+    /// inline-suggestion = "dim italic"
+    /// ```
+    pub fn load_theme_file(&mut self) {
+        self.theme_file.clear();
+        self.apply_theme_file(&crate::config_file::load(
+            "FLYLINE_THEME",
+            "theme.toml",
+            "colour theme",
+        ));
+    }
+
+    /// Apply every valid `name = "style"` entry of a theme file to this
+    /// palette. Styles not set keep their default; invalid entries are logged
+    /// and ignored.
+    fn apply_theme_file(&mut self, entries: &[(String, String)]) {
+        for (name, style_str) in entries {
+            match parse_theme_entry(name, style_str) {
+                Ok((kind, style)) => {
+                    self.set(kind, style);
+                    self.theme_file.push((kind, style));
+                }
+                Err(e) => log::warn!("Theme file: {}", e),
+            }
+        }
+    }
+}
+
+/// Parse one theme file entry. Names may be kebab-case or snake_case.
+fn parse_theme_entry(name: &str, style_str: &str) -> Result<(PaletteStyleKind, Style), String> {
+    let name = name.replace('_', "-");
+    let kind = name
+        .parse::<PaletteStyleKind>()
+        .map_err(|_| format!("unknown style name {:?}", name))?;
+    let style = parse_str_to_style(style_str)
+        .map_err(|e| format!("invalid style for {:?}: {}", name, e))?;
+    Ok((kind, style))
+}
+
 /// Tab-completion for the `NAME=STYLE` arguments of `flyline set-style`.
 ///
 /// Yields each [`PaletteStyleKind`] name (in kebab-case) with `=` appended and
@@ -516,6 +703,95 @@ pub fn possible_style_name_completions(current: &std::ffi::OsStr) -> Vec<Complet
 mod tests {
     use super::*;
 
+    const SAMPLE_THEME: &str = r##"
+# My theme
+[palette]
+recognised-command = "bold #ff8800"
+inline_suggestion = 'italic #112233'   # snake_case works too
+not-a-style = "red"
+"##;
+
+    #[test]
+    fn test_theme_file_sets_configured_roles() {
+        let mut palette = Palette::default();
+        palette.apply_theme_file(&crate::config_file::parse(SAMPLE_THEME, "theme"));
+        assert_eq!(
+            palette.recognised_command(),
+            Style::default()
+                .fg(Color::Rgb(255, 136, 0))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(
+            palette.inline_suggestion(),
+            Style::default()
+                .fg(Color::Rgb(0x11, 0x22, 0x33))
+                .add_modifier(Modifier::ITALIC)
+        );
+    }
+
+    #[test]
+    fn test_theme_file_keeps_defaults_for_missing_or_invalid_roles() {
+        let mut palette = Palette::default();
+        palette.apply_theme_file(&crate::config_file::parse(SAMPLE_THEME, "theme"));
+        let defaults = Palette::default();
+        assert_eq!(palette.comment(), defaults.comment());
+        assert_eq!(palette.env_var(), defaults.env_var());
+    }
+
+    #[test]
+    fn test_parse_theme_entry() {
+        assert!(parse_theme_entry("not-a-style", "red").is_err());
+        assert!(parse_theme_entry("env-var", "nosuchcolour").is_err());
+        assert!(matches!(
+            parse_theme_entry("env_var", "red"),
+            Ok((PaletteStyleKind::EnvVar, _))
+        ));
+    }
+
+    #[test]
+    fn test_theme_file_survives_preset_change() {
+        let mut palette = Palette::default();
+        palette.apply_theme_file(&crate::config_file::parse(SAMPLE_THEME, "theme"));
+        let from_file = palette.recognised_command();
+        palette.apply_theme(ColourTheme::Light);
+        assert_eq!(palette.recognised_command(), from_file);
+        assert_eq!(palette.comment(), Palette::light().comment());
+    }
+
+    #[test]
+    fn test_exit_status_style_success_and_failure() {
+        let mut palette = Palette::default();
+        assert_eq!(palette.exit_status_style(0), palette.exit_success());
+        assert_eq!(palette.exit_status_style(1), palette.exit_failure());
+        assert_eq!(palette.exit_status_style(127), palette.exit_failure());
+
+        palette.apply_theme_file(&[("exit-failure".to_string(), "underlined".to_string())]);
+        assert_eq!(
+            palette.exit_status_style(1),
+            Style::new().add_modifier(Modifier::UNDERLINED)
+        );
+    }
+
+    #[test]
+    fn test_duration_style_either_side_of_threshold() {
+        use std::time::Duration;
+        let threshold = Duration::from_millis(500);
+        let palette = Palette::default();
+        let fast = palette.duration_fast();
+        let slow = palette.duration_slow();
+        assert_ne!(fast, slow);
+        assert_eq!(palette.duration_style(Duration::ZERO, threshold), fast);
+        assert_eq!(
+            palette.duration_style(Duration::from_millis(499), threshold),
+            fast
+        );
+        assert_eq!(palette.duration_style(threshold, threshold), slow);
+        assert_eq!(
+            palette.duration_style(Duration::from_secs(60), threshold),
+            slow
+        );
+    }
+
     #[test]
     fn test_possible_style_name_completions_empty_yields_all() {
         let values: Vec<String> = possible_style_name_completions(std::ffi::OsStr::new(""))
@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use crate::fork_cache::ForkCache;
+
+/// A kind of name read from a project file in the current directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ProjectCompletion {
+    /// Keys of the `scripts` object in `package.json`.
+    NpmScripts,
+    /// Binary targets in `Cargo.toml`.
+    CargoBins,
+    /// Integration test targets in `Cargo.toml`.
+    CargoTests,
+}
+
+/// Completes the word after `command subcommand`, or after `flag` when set,
+/// with names parsed from `file`.
+struct ProjectCompleter {
+    command: &'static str,
+    subcommand: &'static str,
+    flag: Option<&'static str>,
+    file: &'static str,
+    kind: ProjectCompletion,
+}
+
+const COMPLETERS: &[ProjectCompleter] = &[
+    ProjectCompleter {
+        command: "npm",
+        subcommand: "run",
+        flag: None,
+        file: "package.json",
+        kind: ProjectCompletion::NpmScripts,
+    },
+    ProjectCompleter {
+        command: "yarn",
+        subcommand: "run",
+        flag: None,
+        file: "package.json",
+        kind: ProjectCompletion::NpmScripts,
+    },
+    ProjectCompleter {
+        command: "cargo",
+        subcommand: "run",
+        flag: Some("--bin"),
+        file: "Cargo.toml",
+        kind: ProjectCompletion::CargoBins,
+    },
+    ProjectCompleter {
+        command: "cargo",
+        subcommand: "test",
+        flag: Some("--bin"),
+        file: "Cargo.toml",
+        kind: ProjectCompletion::CargoBins,
+    },
+    ProjectCompleter {
+        command: "cargo",
+        subcommand: "test",
+        flag: Some("--test"),
+        file: "Cargo.toml",
+        kind: ProjectCompletion::CargoTests,
+    },
+];
+
+/// Names parsed from a project file, keyed by its path and the kind of name,
+/// and checked against its modification time.
+static NAMES_CACHE: LazyLock<
+    Mutex<ForkCache<(PathBuf, ProjectCompletion), (SystemTime, Vec<String>)>>,
+> = LazyLock::new(|| Mutex::new(ForkCache::default()));
+
+pub type CacheEntry = ((PathBuf, ProjectCompletion), (SystemTime, Vec<String>));
+
+/// Names parsed in this process since the last call, for the forked
+/// completion child to send back.
+pub fn take_filled_cache() -> Vec<CacheEntry> {
+    NAMES_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take_filled()
+}
+
+/// Keep names parsed by a forked completion child.
+pub fn merge_cache(entries: Vec<CacheEntry>) {
+    NAMES_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .merge(entries);
+}
+
+/// The kind of project name the next word is, given the words of the command
+/// before it.
+pub fn completion_for(words_before: &[&str]) -> Option<ProjectCompletion> {
+    let [command, subcommand, rest @ ..] = words_before else {
+        return None;
+    };
+    COMPLETERS
+        .iter()
+        .find(|completer| {
+            completer.command == *command
+                && completer.subcommand == *subcommand
+                && match completer.flag {
+                    Some(flag) => rest.last() == Some(&flag),
+                    None => rest.is_empty(),
+                }
+        })
+        .map(|completer| completer.kind)
+}
+
+/// Names of `kind` from its project file in `dir`, or nothing if there is
+/// none.
+pub fn names(kind: ProjectCompletion, dir: &Path) -> Vec<String> {
+    let Some(completer) = COMPLETERS.iter().find(|completer| completer.kind == kind) else {
+        return vec![];
+    };
+    let path = dir.join(completer.file);
+    let Ok(mtime) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+        return vec![];
+    };
+
+    let key = (path, kind);
+    let mut cache = NAMES_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_mtime, names)) = cache.get(&key)
+        && *cached_mtime == mtime
+    {
+        return names.clone();
+    }
+    let names = match std::fs::read_to_string(&key.0) {
+        Ok(contents) => parse_names(kind, &contents),
+        Err(e) => {
+            log::debug!("Failed to read {}: {}", key.0.display(), e);
+            return vec![];
+        }
+    };
+    cache.insert(key, (mtime, names.clone()));
+    names
+}
+
+fn parse_names(kind: ProjectCompletion, contents: &str) -> Vec<String> {
+    match kind {
+        ProjectCompletion::NpmScripts => npm_scripts(contents),
+        ProjectCompletion::CargoBins => cargo_targets(contents, "bin", true),
+        ProjectCompletion::CargoTests => cargo_targets(contents, "test", false),
+    }
+}
+
+/// Script names in a `package.json`.
+fn npm_scripts(contents: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(contents) else {
+        return vec![];
+    };
+    json.get("scripts")
+        .and_then(serde_json::Value::as_object)
+        .map(|scripts| scripts.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The `name` of each `[[table]]` entry in a `Cargo.toml`, after the package
+/// name when `include_package` is set (the default binary is named after
+/// the package).
+fn cargo_targets(contents: &str, table: &str, include_package: bool) -> Vec<String> {
+    let manifest = match contents.parse::<toml::Table>() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::debug!("Failed to parse Cargo.toml: {}", e);
+            return vec![];
+        }
+    };
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .filter(|_| include_package);
+    let targets = manifest
+        .get(table)
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|target| target.get("name"));
+    let mut names: Vec<String> = vec![];
+    for name in package
+        .into_iter()
+        .chain(targets)
+        .filter_map(toml::Value::as_str)
+    {
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE_JSON: &str = r#"{
+  "name": "web",
+  "scripts": {
+    "build": "vite build",
+    "dev": "vite",
+    "test:unit": "vitest"
+  },
+  "dependencies": { "vite": "^5.0.0" }
+}"#;
+
+    const CARGO_TOML: &str = r#"
+[package]
+name = "tool"
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+
+[[bin]]
+name = "tool-cli"
+path = "src/bin/cli.rs"
+
+[[test]]
+name = "integration" # runs the CLI end to end
+harness = false
+
+[[bin]]
+name = 'helper'
+"#;
+
+    #[test]
+    fn parses_npm_scripts() {
+        assert_eq!(npm_scripts(PACKAGE_JSON), vec!["build", "dev", "test:unit"]);
+        assert!(npm_scripts("{}").is_empty());
+        assert!(npm_scripts("not json").is_empty());
+    }
+
+    #[test]
+    fn parses_cargo_targets() {
+        assert_eq!(
+            parse_names(ProjectCompletion::CargoBins, CARGO_TOML),
+            vec!["tool", "tool-cli", "helper"]
+        );
+        assert_eq!(
+            parse_names(ProjectCompletion::CargoTests, CARGO_TOML),
+            vec!["integration"]
+        );
+        assert!(parse_names(ProjectCompletion::CargoBins, "[package\nname = 'x'").is_empty());
+    }
+
+    #[test]
+    fn completer_matches_command_and_subcommand() {
+        assert_eq!(
+            completion_for(&["npm", "run"]),
+            Some(ProjectCompletion::NpmScripts)
+        );
+        assert_eq!(
+            completion_for(&["yarn", "run"]),
+            Some(ProjectCompletion::NpmScripts)
+        );
+        assert_eq!(completion_for(&["npm", "run", "build"]), None);
+        assert_eq!(completion_for(&["npm", "install"]), None);
+        assert_eq!(
+            completion_for(&["cargo", "run", "--release", "--bin"]),
+            Some(ProjectCompletion::CargoBins)
+        );
+        assert_eq!(
+            completion_for(&["cargo", "test", "--test"]),
+            Some(ProjectCompletion::CargoTests)
+        );
+        assert_eq!(completion_for(&["cargo", "run"]), None);
+        assert_eq!(completion_for(&["cargo"]), None);
+    }
+
+    #[test]
+    fn reads_names_from_dir() {
+        let dir = std::env::temp_dir().join(format!("flyline_project_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(names(ProjectCompletion::NpmScripts, &dir).is_empty());
+
+        std::fs::write(dir.join("package.json"), PACKAGE_JSON).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), CARGO_TOML).unwrap();
+        assert_eq!(
+            names(ProjectCompletion::NpmScripts, &dir),
+            vec!["build", "dev", "test:unit"]
+        );
+        assert_eq!(
+            names(ProjectCompletion::CargoTests, &dir),
+            vec!["integration"]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_merged_from_child_are_served_while_unchanged() {
+        let dir =
+            std::env::temp_dir().join(format!("flyline_project_merge_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("package.json");
+        std::fs::write(&path, PACKAGE_JSON).unwrap();
+        let mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        merge_cache(vec![(
+            (path, ProjectCompletion::NpmScripts),
+            (mtime, vec!["merged".to_string()]),
+        )]);
+        assert_eq!(names(ProjectCompletion::NpmScripts, &dir), vec!["merged"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
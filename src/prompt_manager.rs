@@ -1,7 +1,8 @@
 use crate::bash_funcs;
 use crate::bash_symbols;
-use crate::content_builder::{Tag, TaggedLine, TaggedSpan};
+use crate::content_builder::{SpanTag, Tag, TaggedLine, TaggedSpan};
 use crate::kill_on_drop_child::KillOnDropChild;
+use crate::palette::Palette;
 use crate::settings::{Placeholder, PromptAnimation, PromptWidget, PromptWidgetCustom};
 #[cfg(not(test))]
 use ansi_to_tui::IntoText;
@@ -10,6 +11,7 @@ use ratatui::text::{Line, Span};
 use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::{Arc, Mutex};
+use unicode_segmentation::UnicodeSegmentation;
 
 /// An animation whose frames have been processed through
 /// [`expand_prompt_through_bash`].  Embedded directly inside
@@ -153,6 +155,10 @@ enum PromptSegment {
     /// The widget's text is styled with `base_style` (the surrounding prompt
     /// span's style).
     WidgetLastCommandDuration { text: String, base_style: Style },
+    /// A widget showing the previous command's exit code and duration.
+    /// Like [`PromptSegment::WidgetLastCommandDuration`], the text and style
+    /// are fixed at construction time.
+    WidgetLastCommandStatus { text: String, base_style: Style },
     /// A custom-command widget.  On each render the child process is polled
     /// with `try_wait`; once it exits the output (processed through
     /// `expand_prompt_through_bash`) is shown.  While still pending the
@@ -177,6 +183,10 @@ pub struct PromptManager {
     rprompt_final: Option<Vec<Vec<PromptSegment>>>,
     fill_span: Vec<PromptSegment>,
     fill_span_final: Option<Vec<PromptSegment>>,
+    /// Continuation prompt from a customised `PS2`. `None` when `PS2` is unset
+    /// or bash's default `> `, in which case continuation lines show line
+    /// numbers instead.
+    ps2: Option<Vec<PromptSegment>>,
     /// Time captured at construction; used when animations are disabled so
     /// that time-based prompt fields show the session-start time rather than
     /// updating on every render.
@@ -225,9 +235,9 @@ fn expand_prompt_through_bash(raw: String) -> Option<Vec<Line<'static>>> {
     // the string to `decode_prompt_string`.
     let raw = raw.replace("\\[", "").replace("\\]", "");
 
-    let c_prompt = std::ffi::CString::new(raw).ok()?;
+    let c_prompt = std::ffi::CString::new(raw.as_str()).ok()?;
 
-    let _guard = crate::bash_symbols::BASH_LOCK.lock();
+    let guard = crate::bash_symbols::BASH_LOCK.lock();
 
     let decoded = unsafe {
         #[cfg(not(feature = "pre_bash_4_4"))]
@@ -235,20 +245,26 @@ fn expand_prompt_through_bash(raw: String) -> Option<Vec<Line<'static>>> {
         #[cfg(feature = "pre_bash_4_4")]
         let decoded_prompt_cstr = bash_symbols::decode_prompt_string(c_prompt.as_ptr());
         if decoded_prompt_cstr.is_null() {
-            log::warn!("decode_prompt_string returned null");
-            return None;
-        }
-
-        let decoded = std::ffi::CStr::from_ptr(decoded_prompt_cstr)
-            .to_str()
-            .ok()?
-            .to_string();
+            None
+        } else {
+            let decoded = std::ffi::CStr::from_ptr(decoded_prompt_cstr)
+                .to_str()
+                .ok()?
+                .to_string();
 
-        // `decode_prompt_string` returns an allocated buffer.
-        bash_symbols::locked_xfree(decoded_prompt_cstr as *mut std::ffi::c_void);
+            // `decode_prompt_string` returns an allocated buffer.
+            bash_symbols::locked_xfree(decoded_prompt_cstr as *mut std::ffi::c_void);
 
-        decoded
+            Some(decoded)
+        }
     };
+    // Gathering the fallback values calls back into bash.
+    drop(guard);
+
+    let decoded = decoded.unwrap_or_else(|| {
+        log::warn!("decode_prompt_string returned null; expanding common escapes ourselves");
+        expand_prompt_escapes(&raw, &PromptEscapeValues::from_shell())
+    });
 
     let mut lines = decoded.into_text().ok()?.lines;
     for line in &mut lines {
@@ -265,16 +281,89 @@ fn expand_prompt_through_bash(raw: String) -> Option<Vec<Line<'static>>> {
     Some(lines)
 }
 
-/// In test builds the bash FFI symbols are not linked; this function returns
-/// the raw string unchanged (wrapped in a single [`Line`]) so that unit tests
-/// can exercise the prompt-rendering logic without requiring a live bash
-/// process.
+/// In test builds the bash FFI symbols are not linked; this function only
+/// resolves the escapes handled by [`expand_prompt_escapes`] (wrapped in a
+/// single [`Line`]) so that unit tests can exercise the prompt-rendering logic
+/// without requiring a live bash process.
 #[cfg(test)]
 fn expand_prompt_through_bash(raw: String) -> Option<Vec<Line<'static>>> {
     if raw.is_empty() {
         return Some(vec![]);
     }
-    Some(vec![Line::raw(raw)])
+    Some(vec![Line::raw(expand_prompt_escapes(
+        &raw,
+        &PromptEscapeValues::from_shell(),
+    ))])
+}
+
+/// The values behind the standard bash prompt escapes.
+struct PromptEscapeValues {
+    user: String,
+    hostname: String,
+    cwd: String,
+    home: Option<String>,
+    dirtrim: Option<usize>,
+    is_root: bool,
+}
+
+impl PromptEscapeValues {
+    fn from_shell() -> Self {
+        Self {
+            user: bash_funcs::get_envvar_value("USER").unwrap_or_default(),
+            hostname: bash_funcs::get_hostname(),
+            cwd: bash_funcs::get_cwd(),
+            home: bash_funcs::get_envvar_value("HOME"),
+            dirtrim: bash_funcs::get_envvar_value("PROMPT_DIRTRIM")
+                .as_deref()
+                .and_then(parse_dirtrim),
+            is_root: unsafe { libc::geteuid() } == 0,
+        }
+    }
+}
+
+/// Resolve `\u`, `\h`, `\H`, `\w`, `\W`, `\$` and `\n` the way bash's
+/// `decode_prompt_string` does. Every other escape, including `\\`, is left
+/// intact for later stages.
+fn expand_prompt_escapes(raw: &str, values: &PromptEscapeValues) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(escape) = chars.next() else {
+            out.push(c);
+            break;
+        };
+        match escape {
+            'u' => out.push_str(&values.user),
+            'h' => out.push_str(values.hostname.split('.').next().unwrap_or_default()),
+            'H' => out.push_str(&values.hostname),
+            'w' => {
+                let dir = prompt_dir(&values.cwd, values.home.as_deref());
+                match values.dirtrim {
+                    Some(keep) => out.push_str(&trim_prompt_dir(&dir, keep)),
+                    None => out.push_str(&dir),
+                }
+            }
+            'W' => {
+                let dir = prompt_dir(&values.cwd, values.home.as_deref());
+                if dir == "~" || dir == "/" {
+                    out.push_str(&dir);
+                } else {
+                    out.push_str(current_folder_name(&dir).unwrap_or(&dir));
+                }
+            }
+            '$' => out.push(if values.is_root { '#' } else { '$' }),
+            'n' => out.push('\n'),
+            _ => {
+                out.push(c);
+                out.push(escape);
+            }
+        }
+    }
+    out
 }
 
 /// Builds expanded prompt segment lines from raw bash prompt strings while
@@ -305,10 +394,18 @@ struct PromptStringBuilder<'a> {
     cwd: Option<String>,
     /// Home directory, used to recognise `~`-prefixed path representations.
     home: Option<String>,
+    /// Number of trailing directories `\w` keeps, from `PROMPT_DIRTRIM`.
+    dirtrim: Option<usize>,
+    /// Accumulated map of placeholder → trimmed working directory text for
+    /// each `\w` replaced while `dirtrim` is set.
+    dir_map: HashMap<String, String>,
     /// Timestamp of the most recent flyline app session close.
     /// Passed through to [`PromptSegment::WidgetLastCommandDuration`] so that
     /// the elapsed duration can be computed at render time.
     last_app_closed_at: Option<std::time::Instant>,
+    /// Palette for widgets styled by the exit status or duration of the last
+    /// command.
+    palette: Palette,
 }
 
 impl<'a> PromptStringBuilder<'a> {
@@ -320,7 +417,10 @@ impl<'a> PromptStringBuilder<'a> {
             widgets,
             cwd: None,
             home: None,
+            dirtrim: None,
+            dir_map: HashMap::new(),
             last_app_closed_at: None,
+            palette: Palette::default(),
         }
     }
 
@@ -331,12 +431,24 @@ impl<'a> PromptStringBuilder<'a> {
         self
     }
 
+    /// Keep only the last `dirtrim` directories when expanding `\w`.
+    fn with_dirtrim(mut self, dirtrim: Option<usize>) -> Self {
+        self.dirtrim = dirtrim;
+        self
+    }
+
     /// Set the timestamp of the most recent flyline app session close.
     fn with_last_app_closed_at(mut self, t: Option<std::time::Instant>) -> Self {
         self.last_app_closed_at = t;
         self
     }
 
+    /// Set the palette used by the last command status and duration widgets.
+    fn with_palette(mut self, palette: &Palette) -> Self {
+        self.palette = palette.clone();
+        self
+    }
+
     /// Scan a raw bash prompt string and replace every time format escape
     /// sequence with a unique 8-character placeholder, recording the mapping
     /// in `self.time_map`.  Returns the modified string.
@@ -351,6 +463,9 @@ impl<'a> PromptStringBuilder<'a> {
     /// | `\@`         | 12-hour am/pm                  | `%I:%M %p`    |
     /// | `\A`         | 24-hour HH:MM                  | `%H:%M`       |
     /// | `\D{format}` | chrono format string (custom)  | `format`      |
+    ///
+    /// When `PROMPT_DIRTRIM` is set, `\w` is also replaced with a placeholder
+    /// for the trimmed working directory, recorded in `self.dir_map`.
     fn extract_time_codes(&mut self, s: &str) -> String {
         let mut result = String::with_capacity(s.len());
         let mut chars = s.chars().peekable();
@@ -422,6 +537,19 @@ impl<'a> PromptStringBuilder<'a> {
                         result.push('D');
                     }
                 }
+                Some('w') if self.dirtrim.is_some() && self.cwd.is_some() => {
+                    chars.next();
+                    let dir = trim_prompt_dir(
+                        &prompt_dir(
+                            self.cwd.as_deref().unwrap_or_default(),
+                            self.home.as_deref(),
+                        ),
+                        self.dirtrim.unwrap_or_default(),
+                    );
+                    let id = self.next_id();
+                    self.dir_map.insert(id.clone(), dir);
+                    result.push_str(&id);
+                }
                 _ => {
                     // Not a time code — pass the backslash through so
                     // `decode_prompt_string` can handle the sequence.
@@ -479,6 +607,22 @@ impl<'a> PromptStringBuilder<'a> {
     /// 3. Widget names → `PromptSegment::Widget*`.
     /// 4. CWD substrings → [`PromptSegment::Cwd`].
     fn expand_span_to_segments(&self, span: Span<'static>) -> Vec<PromptSegment> {
+        // Trimmed `\w` placeholders are plain text, so substitute them first
+        // and let the CWD pass pick them up.
+        let span = if self
+            .dir_map
+            .keys()
+            .any(|id| span.content.contains(id.as_str()))
+        {
+            let mut text = span.content.into_owned();
+            for (id, dir) in &self.dir_map {
+                text = text.replace(id.as_str(), dir);
+            }
+            Span::styled(text, span.style)
+        } else {
+            span
+        };
+
         // Pass 1: time placeholders.
         let style = span.style;
         let segs = split_span_by(span, |text| {
@@ -544,7 +688,7 @@ impl<'a> PromptStringBuilder<'a> {
                         (
                             pos,
                             len,
-                            make_widget_segment(widget, style, last_app_closed_at),
+                            make_widget_segment(widget, style, last_app_closed_at, &self.palette),
                         )
                     })
             })
@@ -636,6 +780,7 @@ fn make_widget_segment(
     widget: &PromptWidget,
     base_style: Style,
     last_app_closed_at: Option<std::time::Instant>,
+    palette: &Palette,
 ) -> PromptSegment {
     match widget {
         PromptWidget::MouseMode {
@@ -717,8 +862,51 @@ fn make_widget_segment(
             // render without further computation.
             let elapsed = last_app_closed_at.map(|t| t.elapsed()).unwrap_or_default();
             let text = crate::content_utils::format_duration(elapsed);
+            let base_style = match slow_command_threshold() {
+                Some(threshold) => base_style.patch(palette.duration_style(elapsed, threshold)),
+                None => base_style,
+            };
             PromptSegment::WidgetLastCommandDuration { text, base_style }
         }
+        PromptWidget::LastCommandStatus { .. } => {
+            let exit_code = bash_funcs::get_last_exit_code();
+            let elapsed = last_app_closed_at.map(|t| t.elapsed());
+            PromptSegment::WidgetLastCommandStatus {
+                text: format_last_command_status(exit_code, elapsed),
+                base_style: base_style.patch(palette.exit_status_style(exit_code)),
+            }
+        }
+    }
+}
+
+/// `✓ 0 1.2s` or `✗ 127 3ms`. The duration is left off before the first
+/// command has run.
+fn format_last_command_status(exit_code: i32, elapsed: Option<std::time::Duration>) -> String {
+    let mark = if exit_code == 0 { '✓' } else { '✗' };
+    match elapsed {
+        Some(elapsed) => format!(
+            "{} {} {}",
+            mark,
+            exit_code,
+            crate::content_utils::format_duration(elapsed)
+        ),
+        None => format!("{} {}", mark, exit_code),
+    }
+}
+
+/// Threshold from `FLYLINE_SLOW_MS` above which the last command duration is
+/// highlighted as slow. `None` when unset or not a number of milliseconds.
+fn slow_command_threshold() -> Option<std::time::Duration> {
+    parse_slow_ms(&bash_funcs::get_envvar_value("FLYLINE_SLOW_MS")?)
+}
+
+fn parse_slow_ms(value: &str) -> Option<std::time::Duration> {
+    match value.trim().parse::<u64>() {
+        Ok(ms) => Some(std::time::Duration::from_millis(ms)),
+        Err(e) => {
+            log::warn!("Ignoring invalid FLYLINE_SLOW_MS {:?}: {}", value, e);
+            None
+        }
     }
 }
 
@@ -804,6 +992,38 @@ fn cwd_representations(cwd: &str, home: Option<&str>) -> Vec<String> {
     representations
 }
 
+/// The working directory as bash's `\w` shows it, with `$HOME` abbreviated
+/// to `~`.
+fn prompt_dir(cwd: &str, home: Option<&str>) -> String {
+    cwd_representations(cwd, home)
+        .pop()
+        .unwrap_or_else(|| cwd.to_string())
+}
+
+/// Keep only the last `keep` directories of `dir`, replacing the rest with
+/// `…` like bash's `PROMPT_DIRTRIM`. A leading `~` is always kept.
+fn trim_prompt_dir(dir: &str, keep: usize) -> String {
+    let (prefix, rest) = match dir.strip_prefix("~/") {
+        Some(rest) => ("~/", rest),
+        None => ("", dir.trim_start_matches('/')),
+    };
+    let components: Vec<&str> = rest.split('/').filter(|c| !c.is_empty()).collect();
+    if keep == 0 || components.len() <= keep {
+        return dir.to_string();
+    }
+    format!(
+        "{}…/{}",
+        prefix,
+        components[components.len() - keep..].join("/")
+    )
+}
+
+/// Parse `PROMPT_DIRTRIM`. Like bash, anything other than a positive number
+/// disables trimming.
+fn parse_dirtrim(value: &str) -> Option<usize> {
+    value.trim().parse::<usize>().ok().filter(|n| *n > 0)
+}
+
 fn normalize_cwd_for_matching(path: &str) -> String {
     if path == "/" {
         "/".to_string()
@@ -1060,6 +1280,73 @@ fn advance_pending_widgets(segments: &mut [PromptSegment]) {
     }
 }
 
+/// Placeholder in `PS1` splitting a line into a left part and a right-justified
+/// part, with `PS1_FILL` repeated in the gap between them.
+const PS1_FILL_MARKER: &str = "FLYLINE_PS1_FILL";
+
+/// Split `line` at the first [`PS1_FILL_MARKER`], dropping the marker.
+/// Returns `None` if the line has no marker.
+fn split_at_fill_marker(
+    line: &TaggedLine<'static>,
+) -> Option<(TaggedLine<'static>, TaggedLine<'static>)> {
+    let (span_idx, byte_pos) = line
+        .spans
+        .iter()
+        .enumerate()
+        .find_map(|(idx, ts)| Some((idx, ts.span.content.find(PS1_FILL_MARKER)?)))?;
+
+    let tagged = &line.spans[span_idx];
+    let content = tagged.span.content.as_ref();
+    let before = &content[..byte_pos];
+    let after = &content[byte_pos + PS1_FILL_MARKER.len()..];
+    let (before_tag, after_tag) = match &tagged.tag {
+        SpanTag::Constant(tag) => (SpanTag::Constant(*tag), SpanTag::Constant(*tag)),
+        SpanTag::PerGrapheme(tags) => {
+            let before_len = before.graphemes(true).count();
+            let after_start = before_len + PS1_FILL_MARKER.graphemes(true).count();
+            (
+                SpanTag::PerGrapheme(tags.iter().take(before_len).copied().collect()),
+                SpanTag::PerGrapheme(tags.iter().skip(after_start).copied().collect()),
+            )
+        }
+    };
+
+    let piece = |text: &str, tag: SpanTag| TaggedSpan {
+        span: Span::styled(text.to_string(), tagged.span.style),
+        tag,
+    };
+    let mut left = line.spans[..span_idx].to_vec();
+    if !before.is_empty() {
+        left.push(piece(before, before_tag));
+    }
+    let mut right = vec![];
+    if !after.is_empty() {
+        right.push(piece(after, after_tag));
+    }
+    right.extend_from_slice(&line.spans[span_idx + 1..]);
+    Some((left.into(), right.into()))
+}
+
+/// For each `PS1` line containing [`PS1_FILL_MARKER`], move the text after the
+/// marker to the start of the matching right prompt line so it is drawn
+/// right-justified with the fill between.
+fn move_fill_marker_right_parts(
+    lprompt: &mut [TaggedLine<'static>],
+    rprompt: &mut Vec<TaggedLine<'static>>,
+) {
+    for (row, line) in lprompt.iter_mut().enumerate() {
+        let Some((left, mut right)) = split_at_fill_marker(line) else {
+            continue;
+        };
+        *line = left;
+        if rprompt.len() <= row {
+            rprompt.resize_with(row + 1, TaggedLine::default);
+        }
+        right.spans.append(&mut rprompt[row].spans);
+        rprompt[row] = right;
+    }
+}
+
 /// Convert a slice of [`PromptSegment`]s to a [`TaggedLine`] by resolving each
 /// segment against `now` and attaching an appropriate [`Tag`] to each span.
 ///
@@ -1129,7 +1416,8 @@ fn format_prompt_line(
                     tagged.clone()
                 }
                 PromptSegment::WidgetCopyBuffer { text } => text.clone(),
-                PromptSegment::WidgetLastCommandDuration { text, base_style } => {
+                PromptSegment::WidgetLastCommandDuration { text, base_style }
+                | PromptSegment::WidgetLastCommandStatus { text, base_style } => {
                     vec![TaggedSpan::new(
                         Span::styled(text.clone(), *base_style),
                         Tag::Ps1Prompt,
@@ -1296,6 +1584,7 @@ impl PromptManager {
         animations: &[PromptAnimation],
         widgets: &[PromptWidget],
         last_app_closed_at: Option<std::time::Instant>,
+        palette: &Palette,
     ) -> Self {
         if unfinished_from_prev_command {
             // If the previous command was unfinished, use a simple prompt to avoid confusion
@@ -1329,11 +1618,13 @@ impl PromptManager {
                 rprompt_final: None,
                 fill_span: vec![PromptSegment::Static(Span::raw(" "))],
                 fill_span_final: None,
+                ps2: None,
                 construction_time: chrono::Local::now(),
                 cwd: String::new(),
             }
         } else {
             const PS1_DEFAULT: &str = "bad ps1> ";
+            const PS2_DEFAULT: &str = "> ";
 
             // Process each animation frame through expand_prompt_through_bash
             // so frames are resolved to plain Spans only.
@@ -1373,7 +1664,13 @@ impl PromptManager {
             log::debug!("CWD for prompt detection: {:?}, HOME: {:?}", cwd, home);
             let mut builder = PromptStringBuilder::new(processed_animations, widgets)
                 .with_cwd(cwd.clone(), home)
-                .with_last_app_closed_at(last_app_closed_at);
+                .with_dirtrim(
+                    bash_funcs::get_envvar_value("PROMPT_DIRTRIM")
+                        .as_deref()
+                        .and_then(parse_dirtrim),
+                )
+                .with_last_app_closed_at(last_app_closed_at)
+                .with_palette(palette);
 
             // Read the raw PS1 env var so we can intercept time format codes
             // before handing the string to decode_prompt_string.  Fall back to
@@ -1427,6 +1724,11 @@ impl PromptManager {
                 }
             });
 
+            let ps2 = bash_funcs::get_envvar_value("PS2")
+                .filter(|raw| !raw.is_empty() && raw != PS2_DEFAULT)
+                .and_then(|raw| builder.expand_prompt_string(raw))
+                .and_then(|lines| lines.into_iter().next());
+
             PromptManager {
                 prompt: ps1,
                 prompt_final: ps1_final,
@@ -1434,6 +1736,7 @@ impl PromptManager {
                 rprompt_final: rps1_final,
                 fill_span,
                 fill_span_final,
+                ps2,
                 construction_time: chrono::Local::now(),
                 cwd,
             }
@@ -1475,7 +1778,7 @@ impl PromptManager {
             self.fill_span_final.as_mut().unwrap_or(&mut self.fill_span)
         };
 
-        let formatted_prompt: Vec<TaggedLine<'static>> = prompt_src
+        let mut formatted_prompt: Vec<TaggedLine<'static>> = prompt_src
             .iter_mut()
             .map(|line| {
                 advance_pending_widgets(line);
@@ -1483,7 +1786,7 @@ impl PromptManager {
             })
             .collect();
 
-        let formatted_rprompt: Vec<TaggedLine<'static>> = rprompt_src
+        let mut formatted_rprompt: Vec<TaggedLine<'static>> = rprompt_src
             .iter_mut()
             .map(|line| {
                 advance_pending_widgets(line);
@@ -1491,12 +1794,50 @@ impl PromptManager {
             })
             .collect();
 
+        move_fill_marker_right_parts(&mut formatted_prompt, &mut formatted_rprompt);
+
         advance_pending_widgets(fill_span_src);
         let formatted_fill = format_prompt_line(fill_span_src, &now, mouse_enabled);
 
         (formatted_prompt, formatted_rprompt, formatted_fill)
     }
 
+    /// The continuation prompt drawn at the start of each line after the
+    /// first, or `None` to show line numbers instead.
+    pub fn get_ps2_line(
+        &mut self,
+        show_animations: bool,
+        mouse_enabled: bool,
+    ) -> Option<TaggedLine<'static>> {
+        let now = if show_animations {
+            chrono::Local::now()
+        } else {
+            self.construction_time
+        };
+        let ps2 = self.ps2.as_mut()?;
+        advance_pending_widgets(ps2);
+        Some(format_prompt_line(ps2, &now, mouse_enabled))
+    }
+
+    /// Whether the live (non-final) prompt changes over time without user
+    /// input: a custom widget still running, or — when `show_animations` is
+    /// set — an animation or a dynamic time field.
+    pub fn needs_periodic_redraw(&self, show_animations: bool) -> bool {
+        self.prompt
+            .iter()
+            .chain(self.rprompt.iter())
+            .flatten()
+            .chain(self.fill_span.iter())
+            .any(|seg| match seg {
+                PromptSegment::WidgetCustom {
+                    state: WidgetCustomState::Pending { .. },
+                    ..
+                } => true,
+                PromptSegment::Animation(_) | PromptSegment::DynamicTime { .. } => show_animations,
+                _ => false,
+            })
+    }
+
     /// Return the number of CWD display segments in the left prompt.
     ///
     /// This is the count of *selectable* path spans tagged with
@@ -1794,6 +2135,63 @@ mod tests {
 
     // --- format_prompt_line (DynamicTime rendering) --------------------------
 
+    fn escape_values(cwd: &str, is_root: bool) -> PromptEscapeValues {
+        PromptEscapeValues {
+            user: "john".to_string(),
+            hostname: "box.example.com".to_string(),
+            cwd: cwd.to_string(),
+            home: Some("/home/john".to_string()),
+            dirtrim: None,
+            is_root,
+        }
+    }
+
+    #[test]
+    fn test_expand_prompt_escapes_collapses_home_in_cwd() {
+        let values = escape_values("/home/john/src/flyline", false);
+        assert_eq!(
+            expand_prompt_escapes("\\u@\\h:\\w \\W", &values),
+            "john@box:~/src/flyline flyline"
+        );
+        let at_home = escape_values("/home/john", false);
+        assert_eq!(expand_prompt_escapes("\\w|\\W", &at_home), "~|~");
+        let elsewhere = escape_values("/home/johnny/src", false);
+        assert_eq!(
+            expand_prompt_escapes("\\w|\\W", &elsewhere),
+            "/home/johnny/src|src"
+        );
+        let root_dir = escape_values("/", false);
+        assert_eq!(expand_prompt_escapes("\\w|\\W", &root_dir), "/|/");
+    }
+
+    #[test]
+    fn test_expand_prompt_escapes_dollar_for_root_and_non_root() {
+        assert_eq!(
+            expand_prompt_escapes("\\$ ", &escape_values("/", false)),
+            "$ "
+        );
+        assert_eq!(
+            expand_prompt_escapes("\\$ ", &escape_values("/", true)),
+            "# "
+        );
+    }
+
+    #[test]
+    fn test_expand_prompt_escapes_leaves_other_escapes_intact() {
+        let values = escape_values("/tmp", false);
+        assert_eq!(
+            expand_prompt_escapes("\\H\\n\\[\\e[1m\\]\\\\u\\t\\", &values),
+            "box.example.com\n\\[\\e[1m\\]\\\\u\\t\\"
+        );
+    }
+
+    #[test]
+    fn test_expand_prompt_escapes_respects_dirtrim() {
+        let mut values = escape_values("/home/john/a/b/c", false);
+        values.dirtrim = Some(2);
+        assert_eq!(expand_prompt_escapes("\\w", &values), "~/…/b/c");
+    }
+
     #[test]
     fn test_format_prompt_line_dynamic_time() {
         // Use a fixed time to produce a predictable formatted string.  The actual
@@ -2147,6 +2545,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trim_prompt_dir() {
+        assert_eq!(trim_prompt_dir("/usr/local/share/doc/pkg", 2), "…/doc/pkg");
+        assert_eq!(trim_prompt_dir("~/src/a/b/c", 3), "~/…/a/b/c");
+        assert_eq!(trim_prompt_dir("~/src/a", 3), "~/src/a");
+        assert_eq!(trim_prompt_dir("/", 1), "/");
+        assert_eq!(trim_prompt_dir("~", 1), "~");
+    }
+
+    #[test]
+    fn test_parse_dirtrim() {
+        assert_eq!(parse_dirtrim("3"), Some(3));
+        assert_eq!(parse_dirtrim("0"), None);
+        assert_eq!(parse_dirtrim("-1"), None);
+        assert_eq!(parse_dirtrim("abc"), None);
+    }
+
+    #[test]
+    fn test_extract_dirtrim_placeholder() {
+        let mut builder = PromptStringBuilder::new(vec![], &[])
+            .with_cwd(
+                "/home/foo/qwe/try/ooh/lkj".to_string(),
+                Some("/home/foo".to_string()),
+            )
+            .with_dirtrim(Some(2));
+        let modified = builder.extract_time_codes("\\u:\\w\\$ ");
+        assert_eq!(builder.dir_map.len(), 1);
+        let (id, dir) = builder.dir_map.iter().next().unwrap();
+        assert_eq!(dir, "~/…/ooh/lkj");
+        assert_eq!(modified, format!("\\u:{}\\$ ", id));
+
+        let segs = builder.expand_span_to_segments(Span::raw(format!("me:{}$ ", id)));
+        let text: String = segs
+            .iter()
+            .map(|seg| match seg {
+                PromptSegment::Static(s) => s.content.to_string(),
+                PromptSegment::Cwd(spans) => spans.iter().map(|s| s.content.as_ref()).collect(),
+                _ => panic!("unexpected segment"),
+            })
+            .collect();
+        assert_eq!(text, "me:~/…/ooh/lkj$ ");
+        assert!(segs.iter().any(|s| matches!(s, PromptSegment::Cwd(_))));
+    }
+
     // #[test]
     // fn test_expand_span_cwd_partial_no_tilde() {
     //     let builder = PromptStringBuilder::new(vec![], &[]).with_cwd(
@@ -2308,6 +2750,7 @@ mod tests {
             rprompt_final: None,
             fill_span: vec![],
             fill_span_final: None,
+            ps2: None,
             construction_time: chrono::Local::now(),
             cwd: cwd.to_string(),
         }
@@ -2322,6 +2765,7 @@ mod tests {
             rprompt_final: None,
             fill_span: vec![],
             fill_span_final: None,
+            ps2: None,
             construction_time: chrono::Local::now(),
             cwd: String::new(),
         };
@@ -2698,6 +3142,47 @@ mod tests {
         assert_eq!(line.spans[0].span.style.fg, Some(Color::Cyan));
     }
 
+    // --- WidgetLastCommandStatus --------------------------------------------
+
+    #[test]
+    fn test_format_last_command_status() {
+        use std::time::Duration;
+        assert_eq!(
+            format_last_command_status(0, Some(Duration::from_millis(1200))),
+            "✓ 0 1.200s"
+        );
+        assert_eq!(
+            format_last_command_status(127, Some(Duration::from_millis(3))),
+            "✗ 127 3ms"
+        );
+        assert_eq!(format_last_command_status(1, None), "✗ 1");
+    }
+
+    #[test]
+    fn test_last_command_status_widget_styles_by_exit_code() {
+        // The test build reports an exit code of 0.
+        let widget = PromptWidget::LastCommandStatus {
+            name: "FLYLINE_LAST_COMMAND_STATUS".to_string(),
+        };
+        let seg = make_widget_segment(&widget, Style::default(), None, &Palette::default());
+        let PromptSegment::WidgetLastCommandStatus { text, base_style } = seg else {
+            panic!("expected WidgetLastCommandStatus");
+        };
+        assert_eq!(text, "✓ 0");
+        assert_eq!(base_style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_parse_slow_ms() {
+        assert_eq!(
+            parse_slow_ms("750"),
+            Some(std::time::Duration::from_millis(750))
+        );
+        assert_eq!(parse_slow_ms(" 0 "), Some(std::time::Duration::ZERO));
+        assert_eq!(parse_slow_ms("slow"), None);
+        assert_eq!(parse_slow_ms("-5"), None);
+    }
+
     #[test]
     fn test_expand_span_widget_last_command_duration_name() {
         // The widget name in a span should produce a WidgetLastCommandDuration segment
@@ -2740,4 +3225,90 @@ mod tests {
             _ => panic!("expected Static at 2"),
         }
     }
+
+    fn line_text(line: &TaggedLine) -> String {
+        line.spans
+            .iter()
+            .map(|ts| ts.span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_at_fill_marker() {
+        let line = TaggedLine::from(vec![
+            TaggedSpan::new(Span::raw("user "), Tag::Ps1Prompt),
+            TaggedSpan::new(Span::raw("~/srcFLYLINE_PS1_FILL(main)"), Tag::Ps1Prompt),
+            TaggedSpan::new(Span::raw(" 12:00"), Tag::Ps1Prompt),
+        ]);
+        let (left, right) = split_at_fill_marker(&line).unwrap();
+        assert_eq!(line_text(&left), "user ~/src");
+        assert_eq!(line_text(&right), "(main) 12:00");
+        assert_eq!(left.spans.len(), 2);
+        assert_eq!(right.spans.len(), 2);
+
+        let (left, right) = split_at_fill_marker(&TaggedLine::from_line(
+            Line::from("FLYLINE_PS1_FILL"),
+            Tag::Ps1Prompt,
+        ))
+        .unwrap();
+        assert!(left.spans.is_empty());
+        assert!(right.spans.is_empty());
+
+        assert!(
+            split_at_fill_marker(&TaggedLine::from_line(Line::from("$ "), Tag::Ps1Prompt))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_split_at_fill_marker_keeps_per_grapheme_tags() {
+        let text = format!("ab{}c", PS1_FILL_MARKER);
+        let mut tags = vec![Tag::Ps1PromptCwdWidget(0), Tag::Ps1PromptCwdWidget(1)];
+        tags.extend(std::iter::repeat_n(Tag::Ps1Prompt, PS1_FILL_MARKER.len()));
+        tags.push(Tag::Ps1PromptCwdWidget(2));
+        let line = TaggedLine::from(TaggedSpan {
+            span: Span::raw(text),
+            tag: SpanTag::PerGrapheme(tags),
+        });
+        let (left, right) = split_at_fill_marker(&line).unwrap();
+        assert_eq!(
+            left.spans[0].tag,
+            SpanTag::PerGrapheme(vec![Tag::Ps1PromptCwdWidget(0), Tag::Ps1PromptCwdWidget(1)])
+        );
+        assert_eq!(
+            right.spans[0].tag,
+            SpanTag::PerGrapheme(vec![Tag::Ps1PromptCwdWidget(2)])
+        );
+    }
+
+    #[test]
+    fn test_fill_marker_right_part_is_right_justified() {
+        let mut lprompt = vec![
+            TaggedLine::from_line(Line::from("~/srcFLYLINE_PS1_FILL(main)"), Tag::Ps1Prompt),
+            TaggedLine::from_line(Line::from("$ "), Tag::Ps1Prompt),
+        ];
+        let mut rprompt = vec![TaggedLine::from_line(Line::from(" 12:00"), Tag::Ps1Prompt)];
+        move_fill_marker_right_parts(&mut lprompt, &mut rprompt);
+        assert_eq!(line_text(&lprompt[0]), "~/src");
+        assert_eq!(line_text(&rprompt[0]), "(main) 12:00");
+
+        let mut contents = crate::content_builder::Contents::new(20);
+        contents.write_tagged_line_lrjustified(
+            &lprompt[0],
+            &TaggedLine::from_line(Line::from("-"), Tag::Ps1Prompt),
+            &rprompt[0],
+            false,
+        );
+        assert_eq!(contents.get_buffer_lines(), vec!["~/src---(main) 12:00"]);
+
+        // A marker on a row without a right prompt gets one.
+        let mut lprompt = vec![TaggedLine::from_line(
+            Line::from("aFLYLINE_PS1_FILLb"),
+            Tag::Ps1Prompt,
+        )];
+        let mut rprompt = vec![];
+        move_fill_marker_right_parts(&mut lprompt, &mut rprompt);
+        assert_eq!(rprompt.len(), 1);
+        assert_eq!(line_text(&rprompt[0]), "b");
+    }
 }
@@ -1,10 +1,13 @@
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 use crate::app::actions;
 use crate::content_builder::TaggedSpan;
 use crate::cursor::CursorConfig;
 use crate::history::HistoryManager;
 use crate::palette::Palette;
+use crate::snake_animation::AnimationKind;
+use crate::suggestion_source::SuggestionSource;
 use crate::tutorial::TutorialStep;
 use clap::ValueEnum;
 
@@ -50,6 +53,173 @@ pub enum FuzzyMode {
     FolderPrefixes,
 }
 
+/// What to leave after an accepted completion.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+pub enum CompletionTrailing {
+    /// A space after files, a `/` after directories.
+    #[default]
+    #[value(name = "space")]
+    #[serde(rename = "space")]
+    Space,
+    /// Nothing after files or directories.
+    #[value(name = "none")]
+    #[serde(rename = "none")]
+    None,
+    /// A `/` after directories but no space after files.
+    #[value(name = "slash-only")]
+    #[serde(rename = "slash-only")]
+    SlashOnly,
+}
+
+impl CompletionTrailing {
+    /// Read `$FLYLINE_COMPLETION_TRAILING` (`space`, `none` or `slash-only`).
+    /// Unset or unknown values keep the default.
+    pub fn load_at_startup() -> Self {
+        let Some(value) = crate::bash_funcs::get_envvar_value("FLYLINE_COMPLETION_TRAILING") else {
+            return Self::default();
+        };
+        Self::from_str(value.trim(), true).unwrap_or_else(|e| {
+            log::warn!("Invalid FLYLINE_COMPLETION_TRAILING {:?}: {}", value, e);
+            Self::default()
+        })
+    }
+}
+
+/// How first-word completion compares the typed prefix with command names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CompletionCase {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Case-insensitive unless the typed prefix contains an uppercase letter.
+    Smart,
+}
+
+impl CompletionCase {
+    /// Read `$FLYLINE_COMPLETION_CASE` (`sensitive`, `insensitive` or
+    /// `smart`). Unset or unknown values keep the default.
+    pub fn load_at_startup() -> Self {
+        let Some(value) = crate::bash_funcs::get_envvar_value("FLYLINE_COMPLETION_CASE") else {
+            return Self::default();
+        };
+        Self::from_str(value.trim(), true).unwrap_or_else(|e| {
+            log::warn!("Invalid FLYLINE_COMPLETION_CASE {:?}: {}", value, e);
+            Self::default()
+        })
+    }
+
+    /// Whether `candidate` starts with the `typed` prefix under this mode.
+    pub fn matches_prefix(self, candidate: &str, typed: &str) -> bool {
+        let ignore_case = match self {
+            CompletionCase::Sensitive => false,
+            CompletionCase::Insensitive => true,
+            CompletionCase::Smart => !typed.chars().any(char::is_uppercase),
+        };
+        if ignore_case {
+            candidate.to_lowercase().starts_with(&typed.to_lowercase())
+        } else {
+            candidate.starts_with(typed)
+        }
+    }
+}
+
+/// How long without input before the prompt is dimmed, from
+/// `$FLYLINE_IDLE_DIM_SECS`. Unset, `0` or invalid values disable dimming.
+pub fn load_idle_dim_after_at_startup() -> Option<std::time::Duration> {
+    let value = crate::bash_funcs::get_envvar_value("FLYLINE_IDLE_DIM_SECS")?;
+    match value.trim().parse::<u64>() {
+        Ok(0) => None,
+        Ok(secs) => Some(std::time::Duration::from_secs(secs)),
+        Err(e) => {
+            log::warn!("Invalid FLYLINE_IDLE_DIM_SECS {:?}: {}", value, e);
+            None
+        }
+    }
+}
+
+/// How many glob matches filename completion offers by default.
+pub const DEFAULT_MAX_GLOB_RESULTS: usize = 10_000;
+
+/// How many glob matches to offer, from `$FLYLINE_MAX_GLOB_RESULTS`. Unset,
+/// `0` or invalid values keep the default.
+pub fn load_max_glob_results_at_startup() -> usize {
+    let Some(value) = crate::bash_funcs::get_envvar_value("FLYLINE_MAX_GLOB_RESULTS") else {
+        return DEFAULT_MAX_GLOB_RESULTS;
+    };
+    match value.trim().parse::<usize>() {
+        Ok(max) if max > 0 => max,
+        _ => {
+            log::warn!("Invalid FLYLINE_MAX_GLOB_RESULTS {:?}", value);
+            DEFAULT_MAX_GLOB_RESULTS
+        }
+    }
+}
+
+/// Parse `FLYLINE_GETCOMMAND_TIMEOUT` (seconds, fractions allowed).  Unset,
+/// non-positive, or malformed values mean no timeout.
+fn parse_get_command_timeout(value: &str) -> Option<std::time::Duration> {
+    let secs: f64 = value.trim().parse().ok()?;
+    (secs.is_finite() && secs > 0.0).then(|| std::time::Duration::from_secs_f64(secs))
+}
+
+/// How long `get_command` waits for input before handing bash an empty
+/// command, from `$FLYLINE_GETCOMMAND_TIMEOUT`.
+pub fn load_get_command_timeout_at_startup() -> Option<std::time::Duration> {
+    crate::bash_funcs::get_envvar_value("FLYLINE_GETCOMMAND_TIMEOUT")
+        .and_then(|v| parse_get_command_timeout(&v))
+}
+
+/// Row limit for the tab-completion grid from `$FLYLINE_MAX_SUGGESTION_ROWS`.
+/// `None` when unset, `0` or invalid.
+pub fn load_max_suggestion_rows_at_startup() -> Option<u16> {
+    let value = crate::bash_funcs::get_envvar_value("FLYLINE_MAX_SUGGESTION_ROWS")?;
+    match value.trim().parse::<u16>() {
+        Ok(0) => None,
+        Ok(rows) => Some(rows),
+        Err(e) => {
+            log::warn!("Invalid FLYLINE_MAX_SUGGESTION_ROWS {:?}: {}", value, e);
+            None
+        }
+    }
+}
+
+/// `Some(true)` when the environment variable `name` is `1`, `Some(false)`
+/// when it is set to anything else, `None` when it is unset.
+fn env_flag(name: &str) -> Option<bool> {
+    crate::bash_funcs::get_envvar_value(name).map(|v| v.trim() == "1")
+}
+
+/// Whether `$FLYLINE_SHOW_TRAILING_WS=1` asks for trailing whitespace to be
+/// highlighted.
+pub fn load_show_trailing_whitespace_at_startup() -> bool {
+    crate::bash_funcs::get_envvar_value("FLYLINE_SHOW_TRAILING_WS").is_some_and(|v| v.trim() == "1")
+}
+
+/// Whether `$FLYLINE_BIDI=1` asks for right-to-left text to be drawn in
+/// visual order. Off by default: terminals that do their own BiDi (VTE,
+/// Konsole, mlterm) would reorder the line a second time.
+pub fn load_reorder_bidi_at_startup() -> bool {
+    crate::bash_funcs::get_envvar_value("FLYLINE_BIDI").is_some_and(|v| v.trim() == "1")
+}
+
+/// Prefix marking history lines as comments to leave out of the loaded
+/// history, from `$FLYLINE_HISTORY_COMMENT_PREFIX`. Unset keeps every line,
+/// including `#` commands submitted with comment-line-submit.
+pub fn load_history_comment_prefix_at_startup() -> Option<String> {
+    crate::bash_funcs::get_envvar_value("FLYLINE_HISTORY_COMMENT_PREFIX")
+        .map(|prefix| prefix.trim().to_string())
+        .filter(|prefix| !prefix.is_empty())
+}
+
+/// Characters other than alphanumerics that count as part of a word for
+/// word movement and Ctrl-W, from `$FLYLINE_WORDCHARS`. Unset keeps words
+/// split at whitespace.
+pub fn load_word_chars_at_startup() -> Option<String> {
+    crate::bash_funcs::get_envvar_value("FLYLINE_WORDCHARS")
+}
+
 /// A single custom prompt animation registered with `flyline create-prompt-widget animation`.
 #[derive(Debug, Clone)]
 pub struct PromptAnimation {
@@ -95,6 +265,12 @@ pub enum PromptWidget {
         /// Name used as placeholder in prompt strings (e.g., `FLYLINE_LAST_COMMAND_DURATION`).
         name: String,
     },
+    /// Shows whether the previous command succeeded, its exit code and how
+    /// long ago the flyline app last closed, e.g. `✓ 0 1.2s` or `✗ 127 3ms`.
+    LastCommandStatus {
+        /// Name used as placeholder in prompt strings (e.g., `FLYLINE_LAST_COMMAND_STATUS`).
+        name: String,
+    },
 }
 
 impl PromptWidget {
@@ -105,6 +281,7 @@ impl PromptWidget {
             PromptWidget::CopyBuffer { name, .. } => name,
             PromptWidget::Custom(w) => &w.name,
             PromptWidget::LastCommandDuration { name } => name,
+            PromptWidget::LastCommandStatus { name } => name,
         }
     }
 }
@@ -199,12 +376,21 @@ pub struct Settings {
     /// When `Some`, Zsh history is loaded in addition to Bash history; an empty string or no
     /// value means use the default path (`$HOME/.zsh_history`).
     pub zsh_history_path: Option<String>,
+    /// History lines starting with this prefix are left out of the loaded
+    /// history. `None` keeps them all.
+    pub history_comment_prefix: Option<String>,
+    /// Whether inline history suggestions prefer commands previously run in
+    /// the current directory.
+    pub history_prefer_cwd: bool,
     /// Whether the interactive tutorial is active.
     pub run_tutorial: bool,
     /// Current tutorial step.
     pub tutorial_step: TutorialStep,
     /// Whether to show all animations (cursor movement, cursor fading, dynamic time).
     pub show_animations: bool,
+    /// Animations are off for this shell regardless of `show_animations`
+    /// (`FLYLINE_NO_ANIMATIONS=1`), e.g. when connected over a slow SSH link.
+    pub no_animations: bool,
     /// Whether to show inline history suggestions.
     pub show_inline_history: bool,
     /// Whether to auto-start tab completion suggestions as you type.
@@ -218,14 +404,35 @@ pub struct Settings {
     pub suggestion_sort_order: SuggestionSortOrder,
     /// Controls fuzzy matching behavior for suggestions.
     pub fuzzy_mode: FuzzyMode,
+    /// What to leave after an accepted completion.
+    pub completion_trailing: CompletionTrailing,
+    /// Most glob matches filename completion offers.
+    pub max_glob_results: usize,
+    /// Whether options are scraped from `--help` for commands without a
+    /// compspec. Off by default since `--help` has side effects for some tools.
+    pub help_completion: bool,
+    /// How first-word completion matches case.
+    pub completion_case: CompletionCase,
+    /// Whether programmable completion results are reused for an identical
+    /// request instead of running the compspec again.
+    pub completion_cache: bool,
     /// Maximum number of suggestion rows to render for tab-completion lists.
     pub num_suggestion_rows: u16,
+    /// Whether a lone tab-completion candidate is shown in the menu for the
+    /// user to confirm instead of being accepted immediately.
+    pub confirm_single_completion: bool,
     /// Whether to automatically close opening characters (e.g., parentheses, brackets, quotes).
     pub auto_close_chars: bool,
     /// Whether mouse clicks and drags on the command buffer change the cursor
     /// position and selection. When `false`, mouse interaction with the buffer
     /// does not change the buffer selection or cursor position.
     pub select_with_mouse: bool,
+    /// Whether to strip trailing whitespace from each line of a command when it
+    /// is submitted. Whitespace inside quoted strings and heredocs is kept.
+    pub trim_on_submit: bool,
+    /// Whether command tooltips also show the first line of a shell
+    /// function's definition.
+    pub show_command_preview: bool,
     /// Cursor appearance and animation settings (set via `flyline set-cursor`).
     pub cursor_config: CursorConfig,
     /// Mouse capture mode.
@@ -241,6 +448,15 @@ pub struct Settings {
     pub custom_prompt_widgets: HashMap<String, PromptWidget>,
     /// Run matrix animation in the terminal background.
     pub matrix_animation: MatrixAnimation,
+    /// Draw the prompt and buffer dimmed after this long without input.
+    pub idle_dim_after: Option<std::time::Duration>,
+    /// Hand bash an empty command after this long without input.
+    pub get_command_timeout: Option<std::time::Duration>,
+    /// Characters besides alphanumerics that are part of a word, if set.
+    pub word_chars: Option<String>,
+    /// Animations drawn over command words, keyed by the prefix that
+    /// triggers them (e.g. `python`).
+    pub command_word_animations: HashMap<String, AnimationKind>,
     /// Render frame rate in frames per second (1–120).
     pub frame_rate: u8,
     /// Shell integration escape codes level (OSC 133 / OSC 633).
@@ -256,8 +472,21 @@ pub struct Settings {
     pub colour_palette: Palette,
     /// User defined keybindings
     pub keybindings: Vec<actions::Binding>,
+    /// How many of the first `keybindings` came from the keymap file.
+    pub keymap_file_bindings: usize,
     /// User defined key remappings (applied before matching bindings).
     pub key_remappings: Vec<actions::KeyRemap>,
+    /// Abbreviations expanded when typed as the first word, e.g. `gco` to
+    /// `git checkout`.
+    pub abbreviations: HashMap<String, String>,
+    /// Where inline suggestions come from, in priority order.
+    pub suggestion_sources: Vec<SuggestionSource>,
+    /// Show the history index and age after the inline history suggestion.
+    pub show_suggestion_meta: bool,
+    /// Highlight whitespace at the end of lines in the buffer.
+    pub show_trailing_whitespace: bool,
+    /// Draw lines holding right-to-left text in visual order.
+    pub reorder_bidi: bool,
     /// Show the last key event and dispatched action above the prompt.
     pub key_debug: bool,
     /// Show the last mouse event above the prompt.
@@ -274,46 +503,141 @@ pub struct Settings {
     /// call returns. Used by the `last-command-duration` prompt widget to
     /// compute and display the elapsed time since the last command.
     pub last_app_closed_at: Option<std::time::Instant>,
+    /// Whether flyline already history expanded the submitted command, so
+    /// bash must not expand it a second time.
+    pub history_expanded: bool,
     /// Initial buffer content to pre-fill the command line when Flyline starts.
     pub initial_buffer: Option<String>,
+    /// Directory each command submitted this session was run from, keyed by
+    /// the command with trailing whitespace trimmed.
+    pub command_cwds: HashMap<String, PathBuf>,
+}
+
+impl Settings {
+    /// Load the settings that come from environment variables and config
+    /// files. Runs at startup and again on reload; settings changed with
+    /// `flyline` commands since are kept.
+    pub fn load_config(&mut self) {
+        self.colour_palette.load_theme_file();
+        let keymap = actions::load_keymap_file();
+        let keymap_len = keymap.len();
+        self.keybindings.splice(..self.keymap_file_bindings, keymap);
+        self.keymap_file_bindings = keymap_len;
+        self.abbreviations = crate::abbreviations::load_at_startup();
+        self.suggestion_sources = crate::suggestion_source::load_at_startup();
+        self.show_trailing_whitespace = load_show_trailing_whitespace_at_startup();
+        self.reorder_bidi = load_reorder_bidi_at_startup();
+        self.completion_trailing = CompletionTrailing::load_at_startup();
+        self.completion_case = CompletionCase::load_at_startup();
+        if let Some(enabled) = env_flag("FLYLINE_COMPLETION_CACHE") {
+            self.completion_cache = enabled;
+        }
+        self.cursor_config.load_env();
+        self.idle_dim_after = load_idle_dim_after_at_startup();
+        self.get_command_timeout = load_get_command_timeout_at_startup();
+        self.max_glob_results = load_max_glob_results_at_startup();
+        self.no_animations = env_flag("FLYLINE_NO_ANIMATIONS").unwrap_or(false);
+        if let Some(enabled) = env_flag("FLYLINE_HELP_COMPLETION") {
+            self.help_completion = enabled;
+        }
+        self.word_chars = load_word_chars_at_startup();
+        self.history_comment_prefix = load_history_comment_prefix_at_startup();
+        if let Some(enabled) = env_flag("FLYLINE_TRIM_ON_SUBMIT") {
+            self.trim_on_submit = enabled;
+        }
+        if let Some(enabled) = env_flag("FLYLINE_CONFIRM_SINGLE_COMPLETION") {
+            self.confirm_single_completion = enabled;
+        }
+        if let Some(enabled) = env_flag("FLYLINE_SUGGEST_META") {
+            self.show_suggestion_meta = enabled;
+        }
+        if let Some(enabled) = env_flag("FLYLINE_HIST_CWD") {
+            self.history_prefer_cwd = enabled;
+        }
+        if let Some(rows) = load_max_suggestion_rows_at_startup() {
+            self.num_suggestion_rows = rows;
+        }
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             zsh_history_path: None,
+            history_comment_prefix: None,
+            history_prefer_cwd: false,
             run_tutorial: false,
             tutorial_step: TutorialStep::default(),
             show_animations: true,
+            no_animations: false,
             auto_suggest: true,
             use_flycomp: true,
             flycomp_output: None,
             suggestion_sort_order: SuggestionSortOrder::default(),
             fuzzy_mode: FuzzyMode::default(),
-            num_suggestion_rows: 15,
+            completion_trailing: CompletionTrailing::default(),
+            max_glob_results: DEFAULT_MAX_GLOB_RESULTS,
+            help_completion: false,
+            completion_case: CompletionCase::default(),
+            completion_cache: true,
+            num_suggestion_rows: 8,
+            confirm_single_completion: false,
             show_inline_history: true,
             auto_close_chars: true,
             select_with_mouse: true,
+            trim_on_submit: false,
+            show_command_preview: false,
             cursor_config: CursorConfig::default(),
             mouse_mode: MouseMode::default(),
             agent_commands: HashMap::default(),
             custom_animations: HashMap::default(),
             custom_prompt_widgets: HashMap::default(),
             matrix_animation: MatrixAnimation::default(),
+            idle_dim_after: None,
+            get_command_timeout: None,
+            word_chars: None,
+            command_word_animations: HashMap::from([("python".to_string(), AnimationKind::Snake)]),
             frame_rate: 24,
             send_shell_integration_codes: ShellIntegrationLevel::default(),
             enable_extended_key_codes: true,
             flycomp_blacklist: HashSet::default(),
             colour_palette: Palette::default(),
             keybindings: Vec::default(),
+            keymap_file_bindings: 0,
             key_remappings: Vec::default(),
+            abbreviations: HashMap::default(),
+            suggestion_sources: vec![SuggestionSource::History],
+            show_suggestion_meta: true,
+            show_trailing_whitespace: false,
+            reorder_bidi: false,
             key_debug: false,
             mouse_debug: false,
             mouse_change_shape: true,
             cancelled_command_history_manager: HistoryManager::new_empty(),
             agent_prompt_history_manager: HistoryManager::new_empty(),
             last_app_closed_at: None,
+            history_expanded: false,
             initial_buffer: None,
+            command_cwds: HashMap::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn get_command_timeout_parsing() {
+        assert_eq!(parse_get_command_timeout("5"), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_get_command_timeout(" 0.5 "),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(parse_get_command_timeout("0"), None);
+        assert_eq!(parse_get_command_timeout("-1"), None);
+        assert_eq!(parse_get_command_timeout("inf"), None);
+        assert_eq!(parse_get_command_timeout("soon"), None);
+    }
+}
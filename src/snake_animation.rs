@@ -1,7 +1,50 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::unicode_helpers::{BRAILLE_BLANK, OctantStyle, octant_from_grid};
 
+/// Animations that can be drawn over a command word, e.g. the snake over `python`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationKind {
+    /// A braille snake slithering through the word.
+    Snake,
+    /// A braille spinner turning over the first character of the word.
+    Spinner,
+}
+
+impl AnimationKind {
+    pub fn new_animation(self) -> Box<dyn GlyphAnimation> {
+        match self {
+            AnimationKind::Snake => Box::new(SnakeAnimation::new()),
+            AnimationKind::Spinner => Box::new(SpinnerAnimation::new()),
+        }
+    }
+}
+
+/// A per-column glyph stream drawn over the characters of a word.
+pub trait GlyphAnimation: Send {
+    /// Advance the animation to `now`.
+    fn update_anim(&mut self, now: Instant);
+
+    /// The glyph for each column. [`BRAILLE_BLANK`] leaves the original
+    /// character visible.
+    fn glyphs(&self) -> Vec<char>;
+
+    fn apply_to_string(&self, s: &str) -> String {
+        let glyphs = self.glyphs();
+
+        s.chars()
+            .enumerate()
+            .map(|(i, original_char)| {
+                glyphs
+                    .get(i)
+                    .filter(|&&glyph| glyph != BRAILLE_BLANK)
+                    .unwrap_or(&original_char)
+                    .to_owned()
+            })
+            .collect()
+    }
+}
+
 struct Coord {
     x: usize,
     y: usize,
@@ -26,21 +69,6 @@ impl SnakeAnimation {
         snake
     }
 
-    pub fn apply_to_string(&self, s: &str) -> String {
-        let snake_chars: Vec<char> = self.to_string().chars().collect();
-
-        s.chars()
-            .enumerate()
-            .map(|(i, original_char)| {
-                snake_chars
-                    .get(i)
-                    .filter(|&&snake_char| snake_char != BRAILLE_BLANK)
-                    .unwrap_or(&original_char)
-                    .to_owned()
-            })
-            .collect()
-    }
-
     const MAX_X: usize = 12;
     const MAX_Y: usize = 4;
 
@@ -91,7 +119,7 @@ impl SnakeAnimation {
         }
     }
 
-    pub fn update_anim(&mut self, now: Instant) {
+    fn advance(&mut self, now: Instant) {
         let elapsed_since_last = now.duration_since(self.last_update_time).as_secs_f32();
 
         // Calculate how many steps should have occurred (120ms per step)
@@ -134,10 +162,106 @@ impl SnakeAnimation {
     }
 }
 
+impl GlyphAnimation for SnakeAnimation {
+    fn update_anim(&mut self, now: Instant) {
+        self.advance(now);
+    }
+
+    fn glyphs(&self) -> Vec<char> {
+        self.to_string().chars().collect()
+    }
+}
+
+/// A single braille glyph cycling in place, e.g. while waiting for slow
+/// completions.
+#[derive(Debug)]
+pub struct SpinnerAnimation {
+    frame_idx: usize,
+    last_update_time: Instant,
+}
+
+impl SpinnerAnimation {
+    const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    const FRAME_DURATION: Duration = Duration::from_millis(80);
+
+    pub fn new() -> Self {
+        SpinnerAnimation {
+            frame_idx: 0,
+            last_update_time: Instant::now(),
+        }
+    }
+
+    pub fn frame(&self) -> char {
+        Self::FRAMES[self.frame_idx]
+    }
+}
+
+impl GlyphAnimation for SpinnerAnimation {
+    fn update_anim(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update_time);
+        let steps = (elapsed.as_millis() / Self::FRAME_DURATION.as_millis()) as usize;
+        if steps > 0 {
+            self.frame_idx = (self.frame_idx + steps) % Self::FRAMES.len();
+            self.last_update_time = now;
+        }
+    }
+
+    fn glyphs(&self) -> Vec<char> {
+        vec![self.frame()]
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::GlyphAnimation;
     use crate::unicode_helpers::{OctantDots, OctantStyle, octant};
 
+    #[test]
+    fn test_snake_keeps_characters_under_blank_columns() {
+        let snake = super::SnakeAnimation::new();
+        let glyphs = snake.glyphs();
+        let animated = snake.apply_to_string("python3 -m http.server");
+        assert_eq!(
+            animated.chars().count(),
+            "python3 -m http.server".chars().count()
+        );
+        for ((glyph, original), animated) in glyphs
+            .iter()
+            .chain(std::iter::repeat(&super::BRAILLE_BLANK))
+            .zip("python3 -m http.server".chars())
+            .zip(animated.chars())
+        {
+            let expected = if *glyph == super::BRAILLE_BLANK {
+                original
+            } else {
+                *glyph
+            };
+            assert_eq!(animated, expected);
+        }
+    }
+
+    #[test]
+    fn test_spinner_advances_one_frame_per_tick() {
+        let mut spinner = super::SpinnerAnimation::new();
+        let start = spinner.last_update_time;
+        let tick = super::SpinnerAnimation::FRAME_DURATION;
+        let first = spinner.frame();
+
+        // Too soon for the next frame.
+        spinner.update_anim(start + tick / 2);
+        assert_eq!(spinner.frame(), first);
+
+        let mut seen = vec![first];
+        for i in 1..=super::SpinnerAnimation::FRAMES.len() as u32 {
+            spinner.update_anim(start + tick * i);
+            seen.push(spinner.frame());
+        }
+        assert_eq!(&seen[..10], &super::SpinnerAnimation::FRAMES);
+        // Wraps back to the first frame.
+        assert_eq!(seen[10], first);
+        assert_eq!(spinner.apply_to_string("git"), format!("{}it", seen[10]));
+    }
+
     #[test]
     fn test_braille_top_row() {
         // TOP_LEFT + TOP_RIGHT → braille char '⠉' (DOT_1 + DOT_4)
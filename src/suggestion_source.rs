@@ -0,0 +1,329 @@
+use crate::history::{HistoryEntry, HistoryManager};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long the last word must stay unchanged before files are matched against it.
+const FILE_MATCH_DEBOUNCE: Duration = Duration::from_millis(120);
+
+/// Where an inline suggestion can come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionSource {
+    /// The most recent history entry starting with the buffer.
+    History,
+    /// A command name completing the first word, or the only file matching
+    /// the last word.
+    Completion,
+}
+
+/// The order to try suggestion sources in, from `$FLYLINE_SUGGESTION_SOURCES`
+/// (e.g. `history,completion`). Defaults to history only.
+pub fn load_at_startup() -> Vec<SuggestionSource> {
+    match crate::bash_funcs::get_envvar_value("FLYLINE_SUGGESTION_SOURCES") {
+        Some(value) => parse_sources(&value),
+        None => vec![SuggestionSource::History],
+    }
+}
+
+/// Parse a comma separated list of sources. Unknown names are logged and
+/// skipped, as are repeats.
+fn parse_sources(value: &str) -> Vec<SuggestionSource> {
+    let mut sources = vec![];
+    for name in value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let source = match name.to_ascii_lowercase().as_str() {
+            "history" => SuggestionSource::History,
+            "completion" => SuggestionSource::Completion,
+            _ => {
+                log::warn!(
+                    "Unknown suggestion source {:?} in FLYLINE_SUGGESTION_SOURCES",
+                    name
+                );
+                continue;
+            }
+        };
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    sources
+}
+
+/// Debounces matching files against the word being typed, so typing a path
+/// does not glob the directory on every keypress. Until the word has been
+/// stable for [`FILE_MATCH_DEBOUNCE`] it has no file suggestion; the inline
+/// suggestion is recomputed when a match finishes.
+#[derive(Debug, Default)]
+pub struct FileMatchDebouncer {
+    /// The word waiting to be matched and when it was last requested.
+    pending: Option<(String, Instant)>,
+    /// The last word matched and its only matching path, if any.
+    last: Option<(String, Option<String>)>,
+}
+
+impl FileMatchDebouncer {
+    /// The only path matching `word` if it has been matched already, otherwise
+    /// `None` with a match scheduled.
+    fn single_file_match(&mut self, word: &str, now: Instant) -> Option<String> {
+        if let Some((last_word, path)) = &self.last
+            && last_word == word
+        {
+            return path.clone();
+        }
+        if !self.pending.as_ref().is_some_and(|(p, _)| p == word) {
+            self.pending = Some((word.to_string(), now));
+        }
+        None
+    }
+
+    /// Whether a match is waiting, so the event loop keeps ticking.
+    pub fn is_busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Match a due word. Returns `true` when a match ran and the inline
+    /// suggestion should be recomputed.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let Some((_, requested_at)) = &self.pending else {
+            return false;
+        };
+        if now.saturating_duration_since(*requested_at) < FILE_MATCH_DEBOUNCE {
+            return false;
+        }
+        let Some((word, _)) = self.pending.take() else {
+            return false;
+        };
+        let path = single_file_match(&word);
+        self.last = Some((word, path));
+        true
+    }
+}
+
+/// The inline suggestion for `buffer` from the first source in `sources`
+/// that has one, and the history entry it came from, if any.
+pub fn inline_suggestion(
+    sources: &[SuggestionSource],
+    buffer: &str,
+    history: &mut HistoryManager,
+    files: &mut FileMatchDebouncer,
+    now: Instant,
+) -> Option<(Option<HistoryEntry>, String)> {
+    sources.iter().find_map(|source| match source {
+        SuggestionSource::History => history
+            .get_command_suggestion_suffix(buffer)
+            .map(|(entry, suffix)| (Some(entry), suffix)),
+        SuggestionSource::Completion => {
+            completion_suffix(buffer, history.command_uses(), files, now)
+                .filter(|suffix| !suffix.is_empty())
+                .map(|suffix| (None, suffix))
+        }
+    })
+}
+
+/// What to append to `buffer` to complete its last word. A lone first word
+/// completes to the matching command with the most `uses` in history; any
+/// later word completes only if exactly one file matches it.
+fn completion_suffix(
+    buffer: &str,
+    uses: &HashMap<String, usize>,
+    files: &mut FileMatchDebouncer,
+    now: Instant,
+) -> Option<String> {
+    if buffer.ends_with(char::is_whitespace) {
+        return None;
+    }
+    let word = buffer.rsplit(char::is_whitespace).next().unwrap_or(buffer);
+    let word_start = buffer.len() - word.len();
+    if buffer[..word_start].trim().is_empty() {
+        let candidates = crate::bash_funcs::get_possible_command_words()
+            .map(|info| info.command().to_string())
+            .collect::<Vec<_>>();
+        most_used_command(word, &candidates, uses).map(|command| command[word.len()..].to_string())
+    } else {
+        files
+            .single_file_match(word, now)
+            .map(|path| path[word.len()..].to_string())
+    }
+}
+
+/// The candidate starting with `prefix` with the most `uses`, preferring
+/// shorter names on a tie.
+fn most_used_command<'a>(
+    prefix: &str,
+    candidates: &'a [String],
+    uses: &HashMap<String, usize>,
+) -> Option<&'a str> {
+    candidates
+        .iter()
+        .filter(|candidate| candidate.len() > prefix.len() && candidate.starts_with(prefix))
+        .map(|candidate| (uses.get(candidate).copied().unwrap_or(0), candidate))
+        .max_by(|(a_uses, a), (b_uses, b)| {
+            a_uses
+                .cmp(b_uses)
+                .then_with(|| b.len().cmp(&a.len()))
+                .then_with(|| b.cmp(a))
+        })
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// The only path starting with `word`, with a trailing `/` for directories.
+/// Words with quotes or expansions are left to tab completion.
+fn single_file_match(word: &str) -> Option<String> {
+    if word.is_empty() || word.contains(['\'', '"', '\\', '$', '~', '`', '*', '?', '[']) {
+        return None;
+    }
+    let pattern = format!("{}*", glob::Pattern::escape(word));
+    let mut matches = glob::glob(&pattern).ok()?.filter_map(Result::ok);
+    let path = matches.next()?;
+    if matches.next().is_some() {
+        return None;
+    }
+    let mut path = path.to_str()?.to_string();
+    if !path.starts_with(word) {
+        return None;
+    }
+    if std::path::Path::new(&path).is_dir() {
+        path.push('/');
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(commands: &[&str]) -> HistoryManager {
+        let mut history = HistoryManager::new_empty();
+        for command in commands {
+            history.push_entry(command.to_string());
+        }
+        history
+    }
+
+    fn suggest(
+        sources: &[SuggestionSource],
+        buffer: &str,
+        hm: &mut HistoryManager,
+    ) -> Option<(Option<HistoryEntry>, String)> {
+        let mut files = FileMatchDebouncer::default();
+        inline_suggestion(sources, buffer, hm, &mut files, Instant::now())
+    }
+
+    /// The completion suffix for `buffer` once any file match has finished.
+    fn file_suffix(buffer: &str) -> Option<String> {
+        let mut files = FileMatchDebouncer::default();
+        let t0 = Instant::now();
+        let uses = HashMap::new();
+        completion_suffix(buffer, &uses, &mut files, t0);
+        files.poll(t0 + FILE_MATCH_DEBOUNCE);
+        completion_suffix(buffer, &uses, &mut files, t0 + FILE_MATCH_DEBOUNCE)
+    }
+
+    #[test]
+    fn parses_source_order() {
+        assert_eq!(
+            parse_sources("completion, History,bogus,completion"),
+            vec![SuggestionSource::Completion, SuggestionSource::History]
+        );
+        assert_eq!(parse_sources(""), vec![]);
+    }
+
+    #[test]
+    fn completion_is_used_only_when_history_has_no_match() {
+        let sources = [SuggestionSource::History, SuggestionSource::Completion];
+        let mut hm = history(&["whoami", "while true; do date; done"]);
+
+        let (entry, suffix) = suggest(&sources, "whi", &mut hm).unwrap();
+        assert_eq!(entry.unwrap().command, "while true; do date; done");
+        assert_eq!(suffix, "le true; do date; done");
+
+        let mut hm = history(&["whoami"]);
+        let (entry, suffix) = suggest(&sources, "whi", &mut hm).unwrap();
+        assert!(entry.is_none());
+        assert_eq!(suffix, "le");
+    }
+
+    #[test]
+    fn history_only_by_default() {
+        let mut hm = history(&["whoami"]);
+        assert!(suggest(&[SuggestionSource::History], "whi", &mut hm).is_none());
+    }
+
+    #[test]
+    fn completion_can_come_first() {
+        let sources = [SuggestionSource::Completion, SuggestionSource::History];
+        let mut hm = history(&["while true; do date; done"]);
+        let (entry, suffix) = suggest(&sources, "whi", &mut hm).unwrap();
+        assert!(entry.is_none());
+        assert_eq!(suffix, "le");
+    }
+
+    #[test]
+    fn prefers_the_most_used_command() {
+        let candidates = ["gitk", "git", "gcc"].map(String::from);
+        let hm = history(&["gitk", "gitk --all", "git status"]);
+        assert_eq!(
+            most_used_command("gi", &candidates, hm.command_uses()),
+            Some("gitk")
+        );
+        // Unused commands tie, so the shortest wins.
+        let unused = HashMap::new();
+        assert_eq!(most_used_command("gi", &candidates, &unused), Some("git"));
+        assert_eq!(most_used_command("git", &candidates, &unused), Some("gitk"));
+        assert_eq!(most_used_command("x", &candidates, &unused), None);
+    }
+
+    #[test]
+    fn completes_the_only_matching_file() {
+        let dir = std::env::temp_dir().join(format!("flyline_sugg_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src_dir")).unwrap();
+        std::fs::write(dir.join("readme.md"), "").unwrap();
+        std::fs::write(dir.join("run.sh"), "").unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        assert_eq!(
+            file_suffix(&format!("cat {}/rea", dir_str)),
+            Some("dme.md".to_string())
+        );
+        assert_eq!(
+            file_suffix(&format!("cd {}/sr", dir_str)),
+            Some("c_dir/".to_string())
+        );
+        // Two matches: no suggestion.
+        assert_eq!(file_suffix(&format!("cat {}/r", dir_str)), None);
+        assert_eq!(file_suffix("cat "), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_match_waits_for_the_word_to_settle() {
+        let mut files = FileMatchDebouncer::default();
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+        let uses = HashMap::new();
+        let dir = std::env::temp_dir().join(format!("flyline_settle_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        let word = format!("cat {}/n", dir.to_str().unwrap());
+
+        assert_eq!(
+            completion_suffix(&word[..word.len() - 1], &uses, &mut files, t0),
+            None
+        );
+        assert_eq!(completion_suffix(&word, &uses, &mut files, ms(50)), None);
+        assert!(!files.poll(ms(100)));
+        assert!(files.is_busy());
+        assert!(files.poll(ms(170)));
+        assert!(!files.is_busy());
+        assert_eq!(
+            completion_suffix(&word, &uses, &mut files, ms(170)),
+            Some("otes.txt".to_string())
+        );
+        assert!(!files.poll(ms(500)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,8 +2,11 @@ use flash::lexer::TokenKind;
 use std::{borrow::Cow, vec};
 
 use crate::{
+    assignment_completion,
     dparser::{DParser, ToInclusiveRange},
     globbing,
+    kill_completion::{ProcessArg, SignalForm},
+    project_completion::ProjectCompletion,
     text_buffer::SubString,
 };
 
@@ -23,6 +26,23 @@ pub enum CompType {
         // fuzzy-match the candidates against the full word under cursor.
         command_word: String,
     },
+    AssignmentValue {
+        // the value of a known variable assignment, e.g. "EDITOR=v|i"
+        var_name: String, // "EDITOR"
+    },
+    GitCommitMessage, // the value of `git commit -m`, e.g. "Fi|x bug"
+    KillSignal {
+        // a signal for `kill`, e.g. "-TE|" or the argument of `kill -s`
+        form: SignalForm,
+    },
+    ProjectName {
+        // a name from a project file, e.g. "npm run bu|" or "cargo run --bin cl|"
+        kind: ProjectCompletion,
+    },
+    ProcessArg {
+        // a process argument of `kill`, `pkill` or `renice`, e.g. "kill 12|" or "pkill -u ro|"
+        arg: ProcessArg,
+    },
     EnvVariable,            // the env variable under the cursor, with the leading $
     TildeExpansion,         // the tilde under the cursor, e.g. "~us|erna"
     HostnameExpansion,      // the hostname under the cursor, e.g. "user@ho|st"
@@ -43,6 +63,11 @@ impl CompType {
             CompType::FuzzyFirstWord => "FuzzyFirstWord",
             CompType::CommandComp { .. } => "CommandComp",
             CompType::FuzzyCommandComp { .. } => "FuzzyCommandComp",
+            CompType::AssignmentValue { .. } => "AssignmentValue",
+            CompType::GitCommitMessage => "GitCommitMessage",
+            CompType::KillSignal { .. } => "KillSignal",
+            CompType::ProjectName { .. } => "ProjectName",
+            CompType::ProcessArg { .. } => "ProcessArg",
             CompType::EnvVariable => "EnvVariable",
             CompType::TildeExpansion => "TildeExpansion",
             CompType::HostnameExpansion => "HostnameExpansion",
@@ -125,7 +150,24 @@ impl<'a> CompletionContext<'a> {
         }
 
         let context_until_cursor = Self::context_until_cursor_for(context, cursor_byte_pos);
-        if context.as_ref().trim().is_empty()
+        let assignment = if context_until_cursor.chars().any(|c| c.is_whitespace()) {
+            None
+        } else {
+            assignment_completion::split_assignment(context_until_cursor)
+        };
+
+        if let Some((var_name, _)) = assignment {
+            // The value of `NAME=value` is not a command: known variables get
+            // their own candidates, anything else falls back to paths below.
+            if assignment_completion::is_known_variable(var_name) {
+                log::debug!("Detected value of known variable assignment: {}", var_name);
+                comp_types.push(CompType::AssignmentValue {
+                    var_name: var_name.to_string(),
+                });
+            } else {
+                log::debug!("Detected value of variable assignment: {}", var_name);
+            }
+        } else if context.as_ref().trim().is_empty()
             || !context_until_cursor.chars().any(|c| c.is_whitespace())
         {
             comp_types.push(CompType::FirstWord);
@@ -138,6 +180,29 @@ impl<'a> CompletionContext<'a> {
                 .unwrap_or("")
                 .to_string();
 
+            let words_before = Self::words_before_wuc(context, word_under_cursor, &command_word);
+            let words_before: Vec<&str> = words_before.iter().map(String::as_str).collect();
+
+            if crate::git_completion::is_commit_message_arg(&words_before) {
+                log::debug!("Detected git commit message context");
+                comp_types.push(CompType::GitCommitMessage);
+            }
+
+            if let Some(form) = crate::kill_completion::signal_form(&words_before, wuc) {
+                log::debug!("Detected kill signal context: {:?}", form);
+                comp_types.push(CompType::KillSignal { form });
+            }
+
+            if let Some(arg) = crate::kill_completion::process_arg(&words_before, wuc) {
+                log::debug!("Detected process argument context: {:?}", arg);
+                comp_types.push(CompType::ProcessArg { arg });
+            }
+
+            if let Some(kind) = crate::project_completion::completion_for(&words_before) {
+                log::debug!("Detected project name context: {:?}", kind);
+                comp_types.push(CompType::ProjectName { kind });
+            }
+
             comp_types.push(CompType::CommandComp {
                 command_word: command_word.clone(),
             });
@@ -169,6 +234,28 @@ impl<'a> CompletionContext<'a> {
         comp_types
     }
 
+    /// The words of the command before the word under the cursor, expanding
+    /// an alias on the command word (e.g. `gcm` becomes `git commit -m`).
+    fn words_before_wuc(
+        context: &SubString,
+        word_under_cursor: &SubString,
+        command_word: &str,
+    ) -> Vec<String> {
+        let end = word_under_cursor
+            .start
+            .saturating_sub(context.start)
+            .min(context.as_ref().len());
+        let before_wuc = &context.as_ref()[..end];
+        match crate::bash_funcs::find_alias_or_log(command_word) {
+            Some(alias_def) => alias_def
+                .split_whitespace()
+                .chain(before_wuc.split_whitespace().skip(1))
+                .map(str::to_string)
+                .collect(),
+            None => before_wuc.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+
     fn context_until_cursor_for(context: &SubString, cursor_byte_pos: usize) -> &str {
         let end = cursor_byte_pos
             .saturating_sub(context.start)
@@ -176,7 +263,6 @@ impl<'a> CompletionContext<'a> {
         &context.as_ref()[..end]
     }
 
-    #[cfg(test)]
     pub fn context_until_cursor(&self) -> &str {
         Self::context_until_cursor_for(&self.context, self.cursor_byte_pos)
     }
@@ -776,6 +862,140 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_git_commit_message_comp_type() {
+        let res = run_inline(r#"git commit -a -m "Fix bu█"#);
+        assert_eq!(res.word_under_cursor.as_ref(), r#""Fix bu"#);
+        assert_eq!(res.comp_types().first(), Some(&CompType::GitCommitMessage));
+
+        // `gcm` is a test alias for `git commit -m`.
+        let res = run_inline("gcm Fi█");
+        assert_eq!(res.comp_types().first(), Some(&CompType::GitCommitMessage));
+
+        let res = run_inline("git commit --amend Fi█");
+        assert!(!res.comp_types().contains(&CompType::GitCommitMessage));
+    }
+
+    #[test]
+    fn test_kill_signal_comp_type() {
+        let res = run_inline("kill -TE█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::KillSignal {
+                form: SignalForm::Dashed
+            })
+        );
+
+        let res = run_inline("kill -s HU█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::KillSignal {
+                form: SignalForm::Name
+            })
+        );
+
+        let res = run_inline("kill 12█");
+        assert!(
+            !res.comp_types()
+                .iter()
+                .any(|c| matches!(c, CompType::KillSignal { .. }))
+        );
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::ProcessArg {
+                arg: ProcessArg::Pid
+            })
+        );
+
+        let res = run_inline("pkill fire█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::ProcessArg {
+                arg: ProcessArg::Name
+            })
+        );
+
+        let res = run_inline("renice -n 5 -u ro█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::ProcessArg {
+                arg: ProcessArg::User
+            })
+        );
+    }
+
+    #[test]
+    fn test_project_name_comp_type() {
+        let res = run_inline("npm run bu█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::ProjectName {
+                kind: ProjectCompletion::NpmScripts
+            })
+        );
+
+        let res = run_inline("cargo run --bin cl█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::ProjectName {
+                kind: ProjectCompletion::CargoBins
+            })
+        );
+
+        let res = run_inline("cargo run cl█");
+        assert!(
+            !res.comp_types()
+                .iter()
+                .any(|c| matches!(c, CompType::ProjectName { .. }))
+        );
+    }
+
+    #[test]
+    fn test_assignment_value_comp_type() {
+        let res = run_inline("EDITOR=vi█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::AssignmentValue {
+                var_name: "EDITOR".to_string()
+            })
+        );
+
+        let res = run_inline("FOO=1 SHELL=█");
+        assert_eq!(
+            res.comp_types().first(),
+            Some(&CompType::AssignmentValue {
+                var_name: "SHELL".to_string()
+            })
+        );
+
+        // Unknown variables complete their value as a path, not a command.
+        let res = run_inline("MY_VAR=vi█");
+        assert_eq!(
+            res.comp_types(),
+            vec![
+                CompType::FilenameExpansion,
+                CompType::FuzzyFilenameExpansion
+            ]
+        );
+
+        let res = run_inline("DATA=./fi█");
+        assert_eq!(res.word_under_cursor.as_ref(), "./fi");
+        assert_eq!(
+            res.comp_types(),
+            vec![
+                CompType::FilenameExpansion,
+                CompType::FuzzyFilenameExpansion
+            ]
+        );
+
+        let res = run_inline("echo EDITOR=vi█");
+        assert!(
+            !res.comp_types()
+                .iter()
+                .any(|c| matches!(c, CompType::AssignmentValue { .. }))
+        );
+    }
+
     #[test]
     fn test_cursor_in_middle_of_subshell_command() {
         let res = run_inline(r#"echo $(git rev-parse HEA█D) café"#);
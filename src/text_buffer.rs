@@ -5,6 +5,19 @@ use unicode_segmentation::UnicodeSegmentation;
 use itertools::Itertools;
 use unicode_width::UnicodeWidthStr;
 
+/// Tab stops are every `TAB_WIDTH` columns from the start of a buffer line.
+pub const TAB_WIDTH: usize = 8;
+
+/// The column after drawing `grapheme` at `col` (relative to the start of the
+/// line). A tab advances to the next tab stop.
+pub fn next_display_col(col: usize, grapheme: &str) -> usize {
+    if grapheme == "\t" {
+        (col / TAB_WIDTH + 1) * TAB_WIDTH
+    } else {
+        col + grapheme.width()
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 struct Snapshot {
     buf: String,
@@ -46,6 +59,9 @@ struct SnapshotManager {
 pub enum WordDelim {
     WhiteSpace,
     FineGrained,
+    /// readline's `forward-word`/`backward-word`: words are runs of letters
+    /// and digits, so every punctuation character is a boundary.
+    Alphanumeric,
 }
 
 impl WordDelim {
@@ -53,6 +69,7 @@ impl WordDelim {
         match self {
             WordDelim::WhiteSpace => c.is_whitespace(),
             WordDelim::FineGrained => c.is_whitespace() || c.is_ascii_punctuation(),
+            WordDelim::Alphanumeric => !c.is_alphanumeric(),
         }
     }
 }
@@ -68,6 +85,15 @@ pub struct TextBuffer {
     /// `None`, no selection is active.
     selection_byte: Option<usize>,
     undo_redo: SnapshotManager,
+    /// Text removed by the kill commands, most recent last.
+    kill_ring: Vec<String>,
+    /// Byte range of the text inserted by the last yank and the kill ring
+    /// index it came from, so that `yank_pop` can replace it.
+    last_yank: Option<(std::ops::Range<usize>, usize)>,
+    /// Characters that count as part of a word, along with alphanumerics,
+    /// for whitespace word movement and Ctrl-W. `None` splits words at
+    /// whitespace only.
+    word_chars: Option<String>,
 }
 
 ///////////////////////////////////////////////////////// misc
@@ -78,6 +104,22 @@ impl TextBuffer {
             cursor_byte: starting_str.len(),
             selection_byte: None,
             undo_redo: SnapshotManager::new(),
+            kill_ring: Vec::new(),
+            last_yank: None,
+            word_chars: None,
+        }
+    }
+
+    pub fn set_word_chars(&mut self, word_chars: Option<String>) {
+        self.word_chars = word_chars;
+    }
+
+    fn is_word_boundary(&self, delim: &WordDelim, c: char) -> bool {
+        match (delim, &self.word_chars) {
+            (WordDelim::WhiteSpace | WordDelim::Alphanumeric, Some(word_chars)) => {
+                !(c.is_alphanumeric() || word_chars.contains(c))
+            }
+            _ => delim.is_word_boundary(c),
         }
     }
 
@@ -91,6 +133,9 @@ impl TextBuffer {
             cursor_byte: cursor_byte_pos,
             selection_byte: None,
             undo_redo: SnapshotManager::new(),
+            kill_ring: Vec::new(),
+            last_yank: None,
+            word_chars: None,
         }
     }
 }
@@ -428,10 +473,10 @@ impl TextBuffer {
             .char_indices()
             .rev()
             .skip_while(|(i, _)| *i >= self.cursor_byte)
-            .skip_while(|(_, c)| delim.is_word_boundary(*c))
+            .skip_while(|(_, c)| self.is_word_boundary(&delim, *c))
             .tuple_windows()
             .find_map(|((i, c), (_, next_c))| {
-                if !delim.is_word_boundary(c) && delim.is_word_boundary(next_c) {
+                if !self.is_word_boundary(&delim, c) && self.is_word_boundary(&delim, next_c) {
                     Some(i)
                 } else {
                     None
@@ -440,8 +485,13 @@ impl TextBuffer {
             .unwrap_or(0)
     }
 
+    /// Move the cursor one word to the left. `WordDelim::FineGrained` uses the
+    /// same punctuation/path-segment boundaries as the fine-grained delete.
     pub fn move_one_word_left(&mut self, delim: WordDelim) {
-        self.cursor_byte = self.move_one_word_left_pos(delim);
+        self.cursor_byte = match delim {
+            WordDelim::WhiteSpace | WordDelim::Alphanumeric => self.move_one_word_left_pos(delim),
+            WordDelim::FineGrained => self.fine_grained_word_left_pos(),
+        };
     }
 
     fn move_one_word_right_pos(&self, delim: WordDelim) -> usize {
@@ -452,14 +502,19 @@ impl TextBuffer {
         self.buf
             .char_indices()
             .skip_while(|(i, _)| *i < self.cursor_byte)
-            .skip_while(|(_, c)| delim.is_word_boundary(*c))
-            .skip_while(|(_, c)| !delim.is_word_boundary(*c))
+            .skip_while(|(_, c)| self.is_word_boundary(&delim, *c))
+            .skip_while(|(_, c)| !self.is_word_boundary(&delim, *c))
             .next()
             .map_or(self.buf.len(), |(i, _)| i)
     }
 
+    /// Move the cursor one word to the right. `WordDelim::FineGrained` uses the
+    /// same punctuation/path-segment boundaries as the fine-grained delete.
     pub fn move_one_word_right(&mut self, delim: WordDelim) {
-        self.cursor_byte = self.move_one_word_right_pos(delim);
+        self.cursor_byte = match delim {
+            WordDelim::WhiteSpace | WordDelim::Alphanumeric => self.move_one_word_right_pos(delim),
+            WordDelim::FineGrained => self.fine_grained_word_right_pos(),
+        };
     }
 
     /// Extend the selection one whitespace-delimited word to the right with
@@ -533,11 +588,32 @@ impl TextBuffer {
     }
 
     pub fn move_one_word_left_fine_grained(&mut self) {
-        self.cursor_byte = self.fine_grained_word_left_pos();
+        self.move_one_word_left(WordDelim::FineGrained);
     }
 
     pub fn move_one_word_right_fine_grained(&mut self) {
-        self.cursor_byte = self.fine_grained_word_right_pos();
+        self.move_one_word_right(WordDelim::FineGrained);
+    }
+
+    /// Move the cursor to the start of the next top-level command, if any.
+    pub fn move_to_next_command(&mut self) {
+        if let Some(pos) = crate::dparser::top_level_command_starts(&self.buf)
+            .into_iter()
+            .find(|&pos| pos > self.cursor_byte)
+        {
+            self.cursor_byte = pos;
+        }
+    }
+
+    /// Move the cursor to the start of the previous top-level command, if any.
+    pub fn move_to_prev_command(&mut self) {
+        if let Some(pos) = crate::dparser::top_level_command_starts(&self.buf)
+            .into_iter()
+            .rev()
+            .find(|&pos| pos < self.cursor_byte)
+        {
+            self.cursor_byte = pos;
+        }
     }
 
     pub fn move_to_start(&mut self) {
@@ -601,7 +677,7 @@ impl TextBuffer {
                 cur_row += 1;
                 cur_col = 0;
             } else {
-                cur_col += grapheme.width();
+                cur_col = next_display_col(cur_col, grapheme);
             }
         }
         self.cursor_byte = self.buf.len();
@@ -628,6 +704,25 @@ impl TextBuffer {
 mod test_movement {
     use super::*;
 
+    #[test]
+    fn move_between_commands() {
+        let mut tb = TextBuffer::new("echo a; ls -l; pwd");
+        tb.move_to_prev_command();
+        assert_eq!(tb.cursor_byte, 15);
+        tb.move_to_prev_command();
+        assert_eq!(tb.cursor_byte, 8);
+        tb.move_to_prev_command();
+        assert_eq!(tb.cursor_byte, 0);
+        tb.move_to_prev_command();
+        assert_eq!(tb.cursor_byte, 0);
+        tb.move_to_next_command();
+        assert_eq!(tb.cursor_byte, 8);
+        tb.move_to_next_command();
+        assert_eq!(tb.cursor_byte, 15);
+        tb.move_to_next_command();
+        assert_eq!(tb.cursor_byte, 15);
+    }
+
     #[test]
     fn move_cursor_left() {
         let mut tb = TextBuffer::new("test 👩‍💻");
@@ -685,6 +780,33 @@ mod test_movement {
         assert_eq!(tb.cursor_byte, "  abc def".len());
     }
 
+    #[test]
+    fn move_one_word_with_word_chars() {
+        let mut tb = TextBuffer::new("foo-bar_baz");
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, 0);
+
+        tb.set_word_chars(Some("-_".to_string()));
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "foo-bar_baz".len());
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, 0);
+
+        tb.set_word_chars(Some("_".to_string()));
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "foo".len());
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "foo-bar_baz".len());
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "foo-".len());
+
+        tb.set_word_chars(Some(String::new()));
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, 0);
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "foo".len());
+    }
+
     #[test]
     fn move_right_one_word_extend_selection_smart_from_middle_of_word() {
         // Cursor in the middle of "abc": first press selects "bc", second press
@@ -915,6 +1037,102 @@ mod test_movement {
         assert_eq!(tb2.cursor_byte, 0);
     }
 
+    #[test]
+    fn move_one_word_alphanumeric_stops_at_punctuation() {
+        // Ctrl+Left/Right stop at every punctuation character, like readline.
+        let mut tb = TextBuffer::new("cat foo.bar/baz");
+        tb.move_to_start();
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo.bar".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo.bar/baz".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo.bar/".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo.".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat ".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, 0);
+
+        // FLYLINE_WORDCHARS adds characters to words.
+        let mut tb = TextBuffer::new("cat foo.bar/baz");
+        tb.set_word_chars(Some(".".to_string()));
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat foo.bar/".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "cat ".len());
+    }
+
+    #[test]
+    fn move_one_word_over_punctuated_path() {
+        // Whitespace motion treats the whole path as one word.
+        let mut tb = TextBuffer::new("ls foo/bar-baz.qux");
+        tb.move_to_start();
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "ls".len());
+        tb.move_one_word_right(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "ls foo/bar-baz.qux".len());
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, "ls ".len());
+        tb.move_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.cursor_byte, 0);
+
+        // Fine-grained motion (Alt+Left/Right) stops at path segments, and both
+        // directions visit the same boundaries.
+        let mut tb = TextBuffer::new("foo/bar-baz.qux");
+        tb.move_to_start();
+        tb.move_one_word_right(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "foo".len());
+        tb.move_one_word_right(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "foo/".len());
+        tb.move_one_word_right(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "foo/bar-baz.qux".len());
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "foo/".len());
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "foo".len());
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, 0);
+
+        // Ctrl+Left/Right (readline word motion) stop at every punctuation
+        // character: right lands on word ends, left on word starts.
+        let mut tb = TextBuffer::new("foo/bar-baz.qux");
+        tb.move_to_start();
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/bar".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/bar-baz".len());
+        tb.move_one_word_right(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/bar-baz.qux".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/bar-baz.".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/bar-".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, "foo/".len());
+        tb.move_one_word_left(WordDelim::Alphanumeric);
+        assert_eq!(tb.cursor_byte, 0);
+
+        // Without a slash every punctuation character is a boundary.
+        let mut tb = TextBuffer::new("bar-baz.qux");
+        tb.move_end_of_line();
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "bar-baz.".len());
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "bar-baz".len());
+        tb.move_one_word_left(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "bar-".len());
+        tb.move_one_word_right(WordDelim::FineGrained);
+        assert_eq!(tb.cursor_byte, "bar-baz".len());
+    }
+
     #[test]
     fn move_line_up() {
         let mut tb = TextBuffer::new("Line 1\nLine 2\nLine 3");
@@ -950,6 +1168,78 @@ mod test_movement {
         assert_eq!(tb.cursor_2d_position(), (2, 0));
         assert_eq!(tb.cursor_byte, "Line 1\nLine 2\n".len());
     }
+
+    #[test]
+    fn line_movement_with_wide_characters() {
+        let mut tb = TextBuffer::new("日本語\nab\n中文字符");
+        assert_eq!(tb.cursor_row(), 2);
+        assert_eq!(tb.cursor_2d_position(), (2, 8));
+
+        // Column 8 is past the end of "ab", so the cursor stops at its end.
+        tb.move_line_up();
+        assert_eq!(tb.cursor_byte, "日本語\nab".len());
+        assert_eq!(tb.cursor_2d_position(), (1, 2));
+
+        // Column 2 is the boundary between 日 and 本.
+        tb.move_line_up();
+        assert_eq!(tb.cursor_byte, "日".len());
+        assert_eq!(tb.cursor_row(), 0);
+
+        tb.move_line_down();
+        tb.move_line_down();
+        assert_eq!(tb.cursor_byte, "日本語\nab\n中".len());
+        assert_eq!(tb.cursor_2d_position(), (2, 2));
+
+        tb.move_start_of_line();
+        assert_eq!(tb.cursor_byte, "日本語\nab\n".len());
+        tb.move_end_of_line();
+        assert_eq!(tb.cursor_byte, tb.buffer().len());
+    }
+
+    #[test]
+    fn tab_indented_lines_report_tab_stop_columns() {
+        let mut tb = TextBuffer::new_with_cursor("if true; then\n\techo█ hi\nfi");
+        assert_eq!(tb.cursor_2d_position(), (1, TAB_WIDTH + 4));
+
+        let mut tb2 = TextBuffer::new_with_cursor("ab\t█x");
+        assert_eq!(tb2.cursor_2d_position(), (0, TAB_WIDTH));
+        tb2.move_left();
+        assert_eq!(tb2.cursor_2d_position(), (0, 2));
+
+        // Moving down from column 8 lands after the tab, not eight bytes in.
+        tb.move_line_up();
+        tb.move_line_up();
+        tb.move_start_of_line();
+        for _ in 0..TAB_WIDTH {
+            tb.move_right();
+        }
+        tb.move_line_down();
+        assert_eq!(tb.cursor_byte, "if true; then\n\t".len());
+        assert_eq!(tb.cursor_2d_position(), (1, TAB_WIDTH));
+    }
+
+    #[test]
+    fn next_display_col_advances_tabs_to_tab_stops() {
+        assert_eq!(next_display_col(0, "\t"), TAB_WIDTH);
+        assert_eq!(next_display_col(3, "\t"), TAB_WIDTH);
+        assert_eq!(next_display_col(TAB_WIDTH, "\t"), 2 * TAB_WIDTH);
+        assert_eq!(next_display_col(3, "a"), 4);
+        assert_eq!(next_display_col(3, "日"), 5);
+    }
+
+    #[test]
+    fn move_line_up_from_odd_column_lands_after_wide_character() {
+        // Column 1 falls inside 日, which is two cells wide.
+        let mut tb = TextBuffer::new_with_cursor("日本語\na█b");
+        tb.move_line_up();
+        assert_eq!(tb.cursor_byte, "日".len());
+        assert_eq!(tb.cursor_2d_position(), (0, 2));
+
+        tb.move_end_of_line();
+        assert_eq!(tb.cursor_byte, "日本語".len());
+        tb.move_start_of_line();
+        assert_eq!(tb.cursor_byte, 0);
+    }
 }
 ///////////////////////////////////////////////////////// editing primitives without snapshots
 impl TextBuffer {
@@ -980,12 +1270,41 @@ impl TextBuffer {
     pub fn insert_newline(&mut self) {
         self.insert_char('\n');
     }
+
+    /// Insert a bracketed paste as one edit, replacing any selection. Line
+    /// breaks (including the bare `\r` some terminals send) become literal
+    /// newlines, so pasting never submits the command.
+    pub fn insert_pasted(&mut self, pasted: &str) {
+        self.delete_selection();
+        let normalized = pasted.replace("\r\n", "\n").replace('\r', "\n");
+        self.insert_str(&normalized);
+    }
 }
 
 #[cfg(test)]
 mod test_editing_primitives {
     use super::*;
 
+    #[test]
+    fn pasted_newlines_are_inserted_literally() {
+        let mut tb = TextBuffer::new("echo ");
+        tb.insert_pasted("one\r\ntwo\rthree\n");
+        assert_eq!(tb.buffer(), "echo one\ntwo\nthree\n");
+        assert!(tb.is_cursor_at_end());
+
+        // A single undo removes the whole paste.
+        tb.undo();
+        assert_eq!(tb.buffer(), "echo ");
+    }
+
+    #[test]
+    fn paste_replaces_selection() {
+        let mut tb = TextBuffer::new("echo old");
+        tb.set_selection_range(5..8, false);
+        tb.insert_pasted("new\n");
+        assert_eq!(tb.buffer(), "echo new\n");
+    }
+
     #[test]
     fn zwj_emoji_insertion() {
         let mut tb = TextBuffer::new("test ");
@@ -1216,7 +1535,51 @@ impl TextBuffer {
 
         assert!(new_cursor <= old_cursor_col);
         self.cursor_byte = new_cursor;
-        self.buf.drain(new_cursor..old_cursor_col);
+        self.kill(new_cursor..old_cursor_col);
+    }
+
+    /// Start of the word left of the cursor: skip back over graphemes that are
+    /// not part of a word, then over those that are.
+    fn grapheme_word_left_pos(&self, is_word: impl Fn(&str) -> bool) -> usize {
+        self.buf[..self.cursor_byte]
+            .grapheme_indices(true)
+            .rev()
+            .skip_while(|(_, g)| !is_word(g))
+            .take_while(|(_, g)| is_word(g))
+            .last()
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Delete back to the previous whitespace, like bash's `unix-word-rubout`
+    /// (Ctrl-W): `ls /usr/local/bin` becomes `ls `. With word chars set, stop
+    /// at the first character that is neither alphanumeric nor one of them.
+    pub fn delete_whitespace_word_left(&mut self) {
+        self.push_snapshot(true);
+        let old_cursor = self.cursor_byte;
+        let new_cursor = match &self.word_chars {
+            Some(word_chars) => self.grapheme_word_left_pos(|g| {
+                g.chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphanumeric() || word_chars.contains(c))
+            }),
+            None => {
+                self.grapheme_word_left_pos(|g| !g.chars().next().is_some_and(char::is_whitespace))
+            }
+        };
+        self.cursor_byte = new_cursor;
+        self.kill(new_cursor..old_cursor);
+    }
+
+    /// Delete back to the start of the previous run of alphanumerics, like
+    /// bash's `backward-kill-word` (Alt-Backspace): `ls /usr/local/bin`
+    /// becomes `ls /usr/local/`.
+    pub fn delete_shell_word_left(&mut self) {
+        self.push_snapshot(true);
+        let old_cursor = self.cursor_byte;
+        let new_cursor =
+            self.grapheme_word_left_pos(|g| g.chars().next().is_some_and(char::is_alphanumeric));
+        self.cursor_byte = new_cursor;
+        self.kill(new_cursor..old_cursor);
     }
 
     pub fn delete_right_one_word(&mut self, delim: WordDelim) {
@@ -1241,8 +1604,8 @@ impl TextBuffer {
             self.buf
                 .char_indices()
                 .skip_while(|(i, _)| *i <= self.cursor_byte)
-                .skip_while(|(_, c)| delim.is_word_boundary(*c))
-                .skip_while(|(_, c)| !delim.is_word_boundary(*c))
+                .skip_while(|(_, c)| self.is_word_boundary(&delim, *c))
+                .skip_while(|(_, c)| !self.is_word_boundary(&delim, *c))
                 .next()
                 .map_or(end, |(i, _)| i)
         } else {
@@ -1250,7 +1613,7 @@ impl TextBuffer {
         };
 
         assert!(end_cursor >= self.cursor_byte);
-        self.buf.drain(self.cursor_byte..end_cursor);
+        self.kill(self.cursor_byte..end_cursor);
     }
 
     pub fn replace_word_under_cursor(
@@ -1316,18 +1679,146 @@ impl TextBuffer {
         self.push_snapshot(true);
         let old_cursor = self.cursor_byte;
         self.move_start_of_line();
-        self.buf.drain(self.cursor_byte..old_cursor);
+        self.kill(self.cursor_byte..old_cursor);
     }
 
     pub fn delete_until_end_of_line(&mut self) {
         self.push_snapshot(true);
         let old_cursor = self.cursor_byte;
         self.move_end_of_line();
-        self.buf.drain(old_cursor..self.cursor_byte);
+        self.kill(old_cursor..self.cursor_byte);
         self.cursor_byte = old_cursor;
     }
 }
 
+///////////////////////////////////////////////////////// kill ring
+const KILL_RING_MAX: usize = 32;
+
+impl TextBuffer {
+    /// Remove `range` from the buffer and save it on the kill ring.
+    fn kill(&mut self, range: std::ops::Range<usize>) {
+        let killed: String = self.buf.drain(range).collect();
+        if killed.is_empty() {
+            return;
+        }
+        if self.kill_ring.len() == KILL_RING_MAX {
+            self.kill_ring.remove(0);
+        }
+        self.kill_ring.push(killed);
+    }
+
+    /// Insert the most recently killed text at the cursor.
+    pub fn yank(&mut self) {
+        let Some(text) = self.kill_ring.last().cloned() else {
+            return;
+        };
+        self.push_snapshot(false);
+        let start = self.cursor_byte;
+        self.insert_str_no_snapshot(&text);
+        self.last_yank = Some((start..self.cursor_byte, self.kill_ring.len() - 1));
+    }
+
+    /// Straight after a yank, replace the yanked text with the previous
+    /// entry of the kill ring, wrapping around at the oldest.
+    pub fn yank_pop(&mut self) {
+        let Some((range, idx)) = self.last_yank.clone() else {
+            return;
+        };
+        let still_yanked = self.cursor_byte == range.end
+            && self.buf.get(range.clone()) == self.kill_ring.get(idx).map(String::as_str);
+        if !still_yanked {
+            self.last_yank = None;
+            return;
+        }
+        let idx = idx.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        let text = self.kill_ring[idx].clone();
+        self.push_snapshot(false);
+        self.buf.replace_range(range.clone(), &text);
+        self.cursor_byte = range.start + text.len();
+        self.last_yank = Some((range.start..self.cursor_byte, idx));
+    }
+}
+
+#[cfg(test)]
+mod test_kill_ring {
+    use super::*;
+
+    #[test]
+    fn yank_restores_killed_word() {
+        let mut tb = TextBuffer::new("echo hello world");
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.buffer(), "echo hello ");
+        tb.move_to_start();
+        tb.yank();
+        assert_eq!(tb.buffer(), "worldecho hello ");
+        assert_eq!(tb.cursor_byte, "world".len());
+    }
+
+    #[test]
+    fn yank_with_empty_kill_ring_does_nothing() {
+        let mut tb = TextBuffer::new("echo");
+        tb.yank();
+        tb.yank_pop();
+        assert_eq!(tb.buffer(), "echo");
+    }
+
+    #[test]
+    fn kill_and_yank_emoji_sequences() {
+        let family = "👨‍👩‍👧‍👦";
+        let flag = "🇬🇧";
+        let mut tb = TextBuffer::new_with_cursor(&format!("echo █{} {}", family, flag));
+        tb.delete_until_end_of_line();
+        assert_eq!(tb.buffer(), "echo ");
+        tb.yank();
+        assert_eq!(tb.buffer(), format!("echo {} {}", family, flag));
+
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.buffer(), format!("echo {} ", family));
+        tb.move_to_start();
+        tb.yank();
+        assert_eq!(tb.buffer(), format!("{}echo {} ", flag, family));
+        assert_eq!(tb.cursor_byte, flag.len());
+    }
+
+    #[test]
+    fn kill_to_start_of_line_is_yankable() {
+        let mut tb = TextBuffer::new_with_cursor("first\nsecond█ line");
+        tb.delete_until_start_of_line();
+        assert_eq!(tb.buffer(), "first\n line");
+        tb.move_to_end();
+        tb.yank();
+        assert_eq!(tb.buffer(), "first\n linesecond");
+    }
+
+    #[test]
+    fn yank_pop_rotates_through_kill_ring() {
+        let mut tb = TextBuffer::new("one two three");
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        assert_eq!(tb.buffer(), "");
+
+        tb.yank();
+        assert_eq!(tb.buffer(), "one ");
+        tb.yank_pop();
+        assert_eq!(tb.buffer(), "two ");
+        tb.yank_pop();
+        assert_eq!(tb.buffer(), "three");
+        tb.yank_pop();
+        assert_eq!(tb.buffer(), "one ");
+    }
+
+    #[test]
+    fn yank_pop_needs_a_preceding_yank() {
+        let mut tb = TextBuffer::new("one two");
+        tb.delete_one_word_left(WordDelim::WhiteSpace);
+        tb.yank();
+        tb.insert_char('!');
+        tb.yank_pop();
+        assert_eq!(tb.buffer(), "one two!");
+    }
+}
+
 #[cfg(test)]
 mod test_editing_advanced {
 
@@ -1402,6 +1893,20 @@ mod test_editing_advanced {
         assert_eq!(tb.cursor_byte, "find simple".len());
     }
 
+    #[test]
+    fn replace_word_under_cursor_with_multiple_words() {
+        // A glob expanding to several files is inserted as one replacement.
+        let mut tb = TextBuffer::new("mycmd *.rs --verbose");
+        tb.move_to_start();
+        for _ in 0.."mycmd *.rs".len() {
+            tb.move_right();
+        }
+        tb.replace_word_under_cursor("a.rs b.rs", &create_substring(&tb.buffer(), "*.rs"))
+            .unwrap();
+        assert_eq!(tb.buffer(), "mycmd a.rs b.rs --verbose");
+        assert_eq!(tb.cursor_byte, "mycmd a.rs b.rs".len());
+    }
+
     #[test]
     #[should_panic(expected = "range was out of bounds")]
     fn replace_word_under_cursor_out_of_bounds() {
@@ -1434,6 +1939,57 @@ mod test_editing_advanced {
         .unwrap();
     }
 
+    #[test]
+    fn delete_whitespace_word_left_removes_whole_path() {
+        let mut tb = TextBuffer::new("ls /usr/local/bin");
+        tb.delete_whitespace_word_left();
+        assert_eq!(tb.buffer(), "ls ");
+        tb.delete_whitespace_word_left();
+        assert_eq!(tb.buffer(), "");
+        assert_eq!(tb.kill_ring.last().map(String::as_str), Some("ls "));
+    }
+
+    #[test]
+    fn delete_whitespace_word_left_with_word_chars() {
+        let mut tb = TextBuffer::new("ls foo-bar_baz");
+        tb.set_word_chars(Some("_".to_string()));
+        tb.delete_whitespace_word_left();
+        assert_eq!(tb.buffer(), "ls foo-");
+
+        let mut tb = TextBuffer::new("ls foo-bar_baz");
+        tb.set_word_chars(Some("-_".to_string()));
+        tb.delete_whitespace_word_left();
+        assert_eq!(tb.buffer(), "ls ");
+    }
+
+    #[test]
+    fn delete_shell_word_left_stops_at_punctuation() {
+        let mut tb = TextBuffer::new("ls /usr/local/bin");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "ls /usr/local/");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "ls /usr/");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "ls /");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "");
+    }
+
+    #[test]
+    fn word_deletes_keep_grapheme_clusters_whole() {
+        // "e" + combining acute accent is one grapheme.
+        let mut tb = TextBuffer::new("cd cafe\u{301}-\u{1F4C4}/re\u{301}sume\u{301}");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "cd cafe\u{301}-\u{1F4C4}/");
+        tb.delete_shell_word_left();
+        assert_eq!(tb.buffer(), "cd ");
+
+        let mut tb = TextBuffer::new_with_cursor("echo \u{1F4C4}a\u{301}█ b");
+        tb.delete_whitespace_word_left();
+        assert_eq!(tb.buffer(), "echo  b");
+        assert_eq!(tb.cursor_byte, "echo ".len());
+    }
+
     #[test]
     fn delete_one_word_left() {
         let mut tb = TextBuffer::new("cargo test abc::def::ghi   /etc/asd");
@@ -1797,7 +2353,7 @@ impl TextBuffer {
                 row += 1;
                 col = 0;
             } else {
-                col += grapheme.width();
+                col = next_display_col(col, grapheme);
             }
         }
         (row, col)
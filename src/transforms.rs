@@ -0,0 +1,169 @@
+/// A hook that may rewrite the command line just before it is submitted.
+/// Returning `None` leaves the command unchanged.
+pub type Transform = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Pre-submit transforms, run in registration order. Each sees the output of
+/// the previous one.
+#[derive(Default)]
+pub struct TransformRegistry {
+    transforms: Vec<(&'static str, Transform)>,
+}
+
+impl TransformRegistry {
+    pub fn register(&mut self, name: &'static str, transform: Transform) {
+        self.transforms.push((name, transform));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Run every transform over `command` and return the result.
+    pub fn apply(&self, command: &str) -> String {
+        let mut command = command.to_string();
+        for (name, transform) in &self.transforms {
+            if let Some(rewritten) = transform(&command) {
+                log::info!(
+                    "Submit transform {}: {:?} -> {:?}",
+                    name,
+                    command,
+                    rewritten
+                );
+                command = rewritten;
+            }
+        }
+        command
+    }
+}
+
+/// The built-in transforms enabled by environment variables:
+///
+/// - `FLYLINE_AUTO_SUDO`: space separated commands to prefix with `sudo`,
+///   e.g. `"apt systemctl"`.
+/// - `FLYLINE_GIT_NO_PAGER=1`: run `git` commands with `--no-pager`.
+pub fn load_at_startup() -> TransformRegistry {
+    let mut registry = TransformRegistry::default();
+    if let Some(commands) = crate::bash_funcs::get_envvar_value("FLYLINE_AUTO_SUDO") {
+        let commands: Vec<String> = commands.split_whitespace().map(str::to_string).collect();
+        if !commands.is_empty() {
+            registry.register(
+                "auto-sudo",
+                Box::new(move |command| auto_sudo(&commands, command)),
+            );
+        }
+    }
+    if crate::bash_funcs::get_envvar_value("FLYLINE_GIT_NO_PAGER").is_some_and(|v| v.trim() == "1")
+    {
+        registry.register("git-no-pager", Box::new(git_no_pager));
+    }
+    registry
+}
+
+/// Split off the leading whitespace and first word of `command`.
+fn first_word(command: &str) -> (&str, &str, &str) {
+    let rest = command.trim_start();
+    let indent = &command[..command.len() - rest.len()];
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    (indent, &rest[..end], &rest[end..])
+}
+
+/// Prefix `command` with `sudo` when its first word is one of `commands`.
+fn auto_sudo(commands: &[String], command: &str) -> Option<String> {
+    let (indent, word, rest) = first_word(command);
+    commands
+        .iter()
+        .any(|c| c == word)
+        .then(|| format!("{}sudo {}{}", indent, word, rest))
+}
+
+/// Insert `--no-pager` after a leading `git` unless it is already one of
+/// git's global options.
+fn git_no_pager(command: &str) -> Option<String> {
+    let (indent, word, rest) = first_word(command);
+    if word != "git" || has_global_no_pager(rest) {
+        return None;
+    }
+    Some(format!("{}git --no-pager{}", indent, rest))
+}
+
+/// Whether `--no-pager` is among the global options of git's `args`, the
+/// ones before the subcommand (`-C repo --no-pager log`). A `--no-pager`
+/// after the subcommand is just an argument to it.
+fn has_global_no_pager(args: &str) -> bool {
+    let mut args = args.split_whitespace();
+    while let Some(arg) = args.next() {
+        match arg {
+            "--no-pager" => return true,
+            // Global options whose value is the next word.
+            "-C" | "-c" | "--git-dir" | "--work-tree" | "--namespace" | "--config-env" => {
+                args.next();
+            }
+            _ if arg.starts_with('-') => {}
+            _ => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_transform_rewrites_command() {
+        let mut registry = TransformRegistry::default();
+        registry.register(
+            "shout",
+            Box::new(|command| command.starts_with("echo ").then(|| command.to_uppercase())),
+        );
+        registry.register("bang", Box::new(|command| Some(format!("{}!", command))));
+        assert_eq!(registry.apply("echo hi"), "ECHO HI!");
+        assert_eq!(registry.apply("ls"), "ls!");
+    }
+
+    #[test]
+    fn empty_registry_leaves_command_unchanged() {
+        let registry = TransformRegistry::default();
+        assert!(registry.is_empty());
+        assert_eq!(registry.apply("  ls -l "), "  ls -l ");
+    }
+
+    #[test]
+    fn auto_sudo_prefixes_listed_commands() {
+        let commands = vec!["apt".to_string(), "systemctl".to_string()];
+        assert_eq!(
+            auto_sudo(&commands, "apt install jq"),
+            Some("sudo apt install jq".to_string())
+        );
+        assert_eq!(
+            auto_sudo(&commands, "  systemctl restart nginx"),
+            Some("  sudo systemctl restart nginx".to_string())
+        );
+        assert_eq!(auto_sudo(&commands, "sudo apt install jq"), None);
+        assert_eq!(auto_sudo(&commands, "aptitude"), None);
+    }
+
+    #[test]
+    fn git_no_pager_inserts_flag_once() {
+        assert_eq!(
+            git_no_pager("git log --oneline"),
+            Some("git --no-pager log --oneline".to_string())
+        );
+        assert_eq!(git_no_pager("git"), Some("git --no-pager".to_string()));
+        assert_eq!(git_no_pager("git --no-pager diff"), None);
+        assert_eq!(git_no_pager("git -C repo --no-pager diff"), None);
+        assert_eq!(git_no_pager("gitk"), None);
+    }
+
+    #[test]
+    fn git_no_pager_ignores_the_flag_after_the_subcommand() {
+        assert_eq!(
+            git_no_pager("git commit -m --no-pager"),
+            Some("git --no-pager commit -m --no-pager".to_string())
+        );
+        assert_eq!(
+            git_no_pager("git -c core.pager=less log --no-pager"),
+            Some("git --no-pager -c core.pager=less log --no-pager".to_string())
+        );
+    }
+}
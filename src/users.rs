@@ -79,3 +79,184 @@ static ALL_USERS: LazyLock<Vec<User>> = LazyLock::new(|| {
 pub fn get_all_users() -> &'static [User] {
     &ALL_USERS
 }
+
+static ALL_GROUPS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    let groups = std::fs::read_to_string("/etc/group")
+        .map(|contents| parse_group_names(&contents))
+        .unwrap_or_default();
+    log::debug!("Loaded {} groups", groups.len());
+    groups
+});
+
+/// Group names from the contents of `/etc/group`, without repeats.
+fn parse_group_names(contents: &str) -> Vec<String> {
+    let mut groups: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        // Format: group_name:password:gid:members
+        let name = line.split(':').next().unwrap_or("");
+        if !name.is_empty() && !name.starts_with('#') && !groups.iter().any(|g| g == name) {
+            groups.push(name.to_string());
+        }
+    }
+    groups
+}
+
+pub fn get_all_groups() -> &'static [String] {
+    &ALL_GROUPS
+}
+
+/// Expand a leading `~user` (optionally followed by `/...`) to that user's home
+/// directory. Returns `None` if `path` does not start with `~user` or the user
+/// is unknown. A bare `~` or `~/` is left alone for bash to expand.
+pub fn expand_tilde_user(path: &str) -> Option<String> {
+    let rest = path.strip_prefix('~')?;
+    let (username, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if username.is_empty() {
+        return None;
+    }
+    let user = get_all_users().iter().find(|u| u.username == username)?;
+    let home = user.home_dir.trim_end_matches('/');
+    if home.is_empty() && tail.is_empty() {
+        Some("/".to_string())
+    } else {
+        Some(format!("{}{}", home, tail))
+    }
+}
+
+/// The effective uid and groups (effective gid plus supplementary groups)
+/// the kernel checks execute permission against.
+#[derive(Debug, Clone)]
+struct ExecCredentials {
+    euid: libc::uid_t,
+    groups: Vec<libc::gid_t>,
+}
+
+static CURRENT_CREDENTIALS: LazyLock<ExecCredentials> = LazyLock::new(|| {
+    let euid = unsafe { libc::geteuid() };
+    let mut groups = vec![unsafe { libc::getegid() }];
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count > 0 {
+        let mut supplementary: Vec<libc::gid_t> = vec![0; count as usize];
+        let count = unsafe { libc::getgroups(count, supplementary.as_mut_ptr()) };
+        if count > 0 {
+            supplementary.truncate(count as usize);
+            groups.extend(supplementary);
+        }
+    }
+    log::debug!("Execute credentials: euid={} groups={:?}", euid, groups);
+    ExecCredentials { euid, groups }
+});
+
+/// Whether a file with the given mode and owner can be executed by `creds`.
+/// Like the kernel, only the most specific class applies: the owner bits for
+/// the owner, the group bits for a group member, the other bits otherwise.
+/// Root can execute anything with at least one execute bit set.
+fn mode_allows_execute(
+    mode: u32,
+    file_uid: libc::uid_t,
+    file_gid: libc::gid_t,
+    creds: &ExecCredentials,
+) -> bool {
+    if creds.euid == 0 {
+        mode & 0o111 != 0
+    } else if file_uid == creds.euid {
+        mode & 0o100 != 0
+    } else if creds.groups.contains(&file_gid) {
+        mode & 0o010 != 0
+    } else {
+        mode & 0o001 != 0
+    }
+}
+
+/// Whether the current user may execute the file described by `metadata`.
+pub fn is_executable_by_current_user(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    mode_allows_execute(
+        metadata.mode(),
+        metadata.uid(),
+        metadata.gid(),
+        &CURRENT_CREDENTIALS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_home() -> String {
+        get_all_users()
+            .iter()
+            .find(|u| u.username == "root")
+            .map(|u| u.home_dir.trim_end_matches('/').to_string())
+            .expect("root user should be known")
+    }
+
+    #[test]
+    fn expand_tilde_user_resolves_home() {
+        let home = root_home();
+        assert_eq!(expand_tilde_user("~root"), Some(home.clone()));
+        assert_eq!(expand_tilde_user("~root/"), Some(format!("{}/", home)));
+        assert_eq!(
+            expand_tilde_user("~root/proj/src"),
+            Some(format!("{}/proj/src", home))
+        );
+    }
+
+    #[test]
+    fn expand_tilde_user_ignores_other_paths() {
+        assert_eq!(expand_tilde_user("~"), None);
+        assert_eq!(expand_tilde_user("~/proj"), None);
+        assert_eq!(expand_tilde_user("proj/~root"), None);
+        assert_eq!(expand_tilde_user("~no_such_user_flyline/"), None);
+    }
+
+    #[test]
+    fn parse_group_names_skips_comments_and_repeats() {
+        assert_eq!(
+            parse_group_names("# local\nroot:x:0:\nwheel:x:10:alice,bob\n\nroot:x:0:\n"),
+            vec!["root".to_string(), "wheel".to_string()]
+        );
+    }
+
+    fn creds(euid: libc::uid_t, groups: &[libc::gid_t]) -> ExecCredentials {
+        ExecCredentials {
+            euid,
+            groups: groups.to_vec(),
+        }
+    }
+
+    #[test]
+    fn mode_allows_execute_owner() {
+        let me = creds(1000, &[1000]);
+        assert!(mode_allows_execute(0o700, 1000, 50, &me));
+        assert!(mode_allows_execute(0o100, 1000, 50, &me));
+        // Owner class applies even when group/other could execute.
+        assert!(!mode_allows_execute(0o611, 1000, 1000, &me));
+    }
+
+    #[test]
+    fn mode_allows_execute_group() {
+        let me = creds(1000, &[1000, 27]);
+        assert!(mode_allows_execute(0o750, 0, 27, &me));
+        assert!(mode_allows_execute(0o010, 0, 1000, &me));
+        // Group class applies even when other could execute.
+        assert!(!mode_allows_execute(0o741, 0, 27, &me));
+    }
+
+    #[test]
+    fn mode_allows_execute_other() {
+        let me = creds(1000, &[1000]);
+        assert!(mode_allows_execute(0o755, 0, 0, &me));
+        assert!(mode_allows_execute(0o001, 0, 0, &me));
+        assert!(!mode_allows_execute(0o750, 0, 0, &me));
+        assert!(!mode_allows_execute(0o644, 1000, 1000, &me));
+    }
+
+    #[test]
+    fn mode_allows_execute_root() {
+        let root = creds(0, &[0]);
+        assert!(mode_allows_execute(0o700, 1000, 1000, &root));
+        assert!(mode_allows_execute(0o001, 1000, 1000, &root));
+        assert!(!mode_allows_execute(0o666, 1000, 1000, &root));
+    }
+}
@@ -0,0 +1,364 @@
+use crate::text_buffer::{TextBuffer, WordDelim};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Which vi editing state the buffer is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViMode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+impl ViMode {
+    /// Short indicator drawn before the command buffer.
+    pub fn indicator(&self) -> &'static str {
+        match self {
+            ViMode::Insert => "[I] ",
+            ViMode::Normal => "[N] ",
+        }
+    }
+}
+
+/// A vi key (or key pair, for operators) translated to a buffer edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViCommand {
+    EnterNormal,
+    MoveLeft,
+    MoveRight,
+    WordForward,
+    WordBackward,
+    LineStart,
+    LineEnd,
+    Insert,
+    Append,
+    DeleteChar,
+    DeleteLine,
+    ChangeWord,
+}
+
+/// What to do with a key press in vi mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViKey {
+    Command(ViCommand),
+    /// The key was consumed without editing (an operator waiting for its
+    /// motion, or an unsupported key in Normal mode).
+    Consumed,
+    /// Not a vi key: handle it with the normal keybindings.
+    PassThrough,
+    /// Handle the key as this one with the normal keybindings.
+    Key(KeyCode),
+}
+
+/// Modal editing state, present on the app only when vi mode is enabled.
+#[derive(Debug, Clone, Default)]
+pub struct ViState {
+    pub mode: ViMode,
+    pending_operator: Option<char>,
+}
+
+impl ViState {
+    /// Vi mode is enabled by `FLYLINE_VI_MODE=1` or by `set -o vi`, which adds
+    /// `vi` to `SHELLOPTS`.
+    pub fn from_env() -> Option<Self> {
+        let flag = crate::bash_funcs::get_envvar_value("FLYLINE_VI_MODE");
+        let shellopts = crate::bash_funcs::get_envvar_value("SHELLOPTS");
+        Self::enabled(flag.as_deref(), shellopts.as_deref()).then(Self::default)
+    }
+
+    fn enabled(flag: Option<&str>, shellopts: Option<&str>) -> bool {
+        match flag.map(str::trim) {
+            Some("1") | Some("true") | Some("on") => true,
+            Some("0") | Some("false") | Some("off") => false,
+            _ => shellopts.is_some_and(|opts| opts.split(':').any(|opt| opt == "vi")),
+        }
+    }
+
+    /// Translate `key` in the current mode. Control/Alt chords and non-character
+    /// keys (Enter, arrows, ...) always pass through to the keybindings.
+    /// `k`/`j` act as Up/Down, so on the first or last line (or a one-line
+    /// buffer) they step through history.
+    pub fn translate(&mut self, key: KeyEvent) -> ViKey {
+        if key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            self.pending_operator = None;
+            return ViKey::PassThrough;
+        }
+
+        match self.mode {
+            ViMode::Insert => match key.code {
+                KeyCode::Esc => ViKey::Command(ViCommand::EnterNormal),
+                _ => ViKey::PassThrough,
+            },
+            ViMode::Normal => {
+                let KeyCode::Char(c) = key.code else {
+                    self.pending_operator = None;
+                    return ViKey::PassThrough;
+                };
+                match (self.pending_operator.take(), c) {
+                    (Some('d'), 'd') => ViKey::Command(ViCommand::DeleteLine),
+                    (Some('c'), 'w') => ViKey::Command(ViCommand::ChangeWord),
+                    (Some(_), _) => ViKey::Consumed,
+                    (None, 'd' | 'c') => {
+                        self.pending_operator = Some(c);
+                        ViKey::Consumed
+                    }
+                    (None, c) => match c {
+                        'h' => ViKey::Command(ViCommand::MoveLeft),
+                        'l' => ViKey::Command(ViCommand::MoveRight),
+                        'k' => ViKey::Key(KeyCode::Up),
+                        'j' => ViKey::Key(KeyCode::Down),
+                        'w' => ViKey::Command(ViCommand::WordForward),
+                        'b' => ViKey::Command(ViCommand::WordBackward),
+                        '0' => ViKey::Command(ViCommand::LineStart),
+                        '$' => ViKey::Command(ViCommand::LineEnd),
+                        'i' => ViKey::Command(ViCommand::Insert),
+                        'a' => ViKey::Command(ViCommand::Append),
+                        'x' => ViKey::Command(ViCommand::DeleteChar),
+                        _ => ViKey::Consumed,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Run `command` on `buffer`, switching mode where the command does.
+    pub fn apply(&mut self, command: ViCommand, buffer: &mut TextBuffer) {
+        match command {
+            ViCommand::EnterNormal => {
+                // Like vi, leaving Insert mode steps back onto the last typed character.
+                if !at_line_start(buffer) {
+                    buffer.move_left();
+                }
+                self.mode = ViMode::Normal;
+            }
+            ViCommand::MoveLeft => {
+                if !at_line_start(buffer) {
+                    buffer.move_left();
+                }
+            }
+            ViCommand::MoveRight => {
+                if !at_line_end(buffer) {
+                    buffer.move_right();
+                }
+            }
+            ViCommand::WordForward => {
+                let pos = next_word_start(buffer.buffer(), buffer.cursor_byte_pos());
+                buffer.try_move_cursor_to_byte_pos(pos, false);
+            }
+            ViCommand::WordBackward => buffer.move_one_word_left(WordDelim::WhiteSpace),
+            ViCommand::LineStart => buffer.move_start_of_line(),
+            ViCommand::LineEnd => {
+                // Normal mode rests on the last character, not past it.
+                buffer.move_end_of_line();
+                if !at_line_start(buffer) {
+                    buffer.move_left();
+                }
+            }
+            ViCommand::Insert => self.mode = ViMode::Insert,
+            ViCommand::Append => {
+                if !at_line_end(buffer) {
+                    buffer.move_right();
+                }
+                self.mode = ViMode::Insert;
+            }
+            ViCommand::DeleteChar => {
+                if !at_line_end(buffer) {
+                    buffer.delete_right();
+                }
+            }
+            ViCommand::DeleteLine => {
+                buffer.move_start_of_line();
+                buffer.delete_until_end_of_line();
+                // Remove the line break too, joining the neighbouring lines.
+                if buffer.buffer()[buffer.cursor_byte_pos()..].starts_with('\n') {
+                    buffer.delete_right();
+                } else if !buffer.is_cursor_at_start() {
+                    buffer.delete_left();
+                    buffer.move_start_of_line();
+                }
+            }
+            ViCommand::ChangeWord => {
+                if !at_line_end(buffer) {
+                    buffer.delete_right_one_word(WordDelim::WhiteSpace);
+                }
+                self.mode = ViMode::Insert;
+            }
+        }
+    }
+}
+
+fn at_line_start(buffer: &TextBuffer) -> bool {
+    buffer.buffer()[..buffer.cursor_byte_pos()]
+        .chars()
+        .next_back()
+        .is_none_or(|c| c == '\n')
+}
+
+fn at_line_end(buffer: &TextBuffer) -> bool {
+    buffer.buffer()[buffer.cursor_byte_pos()..]
+        .chars()
+        .next()
+        .is_none_or(|c| c == '\n')
+}
+
+/// Byte position of the start of the next whitespace-delimited word after
+/// `cursor`, or the end of `buf`.
+fn next_word_start(buf: &str, cursor: usize) -> usize {
+    buf[cursor..]
+        .char_indices()
+        .skip_while(|(_, c)| !c.is_whitespace())
+        .find(|(_, c)| !c.is_whitespace())
+        .map_or(buf.len(), |(i, _)| cursor + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn esc() -> KeyEvent {
+        KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)
+    }
+
+    /// Feed `keys` through vi mode, inserting text for keys that pass through.
+    fn type_keys(vi: &mut ViState, buffer: &mut TextBuffer, keys: &[KeyEvent]) {
+        for k in keys {
+            match vi.translate(*k) {
+                ViKey::Command(command) => vi.apply(command, buffer),
+                ViKey::Consumed | ViKey::Key(_) => {}
+                ViKey::PassThrough => {
+                    if let KeyCode::Char(c) = k.code {
+                        buffer.insert_char(c);
+                    }
+                }
+            }
+        }
+    }
+
+    fn normal_mode() -> ViState {
+        ViState {
+            mode: ViMode::Normal,
+            pending_operator: None,
+        }
+    }
+
+    #[test]
+    fn test_translate_normal_mode_keys() {
+        let mut vi = normal_mode();
+        assert_eq!(vi.translate(key('h')), ViKey::Command(ViCommand::MoveLeft));
+        assert_eq!(
+            vi.translate(key('w')),
+            ViKey::Command(ViCommand::WordForward)
+        );
+        assert_eq!(vi.translate(key('$')), ViKey::Command(ViCommand::LineEnd));
+        assert_eq!(vi.translate(key('q')), ViKey::Consumed);
+        assert_eq!(vi.translate(key('k')), ViKey::Key(KeyCode::Up));
+        assert_eq!(vi.translate(key('j')), ViKey::Key(KeyCode::Down));
+
+        assert_eq!(vi.translate(key('d')), ViKey::Consumed);
+        assert_eq!(
+            vi.translate(key('d')),
+            ViKey::Command(ViCommand::DeleteLine)
+        );
+        assert_eq!(vi.translate(key('c')), ViKey::Consumed);
+        assert_eq!(
+            vi.translate(key('w')),
+            ViKey::Command(ViCommand::ChangeWord)
+        );
+        // An operator followed by an unsupported motion is dropped.
+        assert_eq!(vi.translate(key('d')), ViKey::Consumed);
+        assert_eq!(vi.translate(key('z')), ViKey::Consumed);
+        assert_eq!(
+            vi.translate(key('x')),
+            ViKey::Command(ViCommand::DeleteChar)
+        );
+
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(vi.translate(enter), ViKey::PassThrough);
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(vi.translate(ctrl_r), ViKey::PassThrough);
+    }
+
+    #[test]
+    fn test_translate_insert_mode_keys() {
+        let mut vi = ViState::default();
+        assert_eq!(vi.translate(key('h')), ViKey::PassThrough);
+        assert_eq!(vi.translate(esc()), ViKey::Command(ViCommand::EnterNormal));
+    }
+
+    #[test]
+    fn test_escape_then_insert_and_append() {
+        let mut vi = ViState::default();
+        let mut buffer = TextBuffer::new("");
+        type_keys(&mut vi, &mut buffer, &[key('l'), key('s'), esc()]);
+        assert_eq!(vi.mode, ViMode::Normal);
+        assert_eq!(buffer.cursor_byte_pos(), 1);
+
+        type_keys(&mut vi, &mut buffer, &[key('i'), key('-'), esc()]);
+        assert_eq!(buffer.buffer(), "l-s");
+
+        type_keys(&mut vi, &mut buffer, &[key('$'), key('a'), key('!')]);
+        assert_eq!(buffer.buffer(), "l-s!");
+        assert_eq!(vi.mode, ViMode::Insert);
+    }
+
+    #[test]
+    fn test_word_motions_and_line_motions() {
+        let mut vi = normal_mode();
+        let mut buffer = TextBuffer::new("git commit -m msg");
+        type_keys(&mut vi, &mut buffer, &[key('0')]);
+        assert_eq!(buffer.cursor_byte_pos(), 0);
+        type_keys(&mut vi, &mut buffer, &[key('w'), key('w')]);
+        assert_eq!(buffer.cursor_byte_pos(), "git commit ".len());
+        type_keys(&mut vi, &mut buffer, &[key('b')]);
+        assert_eq!(buffer.cursor_byte_pos(), "git ".len());
+        type_keys(&mut vi, &mut buffer, &[key('l'), key('l'), key('h')]);
+        assert_eq!(buffer.cursor_byte_pos(), "git c".len());
+        type_keys(&mut vi, &mut buffer, &[key('$')]);
+        assert_eq!(buffer.cursor_byte_pos(), "git commit -m ms".len());
+        type_keys(&mut vi, &mut buffer, &[key('x')]);
+        assert_eq!(buffer.buffer(), "git commit -m ms");
+    }
+
+    #[test]
+    fn test_delete_char_and_change_word() {
+        let mut vi = normal_mode();
+        let mut buffer = TextBuffer::new("echo hello world");
+        buffer.try_move_cursor_to_byte_pos("echo ".len(), false);
+        type_keys(&mut vi, &mut buffer, &[key('x')]);
+        assert_eq!(buffer.buffer(), "echo ello world");
+
+        type_keys(&mut vi, &mut buffer, &[key('c'), key('w')]);
+        assert_eq!(buffer.buffer(), "echo  world");
+        assert_eq!(vi.mode, ViMode::Insert);
+        type_keys(&mut vi, &mut buffer, &[key('h'), key('i')]);
+        assert_eq!(buffer.buffer(), "echo hi world");
+    }
+
+    #[test]
+    fn test_delete_line() {
+        let mut vi = normal_mode();
+        let mut buffer = TextBuffer::new("one\ntwo\nthree");
+        buffer.try_move_cursor_to_byte_pos("one\nt".len(), false);
+        type_keys(&mut vi, &mut buffer, &[key('d'), key('d')]);
+        assert_eq!(buffer.buffer(), "one\nthree");
+
+        let mut buffer = TextBuffer::new("only line");
+        type_keys(&mut vi, &mut buffer, &[key('d'), key('d')]);
+        assert_eq!(buffer.buffer(), "");
+    }
+
+    #[test]
+    fn test_enabled_from_flag_or_shellopts() {
+        assert!(ViState::enabled(Some("1"), None));
+        assert!(ViState::enabled(None, Some("braceexpand:hashall:vi")));
+        assert!(!ViState::enabled(Some("0"), Some("braceexpand:vi")));
+        assert!(!ViState::enabled(None, Some("braceexpand:emacs")));
+        assert!(!ViState::enabled(None, None));
+    }
+}